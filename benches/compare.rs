@@ -0,0 +1,35 @@
+//! Compare both backends for encode and decode, runs on stable without valgrind.
+//!
+//! The report, with the `bench` feature including the latency percentiles of the iterations, is
+//! printed and written as JSON to `target/compare.json`, with the `profiling` feature also a
+//! flamegraph of all runs to `target/compare-flamegraph.svg`.
+
+use rs_ec_perf::benchmark::{Report, Runner};
+use rs_ec_perf::*;
+
+fn main() {
+	#[cfg(feature = "profiling")]
+	let guard = pprof::ProfilerGuard::new(997).unwrap();
+
+	// enough iterations for the p99 latency to tell apart from the slowest one
+	let runner = Runner::new(3, 200);
+	let mut report = Report::default();
+	for &(n, k) in &[(16, 4), (256, 86), (1024, 342)] {
+		let params = CodeParams::new(n, k).unwrap();
+		for &len in &[1 << 10, 1 << 16] {
+			let payload = workload::random_payload(len, 0);
+			report.measurements.extend(runner.compare(&params, &payload).unwrap().measurements);
+		}
+	}
+	println!("{}", report);
+
+	let target = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("target");
+	std::fs::create_dir_all(&target).unwrap();
+	std::fs::write(target.join("compare.json"), report.to_json()).unwrap();
+
+	#[cfg(feature = "profiling")]
+	{
+		let flamegraph = std::fs::File::create(target.join("compare-flamegraph.svg")).unwrap();
+		guard.report().build().unwrap().flamegraph(flamegraph).unwrap();
+	}
+}
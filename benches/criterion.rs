@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
 use rs_ec_perf::*;
 
 /// Create a new testset for a particular RS encoding.
@@ -8,21 +8,23 @@ macro_rules! instanciate_test {
 	($name:literal, $mp:ident) => {
 		pub mod $mp {
 			use super::super::$mp::{encode, reconstruct};
-			use super::super::{roundtrip, BYTES};
+			use super::super::{roundtrip, workload};
 			use criterion::{black_box, Criterion};
 
 			pub fn bench_roundtrip(crit: &mut Criterion) {
+				let payload = workload::random_payload(256, 0);
 				crit.bench_function(concat!($name, " roudtrip"), |b| {
 					b.iter(|| {
-						roundtrip(encode, reconstruct, black_box(&BYTES[..256]));
+						roundtrip(encode, reconstruct, black_box(&payload[..]), payload.len());
 					})
 				});
 			}
 
 			pub fn bench_encode(crit: &mut Criterion) {
+				let payload = workload::random_payload(256, 0);
 				crit.bench_function(concat!($name, " encode"), |b| {
 					b.iter(|| {
-						let _ = encode(black_box(&BYTES[..256]));
+						let _ = encode(black_box(&payload));
 					})
 				});
 			}
@@ -35,15 +37,189 @@ pub mod tests {
 	instanciate_test!("status quo", status_quo);
 }
 
+/// Encode with a large number of shards, where the transforms dominate.
+pub fn bench_encode_large_n(crit: &mut Criterion) {
+	let payload = workload::random_payload(1 << 20, 0);
+	for &n in &[1_usize << 12, 1 << 14, 1 << 16] {
+		let params = CodeParams::new(n, n / 4).unwrap();
+		crit.bench_function(&format!("novel poly basis encode n={}", n), |b| {
+			b.iter(|| {
+				let _ = novel_poly_basis::encode_with_params(&params, black_box(&payload));
+			})
+		});
+	}
+}
+
+/// Encode payloads far exceeding the caches, where the copies of the message between
+/// the transforms are bound by memory bandwidth.
+pub fn bench_encode_large_payload(crit: &mut Criterion) {
+	let payload = pov::pov_payload(16 << 20, 16);
+	let mut group = crit.benchmark_group("encode 16 MiB");
+	group.throughput(Throughput::Bytes(payload.len() as u64));
+	for &n in &[64_usize, 1024] {
+		let params = CodeParams::new(n, n / 4).unwrap();
+		group.bench_with_input(BenchmarkId::new("novel poly basis", n), &payload, |b, payload| {
+			b.iter(|| novel_poly_basis::encode_with_params(&params, black_box(payload)).unwrap())
+		});
+	}
+	group.finish();
+}
+
+/// Encode a 16 MiB message stored as rows in place, against first copying it from rows of a
+/// full size data buffer into a fresh codeword, as the encoder did before. The copy costs
+/// another pass over the message and another `n` rows of memory, 64 MiB for both `n`.
+pub fn bench_encode_in_place(crit: &mut Criterion) {
+	let tables = novel_poly_basis::low_level::Tables::get();
+	let mut group = crit.benchmark_group("encode rows 16 MiB");
+	for &n in &[64_usize, 1024] {
+		let k = n / 4;
+		let width = (8 << 20) / k;
+		let message = workload::random_payload(k * width * 2, 0);
+		let mut data = vec![0_u16; n * width];
+		for (sym, bytes) in data.iter_mut().zip(message.chunks_exact(2)) {
+			*sym = u16::from_le_bytes([bytes[0], bytes[1]]);
+		}
+		group.throughput(Throughput::Bytes(message.len() as u64));
+
+		let mut codeword = data.clone();
+		group.bench_function(BenchmarkId::new("in place", n), |b| {
+			b.iter(|| tables.encode_rows(black_box(&mut codeword), width, k).unwrap())
+		});
+		group.bench_function(BenchmarkId::new("copied into full rows", n), |b| {
+			b.iter(|| {
+				let mut codeword = vec![0_u16; n * width];
+				codeword[..k * width].copy_from_slice(&black_box(&data)[..k * width]);
+				tables.encode_rows(&mut codeword, width, k).unwrap();
+				codeword
+			})
+		});
+	}
+	group.finish();
+}
+
+/// Encode and reconstruct PoV-like payloads for realistic validator set sizes,
+/// with `k` at the recovery threshold and `n - k` random erasures.
+pub fn bench_pov(crit: &mut Criterion) {
+	use rand::{rngs::StdRng, SeedableRng};
+
+	for &size in pov::POV_SIZES {
+		let payload = pov::pov_payload(size, size as u64);
+		let mut group = crit.benchmark_group(format!("pov {} MiB", size >> 20));
+		group.throughput(Throughput::Bytes(size as u64));
+		for &n in &[100_usize, 300, 1000] {
+			let params = CodeParams::new(n, (n - 1) / 3 + 1).unwrap();
+			let mut rng = StdRng::seed_from_u64(n as u64);
+			let erasures = rand::seq::index::sample(&mut rng, n, n - params.k());
+
+			macro_rules! bench_backend {
+				($name:literal, $mp:ident) => {
+					group.bench_with_input(BenchmarkId::new(concat!($name, " encode"), n), &payload, |b, payload| {
+						b.iter(|| $mp::encode_with_params(&params, black_box(payload)).unwrap())
+					});
+
+					let mut received =
+						$mp::encode_with_params(&params, &payload).unwrap().into_iter().map(Some).collect::<Vec<_>>();
+					for idx in erasures.iter() {
+						received[idx] = None;
+					}
+					group.bench_with_input(
+						BenchmarkId::new(concat!($name, " reconstruct"), n),
+						&received,
+						|b, received| {
+							b.iter(|| $mp::reconstruct_with_params(&params, black_box(received.clone())).unwrap())
+						},
+					);
+				};
+			}
+			bench_backend!("novel poly basis", novel_poly_basis);
+			bench_backend!("status quo", status_quo);
+			bench_backend!("ntt fp31", fp31);
+		}
+		group.finish();
+	}
+}
+
+/// Reconstruct with `0`, `1`, `(n - k) / 2` and `n - k` randomly erased shards,
+/// since real networks usually lose only a few.
+pub fn bench_reconstruct_erasures(crit: &mut Criterion) {
+	use rand::{rngs::StdRng, SeedableRng};
+
+	let payload = &workload::random_payload(1 << 20, 0);
+	let params = CodeParams::new(1024, 342).unwrap();
+	let parity = params.n() - params.k();
+	let mut group = crit.benchmark_group("reconstruct by erasures");
+	group.throughput(Throughput::Bytes(payload.len() as u64));
+	for &erased in &[0, 1, parity / 2, parity] {
+		let mut rng = StdRng::seed_from_u64(erased as u64);
+		let erasures = rand::seq::index::sample(&mut rng, params.n(), erased);
+
+		macro_rules! bench_backend {
+			($name:literal, $mp:ident) => {
+				let mut received =
+					$mp::encode_with_params(&params, payload).unwrap().into_iter().map(Some).collect::<Vec<_>>();
+				for idx in erasures.iter() {
+					received[idx] = None;
+				}
+				group.bench_with_input(BenchmarkId::new($name, erased), &received, |b, received| {
+					b.iter(|| $mp::reconstruct_with_params(&params, black_box(received.clone())).unwrap())
+				});
+			};
+		}
+		bench_backend!("novel poly basis", novel_poly_basis);
+		bench_backend!("status quo", status_quo);
+	}
+	group.finish();
+}
+
+/// Compress with zstd before encoding PoV-like payloads, run with `--features zstd`.
+#[cfg(feature = "zstd")]
+pub fn bench_pov_zstd(crit: &mut Criterion) {
+	use rs_ec_perf::transform::{encode_with, Zstd};
+
+	for &size in pov::POV_SIZES {
+		let payload = pov::pov_payload(size, size as u64);
+		let mut group = crit.benchmark_group(format!("pov {} MiB zstd", size >> 20));
+		group.throughput(Throughput::Bytes(size as u64));
+		for &n in &[100_usize, 300, 1000] {
+			let params = CodeParams::new(n, (n - 1) / 3 + 1).unwrap();
+			group.bench_with_input(BenchmarkId::new("compress and encode", n), &payload, |b, payload| {
+				b.iter(|| encode_with(&Zstd::default(), &params, black_box(payload)).unwrap())
+			});
+		}
+		group.finish();
+	}
+}
+
+#[cfg(not(feature = "zstd"))]
+pub fn bench_pov_zstd(_crit: &mut Criterion) {}
+
 fn adjusted_criterion() -> Criterion {
-	let crit = Criterion::default()
-		.sample_size(10)
-		.warm_up_time(Duration::from_secs(1))
-		.measurement_time(Duration::from_secs(60));
+	with_profiler(
+		Criterion::default()
+			.sample_size(10)
+			.warm_up_time(Duration::from_secs(1))
+			.measurement_time(Duration::from_secs(60)),
+	)
+}
+
+// with the `profiling` feature, `--profile-time <secs>` samples each benchmark instead of
+// measuring it and writes `target/criterion/<benchmark>/profile/flamegraph.svg`
+#[cfg(feature = "profiling")]
+fn with_profiler(crit: Criterion) -> Criterion {
+	use pprof::criterion::{Output, PProfProfiler};
+	crit.with_profiler(PProfProfiler::new(997, Output::Flamegraph(None)))
+}
+
+#[cfg(not(feature = "profiling"))]
+fn with_profiler(crit: Criterion) -> Criterion {
 	crit
 }
 
 criterion_group!(name = acc_novel_poly_basis; config = adjusted_criterion(); targets =  tests::novel_poly_basis::bench_roundtrip, tests::novel_poly_basis::bench_encode);
 criterion_group!(name = acc_status_quo; config = adjusted_criterion(); targets =  tests::status_quo::bench_roundtrip, tests::status_quo::bench_encode);
 
-criterion_main!(acc_novel_poly_basis, acc_status_quo);
+criterion_group!(name = acc_large_n; config = adjusted_criterion(); targets = bench_encode_large_n, bench_encode_large_payload, bench_encode_in_place);
+criterion_group!(name = acc_pov; config = adjusted_criterion(); targets = bench_pov, bench_pov_zstd);
+criterion_group!(name = acc_erasures; config = adjusted_criterion(); targets = bench_reconstruct_erasures);
+
+criterion_main!(acc_novel_poly_basis, acc_status_quo, acc_large_n, acc_pov, acc_erasures);
@@ -0,0 +1,144 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use rs_ec_perf::novel_poly_basis::low_level::{self, Tables};
+use rs_ec_perf::{novel_poly_basis, workload, CodeParams};
+use std::time::Duration;
+
+const SYMBOLS: usize = 4096;
+
+fn symbols() -> Vec<u16> {
+	let bytes = workload::random_payload(SYMBOLS * 2, 0);
+	bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect()
+}
+
+pub fn bench_mul(crit: &mut Criterion) {
+	let tables = Tables::get();
+	let src = symbols();
+	let log_b = 0x1234;
+
+	let mut group = crit.benchmark_group("gf(2^16) mul");
+	group.throughput(Throughput::Elements(SYMBOLS as u64));
+
+	group.bench_function("mul_table", |b| {
+		let mut data = src.clone();
+		b.iter(|| {
+			for a in data.iter_mut() {
+				*a = tables.mul(*a, black_box(log_b));
+			}
+		})
+	});
+
+	group.bench_function("mul_ct", |b| {
+		let mut data = src.clone();
+		b.iter(|| {
+			for a in data.iter_mut() {
+				*a = tables.mul_ct(*a, black_box(log_b));
+			}
+		})
+	});
+
+	group.bench_function("mul_poly_basis", |b| {
+		let mut data = src.clone();
+		b.iter(|| {
+			for a in data.iter_mut() {
+				*a = tables.mul_poly_basis(*a, black_box(log_b));
+			}
+		})
+	});
+
+	group.bench_function("Multiplier::mul_assign_slice", |b| {
+		let multiplier = tables.multiplier(log_b);
+		let mut data = src.clone();
+		b.iter(|| black_box(multiplier).mul_assign_slice(&mut data))
+	});
+
+	group.bench_function("Multiplier::mul_add_slice", |b| {
+		let multiplier = tables.multiplier(log_b);
+		let mut data = src.clone();
+		b.iter(|| black_box(multiplier).mul_add_slice(&mut data, &src))
+	});
+
+	group.bench_function("RegionMultiplier::mul_add_slice", |b| {
+		let multiplier = tables.region_multiplier(log_b);
+		let mut data = src.clone();
+		b.iter(|| black_box(&multiplier).mul_add_slice(&mut data, &src))
+	});
+
+	group.finish();
+}
+
+// encoding with the skew factors multiplied via the log tables or via precomputed region tables
+pub fn bench_skew_multipliers(crit: &mut Criterion) {
+	let tables = Tables::get();
+	let payload = &workload::random_payload(1 << 20, 0);
+
+	let mut group = crit.benchmark_group("skew multipliers");
+	group.throughput(Throughput::Bytes(payload.len() as u64));
+	for &n in &[16, 256, 4096] {
+		let params = CodeParams::new(n, n / 3).unwrap();
+		for &precomputed in &[false, true] {
+			if precomputed {
+				tables.precompute_skew_multipliers(n).unwrap();
+			}
+			group.bench_function(format!("n={} precomputed={}", n, precomputed), |b| {
+				b.iter(|| novel_poly_basis::encode_with_params(&params, black_box(payload)).unwrap())
+			});
+			low_level::set_skew_multipliers(false);
+		}
+	}
+	group.finish();
+}
+
+// transforms of `k` symbols and small message encodes running them, with the transforms unrolled
+// for their size or via the generic loops
+pub fn bench_unrolled_transforms(crit: &mut Criterion) {
+	let tables = Tables::get();
+	let src = symbols();
+	let payload = &workload::random_payload(1 << 10, 0);
+
+	let mut group = crit.benchmark_group("unrolled transforms");
+	for &k in &[16, 32, 64] {
+		let params = CodeParams::new(2 * k, k).unwrap();
+		for &unrolled in &[false, true] {
+			low_level::set_unrolled_transforms(unrolled);
+			group.throughput(Throughput::Elements(k as u64));
+			group.bench_function(format!("fft k={} unrolled={}", k, unrolled), |b| {
+				let mut data = src[..k].to_vec();
+				b.iter(|| {
+					tables.inverse_fft(black_box(&mut data), k, 0).unwrap();
+					tables.fft(black_box(&mut data), k, k).unwrap();
+				})
+			});
+			group.throughput(Throughput::Bytes(payload.len() as u64));
+			group.bench_function(format!("encode k={} unrolled={}", k, unrolled), |b| {
+				b.iter(|| novel_poly_basis::encode_with_params(&params, black_box(payload)).unwrap())
+			});
+		}
+	}
+	low_level::set_unrolled_transforms(true);
+	group.finish();
+}
+
+fn adjusted_criterion() -> Criterion {
+	with_profiler(Criterion::default().warm_up_time(Duration::from_secs(1)).measurement_time(Duration::from_secs(5)))
+}
+
+// with the `profiling` feature, `--profile-time <secs>` samples each benchmark instead of
+// measuring it and writes `target/criterion/<benchmark>/profile/flamegraph.svg`
+#[cfg(feature = "profiling")]
+fn with_profiler(crit: Criterion) -> Criterion {
+	use pprof::criterion::{Output, PProfProfiler};
+	crit.with_profiler(PProfProfiler::new(997, Output::Flamegraph(None)))
+}
+
+#[cfg(not(feature = "profiling"))]
+fn with_profiler(crit: Criterion) -> Criterion {
+	crit
+}
+
+criterion_group!(
+	name = field;
+	config = adjusted_criterion();
+	targets = bench_mul, bench_skew_multipliers, bench_unrolled_transforms
+);
+
+criterion_main!(field);
@@ -0,0 +1,35 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use rs_ec_perf::novel_poly_basis::{self, gpu};
+use rs_ec_perf::CodeParams;
+use std::time::Duration;
+
+/// Bulk payloads as encoded for archival, where offloading pays for the transfers.
+const PAYLOAD_LEN: usize = 64 << 20;
+
+pub fn bench_encode_cpu_vs_gpu(crit: &mut Criterion) {
+	let payload = rs_ec_perf::workload::random_payload(PAYLOAD_LEN, 0);
+	let params = CodeParams::new(1024, 342).unwrap();
+	let adapter = match gpu::gpu() {
+		Some(gpu) => format!("gpu {}", gpu.adapter_info().name),
+		None => "gpu unavailable, falls back to the cpu".to_owned(),
+	};
+
+	let mut group = crit.benchmark_group("encode 64 MiB");
+	group.throughput(Throughput::Bytes(PAYLOAD_LEN as u64));
+	for (name, offload) in [("cpu".to_owned(), false), (adapter, true)] {
+		gpu::set_offload(offload);
+		group.bench_function(name, |b| {
+			b.iter(|| novel_poly_basis::encode_with_params(&params, black_box(&payload)).unwrap())
+		});
+	}
+	gpu::set_offload(true);
+	group.finish();
+}
+
+fn adjusted_criterion() -> Criterion {
+	Criterion::default().sample_size(10).warm_up_time(Duration::from_secs(1)).measurement_time(Duration::from_secs(20))
+}
+
+criterion_group!(name = offload; config = adjusted_criterion(); targets = bench_encode_cpu_vs_gpu);
+
+criterion_main!(offload);
@@ -1,25 +1,138 @@
+#![allow(clippy::needless_range_loop)]
+
 use fs_err::OpenOptions;
-use rand::{self, distributions::Uniform, prelude::Distribution};
 use std::env;
 use std::io::Write;
 
-fn gen_10mb_rand_data() -> Result<(), std::io::Error> {
-	let mut rng = rand::thread_rng();
-	let dice = Uniform::<u8>::new_inclusive(0, 255);
-	let data = dice.sample_iter(&mut rng).take(10_000_000).collect::<Vec<_>>();
+fn out_dir() -> std::path::PathBuf {
+	std::path::PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR is set by cargo after process launch. qed"))
+}
+
+/// Definition of a binary field in the novel polynomial basis, from which the tables are generated.
+struct FieldSpec {
+	/// Module name, the tables are written to `<name>_tables.rs` if the `tables-<name>` feature is enabled.
+	name: &'static str,
+	bits: usize,
+	/// The field polynomial without its leading term.
+	generator: u32,
+	/// Cantor basis, each element `b[i]` satisfying `b[i]^2 + b[i] = b[i - 1]`.
+	basis: &'static [u32],
+}
+
+// the same as `GENERATOR` and `BASE` of the modules, which check the tables against their own
+const FIELDS: &[FieldSpec] = &[
+	FieldSpec { name: "f2e8", bits: 8, generator: 0x1D, basis: &[1, 214, 152, 146, 86, 200, 88, 230] },
+	FieldSpec {
+		name: "f2e16",
+		bits: 16,
+		generator: 0x2D,
+		basis: &[
+			1, 44234, 15374, 5694, 50562, 60718, 37196, 16402, 27800, 4312, 27250, 47360, 64952, 64308, 65336, 39198,
+		],
+	},
+];
+
+struct Tables {
+	log: Vec<u32>,
+	exp: Vec<u32>,
+	skew: Vec<u32>,
+}
 
-	let dest =
-		std::path::PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR is set by cargo after process launch. qed"))
-			.join("rand_data.bin");
+impl FieldSpec {
+	fn modulo(&self) -> u32 {
+		(1 << self.bits) - 1
+	}
 
-	let mut f = OpenOptions::new().truncate(true).write(true).create(true).open(&dest)?;
+	// same construction as `f2e16::init` and the skew factors of `init_dec`
+	fn tables(&self) -> Tables {
+		let (bits, size, modulo) = (self.bits, 1_usize << self.bits, self.modulo());
+		let mut log = vec![0_u32; size];
+		let mut exp = vec![0_u32; size];
 
-	f.write_all(&data)?;
+		let mut state: u32 = 1;
+		for i in 0..modulo {
+			exp[state as usize] = i;
+			state <<= 1;
+			if state >> bits != 0 {
+				state = (state & modulo) ^ self.generator;
+			}
+		}
+		exp[0] = modulo;
 
-	f.flush()?;
-	Ok(())
+		for i in 0..bits {
+			for j in 0..(1 << i) {
+				log[j + (1 << i)] = log[j] ^ self.basis[i];
+			}
+		}
+		for i in 0..size {
+			log[i] = exp[log[i] as usize];
+		}
+		for i in 0..size {
+			exp[log[i] as usize] = i as u32;
+		}
+		exp[modulo as usize] = exp[0];
+
+		let mul = |a: u32, log_b: u32| {
+			if a == 0 {
+				return 0;
+			}
+			let sum = log[a as usize] + log_b;
+			exp[((sum & modulo) + (sum >> bits)) as usize]
+		};
+
+		let mut skew = vec![0_u32; modulo as usize];
+		let mut base = (1..bits).map(|i| 1_u32 << i).collect::<Vec<_>>();
+		for m in 0..(bits - 1) {
+			let step = 1 << (m + 1);
+			skew[(1 << m) - 1] = 0;
+			for i in m..(bits - 1) {
+				let s = 1 << (i + 1);
+				let mut j = (1 << m) - 1;
+				while j < s {
+					skew[j + s] = skew[j] ^ base[i];
+					j += step;
+				}
+			}
+
+			let idx = mul(base[m], log[(base[m] ^ 1) as usize]);
+			base[m] = modulo - log[idx as usize];
+
+			for i in (m + 1)..(bits - 1) {
+				let b = (log[(base[i] ^ 1) as usize] + base[m]) % modulo;
+				base[i] = mul(base[i], b);
+			}
+		}
+		for s in skew.iter_mut() {
+			*s = log[*s as usize];
+		}
+		Tables { log, exp, skew }
+	}
+
+	fn enabled(&self) -> bool {
+		env::var_os(format!("CARGO_FEATURE_TABLES_{}", self.name.to_uppercase())).is_some()
+	}
+
+	fn write_tables(&self) -> Result<(), std::io::Error> {
+		let ty = format!("u{}", std::cmp::max(self.bits.next_power_of_two(), 8));
+		let Tables { log, exp, skew } = self.tables();
+
+		let dest = out_dir().join(format!("{}_tables.rs", self.name));
+		let mut f = std::io::BufWriter::new(OpenOptions::new().truncate(true).write(true).create(true).open(&dest)?);
+		writeln!(f, "// GF(2^{}) with generator {:#x}, generated by build.rs", self.bits, self.generator)?;
+		for (name, table) in [("LOG", log), ("EXP", exp), ("SKEW", skew)] {
+			write!(f, "pub(crate) static {}: [{}; {}] = [", name, ty, table.len())?;
+			for value in table {
+				write!(f, "{},", value)?;
+			}
+			writeln!(f, "];")?;
+		}
+		f.flush()
+	}
 }
 
 fn main() -> Result<(), std::io::Error> {
-	gen_10mb_rand_data()
+	for field in FIELDS.iter().filter(|field| field.enabled()) {
+		field.write_tables()?;
+	}
+	Ok(())
 }
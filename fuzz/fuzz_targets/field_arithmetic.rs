@@ -0,0 +1,29 @@
+//! Products and quotients of GF(2^16) elements via every table based multiplication,
+//! compared against the table free `reference::mul_bitwise`.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rs_ec_perf::novel_poly_basis::{low_level::Tables, reference};
+
+fuzz_target!(|elements: (u16, u16)| {
+	let (a, b) = elements;
+	let tables = Tables::get();
+	let expected = reference::mul_bitwise(a, b);
+	if b == 0 {
+		assert_eq!(expected, 0);
+		return;
+	}
+
+	let log_b = tables.log(b);
+	assert_eq!(tables.exp(log_b), b, "exp(log({}))", b);
+	assert_eq!(tables.mul(a, log_b), expected, "mul {} * {}", a, b);
+	assert_eq!(tables.mul_ct(a, log_b), expected, "mul_ct {} * {}", a, b);
+	assert_eq!(tables.mul_poly_basis(a, log_b), expected, "mul_poly_basis {} * {}", a, b);
+	assert_eq!(tables.region_multiplier(log_b).apply(a), expected, "RegionMultiplier {} * {}", a, b);
+	assert_eq!(reference::mul(&tables, a, log_b), expected, "reference::mul {} * {}", a, b);
+
+	// division multiplies by the inverse, whose log is the negated one
+	let modulo = (Tables::FIELD_SIZE - 1) as u16;
+	let quotient = tables.mul(a, (modulo - log_b) % modulo);
+	assert_eq!(reference::mul_bitwise(quotient, b), a, "{} / {}", a, b);
+});
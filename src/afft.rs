@@ -0,0 +1,98 @@
+//! Additive FFT in the novel polynomial basis, generic over the binary field.
+//!
+//! A field only has to provide its multiplication and skew factor tables
+//! via `FieldAdd`, the transforms are shared by all fields. A field may override
+//! the butterflies with vectorized ones, as `F2e16` does with those of the
+//! `novel_poly_basis` backend, whose transforms are the ones here.
+
+use std::fmt::Debug;
+use std::ops::BitXorAssign;
+
+/// A binary extension field with the tables required by the additive FFT.
+pub trait FieldAdd {
+	/// A field element, logarithms are represented by the same type.
+	type Elt: Copy + Default + PartialEq + Debug + BitXorAssign;
+
+	/// Degree of the field over GF(2).
+	const FIELD_BITS: usize;
+
+	/// Order of the multiplicative group, also used as log of zero.
+	const MODULO: Self::Elt;
+
+	/// Initialize the tables if necessary, called before every transform.
+	fn setup();
+
+	/// `a * exp(log_b)`.
+	fn mul(a: Self::Elt, log_b: Self::Elt) -> Self::Elt;
+
+	/// Log of the skew factor at `idx`, `MODULO` marks a zero factor.
+	fn skew(idx: usize) -> Self::Elt;
+
+	/// `lo[i] ^= hi[i] * exp(log_skew)`, the multiplying half of a butterfly.
+	#[inline(always)]
+	fn butterfly_mul_add(lo: &mut [Self::Elt], hi: &[Self::Elt], log_skew: Self::Elt) {
+		for (l, h) in lo.iter_mut().zip(hi) {
+			*l ^= Self::mul(*h, log_skew);
+		}
+	}
+
+	/// `hi[i] ^= lo[i]`, the adding half of a butterfly.
+	#[inline(always)]
+	fn butterfly_add(lo: &[Self::Elt], hi: &mut [Self::Elt]) {
+		for (l, h) in lo.iter().zip(hi) {
+			*h ^= *l;
+		}
+	}
+}
+
+/// Inverse transform of `data[..size]` at offset `index` into the novel polynomial basis.
+pub fn inverse_afft<F: FieldAdd>(data: &mut [F::Elt], size: usize, index: usize) {
+	F::setup();
+	inverse_afft_rows::<F>(data, 1, size, index)
+}
+
+/// Evaluate the polynomial `data[..size]` in the novel polynomial basis at offset `index`.
+pub fn afft<F: FieldAdd>(data: &mut [F::Elt], size: usize, index: usize) {
+	F::setup();
+	afft_rows::<F>(data, 1, size, index)
+}
+
+// `inverse_afft` of `width` independent codewords stored as rows, i.e. symbol `i` of codeword `c`
+// at `i * width + c`. The skew factor only depends on the position, so each butterfly applies
+// to whole rows, which the compiler vectorizes. The tables must be set up.
+#[inline(always)]
+pub(crate) fn inverse_afft_rows<F: FieldAdd>(data: &mut [F::Elt], width: usize, size: usize, index: usize) {
+	let mut depart_no = 1_usize;
+	while depart_no < size {
+		let mut j = depart_no;
+		while j < size {
+			let (lo, hi) = data[(j - depart_no) * width..(j + depart_no) * width].split_at_mut(depart_no * width);
+			F::butterfly_add(lo, hi);
+			let skew = F::skew(j + index - 1);
+			if skew != F::MODULO {
+				F::butterfly_mul_add(lo, hi, skew);
+			}
+			j += depart_no << 1;
+		}
+		depart_no <<= 1;
+	}
+}
+
+// `afft` of `width` independent codewords stored as rows
+#[inline(always)]
+pub(crate) fn afft_rows<F: FieldAdd>(data: &mut [F::Elt], width: usize, size: usize, index: usize) {
+	let mut depart_no = size >> 1_usize;
+	while depart_no > 0 {
+		let mut j = depart_no;
+		while j < size {
+			let (lo, hi) = data[(j - depart_no) * width..(j + depart_no) * width].split_at_mut(depart_no * width);
+			let skew = F::skew(j + index - 1);
+			if skew != F::MODULO {
+				F::butterfly_mul_add(lo, hi, skew);
+			}
+			F::butterfly_add(lo, hi);
+			j += depart_no << 1;
+		}
+		depart_no >>= 1;
+	}
+}
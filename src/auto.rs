@@ -0,0 +1,196 @@
+//! Pick the faster backend for given code parameters.
+//!
+//! The matrix based `status_quo` wins for small `n`, the `novel_poly_basis`
+//! FFT with its `O(n log n)` complexity for large `n`.
+
+use super::*;
+
+/// Backends available for selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+	StatusQuo,
+	NovelPolyBasis,
+}
+
+/// Crossover points as `(shard_len, n)` pairs: for shards of at least `shard_len`
+/// bytes, the novel poly basis is selected for all codes with at least `n` shards.
+///
+/// Measured with `encode_with_params` followed by `reconstruct_with_params` with
+/// `n / 2` erasures and `k = n / 4` on payloads of 1 KiB, 16 KiB and 256 KiB,
+/// in all cases the novel poly basis overtook at `n = 128`.
+pub const DEFAULT_CROSSOVER: &[(usize, usize)] = &[(0, 128)];
+
+/// Backend selection policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+	crossover: Vec<(usize, usize)>,
+}
+
+impl Default for Selector {
+	fn default() -> Self {
+		Self { crossover: DEFAULT_CROSSOVER.to_vec() }
+	}
+}
+
+impl Selector {
+	/// Create a selector from custom crossover points, see `DEFAULT_CROSSOVER`.
+	///
+	/// The threshold `n` must not grow with the shard length, otherwise a payload
+	/// encoded with the novel poly basis, which pads shards slightly more, could be
+	/// attributed to the status quo backend on reconstruction. Such tables are rejected.
+	pub fn with_crossover(mut crossover: Vec<(usize, usize)>) -> Option<Self> {
+		crossover.sort_unstable();
+		if crossover.windows(2).any(|w| w[0].1 < w[1].1) {
+			return None;
+		}
+		Some(Self { crossover })
+	}
+
+	/// Always select `backend`, ignoring the parameters.
+	pub fn fixed(backend: Backend) -> Self {
+		let n = match backend {
+			Backend::StatusQuo => usize::MAX,
+			Backend::NovelPolyBasis => 0,
+		};
+		Self { crossover: vec![(0, n)] }
+	}
+
+	/// Select the backend for a code with `params` and shards of `shard_len` bytes.
+	pub fn select(&self, params: &CodeParams, shard_len: usize) -> Backend {
+		let threshold =
+			self.crossover.iter().rev().find(|(len, _)| *len <= shard_len).map(|(_, n)| *n).unwrap_or(usize::MAX);
+		if params.n() >= threshold {
+			Backend::NovelPolyBasis
+		} else {
+			Backend::StatusQuo
+		}
+	}
+
+	/// Select the backend to encode a payload of `payload_len` bytes.
+	pub fn select_for_payload(&self, params: &CodeParams, payload_len: usize) -> Backend {
+		self.select(params, status_quo::required_shard_len(params, payload_len))
+	}
+
+	/// Encode with the selected backend.
+	pub fn encode(&self, params: &CodeParams, data: &[u8]) -> Result<Vec<WrappedShard>> {
+		match self.select_for_payload(params, data.len()) {
+			Backend::StatusQuo => status_quo::encode_with_params(params, data),
+			Backend::NovelPolyBasis => novel_poly_basis::encode_with_params(params, data),
+		}
+	}
+
+	/// Reconstruct with the backend that was selected by `encode`.
+	pub fn reconstruct(&self, params: &CodeParams, received_shards: Vec<Option<WrappedShard>>) -> Result<Vec<u8>> {
+		let shard_len = received_shards.iter().flatten().map(|shard| AsRef::<[u8]>::as_ref(shard).len()).next();
+		let shard_len = shard_len.unwrap_or_default();
+		match self.select(params, shard_len) {
+			Backend::StatusQuo => status_quo::reconstruct_with_params(params, received_shards),
+			Backend::NovelPolyBasis => novel_poly_basis::reconstruct_with_params(params, received_shards),
+		}
+	}
+}
+
+/// Length in bytes of each shard `backend` encodes a payload of `payload_len` bytes into,
+/// to pre-allocate buffers.
+pub fn required_shard_len(backend: Backend, params: &CodeParams, payload_len: usize) -> Result<usize> {
+	match backend {
+		Backend::StatusQuo => {
+			params.check_payload_len(payload_len)?;
+			Ok(status_quo::required_shard_len(params, payload_len))
+		}
+		Backend::NovelPolyBasis => novel_poly_basis::required_shard_len(params, payload_len),
+	}
+}
+
+/// Length of the payload including padding that `backend` reconstructs for a payload of
+/// `payload_len` bytes, the novel poly basis pads to its internal power of two `k`.
+pub fn padded_payload_len(backend: Backend, params: &CodeParams, payload_len: usize) -> Result<usize> {
+	match backend {
+		Backend::StatusQuo => {
+			params.check_payload_len(payload_len)?;
+			Ok(status_quo::padded_payload_len(params, payload_len))
+		}
+		Backend::NovelPolyBasis => novel_poly_basis::padded_payload_len(params, payload_len),
+	}
+}
+
+/// Number of data shards of `backend`, which hold the payload as is, followed by the parity
+/// shards. The novel poly basis carries `k` rounded down to a power of two data shards.
+pub fn data_shards(backend: Backend, params: &CodeParams) -> Result<usize> {
+	match backend {
+		Backend::StatusQuo => Ok(params.k()),
+		Backend::NovelPolyBasis => novel_poly_basis::internal_params(params).map(|(_n, k)| k),
+	}
+}
+
+/// Encode with the backend picked by the default `Selector`.
+pub fn encode(params: &CodeParams, data: &[u8]) -> Result<Vec<WrappedShard>> {
+	Selector::default().encode(params, data)
+}
+
+/// Reconstruct with the backend picked by the default `Selector`.
+pub fn reconstruct(params: &CodeParams, received_shards: Vec<Option<WrappedShard>>) -> Result<Vec<u8>> {
+	Selector::default().reconstruct(params, received_shards)
+}
+
+/// Reconstruct from shards in any order, each carrying its index.
+pub fn reconstruct_indexed(params: &CodeParams, shards: impl IntoIterator<Item = IndexedShard>) -> Result<Vec<u8>> {
+	reconstruct(params, arrange_shards(params.n(), shards)?)
+}
+
+/// Reconstruct from exactly the shards at the `trusted` indices, see `select_subset`.
+pub fn reconstruct_subset(
+	params: &CodeParams,
+	received_shards: Vec<Option<WrappedShard>>,
+	trusted: &[ChunkIndex],
+) -> Result<Vec<u8>> {
+	reconstruct(params, select_subset(received_shards, trusted)?)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn selects_by_n() {
+		let selector = Selector::default();
+		assert_eq!(selector.select(&CodeParams::new(16, 4).unwrap(), 1024), Backend::StatusQuo);
+		assert_eq!(selector.select(&CodeParams::new(1024, 341).unwrap(), 1024), Backend::NovelPolyBasis);
+	}
+
+	#[test]
+	fn rejects_growing_threshold() {
+		assert!(Selector::with_crossover(vec![(0, 64), (1024, 128)]).is_none());
+		assert!(Selector::with_crossover(vec![(0, 128), (1024, 64)]).is_some());
+	}
+
+	#[test]
+	fn roundtrip_both_sides_of_crossover() {
+		let payload = &random_payload(1000, 0);
+		for &(n, k) in &[(16, 5), (200, 67)] {
+			let params = CodeParams::new(n, k).unwrap();
+			let mut shards = encode(&params, payload).unwrap().into_iter().map(Some).collect::<Vec<_>>();
+			shards.iter_mut().skip(1).step_by(2).for_each(|shard| *shard = None);
+			let result = reconstruct(&params, shards).unwrap();
+			assert_eq!(&result[..payload.len()], payload);
+		}
+	}
+
+	#[test]
+	fn predicted_lengths_match_backends() {
+		for &(n, k) in &[(16, 5), (10, 4), (200, 67)] {
+			let params = CodeParams::new(n, k).unwrap();
+			for &len in &[0, 1, 7, 1000] {
+				for &backend in &[Backend::StatusQuo, Backend::NovelPolyBasis] {
+					let selector = Selector::fixed(backend);
+					let shards = selector.encode(&params, &random_payload(len, 0)).unwrap();
+					let shard_len = required_shard_len(backend, &params, len).unwrap();
+					assert!(shards.iter().all(|shard| AsRef::<[u8]>::as_ref(shard).len() == shard_len));
+
+					let recovered = selector.reconstruct(&params, shards.into_iter().map(Some).collect()).unwrap();
+					assert_eq!(recovered.len(), padded_payload_len(backend, &params, len).unwrap(), "{:?}", backend);
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,115 @@
+//! Availability chunk API mirroring the `polkadot-erasure-coding` crate,
+//! so it can be swapped for this backend in downstream tests.
+//!
+//! Payloads are SCALE encoded before erasure coding, which allows to strip
+//! the trailing padding on reconstruction.
+
+use super::*;
+
+use codec::{Decode, Encode};
+
+/// The maximum number of validators, limited by the size of the field.
+pub const MAX_VALIDATORS: usize = MAX_SHARDS;
+
+/// Obtain the number of chunks required to recover the data,
+/// less than one third of the validators may be faulty.
+pub fn recovery_threshold(n_validators: usize) -> Result<usize> {
+	crate::recovery_threshold(n_validators)
+}
+
+fn code_params(n_validators: usize) -> Result<CodeParams> {
+	CodeParams::new(n_validators, recovery_threshold(n_validators)?)
+}
+
+/// Obtain erasure-coded chunks, one for each validator.
+pub fn obtain_chunks<T: Encode>(n_validators: usize, data: &T) -> Result<Vec<Vec<u8>>> {
+	let params = code_params(n_validators)?;
+	let encoded = data.encode();
+	if encoded.is_empty() {
+		return Err(Error::BadPayload);
+	}
+
+	let shards = novel_poly_basis::encode_with_params(&params, &encoded)?;
+	Ok(shards.into_iter().map(WrappedShard::into_inner).collect())
+}
+
+/// Reconstruct decodable data from a set of chunks, given as `(chunk, validator index)` pairs.
+///
+/// Provide an iterator containing chunk data and the corresponding index.
+/// The indices of the present chunks must be indicated. If too few chunks
+/// are provided, recovery is not possible.
+pub fn reconstruct<'a, I, T: Decode>(n_validators: usize, chunks: I) -> Result<T>
+where
+	I: IntoIterator<Item = (&'a [u8], usize)> + 'a,
+{
+	let params = code_params(n_validators)?;
+
+	let mut received_shards: Vec<Option<&[u8]>> = vec![None; n_validators];
+	for (chunk, index) in chunks {
+		if index >= n_validators {
+			return Err(Error::ChunkIndexOutOfRange { index, n: n_validators });
+		}
+		received_shards[index] = Some(chunk);
+	}
+
+	// the novel poly basis might recover from fewer, but the recovery threshold is what callers rely on
+	let available = received_shards.iter().filter(|chunk| chunk.is_some()).count();
+	if available < params.k() {
+		return Err(Error::TooFewShards { available, required: params.k() });
+	}
+
+	let payload = novel_poly_basis::reconstruct_ref_with_params(&params, &received_shards)?;
+	T::decode(&mut &payload[..]).map_err(|_| Error::BadPayload)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn thresholds() {
+		assert!(recovery_threshold(0).is_err());
+		assert!(recovery_threshold(1).is_err());
+		assert_eq!(recovery_threshold(2), Ok(1));
+		assert_eq!(recovery_threshold(4), Ok(2));
+		assert_eq!(recovery_threshold(10), Ok(4));
+		assert_eq!(recovery_threshold(1000), Ok(334));
+		assert!(recovery_threshold(MAX_VALIDATORS + 1).is_err());
+	}
+
+	#[test]
+	fn round_trip_works() {
+		let data = random_payload(1000, 0);
+		let chunks = obtain_chunks(10, &data).unwrap();
+		assert_eq!(chunks.len(), 10);
+
+		// any 4 chunks suffice, here the last ones
+		let reconstructed: Vec<u8> =
+			reconstruct(10, chunks.iter().enumerate().skip(6).map(|(i, c)| (&c[..], i))).unwrap();
+		assert_eq!(reconstructed, data);
+	}
+
+	#[test]
+	fn rejects_bad_input() {
+		let chunks = obtain_chunks(10, &random_payload(100, 0)).unwrap();
+		assert_eq!(
+			reconstruct::<_, Vec<u8>>(10, chunks.iter().enumerate().map(|(i, c)| (&c[..], i + 1))),
+			Err(Error::ChunkIndexOutOfRange { index: 10, n: 10 }),
+		);
+		assert_eq!(
+			reconstruct::<_, Vec<u8>>(10, chunks.iter().enumerate().take(2).map(|(i, c)| (&c[..], i))),
+			Err(Error::TooFewShards { available: 2, required: 4 }),
+		);
+		// 1000 validators decode with 256 shards internally, the threshold is 334 nonetheless
+		let chunks = obtain_chunks(1000, &random_payload(100, 0)).unwrap();
+		assert_eq!(
+			reconstruct::<_, Vec<u8>>(1000, chunks.iter().enumerate().take(100).map(|(i, c)| (&c[..], i))),
+			Err(Error::TooFewShards { available: 100, required: 334 }),
+		);
+		// enough for the internal code, but still short of the threshold
+		assert_eq!(
+			reconstruct::<_, Vec<u8>>(1000, chunks.iter().enumerate().take(300).map(|(i, c)| (&c[..], i))),
+			Err(Error::TooFewShards { available: 300, required: 334 }),
+		);
+	}
+}
@@ -0,0 +1,442 @@
+//! Wall clock comparison of both backends that runs on stable, usable from
+//! the benches as well as programmatically, e.g. by the `rs-ec` tool.
+
+use super::*;
+use crate::auto::{Backend, Selector};
+
+#[cfg(feature = "bench")]
+use hdrhistogram::Histogram;
+#[cfg(feature = "bench")]
+use std::convert::TryFrom;
+use std::fmt::{self, Write};
+use std::time::{Duration, Instant};
+
+/// What is being measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scenario {
+	/// Encoding the payload into `n` shards.
+	Encode,
+	/// Reconstructing from exactly `k` shards, the last `n - k` are erased.
+	DecodeMinShards,
+	/// Reconstructing with all `n` shards available.
+	DecodeNoErasures,
+}
+
+impl Scenario {
+	pub const ALL: [Scenario; 3] = [Scenario::Encode, Scenario::DecodeMinShards, Scenario::DecodeNoErasures];
+}
+
+/// Timings of one backend in one scenario.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measurement {
+	pub backend: Backend,
+	pub scenario: Scenario,
+	pub n: usize,
+	pub k: usize,
+	pub payload_len: usize,
+	pub iterations: usize,
+	pub mean: Duration,
+	pub min: Duration,
+	/// Median latency of the iterations.
+	#[cfg(feature = "bench")]
+	pub p50: Duration,
+	/// Tail latencies of the iterations, which matter for availability recovery.
+	#[cfg(feature = "bench")]
+	pub p99: Duration,
+	/// Only with at least `P999_MIN_ITERATIONS`, below that it is just the slowest iteration.
+	#[cfg(feature = "bench")]
+	pub p999: Option<Duration>,
+}
+
+/// Iterations needed for a meaningful p99.9 latency.
+#[cfg(feature = "bench")]
+pub const P999_MIN_ITERATIONS: usize = 1000;
+
+impl Measurement {
+	/// Payload throughput at the mean time, in MiB/s.
+	pub fn throughput(&self) -> f64 {
+		mib_per_sec(self.payload_len, self.mean)
+	}
+
+	/// Payload throughput of the fastest iteration, in MiB/s, less affected by noise.
+	pub fn peak_throughput(&self) -> f64 {
+		mib_per_sec(self.payload_len, self.min)
+	}
+
+	fn to_json(&self) -> String {
+		let mut json = format!(
+			concat!(
+				r#"{{"backend":"{:?}","scenario":"{:?}","n":{},"k":{},"payload_len":{},"iterations":{},"#,
+				r#""mean_ns":{},"min_ns":{}"#
+			),
+			self.backend,
+			self.scenario,
+			self.n,
+			self.k,
+			self.payload_len,
+			self.iterations,
+			self.mean.as_nanos(),
+			self.min.as_nanos(),
+		);
+		#[cfg(feature = "bench")]
+		{
+			let p999 = self.p999.map_or("null".to_owned(), |p999| p999.as_nanos().to_string());
+			write!(json, r#","p50_ns":{},"p99_ns":{},"p999_ns":{}"#, self.p50.as_nanos(), self.p99.as_nanos(), p999)
+				.expect("writing to a string never fails. qed");
+		}
+		json.push('}');
+		json
+	}
+}
+
+fn mib_per_sec(len: usize, time: Duration) -> f64 {
+	len as f64 / (1 << 20) as f64 / time.as_secs_f64().max(f64::MIN_POSITIVE)
+}
+
+/// Runs each scenario `iterations` times after `warm_up` untimed runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Runner {
+	pub warm_up: usize,
+	pub iterations: usize,
+}
+
+impl Default for Runner {
+	fn default() -> Self {
+		Runner { warm_up: 1, iterations: 10 }
+	}
+}
+
+impl Runner {
+	pub fn new(warm_up: usize, iterations: usize) -> Self {
+		Runner { warm_up, iterations: std::cmp::max(iterations, 1) }
+	}
+
+	/// Measure `backend` in `scenario` with code `params` on `payload`.
+	pub fn measure(
+		&self,
+		backend: Backend,
+		scenario: Scenario,
+		params: &CodeParams,
+		payload: &[u8],
+	) -> Result<Measurement> {
+		let selector = Selector::fixed(backend);
+		let shards = selector.encode(params, payload)?;
+		let received = shards
+			.iter()
+			.enumerate()
+			.map(|(idx, shard)| match scenario {
+				Scenario::DecodeMinShards if idx >= params.k() => None,
+				_ => Some(shard.clone()),
+			})
+			.collect::<Vec<_>>();
+
+		let run = || -> Result<()> {
+			match scenario {
+				Scenario::Encode => selector.encode(params, payload).map(drop),
+				Scenario::DecodeMinShards | Scenario::DecodeNoErasures => {
+					selector.reconstruct(params, received.clone()).map(drop)
+				}
+			}
+		};
+		for _ in 0..self.warm_up {
+			run()?;
+		}
+		let mut total = Duration::ZERO;
+		let mut min = Duration::MAX;
+		// nanoseconds to 3 significant digits, iterations beyond an hour are recorded as an hour
+		#[cfg(feature = "bench")]
+		let mut latencies =
+			Histogram::<u64>::new_with_bounds(1, 3_600 * 1_000_000_000, 3).expect("bounds are valid. qed");
+		for _ in 0..self.iterations {
+			let start = Instant::now();
+			run()?;
+			let elapsed = start.elapsed();
+			total += elapsed;
+			min = min.min(elapsed);
+			#[cfg(feature = "bench")]
+			latencies.saturating_record(u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX));
+		}
+		#[cfg(feature = "bench")]
+		let percentile = |quantile| Duration::from_nanos(latencies.value_at_quantile(quantile));
+
+		Ok(Measurement {
+			backend,
+			scenario,
+			n: params.n(),
+			k: params.k(),
+			payload_len: payload.len(),
+			iterations: self.iterations,
+			mean: total / self.iterations as u32,
+			min,
+			#[cfg(feature = "bench")]
+			p50: percentile(0.5),
+			#[cfg(feature = "bench")]
+			p99: percentile(0.99),
+			#[cfg(feature = "bench")]
+			p999: Some(percentile(0.999)).filter(|_| self.iterations >= P999_MIN_ITERATIONS),
+		})
+	}
+
+	/// Measure both backends in all scenarios.
+	pub fn compare(&self, params: &CodeParams, payload: &[u8]) -> Result<Report> {
+		let mut measurements = Vec::new();
+		for &scenario in Scenario::ALL.iter() {
+			for &backend in [Backend::StatusQuo, Backend::NovelPolyBasis].iter() {
+				measurements.push(self.measure(backend, scenario, params, payload)?);
+			}
+		}
+		Ok(Report { measurements })
+	}
+}
+
+/// A collection of measurements, displayed as a table.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+	pub measurements: Vec<Measurement>,
+}
+
+impl Report {
+	/// JSON array with one object per measurement, times in nanoseconds.
+	pub fn to_json(&self) -> String {
+		let mut json = String::from("[");
+		for (i, m) in self.measurements.iter().enumerate() {
+			if i > 0 {
+				json.push(',');
+			}
+			json.push_str(&m.to_json());
+		}
+		json.push(']');
+		json
+	}
+}
+
+impl fmt::Display for Report {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mut line = String::new();
+		write!(
+			f,
+			"{:<16} {:<18} {:>6} {:>6} {:>10} {:>12} {:>12}",
+			"backend", "scenario", "n", "k", "bytes", "mean", "min"
+		)?;
+		#[cfg(feature = "bench")]
+		write!(f, " {:>12} {:>12} {:>12}", "p50", "p99", "p99.9")?;
+		writeln!(f, " {:>10}", "MiB/s")?;
+		for m in &self.measurements {
+			line.clear();
+			write!(
+				line,
+				"{:<16} {:<18} {:>6} {:>6} {:>10} {:>12?} {:>12?}",
+				format!("{:?}", m.backend),
+				format!("{:?}", m.scenario),
+				m.n,
+				m.k,
+				m.payload_len,
+				m.mean,
+				m.min,
+			)?;
+			#[cfg(feature = "bench")]
+			{
+				let p999 = m.p999.map_or("-".to_owned(), |p999| format!("{:?}", p999));
+				write!(line, " {:>12?} {:>12?} {:>12}", m.p50, m.p99, p999)?;
+			}
+			writeln!(f, "{} {:>10.1}", line, m.throughput())?;
+		}
+		Ok(())
+	}
+}
+
+/// Code parameters of the workload of `perf_regression_check`.
+pub const REGRESSION_N: usize = 128;
+pub const REGRESSION_K: usize = 43;
+/// Payload length of the workload of `perf_regression_check`.
+pub const REGRESSION_PAYLOAD_LEN: usize = 256 << 10;
+/// Relative slowdown tolerated by `perf_regression_check`, timings of short runs are noisy.
+pub const REGRESSION_TOLERANCE: f64 = 0.25;
+
+/// Expected peak throughput of a backend in a scenario, as a multiple of that of the calibration.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Baseline {
+	pub backend: Backend,
+	pub scenario: Scenario,
+	pub relative: f64,
+}
+
+/// Baselines of the regression workload, recorded with an optimized build on x86_64.
+/// Update them whenever a change is intended to make the codec faster.
+pub const BASELINES: [Baseline; 4] = [
+	Baseline { backend: Backend::StatusQuo, scenario: Scenario::Encode, relative: STATUS_QUO_ENCODE },
+	Baseline { backend: Backend::NovelPolyBasis, scenario: Scenario::Encode, relative: NOVEL_ENCODE },
+	Baseline { backend: Backend::StatusQuo, scenario: Scenario::DecodeMinShards, relative: STATUS_QUO_DECODE },
+	Baseline { backend: Backend::NovelPolyBasis, scenario: Scenario::DecodeMinShards, relative: NOVEL_DECODE },
+];
+
+/// One backend and scenario of the regression workload compared against its baseline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegressionEntry {
+	pub measurement: Measurement,
+	/// Expected peak throughput relative to the calibration.
+	pub expected: f64,
+	/// Measured peak throughput relative to the calibration.
+	pub relative: f64,
+	/// Slower than `expected` by more than the tolerance.
+	pub regressed: bool,
+}
+
+/// Outcome of `perf_regression_check`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegressionCheck {
+	/// Peak throughput of the calibration workload in MiB/s.
+	pub calibration: f64,
+	pub tolerance: f64,
+	pub entries: Vec<RegressionEntry>,
+}
+
+impl RegressionCheck {
+	/// No entry is slower than its baseline beyond the tolerance.
+	pub fn passed(&self) -> bool {
+		self.entries.iter().all(|entry| !entry.regressed)
+	}
+
+	pub fn regressions(&self) -> impl Iterator<Item = &RegressionEntry> {
+		self.entries.iter().filter(|entry| entry.regressed)
+	}
+}
+
+impl fmt::Display for RegressionCheck {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "calibration {:.1} MiB/s, tolerance {:.0}%", self.calibration, self.tolerance * 100.)?;
+		writeln!(f, "{:<16} {:<18} {:>10} {:>10} {:>10}", "backend", "scenario", "MiB/s", "relative", "expected")?;
+		for entry in &self.entries {
+			writeln!(
+				f,
+				"{:<16} {:<18} {:>10.1} {:>10.3} {:>10.3}{}",
+				format!("{:?}", entry.measurement.backend),
+				format!("{:?}", entry.measurement.scenario),
+				entry.measurement.peak_throughput(),
+				entry.relative,
+				entry.expected,
+				if entry.regressed { "  REGRESSED" } else { "" }
+			)?;
+		}
+		Ok(())
+	}
+}
+
+// The median `relative` column of repeated runs of the release build, regenerate with
+// `for i in $(seq 25); do cargo run -q --release --bin rs-ec -- check-perf; done`
+// after changes to the codecs or to the calibration workload.
+const STATUS_QUO_ENCODE: f64 = 0.016;
+const NOVEL_ENCODE: f64 = 0.139;
+const STATUS_QUO_DECODE: f64 = 0.308;
+const NOVEL_DECODE: f64 = 0.851;
+
+// A codec independent chain of dependent table lookups and xors, resembling the field
+// arithmetic, whose throughput in MiB/s is the yardstick for the speed of the machine.
+fn calibration_throughput(runner: &Runner, payload: &[u8]) -> f64 {
+	let table = (0..=u16::MAX).map(|x| x.wrapping_mul(40503).rotate_left(5)).collect::<Vec<u16>>();
+	let run = || {
+		let mut acc = 0u16;
+		for pair in payload.chunks_exact(2) {
+			acc = table[usize::from(acc ^ u16::from_le_bytes([pair[0], pair[1]]))];
+		}
+		std::hint::black_box(acc);
+	};
+	for _ in 0..runner.warm_up {
+		run();
+	}
+	let mut min = Duration::MAX;
+	for _ in 0..runner.iterations {
+		let start = Instant::now();
+		run();
+		min = min.min(start.elapsed());
+	}
+	mib_per_sec(payload.len(), min)
+}
+
+/// Run a short calibrated workload and compare it against the stored `BASELINES`,
+/// to catch changes that slowed down the codec. The baselines hold for optimized builds.
+pub fn perf_regression_check() -> Result<RegressionCheck> {
+	perf_regression_check_against(&BASELINES, REGRESSION_TOLERANCE)
+}
+
+/// Like `perf_regression_check`, against the given baselines, flagging every entry slower
+/// than `1 - tolerance` times its baseline. Throughputs are taken relative to a calibration
+/// workload, so the baselines carry over between machines to some degree.
+pub fn perf_regression_check_against(baselines: &[Baseline], tolerance: f64) -> Result<RegressionCheck> {
+	let params = CodeParams::new(REGRESSION_N, REGRESSION_K)?;
+	let payload = &workload::random_payload(REGRESSION_PAYLOAD_LEN, 0);
+	let runner = Runner::new(1, 10);
+
+	let calibration = calibration_throughput(&runner, payload);
+	let entries = baselines
+		.iter()
+		.map(|baseline| {
+			let measurement = runner.measure(baseline.backend, baseline.scenario, &params, payload)?;
+			let relative = measurement.peak_throughput() / calibration;
+			let regressed = relative < baseline.relative * (1. - tolerance);
+			Ok(RegressionEntry { measurement, expected: baseline.relative, relative, regressed })
+		})
+		.collect::<Result<Vec<_>>>()?;
+	Ok(RegressionCheck { calibration, tolerance, entries })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn compare_reports_all_scenarios() {
+		let params = CodeParams::new(16, 4).unwrap();
+		let report = Runner::new(0, 2).compare(&params, &random_payload(1000, 0)).unwrap();
+		assert_eq!(report.measurements.len(), 6);
+		assert!(report.measurements.iter().all(|m| m.iterations == 2 && m.min <= m.mean));
+		#[cfg(feature = "bench")]
+		assert!(report.measurements.iter().all(|m| m.min <= m.p50 && m.p50 <= m.p99 && m.p999.is_none()));
+		assert_eq!(report.to_string().lines().count(), 7);
+
+		let json = report.to_json();
+		let parsed: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+		assert_eq!(parsed.len(), 6);
+		assert_eq!(parsed[1]["backend"], "NovelPolyBasis");
+		assert_eq!(parsed[1]["scenario"], "Encode");
+		assert_eq!(parsed[5]["scenario"], "DecodeNoErasures");
+		assert_eq!(parsed[5]["payload_len"], 1000);
+		#[cfg(feature = "bench")]
+		{
+			assert!(parsed[5]["p99_ns"].as_u64().unwrap() >= parsed[5]["p50_ns"].as_u64().unwrap());
+			assert!(parsed[5]["p999_ns"].is_null());
+		}
+	}
+
+	#[cfg(feature = "bench")]
+	#[test]
+	fn p999_needs_enough_iterations() {
+		let params = CodeParams::new(16, 4).unwrap();
+		let payload = &random_payload(64, 0);
+		let runner = Runner::new(0, P999_MIN_ITERATIONS);
+		let m = runner.measure(Backend::NovelPolyBasis, Scenario::Encode, &params, payload).unwrap();
+		assert!(m.p99 <= m.p999.unwrap());
+	}
+
+	#[test]
+	fn regression_check_flags_slowdowns() {
+		// status quo encoding is slow in debug builds, so it is left out
+		let baseline = |relative| {
+			[
+				Baseline { backend: Backend::NovelPolyBasis, scenario: Scenario::Encode, relative },
+				Baseline { backend: Backend::StatusQuo, scenario: Scenario::DecodeMinShards, relative },
+			]
+		};
+
+		let check = perf_regression_check_against(&baseline(0.), REGRESSION_TOLERANCE).unwrap();
+		assert!(check.calibration > 0.);
+		assert_eq!(check.entries.len(), 2);
+		assert!(check.passed());
+		assert!(check.entries.iter().all(|entry| entry.relative > 0. && entry.measurement.n == REGRESSION_N));
+		assert_eq!(check.to_string().lines().count(), 4);
+
+		let check = perf_regression_check_against(&baseline(f64::MAX), REGRESSION_TOLERANCE).unwrap();
+		assert!(!check.passed());
+		assert_eq!(check.regressions().count(), 2);
+	}
+}
@@ -0,0 +1,348 @@
+//! Encode files into shard directories and reassemble them.
+//!
+//! ```text
+//! rs-ec encode [--n 1024] [--k 342] [--backend novel-f2e16|ntt-fp31|status-quo] [--out shard_dir] file
+//! rs-ec reconstruct [--out file] shard_dir
+//! rs-ec sweep [--n 64,256,1024] [--rates 0.25,0.34,0.5] [--iterations 3] file
+//! rs-ec bench [--n 1024] [--k 342] [--iterations 10] [--json out.json] file
+//! rs-ec check-perf [--tolerance 0.25]
+//! rs-ec verify [--n 1024] [--k 342] [--iterations 3] [--erase random|first|last|0,5-9,..] [--seed 0] file
+//! rs-ec corpus [--len 5242880] [--cache dir] [--url url --sha256 digest] random|zeros|text|pov|name file
+//! ```
+//!
+//! The backend is looked up by name in the default `Registry`, `novel` is
+//! accepted for `novel-f2e16`, as written by earlier versions.
+//!
+//! Each shard is written to `shard_dir/<index>.shard`, alongside a `manifest`
+//! recording the code parameters and the payload length. Deleting shard files
+//! simulates erasures. Shards of `novel-f2e16` are reconstructed by streaming
+//! just enough of the shard files, instead of loading all of them.
+//!
+//! `sweep` times both backends on the given file for every `n` and rate
+//! `k / n`, printing CSV to stdout. `bench` compares both backends for
+//! encode and decode with and without erasures, optionally saving JSON.
+//! `check-perf` compares a short workload against the stored baselines and
+//! fails if the codec became slower, for use in CI. `verify` encodes the file
+//! with every registered backend, erases the same shards of each, by default a
+//! random `n - k` of them per iteration, and reconstructs. It prints the digest
+//! of each reconstruction and the time taken, failing if any backend did not
+//! recover the file, as an acceptance test of the backends. `corpus` writes one of the
+//! standard benchmark payloads to a file and prints its SHA-256 digest, so runs
+//! on different machines are comparable. With `--url`, the payload `name` is
+//! downloaded with `curl` instead, and must match the hex `--sha256` digest.
+
+use rand::SeedableRng;
+use rs_ec_perf::auto::Backend;
+use rs_ec_perf::benchmark::{self, Runner};
+use rs_ec_perf::corpus::{self, Corpus, Source};
+use rs_ec_perf::registry::{ErasureCoder, Registry};
+use rs_ec_perf::{recovery_threshold, stream, CodeParams, RoundtripReport, WrappedShard};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+type CliResult<T> = std::result::Result<T, Box<dyn Error>>;
+
+const USAGE: &str = "usage:
+	rs-ec encode [--n N] [--k K] [--backend novel-f2e16|ntt-fp31|status-quo] [--out SHARD_DIR] FILE
+	rs-ec reconstruct [--out FILE] SHARD_DIR
+	rs-ec sweep [--n N,..] [--rates RATE,..] [--iterations I] FILE
+	rs-ec bench [--n N] [--k K] [--iterations I] [--json JSON_FILE] FILE
+	rs-ec check-perf [--tolerance T]
+	rs-ec verify [--n N] [--k K] [--iterations I] [--erase random|first|last|IDX[-IDX],..] [--seed S] FILE
+	rs-ec corpus [--len LEN] [--cache DIR] [--url URL --sha256 DIGEST] random|zeros|text|pov|NAME FILE";
+
+const MANIFEST: &str = "manifest";
+
+// the flags given as `--name value` and the remaining positional arguments
+fn parse_args(mut args: impl Iterator<Item = String>) -> CliResult<(HashMap<String, String>, Vec<String>)> {
+	let mut flags = HashMap::new();
+	let mut positional = Vec::new();
+	while let Some(arg) = args.next() {
+		match arg.strip_prefix("--") {
+			Some(name) => {
+				let value = args.next().ok_or_else(|| format!("missing value for --{}", name))?;
+				flags.insert(name.to_owned(), value);
+			}
+			None => positional.push(arg),
+		}
+	}
+	Ok((flags, positional))
+}
+
+fn lookup_backend<'a>(registry: &'a Registry, name: &str) -> CliResult<&'a dyn ErasureCoder> {
+	let name = if name == "novel" { "novel-f2e16" } else { name };
+	registry.get(name).ok_or_else(|| {
+		format!("unknown backend {}, expected one of {}", name, registry.names().collect::<Vec<_>>().join(", ")).into()
+	})
+}
+
+fn shard_path(dir: &Path, idx: usize) -> PathBuf {
+	dir.join(format!("{:05}.shard", idx))
+}
+
+// `--n` and `--k`, `k` defaults to the recovery threshold of `n` validators
+fn code_params(flags: &HashMap<String, String>) -> CliResult<CodeParams> {
+	let n = flags.get("n").map(|n| n.parse()).transpose()?.unwrap_or(1024);
+	let k = match flags.get("k") {
+		Some(k) => k.parse()?,
+		None => recovery_threshold(n)?,
+	};
+	Ok(CodeParams::new(n, k)?)
+}
+
+fn encode(flags: &HashMap<String, String>, file: &str) -> CliResult<()> {
+	let params = code_params(flags)?;
+	let registry = Registry::default();
+	let backend = lookup_backend(&registry, flags.get("backend").map(String::as_str).unwrap_or("novel-f2e16"))?;
+	let out = flags.get("out").map(PathBuf::from).unwrap_or_else(|| PathBuf::from(format!("{}.shards", file)));
+
+	let payload = fs::read(file)?;
+	let shards = backend.encode(&params, &payload)?;
+
+	fs::create_dir_all(&out)?;
+	for (idx, shard) in shards.iter().enumerate() {
+		fs::write(shard_path(&out, idx), AsRef::<[u8]>::as_ref(shard))?;
+	}
+	let manifest = format!("backend={}\nn={}\nk={}\nlen={}\n", backend.name(), params.n(), params.k(), payload.len());
+	fs::write(out.join(MANIFEST), manifest)?;
+
+	eprintln!(
+		"wrote {} shards of {} bytes to {}",
+		shards.len(),
+		AsRef::<[u8]>::as_ref(&shards[0]).len(),
+		out.display()
+	);
+	Ok(())
+}
+
+fn reconstruct(flags: &HashMap<String, String>, dir: &str) -> CliResult<()> {
+	let dir = Path::new(dir);
+	let manifest = fs::read_to_string(dir.join(MANIFEST))?;
+	let manifest = manifest.lines().filter_map(|line| line.split_once('=')).collect::<HashMap<&str, &str>>();
+	let field = |name: &str| manifest.get(name).copied().ok_or_else(|| format!("manifest lacks {}", name));
+
+	let registry = Registry::default();
+	let backend = lookup_backend(&registry, field("backend")?)?;
+	let params = CodeParams::new(field("n")?.parse()?, field("k")?.parse()?)?;
+	let len: usize = field("len")?.parse()?;
+
+	let mut out: Box<dyn Write> = match flags.get("out") {
+		Some(out) => Box::new(std::io::BufWriter::new(fs::File::create(out)?)),
+		None => Box::new(std::io::stdout().lock()),
+	};
+	if backend.name() == "novel-f2e16" {
+		// only as many shard files as needed are opened, and read in stripes
+		let files = (0..params.n()).filter_map(|idx| Some((idx, fs::File::open(shard_path(dir, idx)).ok()?)));
+		let written = stream::reconstruct_from_files_into(&params, files, &mut out, Some(len))?;
+		eprintln!("reconstructed {} bytes streaming the shard files", written);
+		return Ok(());
+	}
+
+	let received = (0..params.n())
+		.map(|idx| fs::read(shard_path(dir, idx)).ok().map(WrappedShard::new))
+		.collect::<Vec<Option<WrappedShard>>>();
+	let available = received.iter().flatten().count();
+	let mut payload = backend.reconstruct(&params, received)?;
+	payload.truncate(len);
+
+	out.write_all(&payload)?;
+	out.flush()?;
+	eprintln!("reconstructed {} bytes from {} of {} shards", len, available, params.n());
+	Ok(())
+}
+
+// comma separated list of values
+fn parse_list<T: std::str::FromStr>(list: &str) -> CliResult<Vec<T>>
+where
+	T::Err: Error + 'static,
+{
+	list.split(',').map(|item| item.trim().parse::<T>().map_err(Into::into)).collect()
+}
+
+fn sweep(flags: &HashMap<String, String>, file: &str) -> CliResult<()> {
+	let ns = parse_list(flags.get("n").map(String::as_str).unwrap_or("64,256,1024"))?;
+	let rates = parse_list(flags.get("rates").map(String::as_str).unwrap_or("0.25,0.34,0.5"))?;
+	let iterations = flags.get("iterations").map(|i| i.parse()).transpose()?.unwrap_or(3);
+
+	let payload = fs::read(file)?;
+	let grid = rs_ec_perf::sweep::grid(&ns, &rates);
+	let mut rows = Vec::new();
+	for backend in [Backend::StatusQuo, Backend::NovelPolyBasis] {
+		rows.extend(rs_ec_perf::sweep::sweep(backend, &payload, &grid, iterations)?);
+	}
+	rs_ec_perf::sweep::write_csv(std::io::stdout().lock(), &rows)?;
+	Ok(())
+}
+
+fn bench(flags: &HashMap<String, String>, file: &str) -> CliResult<()> {
+	let params = code_params(flags)?;
+	let iterations = flags.get("iterations").map(|i| i.parse()).transpose()?.unwrap_or(10);
+
+	let payload = fs::read(file)?;
+	let report = Runner::new(1, iterations).compare(&params, &payload)?;
+	print!("{}", report);
+	if let Some(json) = flags.get("json") {
+		fs::write(json, report.to_json())?;
+	}
+	Ok(())
+}
+
+fn check_perf(flags: &HashMap<String, String>) -> CliResult<()> {
+	let tolerance = flags.get("tolerance").map(|t| t.parse()).transpose()?.unwrap_or(benchmark::REGRESSION_TOLERANCE);
+	let check = benchmark::perf_regression_check_against(&benchmark::BASELINES, tolerance)?;
+	print!("{}", check);
+	if !check.passed() {
+		return Err(format!("{} of {} baselines regressed", check.regressions().count(), check.entries.len()).into());
+	}
+	Ok(())
+}
+
+// leading hex digits of the SHA-256 digest of `bytes`
+fn short_digest(bytes: &[u8]) -> String {
+	corpus::digest(bytes)[..8].iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// the shards erased by `pattern`: a random `n - k` of them from `seed`, the first or the
+// last `n - k`, or a comma separated list of indices and inclusive ranges of indices
+fn erasure_pattern(pattern: &str, params: &CodeParams, seed: u64) -> CliResult<Vec<usize>> {
+	let (n, erasures) = (params.n(), params.n() - params.k());
+	let erased = match pattern {
+		"random" => {
+			let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+			let mut erased = rand::seq::index::sample(&mut rng, n, erasures).into_vec();
+			erased.sort_unstable();
+			erased
+		}
+		"first" => (0..erasures).collect(),
+		"last" => (n - erasures..n).collect(),
+		list => {
+			let mut erased = Vec::new();
+			for item in list.split(',') {
+				let (start, end) = item.trim().split_once('-').unwrap_or((item.trim(), item.trim()));
+				erased.extend(start.parse::<usize>()?..=end.parse::<usize>()?);
+			}
+			erased
+		}
+	};
+	if let Some(idx) = erased.iter().find(|&&idx| idx >= n) {
+		return Err(format!("erased shard {} out of range for {} shards", idx, n).into());
+	}
+	Ok(erased)
+}
+
+fn verify(flags: &HashMap<String, String>, file: &str) -> CliResult<()> {
+	let params = code_params(flags)?;
+	let iterations = flags.get("iterations").map(|i| i.parse()).transpose()?.unwrap_or(3);
+	let pattern = flags.get("erase").map(String::as_str).unwrap_or("random");
+	let seed: u64 = flags.get("seed").map(|s| s.parse()).transpose()?.unwrap_or(0);
+
+	let payload = fs::read(file)?;
+	// every backend loses the same shards
+	let patterns = (0..iterations)
+		.map(|iteration| erasure_pattern(pattern, &params, seed.wrapping_add(iteration as u64)))
+		.collect::<CliResult<Vec<_>>>()?;
+	let registry = Registry::default();
+	println!("{} bytes, sha256 {}, n {}, k {}", payload.len(), short_digest(&payload), params.n(), params.k());
+	println!(
+		"{:<12} {:>9} {:>7} {:>12} {:>12} {:>16}  result",
+		"backend", "iteration", "erased", "encode", "reconstruct", "sha256"
+	);
+	let mut failed = 0;
+	for (iteration, erased) in patterns.iter().enumerate() {
+		for name in registry.names() {
+			let coder = lookup_backend(&registry, name)?;
+			let start = Instant::now();
+			let encoded = coder.encode(&params, &payload);
+			let encode_time = start.elapsed();
+			let start = Instant::now();
+			let reconstructed = encoded.and_then(|shards| {
+				let mut received = shards.into_iter().map(Some).collect::<Vec<_>>();
+				for &idx in erased {
+					received[idx] = None;
+				}
+				coder.reconstruct(&params, received)
+			});
+			let reconstruct_time = start.elapsed();
+
+			let (digest, result) = match reconstructed {
+				Ok(mut reconstructed) => {
+					reconstructed.truncate(payload.len());
+					let report = RoundtripReport::compare(&payload, &reconstructed);
+					(short_digest(&reconstructed), report.to_string())
+				}
+				Err(e) => ("-".to_owned(), e.to_string()),
+			};
+			let recovered = digest == short_digest(&payload);
+			failed += usize::from(!recovered);
+			println!(
+				"{:<12} {:>9} {:>7} {:>12?} {:>12?} {:>16}  {}{}",
+				name,
+				iteration,
+				erased.len(),
+				encode_time,
+				reconstruct_time,
+				digest,
+				if recovered { "" } else { "FAILED: " },
+				result
+			);
+		}
+	}
+	if failed > 0 {
+		return Err(format!("{} of {} roundtrips failed", failed, registry.names().count() * iterations).into());
+	}
+	Ok(())
+}
+
+// the body of `url` via `curl`, failing on any error status
+fn fetch(url: &str) -> rs_ec_perf::Result<Vec<u8>> {
+	let output = std::process::Command::new("curl")
+		.args(["--fail", "--silent", "--show-error", "--location", "--", url])
+		.output()?;
+	if !output.status.success() {
+		return Err(rs_ec_perf::Error::DownloadFailed);
+	}
+	Ok(output.stdout)
+}
+
+fn corpus(flags: &HashMap<String, String>, name: &str, file: &str) -> CliResult<()> {
+	let len = flags.get("len").map(|len| len.parse()).transpose()?.unwrap_or(5 << 20);
+	let mut corpus = match flags.get("cache") {
+		Some(dir) => Corpus::default().with_cache_dir(dir),
+		None => Corpus::default(),
+	}
+	.with_fetcher(fetch);
+	if let Some(url) = flags.get("url") {
+		let sha256 = corpus::parse_hex(flags.get("sha256").ok_or("--url needs the --sha256 digest of the download")?)?;
+		corpus.register(name, Source::Download { url: url.clone(), sha256 });
+	}
+	let payload = corpus.load(name, len)?;
+	fs::write(file, &payload)?;
+	println!("{}  {}", corpus::hex(&corpus::digest(&payload)), file);
+	Ok(())
+}
+
+fn main() {
+	let run = || -> CliResult<()> {
+		let mut args = std::env::args().skip(1);
+		let command = args.next().ok_or(USAGE)?;
+		let (flags, positional) = parse_args(args)?;
+		match (command.as_str(), positional.as_slice()) {
+			("encode", [file]) => encode(&flags, file),
+			("reconstruct", [dir]) => reconstruct(&flags, dir),
+			("sweep", [file]) => sweep(&flags, file),
+			("bench", [file]) => bench(&flags, file),
+			("check-perf", []) => check_perf(&flags),
+			("verify", [file]) => verify(&flags, file),
+			("corpus", [name, file]) => corpus(&flags, name, file),
+			_ => Err(USAGE.into()),
+		}
+	};
+	if let Err(e) = run() {
+		eprintln!("{}", e);
+		std::process::exit(1);
+	}
+}
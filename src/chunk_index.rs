@@ -0,0 +1,378 @@
+use super::*;
+use crate::registry::ErasureCoder;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "codec")]
+use codec::{Decode, Encode};
+
+/// Index of a shard within the encoding, which equals the index of the
+/// validator it is assigned to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "codec", derive(Encode, Decode))]
+pub struct ChunkIndex(pub u32);
+
+impl ChunkIndex {
+	/// The position within a `Vec<Option<WrappedShard>>` of all shards.
+	pub fn position(self) -> usize {
+		self.0 as usize
+	}
+}
+
+impl From<u32> for ChunkIndex {
+	fn from(index: u32) -> Self {
+		ChunkIndex(index)
+	}
+}
+
+/// Position of a shard of a systematic code, telling the data shards, which hold the payload
+/// as is, from the parity shards following them. The split depends on the backend, see
+/// `ErasureCoder::data_shards`, so parity shards are not mistaken for data in fast paths.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "codec", derive(Encode, Decode))]
+pub enum ShardIndex {
+	/// The `i`th data shard, at position `i`.
+	Data(u32),
+	/// The `i`th parity shard, at position `data_shards + i`.
+	Parity(u32),
+}
+
+impl ShardIndex {
+	/// Classify `index` within a code of `data_shards` data shards.
+	pub fn new(index: ChunkIndex, data_shards: usize) -> Self {
+		match index.0.checked_sub(data_shards as u32) {
+			Some(parity) => ShardIndex::Parity(parity),
+			None => ShardIndex::Data(index.0),
+		}
+	}
+
+	/// The position within all shards of a code of `data_shards` data shards.
+	pub fn chunk_index(self, data_shards: usize) -> ChunkIndex {
+		match self {
+			ShardIndex::Data(index) => ChunkIndex(index),
+			ShardIndex::Parity(index) => ChunkIndex(data_shards as u32 + index),
+		}
+	}
+
+	pub fn is_data(self) -> bool {
+		matches!(self, ShardIndex::Data(_))
+	}
+}
+
+/// Encode with `coder`, tagging each shard with its typed index.
+pub fn encode_typed<C: ErasureCoder + ?Sized>(
+	coder: &C,
+	params: &CodeParams,
+	data: &[u8],
+) -> Result<Vec<(ShardIndex, WrappedShard)>> {
+	let data_shards = coder.data_shards(params)?;
+	let shards = coder.encode(params, data)?.into_iter().enumerate();
+	Ok(shards.map(|(idx, shard)| (ShardIndex::new(ChunkIndex(idx as u32), data_shards), shard)).collect())
+}
+
+/// Reconstruct with `coder` from all of its data shards, in any order, which systematic
+/// backends copy instead of decoding. Parity shards are rejected with `ParityShardAsData`.
+pub fn reconstruct_from_data<C: ErasureCoder + ?Sized>(
+	coder: &C,
+	params: &CodeParams,
+	data_shards: impl IntoIterator<Item = (ShardIndex, WrappedShard)>,
+) -> Result<Vec<u8>> {
+	let count = coder.data_shards(params)?;
+	let mut arranged = vec![None; params.n()];
+	for (index, shard) in data_shards {
+		let idx = index.chunk_index(count).position();
+		if !index.is_data() {
+			return Err(Error::ParityShardAsData(idx));
+		}
+		if idx >= count {
+			return Err(Error::ChunkIndexOutOfRange { index: idx, n: count });
+		}
+		if arranged[idx].replace(shard).is_some() {
+			return Err(Error::DuplicateChunkIndex(idx));
+		}
+	}
+	if let Some(missing) = arranged[..count].iter().position(Option::is_none) {
+		return Err(Error::MissingChunk(missing));
+	}
+	coder.reconstruct(params, arranged)
+}
+
+/// A shard carrying its index explicitly, so it can be passed around in any order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "codec", derive(Encode, Decode))]
+pub struct IndexedShard {
+	pub index: ChunkIndex,
+	pub shard: WrappedShard,
+}
+
+/// Tag each of the shards of an encoding with its index.
+pub fn index_shards(shards: Vec<WrappedShard>) -> Vec<IndexedShard> {
+	shards.into_iter().enumerate().map(|(idx, shard)| IndexedShard { index: ChunkIndex(idx as u32), shard }).collect()
+}
+
+/// How `arrange_shards_with` treats different shards given for the same index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictResolution {
+	/// Fail with `Error::ConflictingShards`.
+	Reject,
+	/// Reconstruct from the uncontested shards with `novel_poly_basis::reconstruct_checked`
+	/// and keep the candidate matching the re-encoded shard, if any, so only applies to
+	/// shards of the novel poly basis backend.
+	Check,
+}
+
+/// Put `shards`, given in any order, at their positions among `n` shards,
+/// the positions of missing shards are `None`.
+///
+/// Identical copies of a shard are dropped, different shards for the same index are
+/// rejected, see `arrange_shards_with` to resolve them instead.
+pub fn arrange_shards(n: usize, shards: impl IntoIterator<Item = IndexedShard>) -> Result<Vec<Option<WrappedShard>>> {
+	let (arranged, _) = arrange(n, shards, ConflictResolution::Reject)?;
+	Ok(arranged)
+}
+
+/// Same as `arrange_shards`, resolving different shards for the same index as `resolution`
+/// demands, contested indices without a consistent candidate are erased.
+pub fn arrange_shards_with(
+	params: &CodeParams,
+	shards: impl IntoIterator<Item = IndexedShard>,
+	resolution: ConflictResolution,
+) -> Result<Vec<Option<WrappedShard>>> {
+	let (mut arranged, mut contested) = arrange(params.n(), shards, resolution)?;
+	if contested.is_empty() {
+		return Ok(arranged);
+	}
+
+	// the first received candidates are just as suspect as the later ones
+	contested.sort_by_key(|candidate| candidate.index);
+	let mut first = contested.iter().map(|candidate| candidate.index.position()).collect::<Vec<_>>();
+	first.dedup();
+	for idx in first {
+		if let Some(shard) = arranged[idx].take() {
+			contested.push(IndexedShard { index: ChunkIndex(idx as u32), shard });
+		}
+	}
+
+	let received = arranged.iter().map(|shard| shard.as_ref().map(AsRef::as_ref)).collect::<Vec<Option<&[u8]>>>();
+	let payload = novel_poly_basis::reconstruct_checked(params, &received).map_err(|err| match err {
+		Error::TooFewShards { .. } => Error::ConflictingShards(contested[0].index.position()),
+		err => err,
+	})?;
+	let expected = novel_poly_basis::encode_with_params(params, &payload)?;
+	for IndexedShard { index, shard } in contested {
+		if shard == expected[index.position()] {
+			arranged[index.position()] = Some(shard);
+		}
+	}
+	Ok(arranged)
+}
+
+// place `shards`, returning those different from the one already placed at their index
+fn arrange(
+	n: usize,
+	shards: impl IntoIterator<Item = IndexedShard>,
+	resolution: ConflictResolution,
+) -> Result<(Vec<Option<WrappedShard>>, Vec<IndexedShard>)> {
+	let mut arranged = vec![None; n];
+	let mut contested = Vec::new();
+	for IndexedShard { index, shard } in shards {
+		let slot =
+			arranged.get_mut(index.position()).ok_or(Error::ChunkIndexOutOfRange { index: index.position(), n })?;
+		match slot {
+			None => *slot = Some(shard),
+			Some(present) if *present == shard => {}
+			Some(_) if resolution == ConflictResolution::Check => contested.push(IndexedShard { index, shard }),
+			Some(_) => return Err(Error::ConflictingShards(index.position())),
+		}
+	}
+	Ok((arranged, contested))
+}
+
+/// Keep only the shards at the `trusted` indices, all others are treated as erased,
+/// i.e. to prefer shards with valid signatures or for reproducible reconstruction.
+pub fn select_subset<T>(received_shards: Vec<Option<T>>, trusted: &[ChunkIndex]) -> Result<Vec<Option<T>>> {
+	let n = received_shards.len();
+	let mut received_shards = received_shards;
+	let mut selected = (0..n).map(|_| None).collect::<Vec<Option<T>>>();
+	for index in trusted {
+		let idx = index.position();
+		if idx >= n {
+			return Err(Error::ChunkIndexOutOfRange { index: idx, n });
+		}
+		if selected[idx].is_some() {
+			return Err(Error::DuplicateChunkIndex(idx));
+		}
+		selected[idx] = Some(received_shards[idx].take().ok_or(Error::MissingChunk(idx))?);
+	}
+	Ok(selected)
+}
+
+/// An available shard with the cost of fetching it, e.g. the expected latency.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShardHint {
+	pub index: ChunkIndex,
+	pub cost: u64,
+}
+
+/// Choose `params.k() + margin` of the available shards to fetch, as few if fewer are available.
+///
+/// Both backends skip decoding entirely if all data shards are at hand, so those are
+/// chosen if available, topped up by the cheapest parity shards for the margin. Once a
+/// single data shard is missing the full decode runs, whichever shards are used, so the
+/// cheapest shards are chosen, ties broken towards lower and thus contiguous indices.
+/// Returns the indices in increasing order, to be passed on to `select_subset`.
+pub fn select_for_decode(params: &CodeParams, hints: &[ShardHint], margin: usize) -> Result<Vec<ChunkIndex>> {
+	let (n, k) = (params.n(), params.k());
+	let mut seen = vec![false; n];
+	for hint in hints {
+		let idx = hint.index.position();
+		if idx >= n {
+			return Err(Error::ChunkIndexOutOfRange { index: idx, n });
+		}
+		if std::mem::replace(&mut seen[idx], true) {
+			return Err(Error::DuplicateChunkIndex(idx));
+		}
+	}
+	if hints.len() < k {
+		return Err(Error::TooFewShards { available: hints.len(), required: k });
+	}
+
+	let data_complete = seen[..k].iter().all(|seen| *seen);
+	let mut ranked = hints.to_vec();
+	ranked.sort_by_key(|hint| (data_complete && hint.index.position() >= k, hint.cost, hint.index));
+	let mut selected = ranked.into_iter().take(k + margin).map(|hint| hint.index).collect::<Vec<_>>();
+	selected.sort();
+	Ok(selected)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn reconstruct_from_shuffled_shards() {
+		use rand::seq::SliceRandom;
+
+		let params = CodeParams::new(12, 4).unwrap();
+		let payload = &random_payload(1000, 0);
+		for backend in [auto::Backend::StatusQuo, auto::Backend::NovelPolyBasis] {
+			let selector = auto::Selector::fixed(backend);
+			let mut shards = index_shards(selector.encode(&params, payload).unwrap());
+			shards.shuffle(&mut rand::thread_rng());
+			shards.truncate(params.k());
+
+			let arranged = arrange_shards(params.n(), shards).unwrap();
+			assert_eq!(&selector.reconstruct(&params, arranged).unwrap()[..payload.len()], payload);
+		}
+	}
+
+	#[test]
+	fn typed_indices_separate_data_and_parity() {
+		let index = ShardIndex::new(ChunkIndex(5), 4);
+		assert_eq!(index, ShardIndex::Parity(1));
+		assert_eq!(index.chunk_index(4), ChunkIndex(5));
+		assert!(ShardIndex::new(ChunkIndex(3), 4).is_data());
+
+		// the novel poly basis has 4 data shards for `k = 5`, the status quo 5
+		let params = CodeParams::new(12, 5).unwrap();
+		let payload = &random_payload(1000, 0);
+		for (backend, data_shards) in [(auto::Backend::NovelPolyBasis, 4), (auto::Backend::StatusQuo, 5)] {
+			let shards = encode_typed(&backend, &params, payload).unwrap();
+			assert_eq!(shards.iter().filter(|(index, _)| index.is_data()).count(), data_shards);
+			let data = shards.iter().filter(|(index, _)| index.is_data()).cloned().rev();
+			assert_eq!(&reconstruct_from_data(&backend, &params, data).unwrap()[..payload.len()], payload);
+
+			let mixed = shards.iter().skip(1).take(data_shards).cloned();
+			assert_eq!(reconstruct_from_data(&backend, &params, mixed), Err(Error::ParityShardAsData(data_shards)));
+			let partial = shards.iter().skip(1).take(data_shards - 1).cloned();
+			assert_eq!(reconstruct_from_data(&backend, &params, partial), Err(Error::MissingChunk(0)));
+		}
+	}
+
+	#[test]
+	fn rejects_bad_indices() {
+		let shard = || WrappedShard::new(vec![1, 2]);
+		let out_of_range = vec![IndexedShard { index: ChunkIndex(5), shard: shard() }];
+		assert_eq!(arrange_shards(5, out_of_range), Err(Error::ChunkIndexOutOfRange { index: 5, n: 5 }));
+
+		let duplicate = vec![
+			IndexedShard { index: ChunkIndex(1), shard: shard() },
+			IndexedShard { index: ChunkIndex(1), shard: shard() },
+		];
+		assert_eq!(arrange_shards(5, duplicate).unwrap().iter().flatten().count(), 1);
+
+		let conflicting = vec![
+			IndexedShard { index: ChunkIndex(1), shard: shard() },
+			IndexedShard { index: ChunkIndex(1), shard: WrappedShard::new(vec![2, 1]) },
+		];
+		assert_eq!(arrange_shards(5, conflicting), Err(Error::ConflictingShards(1)));
+	}
+
+	#[test]
+	fn conflicts_are_resolved_by_checking() {
+		let params = CodeParams::new(12, 4).unwrap();
+		let payload = &random_payload(1000, 0);
+		let genuine = index_shards(novel_poly_basis::encode_with_params(&params, payload).unwrap());
+		let forge = |idx: usize, at: usize| {
+			let mut forged = genuine[idx].clone();
+			AsMut::<[u8]>::as_mut(&mut forged.shard)[at] ^= 1;
+			forged
+		};
+		// forgeries received before the genuine shards at 2 and 5, and two different ones at 6
+		let mut shards = vec![forge(2, 0), forge(5, 0), forge(6, 0), forge(6, 1)];
+		shards.extend(genuine.into_iter().take(8).filter(|shard| shard.index != ChunkIndex(6)));
+
+		assert_eq!(arrange_shards(params.n(), shards.clone()), Err(Error::ConflictingShards(6)));
+		let arranged = arrange_shards_with(&params, shards, ConflictResolution::Check).unwrap();
+		assert_eq!(arranged.iter().flatten().count(), 7);
+		assert_eq!(&novel_poly_basis::reconstruct_with_params(&params, arranged).unwrap()[..payload.len()], payload);
+	}
+
+	#[test]
+	fn subset_ignores_other_shards() {
+		let params = CodeParams::new(12, 4).unwrap();
+		let payload = &random_payload(1000, 0);
+		let mut received =
+			novel_poly_basis::encode_with_params(&params, payload).unwrap().into_iter().map(Some).collect::<Vec<_>>();
+		// corrupt an untrusted shard, which must not affect the result
+		received[0] = Some(WrappedShard::new(vec![0xFF; 2 * 125]));
+		received[1] = None;
+
+		let trusted = [3, 7, 9, 11].iter().map(|idx| ChunkIndex(*idx)).collect::<Vec<_>>();
+		let subset = select_subset(received.clone(), &trusted).unwrap();
+		assert_eq!(subset.iter().flatten().count(), 4);
+		assert_eq!(&novel_poly_basis::reconstruct_with_params(&params, subset).unwrap()[..payload.len()], payload);
+
+		assert_eq!(select_subset(received.clone(), &[ChunkIndex(1)]), Err(Error::MissingChunk(1)));
+		assert_eq!(select_subset(received, &[ChunkIndex(12)]), Err(Error::ChunkIndexOutOfRange { index: 12, n: 12 }));
+	}
+
+	#[test]
+	fn decode_selection_prefers_complete_data() {
+		let params = CodeParams::new(8, 3).unwrap();
+		let hint = |index: u32, cost: u64| ShardHint { index: ChunkIndex(index), cost };
+		let indices = |idx: &[u32]| idx.iter().map(|idx| ChunkIndex(*idx)).collect::<Vec<_>>();
+
+		// all data shards, even if parity is cheaper, plus the cheapest parity for the margin
+		let hints = [hint(0, 50), hint(1, 50), hint(2, 90), hint(5, 10), hint(6, 5), hint(7, 5)];
+		assert_eq!(select_for_decode(&params, &hints, 0).unwrap(), indices(&[0, 1, 2]));
+		assert_eq!(select_for_decode(&params, &hints, 2).unwrap(), indices(&[0, 1, 2, 6, 7]));
+
+		// a data shard is missing, so the cheapest win, ties towards lower indices
+		let hints = [hint(0, 50), hint(2, 90), hint(3, 5), hint(4, 5), hint(5, 5), hint(7, 1)];
+		assert_eq!(select_for_decode(&params, &hints, 0).unwrap(), indices(&[3, 4, 7]));
+		assert_eq!(select_for_decode(&params, &hints, 10).unwrap().len(), 6);
+
+		let payload = &random_payload(500, 0);
+		let received = status_quo::encode_with_params(&params, payload).unwrap().into_iter().map(Some).collect();
+		let subset = select_subset(received, &select_for_decode(&params, &hints, 0).unwrap()).unwrap();
+		assert_eq!(&status_quo::reconstruct_with_params(&params, subset).unwrap()[..payload.len()], payload);
+
+		assert_eq!(select_for_decode(&params, &hints[..2], 0), Err(Error::TooFewShards { available: 2, required: 3 }));
+		assert_eq!(select_for_decode(&params, &[hint(8, 0)], 0), Err(Error::ChunkIndexOutOfRange { index: 8, n: 8 }));
+		assert_eq!(select_for_decode(&params, &[hint(1, 0), hint(1, 2)], 0), Err(Error::DuplicateChunkIndex(1)));
+	}
+}
@@ -0,0 +1,262 @@
+//! Named benchmark payloads, so comparisons are reproducible across machines.
+//!
+//! The standard payloads are generated deterministically, from fixed seeds, and range
+//! from incompressible to highly regular data. Real data, e.g. a block, enters as a
+//! fixture file or a download, pinned by its SHA-256 digest, which is checked whenever it
+//! is loaded. Downloads are fetched by the caller, see `Corpus::with_fetcher`, and, with a
+//! cache directory, kept there by digest, so later runs only fetch them again if the cached
+//! copy does not match. Generated payloads are cheap and never cached.
+//!
+//! See `workload` for plain random payloads from any seed.
+
+use super::*;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const SEED: u64 = 0x5EED;
+
+const WORDS: &[&str] = &[
+	"the", "of", "and", "block", "chain", "validator", "erasure", "code", "shard", "payload", "a", "to", "in",
+	"is", "that", "for", "availability", "proof", "state", "with", "as", "on", "by", "reconstruct", "field",
+];
+
+/// Where the bytes of a corpus payload come from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+	/// Uniformly random bytes.
+	Random,
+	/// All zero bytes.
+	Zeros,
+	/// Compressible text of words drawn from a small vocabulary.
+	Text,
+	/// Proof of validity like data, see `pov::pov_payload`.
+	Pov,
+	/// The file at `path`, repeated to the requested length, with its digest pinned.
+	Fixture { path: PathBuf, sha256: [u8; 32] },
+	/// The file at `url`, repeated to the requested length, with its digest pinned.
+	Download { url: String, sha256: [u8; 32] },
+}
+
+// `pinned` repeated to `len` bytes, if it matches `sha256`
+fn repeat_pinned(pinned: &[u8], sha256: &[u8; 32], len: usize) -> Result<Vec<u8>> {
+	if digest(pinned) != *sha256 {
+		return Err(Error::FixtureDigestMismatch);
+	}
+	if pinned.is_empty() && len > 0 {
+		return Err(Error::BadPayload);
+	}
+	Ok(pinned.iter().copied().cycle().take(len).collect())
+}
+
+/// Fetches the body of a URL for `Source::Download`.
+pub type Fetcher = Arc<dyn Fn(&str) -> Result<Vec<u8>> + Send + Sync>;
+
+impl Source {
+	fn generate(&self, len: usize, cache_dir: Option<&Path>, fetch: Option<&Fetcher>) -> Result<Vec<u8>> {
+		let mut rng = StdRng::seed_from_u64(SEED);
+		Ok(match self {
+			Source::Random => (0..len).map(|_| rng.gen()).collect(),
+			Source::Zeros => vec![0; len],
+			Source::Text => {
+				let mut text = Vec::with_capacity(len + 16);
+				while text.len() < len {
+					text.extend_from_slice(WORDS[rng.gen_range(0..WORDS.len())].as_bytes());
+					text.push(if rng.gen_ratio(1, 12) { b'\n' } else { b' ' });
+				}
+				text.truncate(len);
+				text
+			}
+			Source::Pov => pov::pov_payload(len, SEED),
+			Source::Fixture { path, sha256 } => repeat_pinned(&std::fs::read(path)?, sha256, len)?,
+			Source::Download { url, sha256 } => {
+				let cached = cache_dir.map(|dir| dir.join(format!("{}.bin", hex(sha256))));
+				if let Some(download) = cached.as_ref().and_then(|path| std::fs::read(path).ok()) {
+					if digest(&download) == *sha256 {
+						return repeat_pinned(&download, sha256, len);
+					}
+				}
+				let fetch = fetch.ok_or(Error::DownloadFailed)?;
+				let download = fetch(url)?;
+				let payload = repeat_pinned(&download, sha256, len)?;
+				if let Some(path) = cached {
+					std::fs::create_dir_all(path.parent().expect("cached downloads are in the cache dir. qed"))?;
+					// written under a temporary name first, so concurrent runs never read a partial download
+					let partial = path.with_extension(format!("{}.partial", std::process::id()));
+					std::fs::write(&partial, &download)?;
+					std::fs::rename(&partial, &path)?;
+				}
+				payload
+			}
+		})
+	}
+}
+
+/// SHA-256 digest of `payload`, to record which payload a measurement was taken on.
+pub fn digest(payload: &[u8]) -> [u8; 32] {
+	Sha256::digest(payload).into()
+}
+
+/// `digest` as lower case hex.
+pub fn hex(digest: &[u8; 32]) -> String {
+	digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// The digest written as hex by `hex`, e.g. to pin a download given on the command line.
+pub fn parse_hex(hex: &str) -> Result<[u8; 32]> {
+	let mut digest = [0; 32];
+	if hex.len() != 64 || !hex.is_ascii() {
+		return Err(Error::MalformedDigest);
+	}
+	for (byte, pair) in digest.iter_mut().zip(hex.as_bytes().chunks(2)) {
+		let pair = std::str::from_utf8(pair).expect("ascii was checked above. qed");
+		*byte = u8::from_str_radix(pair, 16).map_err(|_| Error::MalformedDigest)?;
+	}
+	Ok(digest)
+}
+
+/// Payloads by name, with downloads optionally cached on disk.
+#[derive(Clone)]
+pub struct Corpus {
+	entries: Vec<(String, Source)>,
+	cache_dir: Option<PathBuf>,
+	fetch: Option<Fetcher>,
+}
+
+impl std::fmt::Debug for Corpus {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("Corpus")
+			.field("entries", &self.entries)
+			.field("cache_dir", &self.cache_dir)
+			.field("fetch", &self.fetch.is_some())
+			.finish()
+	}
+}
+
+impl Default for Corpus {
+	/// The standard payloads `random`, `zeros`, `text` and `pov`, without a cache.
+	fn default() -> Self {
+		let entries = [
+			("random", Source::Random),
+			("zeros", Source::Zeros),
+			("text", Source::Text),
+			("pov", Source::Pov),
+		];
+		let entries = entries.iter().map(|(name, source)| (name.to_string(), source.clone())).collect();
+		Corpus { entries, cache_dir: None, fetch: None }
+	}
+}
+
+impl Corpus {
+	/// Cache downloaded payloads in `dir`, which is created if needed.
+	pub fn with_cache_dir(mut self, dir: impl AsRef<Path>) -> Self {
+		self.cache_dir = Some(dir.as_ref().to_owned());
+		self
+	}
+
+	/// Fetch downloads with `fetch`, without one only cached downloads load.
+	pub fn with_fetcher(mut self, fetch: impl Fn(&str) -> Result<Vec<u8>> + Send + Sync + 'static) -> Self {
+		self.fetch = Some(Arc::new(fetch));
+		self
+	}
+
+	/// Add the payload `name`, replacing any previous one of that name.
+	pub fn register(&mut self, name: &str, source: Source) {
+		self.entries.retain(|(other, _)| other != name);
+		self.entries.push((name.to_owned(), source));
+	}
+
+	/// Names of all payloads, in registration order.
+	pub fn names(&self) -> impl Iterator<Item = &str> {
+		self.entries.iter().map(|(name, _)| name.as_str())
+	}
+
+	/// The payload `name` of `len` bytes, downloading it unless the cache holds a matching copy.
+	pub fn load(&self, name: &str, len: usize) -> Result<Vec<u8>> {
+		let source = self
+			.entries
+			.iter()
+			.find(|(other, _)| other == name)
+			.map(|(_, source)| source)
+			.ok_or(Error::UnknownCorpusPayload)?;
+		source.generate(len, self.cache_dir.as_deref(), self.fetch.as_ref())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn payloads_are_reproducible() {
+		let dir = tempfile::tempdir().unwrap();
+		let corpus = Corpus::default().with_cache_dir(dir.path().join("corpus"));
+		assert_eq!(corpus.names().collect::<Vec<_>>(), ["random", "zeros", "text", "pov"]);
+		for name in corpus.names() {
+			let payload = corpus.load(name, 10_000).unwrap();
+			assert_eq!(payload.len(), 10_000);
+			assert_eq!(payload, Corpus::default().load(name, 10_000).unwrap(), "{}", name);
+		}
+		// generated payloads never touch the cache
+		assert!(!dir.path().join("corpus").exists());
+		assert!(corpus.load("text", 100).unwrap().iter().all(|byte| byte.is_ascii()));
+		assert_eq!(corpus.load("block", 100), Err(Error::UnknownCorpusPayload));
+
+		let fixture = dir.path().join("block.bin");
+		let bytes = random_payload(1000, 0);
+		std::fs::write(&fixture, &bytes).unwrap();
+		let mut corpus = corpus;
+		corpus.register("block", Source::Fixture { path: fixture.clone(), sha256: digest(&bytes) });
+		let block = corpus.load("block", 2500).unwrap();
+		assert_eq!(&block[..1000], &bytes[..]);
+		assert_eq!(&block[2000..], &bytes[..500]);
+		corpus.register("block", Source::Fixture { path: fixture, sha256: [0; 32] });
+		assert_eq!(corpus.load("block", 100), Err(Error::FixtureDigestMismatch));
+
+		assert_eq!(parse_hex(&hex(&digest(&bytes))), Ok(digest(&bytes)));
+		assert_eq!(parse_hex("00"), Err(Error::MalformedDigest));
+		assert_eq!(parse_hex(&"g".repeat(64)), Err(Error::MalformedDigest));
+	}
+
+	#[test]
+	fn downloads_are_verified_and_cached() {
+		let dir = tempfile::tempdir().unwrap();
+		let url = "https://example.com/block.bin";
+		let bytes = random_payload(1000, 1);
+		let sha256 = digest(&bytes);
+		let cached = dir.path().join("corpus").join(format!("{}.bin", hex(&sha256)));
+
+		// an in memory remote, which can be taken offline
+		let remote = Arc::new(std::sync::Mutex::new(Some(bytes.clone())));
+		let fetcher = {
+			let remote = remote.clone();
+			move |fetched: &str| {
+				assert_eq!(fetched, url);
+				remote.lock().unwrap().clone().ok_or(Error::DownloadFailed)
+			}
+		};
+
+		let mut corpus = Corpus::default().with_cache_dir(dir.path().join("corpus"));
+		corpus.register("block", Source::Download { url: url.to_owned(), sha256 });
+		assert_eq!(corpus.load("block", 1000), Err(Error::DownloadFailed));
+		let mut corpus = corpus.with_fetcher(fetcher);
+		let block = corpus.load("block", 1500).unwrap();
+		assert_eq!(&block[..1000], &bytes[..]);
+		assert_eq!(&block[1000..], &bytes[..500]);
+		assert_eq!(std::fs::read(&cached).unwrap(), bytes);
+
+		// the cached copy is used while it matches, and fetched again once it does not
+		*remote.lock().unwrap() = None;
+		assert_eq!(corpus.load("block", 1000).unwrap(), bytes);
+		std::fs::write(&cached, vec![0; 1000]).unwrap();
+		assert_eq!(corpus.load("block", 1000), Err(Error::DownloadFailed));
+		*remote.lock().unwrap() = Some(bytes.clone());
+		assert_eq!(corpus.load("block", 1000).unwrap(), bytes);
+		assert_eq!(std::fs::read(&cached).unwrap(), bytes);
+
+		corpus.register("block", Source::Download { url: url.to_owned(), sha256: [0; 32] });
+		assert_eq!(corpus.load("block", 1000), Err(Error::FixtureDigestMismatch));
+	}
+}
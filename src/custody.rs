@@ -0,0 +1,93 @@
+//! Proof of custody tags, a keyed MAC per shard, i.e. for protocols in which validators
+//! prove they still hold their chunks without revealing them.
+//!
+//! The tag is HMAC-SHA256 under a caller provided key over the chunk index and the shard
+//! bytes, so a tag neither verifies for other bytes nor at another position. Tags are kept
+//! as metadata of the `WrappedShard`, they are neither compared nor serialized with it.
+
+use super::*;
+
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The MAC of a shard at its chunk index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CustodyTag(pub [u8; 32]);
+
+fn mac(key: &[u8], index: ChunkIndex, shard: &[u8]) -> HmacSha256 {
+	let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length. qed");
+	mac.update(&index.0.to_le_bytes());
+	mac.update(shard);
+	mac
+}
+
+/// Tag of `shard` at `index` under `key`.
+pub fn custody_tag(key: &[u8], index: ChunkIndex, shard: &[u8]) -> CustodyTag {
+	CustodyTag(mac(key, index, shard).finalize().into_bytes().into())
+}
+
+/// Attach the tag under `key` to each of the shards of an encoding, at its position.
+pub fn tag_shards(key: &[u8], shards: &mut [WrappedShard]) {
+	for (idx, shard) in shards.iter_mut().enumerate() {
+		let tag = custody_tag(key, ChunkIndex(idx as u32), shard.as_ref());
+		shard.set_custody_tag(tag);
+	}
+}
+
+/// Check the tag attached to `shard` at `index`, in constant time.
+pub fn verify_tag(key: &[u8], index: ChunkIndex, shard: &WrappedShard) -> Result<()> {
+	let tag = shard.custody_tag().ok_or(Error::MissingCustodyTag(index.position()))?;
+	mac(key, index, shard.as_ref()).verify(&tag.0).map_err(|_| Error::CustodyTagMismatch(index.position()))
+}
+
+// the received shards with their indices
+fn present(received_shards: &[Option<WrappedShard>]) -> impl Iterator<Item = (ChunkIndex, &WrappedShard)> {
+	received_shards.iter().enumerate().filter_map(|(idx, shard)| Some((ChunkIndex(idx as u32), shard.as_ref()?)))
+}
+
+/// Check the tags of all received shards, failing at the first shard without a valid tag.
+pub fn verify_tags(key: &[u8], received_shards: &[Option<WrappedShard>]) -> Result<()> {
+	present(received_shards).try_for_each(|(index, shard)| verify_tag(key, index, shard))
+}
+
+/// The received shards without a valid tag, to be dropped before reconstruction.
+pub fn invalid_tags(key: &[u8], received_shards: &[Option<WrappedShard>]) -> Vec<ChunkIndex> {
+	let invalid = present(received_shards).filter(|(index, shard)| verify_tag(key, *index, shard).is_err());
+	invalid.map(|(index, _)| index).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn tags_bind_key_index_and_bytes() {
+		let params = CodeParams::new(10, 4).unwrap();
+		let key = b"custody key";
+		let mut shards = novel_poly_basis::encode_with_params(&params, &random_payload(1000, 0)).unwrap();
+		tag_shards(key, &mut shards);
+
+		let mut received = shards.into_iter().map(Some).collect::<Vec<_>>();
+		assert_eq!(verify_tags(key, &received), Ok(()));
+		assert_eq!(verify_tags(b"other key", &received), Err(Error::CustodyTagMismatch(0)));
+
+		// tags do not verify at another index, nor for altered bytes, nor if missing
+		received.swap(1, 2);
+		AsMut::<[u8]>::as_mut(received[5].as_mut().unwrap())[0] ^= 1;
+		received[7] = Some(WrappedShard::new(received[7].as_ref().unwrap().clone().into_inner()));
+		received[9] = None;
+		assert_eq!(verify_tags(key, &received), Err(Error::CustodyTagMismatch(1)));
+		assert_eq!(invalid_tags(key, &received), [1, 2, 5, 7].iter().map(|idx| ChunkIndex(*idx)).collect::<Vec<_>>());
+		assert_eq!(
+			verify_tag(key, ChunkIndex(7), received[7].as_ref().unwrap()),
+			Err(Error::MissingCustodyTag(7))
+		);
+
+		// tags are metadata, a tagged shard equals the untagged one
+		let tagged = received[0].clone().unwrap();
+		assert!(tagged.custody_tag().is_some());
+		assert_eq!(tagged, WrappedShard::new(tagged.clone().into_inner()));
+	}
+}
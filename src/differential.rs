@@ -0,0 +1,123 @@
+//! Differential testing of the novel poly basis against the status quo.
+//!
+//! Both backends are fed the same payload and lose the shards at the same
+//! positions, any difference in the outcome is reported as a `Divergence`.
+
+use super::*;
+
+use rand::{rngs::StdRng, seq::index, SeedableRng};
+
+/// Result of reconstructing with one backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+	/// The payload was recovered.
+	Recovered,
+	/// Reconstruction succeeded but returned a different payload.
+	Mismatch,
+	/// Encoding or reconstruction failed.
+	Failed(Error),
+}
+
+/// A case in which the backends disagree or either returned a wrong payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+	pub n: usize,
+	pub k: usize,
+	pub payload_len: usize,
+	pub erased: Vec<usize>,
+	pub novel_poly_basis: Outcome,
+	pub status_quo: Outcome,
+}
+
+fn outcome(
+	params: &CodeParams,
+	payload: &[u8],
+	erased: &[usize],
+	encode: impl Fn(&CodeParams, &[u8]) -> Result<Vec<WrappedShard>>,
+	reconstruct: impl Fn(&CodeParams, Vec<Option<WrappedShard>>) -> Result<Vec<u8>>,
+) -> Outcome {
+	let run = || -> Result<Vec<u8>> {
+		let mut received = encode(params, payload)?.into_iter().map(Some).collect::<Vec<_>>();
+		for &idx in erased {
+			received[idx] = None;
+		}
+		reconstruct(params, received)
+	};
+	match run() {
+		Ok(recovered) if recovered.get(..payload.len()) == Some(payload) => Outcome::Recovered,
+		Ok(_) => Outcome::Mismatch,
+		Err(e) => Outcome::Failed(e),
+	}
+}
+
+/// Encode and reconstruct `payload` with both backends, erasing the shards at `erased`.
+pub fn compare(params: &CodeParams, payload: &[u8], erased: &[usize]) -> Option<Divergence> {
+	let novel_poly_basis = outcome(
+		params,
+		payload,
+		erased,
+		novel_poly_basis::encode_with_params,
+		novel_poly_basis::reconstruct_with_params,
+	);
+	let status_quo =
+		outcome(params, payload, erased, status_quo::encode_with_params, status_quo::reconstruct_with_params);
+	if novel_poly_basis == Outcome::Recovered && status_quo == Outcome::Recovered {
+		return None;
+	}
+	if novel_poly_basis == status_quo && novel_poly_basis != Outcome::Mismatch {
+		return None;
+	}
+	Some(Divergence {
+		n: params.n(),
+		k: params.k(),
+		payload_len: payload.len(),
+		erased: erased.to_vec(),
+		novel_poly_basis,
+		status_quo,
+	})
+}
+
+/// Compare both backends for each of the `payload_lens`, with `erasures` shards
+/// erased at positions chosen deterministically in `seed`, and additionally with
+/// the first `erasures` shards erased, so data shards are always lost.
+pub fn compare_lengths(
+	params: &CodeParams,
+	payload_lens: impl IntoIterator<Item = usize>,
+	erasures: usize,
+	seed: u64,
+) -> Vec<Divergence> {
+	let mut rng = StdRng::seed_from_u64(seed);
+	let leading = (0..erasures).collect::<Vec<_>>();
+	let mut divergences = Vec::new();
+	for len in payload_lens {
+		let payload = &workload::random_payload(len, 0);
+		let random = index::sample(&mut rng, params.n(), erasures).into_vec();
+		divergences.extend(compare(params, payload, &random));
+		divergences.extend(compare(params, payload, &leading));
+	}
+	divergences
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn backends_agree_up_to_n_minus_k_erasures() {
+		for &(n, k) in &[(4, 2), (10, 3), (16, 4), (33, 11), (64, 22)] {
+			let params = CodeParams::new(n, k).unwrap();
+			let lens = (0..20).chain([63, 64, 65, 255, 256, 257, 1000, 4097]);
+			assert_eq!(compare_lengths(&params, lens, n - k, n as u64), vec![]);
+		}
+	}
+
+	#[test]
+	fn flags_novel_recovering_below_k() {
+		// the novel poly basis only needs `k` rounded down to a power of two shards
+		let params = CodeParams::new(10, 3).unwrap();
+		let divergences = compare_lengths(&params, [100], 8, 0);
+		assert_eq!(divergences.len(), 2);
+		assert!(divergences.iter().all(|d| d.novel_poly_basis == Outcome::Recovered
+			&& d.status_quo == Outcome::Failed(Error::TooFewShards { available: 2, required: 3 })));
+	}
+}
@@ -0,0 +1,232 @@
+use super::*;
+
+use bitvec::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
+
+#[cfg(feature = "codec")]
+use codec::{Compact, Decode, Encode, Input, Output};
+
+/// Which of `n` shards are erased, one bit per shard.
+///
+/// The bit-packed form of `to_bytes` is suitable for gossiping which
+/// chunks a node holds, it takes `n / 8` bytes rounded up.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+	feature = "serde",
+	derive(Serialize, Deserialize),
+	serde(try_from = "CompactErasureMap", into = "CompactErasureMap")
+)]
+pub struct ErasureMap {
+	bits: BitVec<u8, Lsb0>,
+}
+
+impl ErasureMap {
+	/// A map of `n` shards, none of which are erased.
+	pub fn new(n: usize) -> Self {
+		ErasureMap { bits: bitvec![u8, Lsb0; 0; n] }
+	}
+
+	/// A map of `n` shards, all of which are erased.
+	pub fn all_erased(n: usize) -> Self {
+		ErasureMap { bits: bitvec![u8, Lsb0; 1; n] }
+	}
+
+	/// The erasures of a received shard set, `None` marks an erased shard.
+	pub fn from_received<T>(received_shards: &[Option<T>]) -> Self {
+		received_shards.iter().map(Option::is_none).collect()
+	}
+
+	/// Number of shards covered.
+	pub fn len(&self) -> usize {
+		self.bits.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.bits.is_empty()
+	}
+
+	/// Mark shard `idx` as erased or available.
+	pub fn set(&mut self, idx: usize, erased: bool) {
+		self.bits.set(idx, erased)
+	}
+
+	/// Whether shard `idx` is erased, panics if `idx` is out of range.
+	pub fn is_erased(&self, idx: usize) -> bool {
+		self.bits[idx]
+	}
+
+	/// Grow or shrink to `n` shards, new shards are marked as `erased`.
+	pub fn resize(&mut self, n: usize, erased: bool) {
+		self.bits.resize(n, erased)
+	}
+
+	/// Number of erased shards.
+	pub fn count_erased(&self) -> usize {
+		self.bits.count_ones()
+	}
+
+	/// Number of available shards.
+	pub fn count_available(&self) -> usize {
+		self.bits.count_zeros()
+	}
+
+	/// Whether each shard is erased, in order, without materializing a `Vec<bool>`.
+	pub fn iter(&self) -> impl Iterator<Item = bool> + Clone + '_ {
+		self.bits.iter().map(|bit| *bit)
+	}
+
+	/// Indices of the erased shards, in increasing order.
+	pub fn erased(&self) -> impl Iterator<Item = usize> + '_ {
+		self.bits.iter_ones()
+	}
+
+	/// Indices of the available shards, in increasing order.
+	pub fn available(&self) -> impl Iterator<Item = usize> + '_ {
+		self.bits.iter_zeros()
+	}
+
+	/// Bit-packed representation, bit `i % 8` of byte `i / 8` is set if shard `i` is erased.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut bits = self.bits.clone();
+		bits.set_uninitialized(false);
+		bits.into_vec()
+	}
+
+	/// Inverse of `to_bytes` for a map of `n` shards, unused trailing bits must be zero.
+	pub fn from_bytes(n: usize, bytes: &[u8]) -> Result<Self> {
+		if bytes.len() != n.div_ceil(8) {
+			return Err(Error::MalformedErasureMap { n, len: bytes.len() });
+		}
+		let mut bits = BitVec::<u8, Lsb0>::from_slice(bytes);
+		if bits[n..].any() {
+			return Err(Error::MalformedErasureMap { n, len: bytes.len() });
+		}
+		bits.truncate(n);
+		Ok(ErasureMap { bits })
+	}
+}
+
+impl std::ops::Index<usize> for ErasureMap {
+	type Output = bool;
+
+	fn index(&self, idx: usize) -> &bool {
+		&self.bits[idx]
+	}
+}
+
+impl From<&[bool]> for ErasureMap {
+	fn from(erasures: &[bool]) -> Self {
+		erasures.iter().copied().collect()
+	}
+}
+
+impl std::iter::FromIterator<bool> for ErasureMap {
+	fn from_iter<I: IntoIterator<Item = bool>>(iterable: I) -> Self {
+		ErasureMap { bits: iterable.into_iter().collect() }
+	}
+}
+
+// serialized form of `ErasureMap`
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct CompactErasureMap {
+	n: usize,
+	bits: Vec<u8>,
+}
+
+#[cfg(feature = "serde")]
+impl From<ErasureMap> for CompactErasureMap {
+	fn from(map: ErasureMap) -> Self {
+		CompactErasureMap { n: map.len(), bits: map.to_bytes() }
+	}
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<CompactErasureMap> for ErasureMap {
+	type Error = Error;
+
+	fn try_from(compact: CompactErasureMap) -> Result<Self> {
+		ErasureMap::from_bytes(compact.n, &compact.bits)
+	}
+}
+
+#[cfg(feature = "codec")]
+impl Encode for ErasureMap {
+	fn size_hint(&self) -> usize {
+		Compact(self.len() as u32).size_hint() + self.len().div_ceil(8)
+	}
+
+	fn encode_to<O: Output + ?Sized>(&self, dest: &mut O) {
+		Compact(self.len() as u32).encode_to(dest);
+		dest.write(&self.to_bytes());
+	}
+}
+
+#[cfg(feature = "codec")]
+impl Decode for ErasureMap {
+	fn decode<I: Input>(input: &mut I) -> std::result::Result<Self, codec::Error> {
+		let n = Compact::<u32>::decode(input)?.0 as usize;
+		if n > 1 << 16 {
+			return Err("ErasureMap covers more shards than the field size".into());
+		}
+		let mut bytes = vec![0_u8; n.div_ceil(8)];
+		input.read(&mut bytes)?;
+		ErasureMap::from_bytes(n, &bytes).map_err(|_| "ErasureMap has trailing bits set".into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn set_count_iter() {
+		let mut map = ErasureMap::new(10);
+		map.set(1, true);
+		map.set(8, true);
+		assert!(map.is_erased(8) && map[1] && !map[0]);
+		assert_eq!(map.count_erased(), 2);
+		assert_eq!(map.count_available(), 8);
+		assert_eq!(map.erased().collect::<Vec<_>>(), vec![1, 8]);
+		assert_eq!(map.available().count(), 8);
+
+		let received = [Some(()), None, Some(()), Some(()), Some(()), Some(()), Some(()), Some(()), None, Some(())];
+		assert_eq!(ErasureMap::from_received(&received), map);
+	}
+
+	#[test]
+	fn bit_packed_roundtrip() {
+		let mut map = ErasureMap::all_erased(12);
+		map.set(0, false);
+		map.set(9, false);
+		let bytes = map.to_bytes();
+		assert_eq!(bytes, vec![0b1111_1110, 0b0000_1101]);
+		assert_eq!(ErasureMap::from_bytes(12, &bytes), Ok(map));
+
+		assert_eq!(ErasureMap::from_bytes(12, &[0]), Err(Error::MalformedErasureMap { n: 12, len: 1 }));
+		assert_eq!(ErasureMap::from_bytes(12, &[0, 0x10]), Err(Error::MalformedErasureMap { n: 12, len: 2 }));
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn serde_json_roundtrip() {
+		let map = ErasureMap::from(&[true, false, true][..]);
+		let json = serde_json::to_string(&map).unwrap();
+		assert_eq!(json, r#"{"n":3,"bits":[5]}"#);
+		assert_eq!(serde_json::from_str::<ErasureMap>(&json).unwrap(), map);
+	}
+
+	#[cfg(feature = "codec")]
+	#[test]
+	fn scale_roundtrip() {
+		let map = ErasureMap::from(&[true, false, true][..]);
+		let encoded = map.encode();
+		assert_eq!(encoded, vec![3 << 2, 5]);
+		assert_eq!(ErasureMap::decode(&mut &encoded[..]).unwrap(), map);
+		assert!(ErasureMap::decode(&mut &[3 << 2, 0xFF][..]).is_err());
+	}
+}
@@ -0,0 +1,180 @@
+use std::sync::Arc;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Error {
+	#[error("Number of data shards must be at least 1, but is {0}")]
+	DataShardCountTooLow(usize),
+
+	#[error("Number of shards {n} must exceed the number of data shards {k}")]
+	ShardCountTooLow { n: usize, k: usize },
+
+	#[error("Number of shards {0} exceeds the field size")]
+	ShardCountTooHigh(usize),
+
+	#[error("Expected {expected} shards, but got {got}")]
+	WrongShardCount { expected: usize, got: usize },
+
+	#[error("Only {available} shards available, but {required} are required to reconstruct")]
+	TooFewShards { available: usize, required: usize },
+
+	#[error("Shards have inconsistent lengths: first = {first}, other = {other}")]
+	InconsistentShardLengths { first: usize, other: usize },
+
+	#[error("Chunk index {index} is out of range for {n} validators")]
+	ChunkIndexOutOfRange { index: usize, n: usize },
+
+	#[error("Chunk {0} was requested but not received")]
+	MissingChunk(usize),
+
+	#[error("Chunk index {0} was given more than once")]
+	DuplicateChunkIndex(usize),
+
+	#[error("Different shards were given for chunk index {0}")]
+	ConflictingShards(usize),
+
+	#[error("Chunk {0} is a parity shard, but only data shards are accepted")]
+	ParityShardAsData(usize),
+
+	#[error("The payload is empty or failed to decode")]
+	BadPayload,
+
+	#[error("Shard length {0} is not a multiple of the symbol size")]
+	UnalignedShardLength(usize),
+
+	#[error("Shard length {len} is not a multiple of the {group} symbol group")]
+	UngroupedShardLength { len: usize, group: usize },
+
+	#[error("Unknown codec version {0}")]
+	UnknownCodecVersion(u8),
+
+	#[error("Shards of different codec versions {first} and {other}")]
+	MixedCodecVersions { first: u8, other: u8 },
+
+	#[error("Framed shard lacks the codec version")]
+	EmptyFrame,
+
+	#[error("Malformed shard stream: {0}")]
+	MalformedShardStream(&'static str),
+
+	#[error("Malformed shard header: {0}")]
+	MalformedShardHeader(&'static str),
+
+	#[error("I/O error: {0}")]
+	Io(#[source] IoError),
+
+	#[error("The status quo backend failed")]
+	ReedSolomon(#[from] reed_solomon_erasure::Error),
+
+	#[error("Payload of {len} bytes exceeds the maximum of {max} bytes")]
+	PayloadTooLarge { len: usize, max: usize },
+
+	#[error("Payload {index} was requested, but only {count} are packed")]
+	PayloadIndexOutOfRange { index: usize, count: usize },
+
+	#[error("Shard {0} is inconsistent with the reconstructed payload")]
+	InconsistentShard(usize),
+
+	#[error("Check symbol {0} does not match the payload")]
+	CheckMismatch(usize),
+
+	#[error("Shard {0} holds a symbol outside the field")]
+	SymbolOutOfField(usize),
+
+	#[error("Shard {0} lacks a custody tag")]
+	MissingCustodyTag(usize),
+
+	#[error("Custody tag of shard {0} does not match")]
+	CustodyTagMismatch(usize),
+
+	#[error("GPU offload failed: {0}")]
+	GpuOffload(&'static str),
+
+	#[error("No corpus payload of that name")]
+	UnknownCorpusPayload,
+
+	#[error("Fixture does not match its pinned digest")]
+	FixtureDigestMismatch,
+
+	#[error("Download of a corpus payload failed")]
+	DownloadFailed,
+
+	#[error("Digest is not 64 hex digits")]
+	MalformedDigest,
+
+	#[error("Size {0} is not a power of two")]
+	NotPowerOfTwo(usize),
+
+	#[error("Buffer of {len} symbols is too short, {required} are required")]
+	BufferTooShort { len: usize, required: usize },
+
+	#[error("Buffer of {len} symbols, but {expected} are expected")]
+	WrongBufferLength { len: usize, expected: usize },
+
+	#[error("Buffer of {0} symbols can not be split into pairs")]
+	OddBufferLength(usize),
+
+	#[error("Erasure map of {n} shards can not be {len} bytes long or has trailing bits set")]
+	MalformedErasureMap { n: usize, len: usize },
+}
+
+// `reed_solomon_erasure::Error` lacks `Eq`, though its derived `PartialEq` is total
+impl Eq for Error {}
+
+impl From<std::io::Error> for Error {
+	fn from(e: std::io::Error) -> Self {
+		Error::Io(IoError(Arc::new(e)))
+	}
+}
+
+impl From<std::io::ErrorKind> for Error {
+	fn from(kind: std::io::ErrorKind) -> Self {
+		std::io::Error::from(kind).into()
+	}
+}
+
+/// An `std::io::Error` shared by the clones of an `Error`, compared by its kind.
+#[derive(Debug, Clone)]
+pub struct IoError(Arc<std::io::Error>);
+
+impl IoError {
+	pub fn kind(&self) -> std::io::ErrorKind {
+		self.0.kind()
+	}
+
+	pub fn get_ref(&self) -> &std::io::Error {
+		&self.0
+	}
+}
+
+impl PartialEq for IoError {
+	fn eq(&self, other: &Self) -> bool {
+		self.kind() == other.kind()
+	}
+}
+
+impl std::fmt::Display for IoError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		self.0.fmt(f)
+	}
+}
+
+impl std::error::Error for IoError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		Some(&*self.0)
+	}
+}
+
+/// Inconsistency found by `novel_poly_basis::verify_tables`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum TableError {
+	#[error("Log and exp tables are not inverse at element {element}")]
+	LogExpMismatch { element: u16 },
+
+	#[error("Skew factor {index} is {actual}, expected {expected}")]
+	SkewFactor { index: usize, expected: u16, actual: u16 },
+
+	#[error("Checksum of the {table} table is {actual:#018x}, expected {expected:#018x}")]
+	Checksum { table: &'static str, expected: u64, actual: u64 },
+}
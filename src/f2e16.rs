@@ -0,0 +1,590 @@
+//! GF(2^16), the field of the `novel_poly_basis` backend.
+//!
+//! The definition of the field lives here, as do its log, exp and skew factor tables and
+//! the factors derived from them, built once by `setup`. The codec reads them only through
+//! the functions of this module.
+
+#![allow(clippy::needless_range_loop)]
+
+use crate::afft::FieldAdd;
+use crate::novel_poly_basis::{butterfly_add, butterfly_mul_add};
+use crate::stats::{record, Op};
+use crate::TableError;
+use std::sync::Once;
+
+pub(crate) type GFSymbol = u16;
+
+pub(crate) const FIELD_BITS: usize = 16;
+
+pub(crate) const GENERATOR: GFSymbol = 0x2D; //x^16 + x^5 + x^3 + x^2 + 1
+
+// Cantor basis
+pub(crate) const BASE: [GFSymbol; FIELD_BITS] =
+	[1_u16, 44234, 15374, 5694, 50562, 60718, 37196, 16402, 27800, 4312, 27250, 47360, 64952, 64308, 65336, 39198];
+
+pub(crate) const FIELD_SIZE: usize = 1_usize << FIELD_BITS;
+
+pub(crate) const MODULO: GFSymbol = (FIELD_SIZE - 1) as GFSymbol;
+
+// the log, exp and skew factor tables, checked by `verify_tables`
+#[cfg(feature = "tables-f2e16")]
+pub(crate) mod generated {
+	include!(concat!(env!("OUT_DIR"), "/f2e16_tables.rs"));
+}
+
+static mut LOG_TABLE: [GFSymbol; FIELD_SIZE] = [0_u16; FIELD_SIZE];
+static mut EXP_TABLE: [GFSymbol; FIELD_SIZE] = [0_u16; FIELD_SIZE];
+
+// change of basis between the Cantor and the polynomial basis, per byte of the symbol,
+// 2 KiB in total, used to multiply without the log table
+static mut TO_POLY: [[GFSymbol; 256]; 2] = [[0_u16; 256]; 2];
+static mut TO_CANTOR: [[GFSymbol; 256]; 2] = [[0_u16; 256]; 2];
+
+//-----Used in decoding procedure-------
+//twisted factors used in FFT
+static mut SKEW_FACTOR: [GFSymbol; MODULO as usize] = [0_u16; MODULO as usize];
+
+//factors used in formal derivative
+static mut B: [GFSymbol; FIELD_SIZE >> 1] = [0_u16; FIELD_SIZE >> 1];
+
+//factors used in the evaluation of the error locator polynomial
+static mut LOG_WALSH: [GFSymbol; FIELD_SIZE] = [0_u16; FIELD_SIZE];
+
+// assert an invariant of the field arithmetic if the `debug-field-checks` feature
+// is enabled, catching table corruption early, compiled out otherwise
+macro_rules! field_check {
+	($cond:expr, $($arg:tt)+) => {
+		if cfg!(feature = "debug-field-checks") {
+			assert!($cond, $($arg)+);
+		}
+	};
+}
+
+// narrow a value reduced modulo `MODULO` back to a symbol
+#[inline(always)]
+fn to_symbol(x: u32) -> GFSymbol {
+	if cfg!(feature = "debug-field-checks") {
+		use std::convert::TryFrom;
+		GFSymbol::try_from(x).expect("Reduced value exceeds the field. qed")
+	} else {
+		x as GFSymbol
+	}
+}
+
+// log of the skew factor at `idx`, `MODULO` marks a zero factor
+#[inline(always)]
+pub(crate) fn skew_factor(idx: usize) -> GFSymbol {
+	record(Op::Lookup, 1);
+	let skew = unsafe { SKEW_FACTOR[idx] };
+	field_check!(
+		skew == MODULO || unsafe { LOG_TABLE[EXP_TABLE[skew as usize] as usize] } == skew,
+		"Skew factor {} at {} is not a valid log",
+		skew,
+		idx
+	);
+	skew
+}
+
+//return a*EXP_TABLE[b] over GF(2^r)
+#[inline(always)]
+pub(crate) fn mul_table(a: GFSymbol, b: GFSymbol) -> GFSymbol {
+	record(Op::Mul, 1);
+	if cfg!(feature = "constant-time") {
+		mul_ct(a, b)
+	} else {
+		mul_table_lookup(a, b)
+	}
+}
+
+// Multiplication by `EXP_TABLE[b]` is linear over GF(2) on the symbols in the Cantor basis,
+// so the product is the sum of the images of the basis elements selected by the bits
+// of `a`. Table lookups only depend on `b`, the run time is independent of `a`. The log table
+// is used even with `compact-tables`, multiplying via the polynomial basis 16 times is too slow.
+#[inline(always)]
+pub(crate) fn mul_ct(a: GFSymbol, b: GFSymbol) -> GFSymbol {
+	let mut product = 0_u16;
+	for j in 0..FIELD_BITS {
+		let mask = 0_u16.wrapping_sub((a >> j) & 1);
+		product ^= mul_log_exp(1 << j, b) & mask;
+	}
+	product
+}
+
+#[inline(always)]
+pub(crate) fn mul_table_lookup(a: GFSymbol, b: GFSymbol) -> GFSymbol {
+	if cfg!(feature = "compact-tables") {
+		mul_poly_basis(a, b)
+	} else {
+		mul_log_exp(a, b)
+	}
+}
+
+#[inline(always)]
+pub(crate) fn mul_log_exp(a: GFSymbol, b: GFSymbol) -> GFSymbol {
+	if a != 0_u16 {
+		record(Op::Lookup, 2);
+		unsafe {
+			let log_a = LOG_TABLE[a as usize];
+			field_check!(EXP_TABLE[log_a as usize] == a, "Field tables are corrupted, exp(log({})) != {}", a, a);
+			let offset = ((log_a as u32 + b as u32) & MODULO as u32) + ((log_a as u32 + b as u32) >> FIELD_BITS);
+			field_check!(offset <= MODULO as u32, "Log offset {} out of range", offset);
+			EXP_TABLE[offset as usize]
+		}
+	} else {
+		0_u16
+	}
+}
+
+// linear map of a symbol given per byte
+#[inline(always)]
+fn change_basis(map: &[[GFSymbol; 256]; 2], x: GFSymbol) -> GFSymbol {
+	map[0][(x & 0xFF) as usize] ^ map[1][(x >> 8) as usize]
+}
+
+// `a * EXP_TABLE[b]` as product of polynomials modulo the field polynomial, leaving the
+// 128 KiB log table alone, at the cost of the change of basis and a carry-less multiplication
+#[inline(always)]
+pub(crate) fn mul_poly_basis(a: GFSymbol, b: GFSymbol) -> GFSymbol {
+	record(Op::Lookup, 5);
+	let (to_poly, to_cantor) = unsafe { (&*std::ptr::addr_of!(TO_POLY), &*std::ptr::addr_of!(TO_CANTOR)) };
+	let (a, b) = (change_basis(to_poly, a), change_basis(to_poly, unsafe { EXP_TABLE[b as usize] }));
+	let mut product = 0_u32;
+	for j in 0..FIELD_BITS {
+		product ^= (u32::from(a) << j) & 0_u32.wrapping_sub(u32::from(b >> j) & 1);
+	}
+	// x^16 = x^5 + x^3 + x^2 + 1, the high half is at most of degree 14, folding twice suffices
+	for _ in 0..2 {
+		let high = product >> FIELD_BITS;
+		product &= u32::from(MODULO);
+		for j in [0, 2, 3, 5] {
+			product ^= high << j;
+		}
+	}
+	change_basis(to_cantor, product as GFSymbol)
+}
+
+// The tables are only read once `setup` ran, which every entry point of the codec ensures.
+
+// log of `a`
+#[inline(always)]
+pub(crate) fn log(a: GFSymbol) -> GFSymbol {
+	unsafe { LOG_TABLE[a as usize] }
+}
+
+// inverse of `log`
+#[inline(always)]
+pub(crate) fn exp(log_a: GFSymbol) -> GFSymbol {
+	unsafe { EXP_TABLE[log_a as usize] }
+}
+
+pub(crate) fn log_table() -> &'static [GFSymbol; FIELD_SIZE] {
+	unsafe { &*std::ptr::addr_of!(LOG_TABLE) }
+}
+
+// log domain skew factors of the transforms
+pub(crate) fn skew_factors() -> &'static [GFSymbol; MODULO as usize] {
+	unsafe { &*std::ptr::addr_of!(SKEW_FACTOR) }
+}
+
+// log domain factors of the formal derivative
+pub(crate) fn b_factors() -> &'static [GFSymbol; FIELD_SIZE >> 1] {
+	unsafe { &*std::ptr::addr_of!(B) }
+}
+
+// the Walsh transform of the log table, to evaluate the error locator polynomial
+pub(crate) fn log_walsh() -> &'static [GFSymbol; FIELD_SIZE] {
+	unsafe { &*std::ptr::addr_of!(LOG_WALSH) }
+}
+
+//fast Walsh–Hadamard transform over modulo mod
+pub(crate) fn walsh(data: &mut [GFSymbol], size: usize) {
+	walsh_levels(data, size, 1)
+}
+
+// the levels of `walsh` from `depart_no` on, the levels below it stay within blocks of `depart_no`
+fn walsh_levels(data: &mut [GFSymbol], size: usize, mut depart_no: usize) {
+	while depart_no < size {
+		let mut j = 0;
+		let depart_no_next = depart_no << 1;
+		while j < size {
+			for i in j..(depart_no + j) {
+				let tmp2: u32 = data[i] as u32 + MODULO as u32 - data[i + depart_no] as u32;
+				data[i] = to_symbol(
+					((data[i] as u32 + data[i + depart_no] as u32) & MODULO as u32)
+						+ ((data[i] as u32 + data[i + depart_no] as u32) >> FIELD_BITS),
+				);
+				data[i + depart_no] = to_symbol((tmp2 & MODULO as u32) + (tmp2 >> FIELD_BITS));
+			}
+			j += depart_no_next;
+		}
+		depart_no = depart_no_next;
+	}
+}
+
+// At most this many threads share the transform of `LOG_WALSH` during setup.
+const WALSH_MAX_THREADS: usize = 8;
+
+// `walsh` with the blocks of the lower levels transformed on `threads` threads
+pub(crate) fn walsh_parallel(data: &mut [GFSymbol], size: usize, threads: usize) {
+	let threads = threads.min(WALSH_MAX_THREADS).min(size);
+	if threads <= 1 {
+		return walsh(data, size);
+	}
+	let block = size >> threads.ilog2();
+	std::thread::scope(|scope| {
+		for chunk in data[..size].chunks_mut(block) {
+			scope.spawn(move || walsh(chunk, block));
+		}
+	});
+	walsh_levels(data, size, block);
+}
+
+//initialize LOG_TABLE[], EXP_TABLE[]
+unsafe fn init() {
+	let mas: GFSymbol = (1 << (FIELD_BITS - 1)) - 1;
+	let mut state: usize = 1;
+	for i in 0_usize..(MODULO as usize) {
+		EXP_TABLE[state] = i as GFSymbol;
+		if (state >> (FIELD_BITS - 1)) != 0 {
+			state &= mas as usize;
+			state = (state << 1_usize) ^ GENERATOR as usize;
+		} else {
+			state <<= 1;
+		}
+	}
+	EXP_TABLE[0] = MODULO;
+
+	LOG_TABLE[0] = 0;
+	for i in 0..FIELD_BITS {
+		for j in 0..(1 << i) {
+			LOG_TABLE[j + (1 << i)] = LOG_TABLE[j] ^ BASE[i];
+		}
+	}
+	// so far the log table maps the Cantor to the polynomial basis
+	for byte in 0..256 {
+		TO_POLY[0][byte] = LOG_TABLE[byte];
+		TO_POLY[1][byte] = LOG_TABLE[byte << 8];
+	}
+	for i in 0..FIELD_SIZE {
+		let poly = LOG_TABLE[i] as usize;
+		if poly & 0xFF == 0 {
+			TO_CANTOR[1][poly >> 8] = i as GFSymbol;
+		}
+		if poly >> 8 == 0 {
+			TO_CANTOR[0][poly] = i as GFSymbol;
+		}
+	}
+
+	#[cfg(feature = "tables-f2e16")]
+	{
+		LOG_TABLE = generated::LOG;
+		EXP_TABLE = generated::EXP;
+	}
+	#[cfg(not(feature = "tables-f2e16"))]
+	{
+		for i in 0..FIELD_SIZE {
+			LOG_TABLE[i] = EXP_TABLE[LOG_TABLE[i] as usize];
+		}
+
+		for i in 0..FIELD_SIZE {
+			EXP_TABLE[LOG_TABLE[i] as usize] = i as GFSymbol;
+		}
+		EXP_TABLE[MODULO as usize] = EXP_TABLE[0];
+	}
+}
+
+//initialize SKEW_FACTOR[], B[], LOG_WALSH[]
+unsafe fn init_dec() {
+	let mut base: [GFSymbol; FIELD_BITS - 1] = Default::default();
+
+	for i in 1..FIELD_BITS {
+		base[i - 1] = 1 << i;
+	}
+
+	for m in 0..(FIELD_BITS - 1) {
+		let step = 1 << (m + 1);
+		SKEW_FACTOR[(1 << m) - 1] = 0;
+		for i in m..(FIELD_BITS - 1) {
+			let s = 1 << (i + 1);
+
+			let mut j = (1 << m) - 1;
+			while j < s {
+				SKEW_FACTOR[j + s] = SKEW_FACTOR[j] ^ base[i];
+				j += step;
+			}
+		}
+
+		let idx = mul_table(base[m], LOG_TABLE[(base[m] ^ 1_u16) as usize]);
+		base[m] = MODULO - LOG_TABLE[idx as usize];
+
+		for i in (m + 1)..(FIELD_BITS - 1) {
+			let b = LOG_TABLE[(base[i] ^ 1_u16) as usize] as u32 + base[m] as u32;
+			let b = b % MODULO as u32;
+			base[i] = mul_table(base[i], b as u16);
+		}
+	}
+	#[cfg(feature = "tables-f2e16")]
+	{
+		SKEW_FACTOR = generated::SKEW;
+	}
+	#[cfg(not(feature = "tables-f2e16"))]
+	for i in 0..(MODULO as usize) {
+		SKEW_FACTOR[i] = LOG_TABLE[SKEW_FACTOR[i] as usize];
+	}
+
+	base[0] = MODULO - base[0];
+	for i in 1..(FIELD_BITS - 1) {
+		base[i] = ((MODULO as u32 - base[i] as u32 + base[i - 1] as u32) % MODULO as u32) as GFSymbol;
+	}
+
+	B[0] = 0;
+	for i in 0..(FIELD_BITS - 1) {
+		let depart = 1 << i;
+		for j in 0..depart {
+			B[j + depart] = ((B[j] as u32 + base[i] as u32) % MODULO as u32) as GFSymbol;
+		}
+	}
+
+	LOG_WALSH = LOG_TABLE;
+	LOG_WALSH[0] = 0;
+	let threads = std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get);
+	walsh_parallel(&mut LOG_WALSH[..], FIELD_SIZE, threads);
+}
+
+// initialize all tables exactly once, concurrent callers block until done
+pub(crate) fn setup() {
+	static SETUP: Once = Once::new();
+	SETUP.call_once(|| unsafe {
+		init();
+		init_dec();
+		if cfg!(debug_assertions) {
+			check_tables().expect("Field tables failed verification");
+		}
+	});
+}
+
+// FNV-1a over the symbols
+fn checksum(table: &[GFSymbol]) -> u64 {
+	table
+		.iter()
+		.flat_map(|sym| sym.to_le_bytes())
+		.fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3))
+}
+
+// `(index, log)` of skew factors and checksums of the decoding tables, as computed by `RSErasureCode.c`
+const KNOWN_SKEW_FACTORS: [(usize, GFSymbol); 6] =
+	[(0, MODULO), (1, MODULO), (2, 21845), (100, 1028), (4095, MODULO), (65534, 43173)];
+
+const SKEW_CHECKSUM: u64 = 0x9844_9ca5_690b_2987;
+const B_CHECKSUM: u64 = 0xeb05_052e_a5b6_2325;
+const LOG_WALSH_CHECKSUM: u64 = 0xb78f_b938_9b89_eb87;
+
+/// Check the field tables against each other and against known values, catching
+/// miscompiled or corrupted tables. Runs automatically during `setup` in debug builds.
+pub fn verify_tables() -> std::result::Result<(), TableError> {
+	setup();
+	unsafe { check_tables() }
+}
+
+// only to be called once the tables are initialized
+unsafe fn check_tables() -> std::result::Result<(), TableError> {
+	check_table_values(
+		&*std::ptr::addr_of!(LOG_TABLE),
+		&*std::ptr::addr_of!(EXP_TABLE),
+		&*std::ptr::addr_of!(SKEW_FACTOR),
+		&*std::ptr::addr_of!(B),
+		&*std::ptr::addr_of!(LOG_WALSH),
+	)
+}
+
+fn check_table_values(
+	log: &[GFSymbol],
+	exp: &[GFSymbol],
+	skew: &[GFSymbol],
+	b: &[GFSymbol],
+	log_walsh: &[GFSymbol],
+) -> std::result::Result<(), TableError> {
+	for element in 1..=MODULO {
+		if exp[log[element as usize] as usize] != element {
+			return Err(TableError::LogExpMismatch { element });
+		}
+	}
+	for &(index, expected) in KNOWN_SKEW_FACTORS.iter() {
+		if skew[index] != expected {
+			return Err(TableError::SkewFactor { index, expected, actual: skew[index] });
+		}
+	}
+	for &(table, expected, values) in [
+		("skew factor", SKEW_CHECKSUM, skew),
+		("formal derivative", B_CHECKSUM, b),
+		("log walsh", LOG_WALSH_CHECKSUM, log_walsh),
+	]
+	.iter()
+	{
+		let actual = checksum(values);
+		if actual != expected {
+			return Err(TableError::Checksum { table, expected, actual });
+		}
+	}
+	Ok(())
+}
+
+/// GF(2^16) as used by the `novel_poly_basis` backend, whose transforms are the generic ones
+/// in `afft` with the vectorized butterflies of the backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct F2e16;
+
+impl FieldAdd for F2e16 {
+	type Elt = GFSymbol;
+
+	const FIELD_BITS: usize = FIELD_BITS;
+
+	const MODULO: GFSymbol = MODULO;
+
+	fn setup() {
+		setup()
+	}
+
+	#[inline(always)]
+	fn mul(a: GFSymbol, log_b: GFSymbol) -> GFSymbol {
+		mul_table(a, log_b)
+	}
+
+	#[inline(always)]
+	fn skew(idx: usize) -> GFSymbol {
+		skew_factor(idx)
+	}
+
+	#[inline(always)]
+	fn butterfly_mul_add(lo: &mut [GFSymbol], hi: &[GFSymbol], log_skew: GFSymbol) {
+		butterfly_mul_add(lo, hi, log_skew)
+	}
+
+	#[inline(always)]
+	fn butterfly_add(lo: &[GFSymbol], hi: &mut [GFSymbol]) {
+		butterfly_add(lo, hi)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn walsh_is_split_across_threads() {
+		let data = crate::workload::random_payload(2 * 4096, 0)
+			.chunks_exact(2)
+			.map(|pair| u16::from_le_bytes([pair[0], pair[1]]) % MODULO)
+			.collect::<Vec<_>>();
+		let mut expected = data.clone();
+		walsh(&mut expected, 4096);
+		for threads in [1, 2, 3, 8, 64] {
+			let mut transformed = data.clone();
+			walsh_parallel(&mut transformed, 4096, threads);
+			assert_eq!(transformed, expected, "{} threads", threads);
+		}
+	}
+
+	#[test]
+	fn tables_verify() {
+		assert_eq!(verify_tables(), Ok(()));
+
+		let tables = unsafe {
+			[
+				(*std::ptr::addr_of!(LOG_TABLE)).to_vec(),
+				(*std::ptr::addr_of!(EXP_TABLE)).to_vec(),
+				(*std::ptr::addr_of!(SKEW_FACTOR)).to_vec(),
+				(*std::ptr::addr_of!(B)).to_vec(),
+				(*std::ptr::addr_of!(LOG_WALSH)).to_vec(),
+			]
+		};
+		let check = |tables: &[Vec<GFSymbol>; 5]| {
+			check_table_values(&tables[0], &tables[1], &tables[2], &tables[3], &tables[4])
+		};
+		let corrupt = |table: usize, idx: usize| {
+			let mut tables = tables.clone();
+			tables[table][idx] ^= 1;
+			check(&tables)
+		};
+		assert_eq!(check(&tables), Ok(()));
+		assert!(matches!(corrupt(1, 1234), Err(TableError::LogExpMismatch { .. })));
+		assert_eq!(corrupt(2, 100), Err(TableError::SkewFactor { index: 100, expected: 1028, actual: 1029 }));
+		assert!(matches!(corrupt(2, 101), Err(TableError::Checksum { table: "skew factor", .. })));
+		assert!(matches!(corrupt(3, 7), Err(TableError::Checksum { table: "formal derivative", .. })));
+		assert!(matches!(corrupt(4, 7), Err(TableError::Checksum { table: "log walsh", .. })));
+	}
+
+	// schoolbook multiplication modulo the field polynomial
+	fn mul_poly(mut a: GFSymbol, mut b: GFSymbol) -> GFSymbol {
+		let mut r = 0;
+		while b != 0 {
+			if b & 1 == 1 {
+				r ^= a;
+			}
+			b >>= 1;
+			a = (a << 1) ^ if a & 0x8000 != 0 { GENERATOR } else { 0 };
+		}
+		r
+	}
+
+	#[test]
+	fn cantor_basis() {
+		for i in 1..FIELD_BITS {
+			assert_eq!(mul_poly(BASE[i], BASE[i]) ^ BASE[i], BASE[i - 1]);
+		}
+	}
+
+	// `a^e` by square and multiply
+	fn pow(mut a: GFSymbol, mut e: usize) -> GFSymbol {
+		let mut acc = 1;
+		while e != 0 {
+			if e & 1 == 1 {
+				acc = mul_poly(acc, a);
+			}
+			a = mul_poly(a, a);
+			e >>= 1;
+		}
+		acc
+	}
+
+	// Recompute the Cantor basis and the skew factors from their definition in the polynomial
+	// basis, without any table, so a change of the table generation can not slip through.
+	#[test]
+	fn tables_match_algebraic_definition() {
+		// starting from 1, each element is the root of `b^2 + b = b[i - 1]` with the constant term cleared
+		let mut basis = vec![1];
+		for i in 1..FIELD_BITS {
+			let root = (0..=MODULO).find(|&b| b & 1 == 0 && mul_poly(b, b) ^ b == basis[i - 1]).unwrap();
+			basis.push(root);
+		}
+		assert_eq!(basis, BASE);
+
+		// the subspace polynomials `W[i](x) = prod (x - a)` over `a` in the span of `v[0], .., v[i - 1]` are
+		// linear, and satisfy `W[i + 1](x) = W[i](x) (W[i](x) + W[i](v[i]))`, so are fixed by `W[i](v[j])`
+		let mut w = vec![BASE];
+		for i in 0..FIELD_BITS - 1 {
+			let (prev, vi) = (w[i], w[i][i]);
+			let mut next = [0; FIELD_BITS];
+			for j in 0..FIELD_BITS {
+				next[j] = mul_poly(prev[j], prev[j] ^ vi);
+			}
+			w.push(next);
+		}
+
+		// the butterflies of layer `i` of the block starting at `beta` use the normalized
+		// `W[i](beta) / W[i](v[i])`, which is stored at `beta + 2^i - 1` by its logarithm
+		let skew = crate::novel_poly_basis::low_level::Tables::get().skew_factors();
+		let inverses = w.iter().enumerate().map(|(i, w)| pow(w[i], FIELD_SIZE - 2)).collect::<Vec<_>>();
+		for (idx, &log) in skew.iter().enumerate() {
+			let i = (idx + 1).trailing_zeros() as usize;
+			let beta = idx + 1 - (1 << i);
+			let value = (0..FIELD_BITS).filter(|j| beta >> j & 1 == 1).fold(0, |acc, j| acc ^ w[i][j]);
+			let expected = mul_poly(value, inverses[i]);
+			if expected == 0 {
+				assert_eq!(log, MODULO, "skew factor {}", idx);
+			} else {
+				assert_eq!(pow(2, log.into()), expected, "skew factor {}", idx);
+			}
+		}
+	}
+}
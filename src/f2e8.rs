@@ -0,0 +1,197 @@
+//! GF(2^8) with the tables required by the generic additive FFT.
+//!
+//! A GF(2^32) instantiation is not provided, its log and exp tables
+//! would take 32 GiB.
+
+#![allow(clippy::needless_range_loop)]
+
+use crate::afft::FieldAdd;
+
+const FIELD_BITS: usize = 8;
+
+const GENERATOR: u8 = 0x1D; //x^8 + x^4 + x^3 + x^2 + 1
+
+// Cantor basis
+const BASE: [u8; FIELD_BITS] = [1, 214, 152, 146, 86, 200, 88, 230];
+
+const FIELD_SIZE: usize = 1 << FIELD_BITS;
+
+const MODULO: u8 = (FIELD_SIZE - 1) as u8;
+
+#[cfg(feature = "tables-f2e8")]
+mod generated {
+	include!(concat!(env!("OUT_DIR"), "/f2e8_tables.rs"));
+}
+
+struct Tables {
+	log: [u8; FIELD_SIZE],
+	exp: [u8; FIELD_SIZE],
+	skew: [u8; MODULO as usize],
+}
+
+impl Tables {
+	// same construction as `f2e16::init` and the skew factors of `init_dec`
+	#[cfg_attr(feature = "tables-f2e8", allow(dead_code))]
+	fn new() -> Self {
+		let mut log = [0_u8; FIELD_SIZE];
+		let mut exp = [0_u8; FIELD_SIZE];
+
+		let mas: u8 = (1 << (FIELD_BITS - 1)) - 1;
+		let mut state: usize = 1;
+		for i in 0..(MODULO as usize) {
+			exp[state] = i as u8;
+			if (state >> (FIELD_BITS - 1)) != 0 {
+				state &= mas as usize;
+				state = (state << 1) ^ GENERATOR as usize;
+			} else {
+				state <<= 1;
+			}
+		}
+		exp[0] = MODULO;
+
+		for i in 0..FIELD_BITS {
+			for j in 0..(1 << i) {
+				log[j + (1 << i)] = log[j] ^ BASE[i];
+			}
+		}
+		for i in 0..FIELD_SIZE {
+			log[i] = exp[log[i] as usize];
+		}
+		for i in 0..FIELD_SIZE {
+			exp[log[i] as usize] = i as u8;
+		}
+		exp[MODULO as usize] = exp[0];
+
+		let mut tables = Tables { log, exp, skew: [0_u8; MODULO as usize] };
+
+		let mut base = [0_u8; FIELD_BITS - 1];
+		for i in 1..FIELD_BITS {
+			base[i - 1] = 1 << i;
+		}
+		for m in 0..(FIELD_BITS - 1) {
+			let step = 1 << (m + 1);
+			tables.skew[(1 << m) - 1] = 0;
+			for i in m..(FIELD_BITS - 1) {
+				let s = 1 << (i + 1);
+				let mut j = (1 << m) - 1;
+				while j < s {
+					tables.skew[j + s] = tables.skew[j] ^ base[i];
+					j += step;
+				}
+			}
+
+			let idx = tables.mul(base[m], tables.log[(base[m] ^ 1) as usize]);
+			base[m] = MODULO - tables.log[idx as usize];
+
+			for i in (m + 1)..(FIELD_BITS - 1) {
+				let b = (tables.log[(base[i] ^ 1) as usize] as u32 + base[m] as u32) % MODULO as u32;
+				base[i] = tables.mul(base[i], b as u8);
+			}
+		}
+		for i in 0..(MODULO as usize) {
+			tables.skew[i] = tables.log[tables.skew[i] as usize];
+		}
+		tables
+	}
+
+	fn mul(&self, a: u8, log_b: u8) -> u8 {
+		if a == 0 {
+			return 0;
+		}
+		let sum = self.log[a as usize] as u32 + log_b as u32;
+		self.exp[((sum & MODULO as u32) + (sum >> FIELD_BITS)) as usize]
+	}
+}
+
+#[cfg(not(feature = "tables-f2e8"))]
+fn tables() -> &'static Tables {
+	static TABLES: std::sync::OnceLock<Tables> = std::sync::OnceLock::new();
+	TABLES.get_or_init(Tables::new)
+}
+
+#[cfg(feature = "tables-f2e8")]
+fn tables() -> &'static Tables {
+	static TABLES: Tables = Tables { log: generated::LOG, exp: generated::EXP, skew: generated::SKEW };
+	&TABLES
+}
+
+/// GF(2^8) in the novel polynomial basis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct F2e8;
+
+impl FieldAdd for F2e8 {
+	type Elt = u8;
+
+	const FIELD_BITS: usize = FIELD_BITS;
+
+	const MODULO: u8 = MODULO;
+
+	fn setup() {
+		tables();
+	}
+
+	#[inline(always)]
+	fn mul(a: u8, log_b: u8) -> u8 {
+		tables().mul(a, log_b)
+	}
+
+	#[inline(always)]
+	fn skew(idx: usize) -> u8 {
+		tables().skew[idx]
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::afft::{afft, inverse_afft};
+
+	// schoolbook multiplication modulo the field polynomial
+	fn mul_poly(mut a: u8, mut b: u8) -> u8 {
+		let mut r = 0;
+		while b != 0 {
+			if b & 1 == 1 {
+				r ^= a;
+			}
+			b >>= 1;
+			a = (a << 1) ^ if a & 0x80 != 0 { GENERATOR } else { 0 };
+		}
+		r
+	}
+
+	#[test]
+	fn cantor_basis() {
+		for i in 1..FIELD_BITS {
+			assert_eq!(mul_poly(BASE[i], BASE[i]) ^ BASE[i], BASE[i - 1]);
+		}
+	}
+
+	#[test]
+	fn log_exp_are_inverse() {
+		let tables = tables();
+		for a in 1..=255_u8 {
+			assert_eq!(tables.exp[tables.log[a as usize] as usize], a);
+		}
+	}
+
+	#[cfg(feature = "tables-f2e8")]
+	#[test]
+	fn generated_tables_match() {
+		let (generated, computed) = (tables(), Tables::new());
+		assert_eq!(generated.log, computed.log);
+		assert_eq!(generated.exp, computed.exp);
+		assert_eq!(generated.skew, computed.skew);
+	}
+
+	#[test]
+	fn afft_roundtrip() {
+		let data = crate::workload::random_payload(64, 0);
+		for &index in &[0, 64, 128] {
+			let mut transformed = data.clone();
+			inverse_afft::<F2e8>(&mut transformed, 64, index);
+			assert_ne!(transformed, data);
+			afft::<F2e8>(&mut transformed, 64, index);
+			assert_eq!(transformed, data);
+		}
+	}
+}
@@ -0,0 +1,118 @@
+//! Seeded corruption of the decoder's intermediate buffers, to confirm that
+//! `novel_poly_basis::reconstruct_checked` detects faults in the decoder itself.
+//!
+//! Faults are only injected with the `fault-injection` feature, otherwise the
+//! hooks compile to nothing and `with_fault` merely runs its closure.
+
+use crate::f2e16::GFSymbol;
+
+/// Where the decoder is corrupted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+	/// Flip bits of one symbol of the received codeword after its inverse transform.
+	FlipAfterIfft,
+	/// Scale one row by a wrong factor before the formal derivative.
+	ScrambleSkew,
+}
+
+#[cfg(feature = "fault-injection")]
+thread_local! {
+	static ARMED: std::cell::RefCell<Option<(Fault, rand::rngs::StdRng)>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Run `f` with `fault` injected into every batch decoded on the current thread,
+/// deterministic in `seed`.
+pub fn with_fault<R>(fault: Fault, seed: u64, f: impl FnOnce() -> R) -> R {
+	#[cfg(feature = "fault-injection")]
+	{
+		use rand::SeedableRng;
+		let before = ARMED.with(|armed| armed.replace(Some((fault, rand::rngs::StdRng::seed_from_u64(seed)))));
+		let r = f();
+		ARMED.with(|armed| armed.replace(before));
+		r
+	}
+	#[cfg(not(feature = "fault-injection"))]
+	{
+		let _ = (fault, seed);
+		f()
+	}
+}
+
+/// Hook after the inverse transform of the decoder, for `Fault::FlipAfterIfft`.
+#[inline(always)]
+pub(crate) fn after_ifft(codeword: &mut [GFSymbol]) {
+	#[cfg(feature = "fault-injection")]
+	ARMED.with(|armed| {
+		use rand::Rng;
+		if let Some((Fault::FlipAfterIfft, rng)) = armed.borrow_mut().as_mut() {
+			let at = rng.gen_range(0..codeword.len());
+			codeword[at] ^= rng.gen_range(1..=GFSymbol::MAX);
+		}
+	});
+	#[cfg(not(feature = "fault-injection"))]
+	let _ = codeword;
+}
+
+/// Hook before the formal derivative of `rows` rows is scaled, for `Fault::ScrambleSkew`,
+/// yields the row and the log offset to its factor.
+#[inline(always)]
+pub(crate) fn scramble_skew(rows: usize) -> Option<(usize, GFSymbol)> {
+	#[cfg(feature = "fault-injection")]
+	return ARMED.with(|armed| match armed.borrow_mut().as_mut() {
+		Some((Fault::ScrambleSkew, rng)) => {
+			use rand::Rng;
+			Some((rng.gen_range(0..rows), rng.gen_range(1..crate::f2e16::MODULO)))
+		}
+		_ => None,
+	});
+	#[cfg(not(feature = "fault-injection"))]
+	{
+		let _ = rows;
+		None
+	}
+}
+
+#[cfg(all(test, feature = "fault-injection"))]
+mod tests {
+	use super::*;
+	use crate::*;
+
+	// decode with `fault` for several seeds, requiring every wrong payload to be flagged
+	fn detects(fault: Fault) {
+		let params = CodeParams::new(64, 16).unwrap();
+		let payload = &random_payload(1000, 0);
+		let shards = novel_poly_basis::encode_with_params(&params, payload).unwrap();
+		let mut corrupted = 0;
+		for seed in 0..16 {
+			// a window of `k + 8` shards, starting at a different offset for each seed
+			let kept = |idx: usize| (idx + 64 - 4 * seed as usize) % 64 < params.k() + 8;
+			let received = shards
+				.iter()
+				.enumerate()
+				.map(|(idx, shard)| Some(shard.as_ref()).filter(|_| kept(idx)))
+				.collect::<Vec<Option<&[u8]>>>();
+
+			let recovered = with_fault(fault, seed, || novel_poly_basis::reconstruct_ref_with_params(&params, &received));
+			if recovered.unwrap()[..payload.len()] == payload[..] {
+				continue;
+			}
+			corrupted += 1;
+			let checked = with_fault(fault, seed, || novel_poly_basis::reconstruct_checked(&params, &received));
+			assert!(matches!(checked, Err(Error::InconsistentShard(_))), "{:?} seed {} went unnoticed", fault, seed);
+		}
+		assert!(corrupted > 0, "{:?} never corrupted the payload", fault);
+
+		let received = shards.iter().map(|shard| Some(shard.as_ref())).collect::<Vec<_>>();
+		assert!(novel_poly_basis::reconstruct_checked(&params, &received).is_ok());
+	}
+
+	#[test]
+	fn detects_flip_after_ifft() {
+		detects(Fault::FlipAfterIfft);
+	}
+
+	#[test]
+	fn detects_scrambled_skew() {
+		detects(Fault::ScrambleSkew);
+	}
+}
@@ -0,0 +1,130 @@
+//! Shard framing with a leading codec version byte.
+//!
+//! Nodes which upgrade at different times may hold shards of different codecs,
+//! the tag lets `reconstruct` pick the right backend without out of band
+//! signalling. Unknown tags are rejected, so new codecs can be introduced
+//! before they are used for encoding.
+
+use super::*;
+
+use auto::{Backend, Selector};
+
+/// Codec and format version of a framed shard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CodecVersion {
+	NovelPolyBasisV1 = 1,
+	StatusQuoV1 = 2,
+}
+
+impl CodecVersion {
+	/// The current version for `backend`.
+	pub fn current(backend: Backend) -> Self {
+		match backend {
+			Backend::NovelPolyBasis => CodecVersion::NovelPolyBasisV1,
+			Backend::StatusQuo => CodecVersion::StatusQuoV1,
+		}
+	}
+
+	/// The backend to decode this version with.
+	pub fn backend(self) -> Backend {
+		match self {
+			CodecVersion::NovelPolyBasisV1 => Backend::NovelPolyBasis,
+			CodecVersion::StatusQuoV1 => Backend::StatusQuo,
+		}
+	}
+
+	pub fn from_byte(tag: u8) -> Result<Self> {
+		match tag {
+			1 => Ok(CodecVersion::NovelPolyBasisV1),
+			2 => Ok(CodecVersion::StatusQuoV1),
+			other => Err(Error::UnknownCodecVersion(other)),
+		}
+	}
+}
+
+/// Prepend the version tag to `shard`.
+pub fn frame(version: CodecVersion, shard: &[u8]) -> Vec<u8> {
+	let mut framed = Vec::with_capacity(shard.len() + 1);
+	framed.push(version as u8);
+	framed.extend_from_slice(shard);
+	framed
+}
+
+/// Split a framed shard into its version and the shard itself.
+pub fn unframe(framed: &[u8]) -> Result<(CodecVersion, &[u8])> {
+	let (tag, shard) = framed.split_first().ok_or(Error::EmptyFrame)?;
+	Ok((CodecVersion::from_byte(*tag)?, shard))
+}
+
+/// Encode with the backend picked by `selector` into framed shards.
+pub fn encode(selector: &Selector, params: &CodeParams, data: &[u8]) -> Result<Vec<Vec<u8>>> {
+	let version = CodecVersion::current(selector.select_for_payload(params, data.len()));
+	let shards = selector.encode(params, data)?;
+	Ok(shards.iter().map(|shard| frame(version, shard.as_ref())).collect())
+}
+
+/// Reconstruct from framed shards with the backend given by their tags,
+/// which must all agree.
+pub fn reconstruct(params: &CodeParams, received_shards: &[Option<&[u8]>]) -> Result<Vec<u8>> {
+	let mut version = None;
+	let mut unframed = Vec::with_capacity(received_shards.len());
+	for framed in received_shards {
+		let shard = match framed {
+			Some(framed) => {
+				let (v, shard) = unframe(framed)?;
+				match version {
+					Some(first) if first != v => {
+						return Err(Error::MixedCodecVersions { first: first as u8, other: v as u8 })
+					}
+					_ => version = Some(v),
+				}
+				Some(shard)
+			}
+			None => None,
+		};
+		unframed.push(shard);
+	}
+
+	// without any shard the backend does not matter, both report too few shards
+	match version.map(CodecVersion::backend).unwrap_or(Backend::NovelPolyBasis) {
+		Backend::NovelPolyBasis => novel_poly_basis::reconstruct_ref_with_params(params, &unframed),
+		Backend::StatusQuo => status_quo::reconstruct_ref_with_params(params, &unframed),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn dispatches_on_tag() {
+		let params = CodeParams::new(12, 4).unwrap();
+		let payload = &random_payload(1000, 0);
+		for backend in [Backend::StatusQuo, Backend::NovelPolyBasis] {
+			let framed = encode(&Selector::fixed(backend), &params, payload).unwrap();
+			assert!(framed.iter().all(|shard| shard[0] == CodecVersion::current(backend) as u8));
+
+			let mut received = framed.iter().map(|shard| Some(&shard[..])).collect::<Vec<_>>();
+			for shard in received.iter_mut().take(params.n() - params.k()) {
+				*shard = None;
+			}
+			assert_eq!(&reconstruct(&params, &received).unwrap()[..payload.len()], payload);
+		}
+	}
+
+	#[test]
+	fn rejects_unknown_and_mixed_tags() {
+		let params = CodeParams::new(4, 2).unwrap();
+		let novel = frame(CodecVersion::NovelPolyBasisV1, &[0, 0]);
+		let status_quo = frame(CodecVersion::StatusQuoV1, &[0, 0]);
+		let unknown = [7_u8, 0, 0];
+
+		assert_eq!(
+			reconstruct(&params, &[Some(&novel), Some(&status_quo), None, None]),
+			Err(Error::MixedCodecVersions { first: 1, other: 2 })
+		);
+		assert_eq!(reconstruct(&params, &[Some(&unknown), None, None, None]), Err(Error::UnknownCodecVersion(7)));
+		assert_eq!(reconstruct(&params, &[Some(&[]), None, None, None]), Err(Error::EmptyFrame));
+	}
+}
@@ -0,0 +1,177 @@
+//! Self describing shards, each prefixed with a header record of the code parameters,
+//! the codec and the exact payload length, so `reconstruct` needs no out of band metadata
+//! and returns the payload without trailing padding.
+//!
+//! The header is the magic `RSEH`, the format version, the codec version tag of `framing`,
+//! the endianness and layout bytes, then `n`, `k` and the symbol group as little endian
+//! `u32`s and the payload length as little endian `u64`. It closes with the first four
+//! bytes of the SHA-256 digest of all previous ones. Every shard carries the same header,
+//! so any received shard describes the code.
+
+use super::*;
+
+use auto::{Backend, Selector};
+use framing::CodecVersion;
+use sha2::{Digest, Sha256};
+use std::convert::{TryFrom, TryInto};
+use std::num::NonZeroUsize;
+
+const MAGIC: &[u8; 4] = b"RSEH";
+const FORMAT_VERSION: u8 = 1;
+const CHECKSUM_LEN: usize = 4;
+
+/// Length in bytes of the header in front of every shard.
+pub const HEADER_LEN: usize = 32;
+
+/// The metadata in front of every shard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShardHeader {
+	pub version: CodecVersion,
+	pub params: CodeParams,
+	pub payload_len: usize,
+}
+
+fn checksum(bytes: &[u8]) -> [u8; CHECKSUM_LEN] {
+	Sha256::digest(bytes)[..CHECKSUM_LEN].try_into().expect("digests exceed the checksum. qed")
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> usize {
+	u32::from_le_bytes(bytes[at..at + 4].try_into().expect("slice has 4 bytes. qed")) as usize
+}
+
+impl ShardHeader {
+	/// Serialize the header, failing if a parameter exceeds its field.
+	pub fn to_bytes(&self) -> Result<[u8; HEADER_LEN]> {
+		let u32_le = |value: usize| {
+			u32::try_from(value).map(u32::to_le_bytes).map_err(|_| Error::MalformedShardHeader("value exceeds u32"))
+		};
+		let endianness = match self.params.endianness() {
+			Endianness::Little => 0,
+			Endianness::Big => 1,
+		};
+		let layout = match self.params.layout() {
+			ShardLayout::Interleaved => 0,
+			ShardLayout::Sequential => 1,
+		};
+		let mut bytes = [0_u8; HEADER_LEN];
+		bytes[..4].copy_from_slice(MAGIC);
+		bytes[4..8].copy_from_slice(&[FORMAT_VERSION, self.version as u8, endianness, layout]);
+		bytes[8..12].copy_from_slice(&u32_le(self.params.n())?);
+		bytes[12..16].copy_from_slice(&u32_le(self.params.k())?);
+		bytes[16..20].copy_from_slice(&u32_le(self.params.symbol_group())?);
+		bytes[20..28].copy_from_slice(&(self.payload_len as u64).to_le_bytes());
+		let sum = checksum(&bytes[..HEADER_LEN - CHECKSUM_LEN]);
+		bytes[HEADER_LEN - CHECKSUM_LEN..].copy_from_slice(&sum);
+		Ok(bytes)
+	}
+
+	/// Split a shard into its header and the shard itself.
+	pub fn parse(shard: &[u8]) -> Result<(Self, &[u8])> {
+		if shard.len() < HEADER_LEN {
+			return Err(Error::MalformedShardHeader("shard shorter than the header"));
+		}
+		let (bytes, rest) = shard.split_at(HEADER_LEN);
+		if &bytes[..4] != MAGIC {
+			return Err(Error::MalformedShardHeader("not a shard header"));
+		}
+		if bytes[HEADER_LEN - CHECKSUM_LEN..] != checksum(&bytes[..HEADER_LEN - CHECKSUM_LEN]) {
+			return Err(Error::MalformedShardHeader("checksum mismatch"));
+		}
+		if bytes[4] != FORMAT_VERSION {
+			return Err(Error::MalformedShardHeader("unknown format version"));
+		}
+		let version = CodecVersion::from_byte(bytes[5])?;
+		let endianness = match bytes[6] {
+			0 => Endianness::Little,
+			1 => Endianness::Big,
+			_ => return Err(Error::MalformedShardHeader("unknown endianness")),
+		};
+		let layout = match bytes[7] {
+			0 => ShardLayout::Interleaved,
+			1 => ShardLayout::Sequential,
+			_ => return Err(Error::MalformedShardHeader("unknown layout")),
+		};
+		let group = NonZeroUsize::new(read_u32(bytes, 16)).ok_or(Error::MalformedShardHeader("empty symbol group"))?;
+		let params = CodeParams::new(read_u32(bytes, 8), read_u32(bytes, 12))?
+			.with_endianness(endianness)
+			.with_layout(layout)
+			.with_symbol_group(group);
+		let payload_len = u64::from_le_bytes(bytes[20..28].try_into().expect("slice has 8 bytes. qed"));
+		let payload_len =
+			usize::try_from(payload_len).map_err(|_| Error::MalformedShardHeader("payload length exceeds usize"))?;
+		Ok((ShardHeader { version, params, payload_len }, rest))
+	}
+}
+
+/// Encode with the backend picked by `selector` into shards prefixed with their header.
+pub fn encode(selector: &Selector, params: &CodeParams, data: &[u8]) -> Result<Vec<Vec<u8>>> {
+	let version = CodecVersion::current(selector.select_for_payload(params, data.len()));
+	let header = ShardHeader { version, params: *params, payload_len: data.len() }.to_bytes()?;
+	let shards = selector.encode(params, data)?;
+	Ok(shards.iter().map(|shard| [&header[..], shard.as_ref()].concat()).collect())
+}
+
+/// Reconstruct the exact payload from shards encoded by `encode`, whose headers must agree,
+/// given at their positions among the `n` shards of the code.
+pub fn reconstruct(received_shards: &[Option<&[u8]>]) -> Result<Vec<u8>> {
+	let mut header = None;
+	let mut shards = Vec::with_capacity(received_shards.len());
+	for shard in received_shards {
+		shards.push(match shard {
+			Some(shard) => {
+				let (parsed, shard) = ShardHeader::parse(shard)?;
+				if header.is_some_and(|header| header != parsed) {
+					return Err(Error::MalformedShardHeader("headers disagree"));
+				}
+				header = Some(parsed);
+				Some(shard)
+			}
+			None => None,
+		});
+	}
+	let header = header.ok_or(Error::TooFewShards { available: 0, required: 1 })?;
+
+	let mut payload = match header.version.backend() {
+		Backend::NovelPolyBasis => novel_poly_basis::reconstruct_ref_with_params(&header.params, &shards)?,
+		Backend::StatusQuo => status_quo::reconstruct_ref_with_params(&header.params, &shards)?,
+	};
+	if payload.len() < header.payload_len {
+		return Err(Error::BadPayload);
+	}
+	payload.truncate(header.payload_len);
+	Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn headers_describe_the_code() {
+		let params = CodeParams::new(12, 4).unwrap().with_layout(ShardLayout::Sequential);
+		let payload = &random_payload(1001, 0);
+		for backend in [Backend::StatusQuo, Backend::NovelPolyBasis] {
+			let shards = encode(&Selector::fixed(backend), &params, payload).unwrap();
+			let (header, _) = ShardHeader::parse(&shards[3]).unwrap();
+			let version = CodecVersion::current(backend);
+			assert_eq!(header, ShardHeader { version, params, payload_len: 1001 });
+
+			let mut received = shards.iter().map(|shard| Some(&shard[..])).collect::<Vec<_>>();
+			received.iter_mut().skip(2).take(8).for_each(|shard| *shard = None);
+			assert_eq!(&reconstruct(&received).unwrap(), payload);
+		}
+
+		let shards = encode(&Selector::fixed(Backend::NovelPolyBasis), &params, payload).unwrap();
+		let mut corrupted = shards[0].clone();
+		corrupted[20] ^= 1;
+		assert_eq!(ShardHeader::parse(&corrupted), Err(Error::MalformedShardHeader("checksum mismatch")));
+		let other = ShardHeader { version: CodecVersion::NovelPolyBasisV1, params, payload_len: 1000 };
+		let other = [&other.to_bytes().unwrap()[..], &shards[1][HEADER_LEN..]].concat();
+		let mut received = vec![None; 12];
+		received[0] = Some(&shards[0][..]);
+		received[1] = Some(&other[..]);
+		assert_eq!(reconstruct(&received), Err(Error::MalformedShardHeader("headers disagree")));
+		let truncated = Error::MalformedShardHeader("shard shorter than the header");
+		assert_eq!(ShardHeader::parse(&shards[0][..HEADER_LEN - 1]), Err(truncated));
+	}
+}
@@ -1,25 +1,126 @@
+mod errors;
+pub use errors::*;
+
+mod params;
+pub use params::*;
+
+mod symbols;
+pub use symbols::*;
+
 mod wrapped_shard;
 pub use wrapped_shard::*;
 
+mod chunk_index;
+pub use chunk_index::*;
+
+mod erasure_map;
+pub use erasure_map::*;
+
+mod shard_arena;
+pub use shard_arena::*;
+
 pub mod status_quo;
 
+pub mod afft;
+
+pub mod f2e8;
+
+pub mod f2e16;
+
 pub mod novel_poly_basis;
 
+pub mod fp31;
+
+pub mod auto;
+
+pub mod registry;
+
+pub mod framing;
+
+pub mod header;
+
+pub mod packing;
+
+#[cfg(feature = "mmap")]
+pub mod mmap;
+
+pub mod transform;
+
+pub mod ordering;
+
+pub mod sink;
+
+pub mod stream;
+
+pub mod lrc;
+
+pub mod repair;
+
+pub mod network_coding;
+
+pub mod differential;
+
+#[cfg(feature = "custody")]
+pub mod custody;
+
+#[cfg(feature = "codec")]
+pub mod availability;
+
+pub mod profiling;
+
+pub mod stats;
+
+pub mod fault;
+
+pub mod pov;
+
+pub mod corpus;
+
+pub mod workload;
+
+pub mod sweep;
+
+pub mod benchmark;
+
+#[cfg(feature = "testing")]
+pub mod testing;
+
+/// Perform all one time setup, i.e. build the field tables and detect the SIMD kernel,
+/// which otherwise happens on the first encode or reconstruct. Cheap once done.
+pub fn warm_up() {
+	f2e16::setup();
+	novel_poly_basis::codec_features();
+	<f2e8::F2e8 as afft::FieldAdd>::setup();
+}
+
+/// `warm_up` on a background thread, e.g. at process start. Encoding or reconstructing
+/// before it finished blocks until the tables are ready. Fails if the thread can not be
+/// spawned, in which case the tables are still built on first use.
+pub fn warm_up_in_background() -> std::io::Result<std::thread::JoinHandle<()>> {
+	std::thread::Builder::new().name("rs-ec-warm-up".into()).spawn(warm_up)
+}
+
 // we want one message per validator, so this is the total number of shards that we should own
 // after
 const N_VALIDATORS: usize = 16; //256;
 const DATA_SHARDS: usize = 4; // N_VALIDATORS / 3;
-const PARITY_SHARDS: usize = N_VALIDATORS - DATA_SHARDS;
 
-pub const BYTES: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/rand_data.bin"));
+#[cfg(test)]
+use workload::random_payload;
 
-pub fn roundtrip<E, R>(encode: E, reconstruct: R, payload: &[u8])
+/// Encode a payload of `len` bytes from `workload`, erase random shards and check the reconstruction.
+///
+/// Benchmarks pass a payload generated outside of the measured loop, a slice being a `Workload`.
+pub fn roundtrip<E, R, W>(encode: E, reconstruct: R, workload: &W, len: usize)
 where
-	E: Fn(&[u8]) -> Vec<WrappedShard>,
-	R: Fn(Vec<Option<WrappedShard>>) -> Option<Vec<u8>>,
+	E: Fn(&[u8]) -> Result<Vec<WrappedShard>>,
+	R: Fn(Vec<Option<WrappedShard>>) -> Result<Vec<u8>>,
+	W: workload::Workload + ?Sized,
 {
+	let payload = &workload.payload(len)[..];
+
 	// Construct the shards
-	let encoded = encode(payload);
+	let encoded = encode(payload).expect("encoding must work");
 
 	// Make a copy and transform it into option shards arrangement
 	// for feeding into reconstruct_shards
@@ -36,21 +137,257 @@ where
 
 	let result = reconstruct(shards).expect("reconstruction must work");
 
-	// the result might have trailing zeros
-	assert_eq!(&payload[..], &result[0..payload.len()]);
+	let report = RoundtripReport::compare(payload, &result);
+	assert!(report.is_ok(), "{}", report);
+}
+
+/// `roundtrip` repeated until `budget` elapsed, but at least once, returning the number of
+/// roundtrips, for benchmarks bounded by time instead of by iterations.
+pub fn roundtrip_for<E, R, W>(encode: E, reconstruct: R, workload: &W, len: usize, budget: std::time::Duration) -> usize
+where
+	E: Fn(&[u8]) -> Result<Vec<WrappedShard>>,
+	R: Fn(Vec<Option<WrappedShard>>) -> Result<Vec<u8>>,
+	W: workload::Workload + ?Sized,
+{
+	let start = std::time::Instant::now();
+	let mut roundtrips = 0;
+	while roundtrips == 0 || start.elapsed() < budget {
+		roundtrip(&encode, &reconstruct, workload, len);
+		roundtrips += 1;
+	}
+	roundtrips
+}
+
+/// How a reconstructed payload compares to the original one, see `roundtrip_checked`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RoundtripReport {
+	pub payload_len: usize,
+	/// Length of the reconstruction, which might have trailing zeros.
+	pub reconstructed_len: usize,
+	/// Offset of the first payload byte which was not recovered, `None` if all were.
+	pub first_mismatch: Option<usize>,
+}
+
+impl RoundtripReport {
+	/// Compare `reconstructed` against `payload`, ignoring bytes beyond the payload.
+	pub fn compare(payload: &[u8], reconstructed: &[u8]) -> Self {
+		let first_mismatch = payload
+			.iter()
+			.zip(reconstructed)
+			.position(|(expected, got)| expected != got)
+			.or_else(|| Some(reconstructed.len()).filter(|len| *len < payload.len()));
+		RoundtripReport { payload_len: payload.len(), reconstructed_len: reconstructed.len(), first_mismatch }
+	}
+
+	/// Whether the whole payload was recovered.
+	pub fn is_ok(&self) -> bool {
+		self.first_mismatch.is_none()
+	}
+}
+
+impl std::fmt::Display for RoundtripReport {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self.first_mismatch {
+			None => write!(f, "recovered all {} bytes", self.payload_len),
+			Some(offset) => write!(
+				f,
+				"mismatch at offset {} of {} bytes, {} bytes reconstructed",
+				offset, self.payload_len, self.reconstructed_len
+			),
+		}
+	}
+}
+
+/// `roundtrip` through `coder` which reports instead of panicking.
+///
+/// A random `params.n() - params.k()` of the shards are erased. Failures to encode or to
+/// reconstruct are returned as errors, a wrong reconstruction in the report.
+pub fn roundtrip_checked<C>(coder: &C, params: &CodeParams, payload: &[u8]) -> Result<RoundtripReport>
+where
+	C: registry::ErasureCoder + ?Sized,
+{
+	let mut shards = coder.encode(params, payload)?.into_iter().map(Some).collect::<Vec<_>>();
+	let mut rng = rand::thread_rng();
+	for idx in rand::seq::index::sample(&mut rng, params.n(), params.n() - params.k()) {
+		shards[idx] = None;
+	}
+	let result = coder.reconstruct(params, shards)?;
+	Ok(RoundtripReport::compare(payload, &result))
 }
 
 #[cfg(test)]
 mod test {
 	use super::*;
 
+	fn as_refs(shards: &[Option<WrappedShard>]) -> Vec<Option<&[u8]>> {
+		shards.iter().map(|shard| shard.as_ref().map(AsRef::as_ref)).collect()
+	}
+
+	#[test]
+	fn warm_up_builds_tables() {
+		warm_up_in_background().unwrap().join().unwrap();
+		assert_eq!(novel_poly_basis::verify_tables(), Ok(()));
+		warm_up();
+	}
+
 	#[test]
 	fn status_quo_roundtrip() {
-		roundtrip(status_quo::encode, status_quo::reconstruct, &BYTES[0..32])
+		roundtrip(status_quo::encode, status_quo::reconstruct, &workload::Random(0), 32)
 	}
 
 	#[test]
 	fn novel_poly_basis_roundtrip() {
-		roundtrip(novel_poly_basis::encode, novel_poly_basis::reconstruct, &BYTES[0..32])
+		roundtrip(novel_poly_basis::encode, novel_poly_basis::reconstruct, &workload::Random(0), 32)
+	}
+
+	#[test]
+	fn status_quo_roundtrip_ref() {
+		roundtrip(
+			status_quo::encode,
+			|shards| status_quo::reconstruct_ref(&as_refs(&shards)),
+			&workload::Random(0),
+			32,
+		)
+	}
+
+	#[test]
+	fn novel_poly_basis_roundtrip_ref() {
+		roundtrip(
+			novel_poly_basis::encode,
+			|shards| novel_poly_basis::reconstruct_ref(&as_refs(&shards)),
+			&workload::Random(0),
+			32,
+		)
+	}
+
+	#[test]
+	fn odd_payload_sizes_roundtrip() {
+		let payload = random_payload(99, 0);
+		for &len in &[1, 3, 7, 31, 33, 63, 65, 99] {
+			roundtrip(status_quo::encode, status_quo::reconstruct, &payload[..len], len);
+			roundtrip(novel_poly_basis::encode, novel_poly_basis::reconstruct, &payload[..len], len);
+		}
+	}
+
+	#[test]
+	fn roundtrips_are_time_bounded() {
+		use std::time::Duration;
+
+		let payload = &random_payload(32, 0)[..];
+		let budget = Duration::from_millis(50);
+		let once = roundtrip_for(novel_poly_basis::encode, novel_poly_basis::reconstruct, payload, 32, Duration::ZERO);
+		assert_eq!(once, 1);
+		let start = std::time::Instant::now();
+		assert!(roundtrip_for(status_quo::encode, status_quo::reconstruct, payload, 32, budget) > 1);
+		assert!(start.elapsed() >= budget);
+	}
+
+	#[test]
+	fn plain_wrappers_return_errors() {
+		let payload = &random_payload(32, 0);
+		let mut received = status_quo::encode(payload).unwrap().into_iter().map(Some).collect::<Vec<_>>();
+		received.iter_mut().skip(DATA_SHARDS - 1).for_each(|shard| *shard = None);
+		let too_few = Error::TooFewShards { available: DATA_SHARDS - 1, required: DATA_SHARDS };
+		assert_eq!(status_quo::reconstruct(received), Err(too_few));
+
+		let mut received = novel_poly_basis::encode(payload).unwrap().into_iter().map(Some).collect::<Vec<_>>();
+		received.iter_mut().skip(3).for_each(|shard| *shard = None);
+		assert!(matches!(novel_poly_basis::reconstruct(received), Err(Error::TooFewShards { available: 3, .. })));
+	}
+
+	#[cfg(feature = "mmap")]
+	#[test]
+	fn plain_wrappers_reject_oversized_payloads() {
+		// a sparse file maps an oversized payload without allocating it
+		let file = tempfile::tempfile().unwrap();
+		file.set_len(CodeParams::default().max_payload_len() as u64 + 1).unwrap();
+		// Safety: the file is private to this test and never truncated
+		let oversized = unsafe { memmap2::Mmap::map(&file) }.unwrap();
+		assert!(matches!(status_quo::encode(&oversized), Err(Error::PayloadTooLarge { .. })));
+		assert!(matches!(novel_poly_basis::encode(&oversized), Err(Error::PayloadTooLarge { .. })));
+	}
+
+	#[test]
+	fn erasure_position_matrix() {
+		for params in
+			[CodeParams::new(16, 4).unwrap(), CodeParams::new(32, 8).unwrap(), CodeParams::new(10, 3).unwrap()]
+		{
+			let (n, k) = (params.n(), params.k());
+			let payload = &random_payload(1000, 0);
+			let patterns: Vec<(&str, Vec<usize>)> = vec![
+				("all data erased", (k..2 * k).collect()),
+				("last parity only", (n - k..n).collect()),
+				("all parity erased", (0..k).collect()),
+				("interleaved", (0..n).step_by(n / k).take(k).collect()),
+				("mixed", (k / 2..k / 2 + k).collect()),
+			];
+			for (name, available) in patterns {
+				assert_eq!(available.len(), k, "{} must keep exactly k shards", name);
+
+				let shards = status_quo::encode_with_params(&params, payload).unwrap();
+				let received =
+					shards.into_iter().enumerate().map(|(i, s)| Some(s).filter(|_| available.contains(&i))).collect();
+				let recovered = status_quo::reconstruct_with_params(&params, received).unwrap();
+				assert_eq!(&recovered[..payload.len()], payload, "status quo {} n={} k={}", name, n, k);
+
+				let shards = novel_poly_basis::encode_with_params(&params, payload).unwrap();
+				let received =
+					shards.into_iter().enumerate().map(|(i, s)| Some(s).filter(|_| available.contains(&i))).collect();
+				let recovered = novel_poly_basis::reconstruct_with_params(&params, received).unwrap();
+				assert_eq!(&recovered[..payload.len()], payload, "novel poly basis {} n={} k={}", name, n, k);
+			}
+		}
+	}
+
+	#[test]
+	fn checked_roundtrip_reports_mismatches() {
+		let params = CodeParams::new(16, 4).unwrap();
+		let payload = &random_payload(1000, 0);
+		let registry = registry::Registry::default();
+		for name in registry.names() {
+			let report = roundtrip_checked(registry.get(name).unwrap(), &params, payload).unwrap();
+			assert!(report.is_ok(), "{}: {}", name, report);
+		}
+		assert!(roundtrip_checked(&auto::Backend::NovelPolyBasis, &params, payload).unwrap().is_ok());
+
+		let mut altered = payload.to_vec();
+		altered[700] ^= 1;
+		altered.push(0);
+		let report = RoundtripReport::compare(payload, &altered);
+		assert_eq!(report, RoundtripReport { payload_len: 1000, reconstructed_len: 1001, first_mismatch: Some(700) });
+		assert_eq!(report.to_string(), "mismatch at offset 700 of 1000 bytes, 1001 bytes reconstructed");
+		assert_eq!(RoundtripReport::compare(payload, &payload[..600]).first_mismatch, Some(600));
+	}
+
+	#[test]
+	fn status_quo_errors_are_chained() {
+		let params = CodeParams::new(8, 4).unwrap();
+		let received = vec![Some(WrappedShard::new(Vec::new())); params.n()];
+		let err = status_quo::reconstruct_with_params(&params, received).unwrap_err();
+		assert_eq!(err, Error::ReedSolomon(reed_solomon_erasure::Error::EmptyShard));
+		assert!(std::error::Error::source(&err).is_some());
+	}
+
+	#[test]
+	fn tiny_payloads_roundtrip() {
+		for params in [CodeParams::default(), CodeParams::new(10, 3).unwrap(), CodeParams::new(100, 33).unwrap()] {
+			for len in [0, 1, params.k() - 1] {
+				let payload = &random_payload(len, 0);
+
+				let shards = status_quo::encode_with_params(&params, payload).unwrap();
+				let recovered =
+					status_quo::reconstruct_with_params(&params, shards.into_iter().map(Some).collect()).unwrap();
+				assert_eq!(&recovered[..len], payload);
+
+				let shards = novel_poly_basis::encode_with_params(&params, payload).unwrap();
+				assert!(shards.iter().all(|shard| AsRef::<[u8]>::as_ref(shard).len() == 2));
+				let mut received = shards.into_iter().map(Some).collect::<Vec<_>>();
+				for shard in received.iter_mut().skip(params.k()) {
+					*shard = None;
+				}
+				let recovered = novel_poly_basis::reconstruct_with_params(&params, received).unwrap();
+				assert_eq!(&recovered[..len], payload);
+			}
+		}
 	}
 }
@@ -0,0 +1,121 @@
+//! Locally repairable codes, a global erasure code with an extra parity shard per group of
+//! shards, i.e. per group of validators.
+//!
+//! The local parity is the XOR of the shards of its group, see `CodeParams::with_local_groups`.
+//! So a single missing shard of a group is recovered from the rest of its group, reading
+//! `size` shards instead of `k`, and only larger losses fall back to the global code.
+
+use super::*;
+use crate::registry::ErasureCoder;
+
+fn xor_into(acc: &mut [u8], shard: &[u8]) {
+	for (a, b) in acc.iter_mut().zip(shard.iter()) {
+		*a ^= *b;
+	}
+}
+
+/// Encode `payload` with `coder` into `params.n()` shards, followed by the local parity shards
+/// if `params` has local groups.
+pub fn encode<C: ErasureCoder + ?Sized>(coder: &C, params: &CodeParams, payload: &[u8]) -> Result<Vec<WrappedShard>> {
+	let mut shards = coder.encode(params, payload)?;
+	if let Some(groups) = params.local_groups() {
+		let parities = (0..groups.count())
+			.map(|group| {
+				let members = &shards[groups.members(group)];
+				let mut parity = vec![0_u8; AsRef::<[u8]>::as_ref(&members[0]).len()];
+				members.iter().for_each(|shard| xor_into(&mut parity, shard.as_ref()));
+				WrappedShard::new(parity)
+			})
+			.collect::<Vec<_>>();
+		shards.extend(parities);
+	}
+	Ok(shards)
+}
+
+/// Recover the missing shards of all groups which miss just one, from the rest of the group,
+/// returning the indices of the recovered shards.
+pub fn repair(params: &CodeParams, received_shards: &mut [Option<WrappedShard>]) -> Result<Vec<usize>> {
+	let groups = match params.local_groups() {
+		Some(groups) => groups,
+		None => return Ok(Vec::new()),
+	};
+	if received_shards.len() != groups.total_shards() {
+		return Err(Error::WrongShardCount { expected: groups.total_shards(), got: received_shards.len() });
+	}
+
+	let mut repaired = Vec::new();
+	for group in 0..groups.count() {
+		let group_shards = groups.members(group).chain(std::iter::once(groups.parity_index(group)));
+		let (missing, present): (Vec<usize>, Vec<usize>) =
+			group_shards.partition(|index| received_shards[*index].is_none());
+		if missing.len() != 1 {
+			continue;
+		}
+
+		let shard_bytes = |index: usize| -> &[u8] {
+			received_shards[index].as_ref().expect("partitioned by presence. qed").as_ref()
+		};
+		let first = shard_bytes(present[0]).len();
+		let mut shard = vec![0_u8; first];
+		for index in present {
+			let other = shard_bytes(index);
+			if other.len() != first {
+				return Err(Error::InconsistentShardLengths { first, other: other.len() });
+			}
+			xor_into(&mut shard, other);
+		}
+		received_shards[missing[0]] = Some(WrappedShard::new(shard));
+		repaired.push(missing[0]);
+	}
+	Ok(repaired)
+}
+
+/// Reconstruct the payload, repairing locally first and decoding the global code
+/// from the then received global shards.
+pub fn reconstruct<C: ErasureCoder + ?Sized>(
+	coder: &C,
+	params: &CodeParams,
+	mut received_shards: Vec<Option<WrappedShard>>,
+) -> Result<Vec<u8>> {
+	repair(params, &mut received_shards)?;
+	received_shards.truncate(params.n());
+	coder.reconstruct(params, received_shards)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::auto::Backend;
+	use std::num::NonZeroUsize;
+
+	#[test]
+	fn groups_repair_single_losses_locally() {
+		let params = CodeParams::new(16, 4).unwrap().with_local_groups(NonZeroUsize::new(5).unwrap());
+		let payload = &random_payload(1000, 0);
+		for coder in [Backend::NovelPolyBasis, Backend::StatusQuo] {
+			let shards = encode(&coder, &params, payload).unwrap();
+			assert_eq!(shards.len(), 20);
+			assert_eq!(shards[..16], coder.encode(&params, payload).unwrap()[..]);
+
+			// one lost shard in groups 0 and 3, the local parity of group 1 and two shards of group 2
+			let mut received = shards.iter().cloned().map(Some).collect::<Vec<_>>();
+			for index in [2, 15, 17, 10, 11] {
+				received[index] = None;
+			}
+			assert_eq!(repair(&params, &mut received).unwrap(), [2, 17, 15]);
+			assert!(received[10].is_none() && received[11].is_none());
+			for index in [2, 15, 17] {
+				assert_eq!(received[index].as_ref(), Some(&shards[index]));
+			}
+
+			// beyond local repair, the global code recovers the payload
+			let mut received = shards.into_iter().map(Some).collect::<Vec<_>>();
+			received.iter_mut().skip(2).take(11).for_each(|shard| *shard = None);
+			assert_eq!(&reconstruct(&coder, &params, received).unwrap()[..payload.len()], payload);
+		}
+		assert_eq!(
+			repair(&params, &mut vec![None; 16]),
+			Err(Error::WrongShardCount { expected: 20, got: 16 })
+		);
+	}
+}
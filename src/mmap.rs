@@ -0,0 +1,100 @@
+//! Erasure coding of files via memory maps.
+//!
+//! Neither the input of `encode_file` nor the output of `reconstruct_to_file`
+//! is loaded into memory as a whole, the kernel pages them in and out as the
+//! codewords are processed, so multi-GiB archives can be coded.
+
+use super::*;
+
+use memmap2::{Mmap, MmapMut};
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+
+/// Stripe length in bytes per shard while reconstructing into a file.
+const STRIPE_LEN: usize = 1 << 16;
+
+/// Encode the content of the file at `path` with the novel poly basis.
+pub fn encode_file(path: impl AsRef<Path>, params: &CodeParams) -> Result<Vec<WrappedShard>> {
+	let file = File::open(path)?;
+	if file.metadata()?.len() == 0 {
+		return novel_poly_basis::encode_with_params(params, &[]);
+	}
+	// Safety: the file must not be truncated while mapped, which callers have to ensure
+	let payload = unsafe { Mmap::map(&file)? };
+	novel_poly_basis::encode_with_params(params, &payload[..])
+}
+
+/// Reconstruct the payload encoded with the novel poly basis into the file at `path`,
+/// writing stripe by stripe through a memory map. The file is truncated to `payload_len`
+/// if given, otherwise it retains the padding. Returns the length of the file.
+pub fn reconstruct_to_file(
+	params: &CodeParams,
+	received_shards: &[Option<&[u8]>],
+	path: impl AsRef<Path>,
+	payload_len: Option<usize>,
+) -> Result<usize> {
+	let (_n, k) = novel_poly_basis::internal_params(params)?;
+	let shard_len = received_shards.iter().flatten().map(|shard| shard.len()).next().unwrap_or_default();
+	let padded_len = novel_poly_basis::padded_len(k, shard_len);
+
+	let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(path)?;
+	file.set_len(padded_len as u64)?;
+	if padded_len > 0 {
+		// Safety: the file was just created by us with the mapped length
+		let mut out = unsafe { MmapMut::map_mut(&file)? };
+		let mut scratch = novel_poly_basis::Scratch::new(STRIPE_LEN);
+		novel_poly_basis::reconstruct_bounded(params, received_shards, &mut scratch, |offset, bytes| {
+			out[offset..offset + bytes.len()].copy_from_slice(bytes)
+		})?;
+		out.flush()?;
+	} else {
+		// nothing to write, but the shards are still validated
+		let mut scratch = novel_poly_basis::Scratch::new(STRIPE_LEN);
+		novel_poly_basis::reconstruct_bounded(params, received_shards, &mut scratch, |_, _| {})?;
+	}
+
+	let len = payload_len.map_or(padded_len, |len| std::cmp::min(len, padded_len));
+	file.set_len(len as u64)?;
+	Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn file_roundtrip() {
+		let dir = tempfile::tempdir().unwrap();
+		let input = dir.path().join("payload");
+		let output = dir.path().join("reconstructed");
+		let payload = &random_payload(300_001, 0);
+		std::fs::write(&input, payload).unwrap();
+
+		let params = CodeParams::new(30, 10).unwrap();
+		let shards = encode_file(&input, &params).unwrap();
+		assert_eq!(shards, novel_poly_basis::encode_with_params(&params, payload).unwrap());
+
+		let mut received = shards.iter().map(|shard| Some(shard.as_ref())).collect::<Vec<Option<&[u8]>>>();
+		for shard in received.iter_mut().step_by(2).take(15) {
+			*shard = None;
+		}
+		let len = reconstruct_to_file(&params, &received, &output, Some(payload.len())).unwrap();
+		assert_eq!(len, payload.len());
+		assert_eq!(&std::fs::read(&output).unwrap(), payload);
+	}
+
+	#[test]
+	fn empty_file() {
+		let dir = tempfile::tempdir().unwrap();
+		let input = dir.path().join("empty");
+		std::fs::write(&input, []).unwrap();
+		let params = CodeParams::new(4, 2).unwrap();
+		let shards = encode_file(&input, &params).unwrap();
+		let received = shards.iter().map(|shard| Some(shard.as_ref())).collect::<Vec<Option<&[u8]>>>();
+		assert_eq!(reconstruct_to_file(&params, &received, dir.path().join("out"), Some(0)).unwrap(), 0);
+		assert_eq!(
+			reconstruct_to_file(&params, &[None, None, None, None], dir.path().join("out"), None),
+			Err(Error::TooFewShards { available: 0, required: 2 })
+		);
+	}
+}
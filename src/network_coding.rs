@@ -0,0 +1,170 @@
+//! Random linear network coding of shards, for gossip experiments on top of the shard framing.
+//!
+//! A `CodedShard` is a linear combination over GF(2^16) of `count` source shards, i.e. the
+//! shards of any backend, together with its coefficients. Nodes forward random combinations
+//! of the coded shards they hold without decoding, any `count` linearly independent ones
+//! yield the source shards again by Gaussian elimination. Symbols are read in the given
+//! endianness as elements in the Cantor basis of `novel_poly_basis`, so addition is XOR.
+
+use super::*;
+
+use f2e16::MODULO;
+use novel_poly_basis::low_level::Tables;
+use rand::Rng;
+
+/// A linear combination of the source shards, with the coefficient of each.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodedShard {
+	pub coefficients: Vec<u16>,
+	pub shard: WrappedShard,
+}
+
+impl CodedShard {
+	/// Source shard `index` of `count` itself.
+	pub fn source(index: usize, count: usize, shard: WrappedShard) -> Self {
+		let mut coefficients = vec![0; count];
+		coefficients[index] = 1;
+		CodedShard { coefficients, shard }
+	}
+}
+
+// fails for odd length shards created by `WrappedShard::new_unpadded`
+fn to_symbols(shard: &WrappedShard, endianness: Endianness) -> Result<Vec<u16>> {
+	Ok(shard.symbols::<2>()?.iter().map(|bytes| endianness.symbol_from_bytes(*bytes)).collect())
+}
+
+fn to_shard(symbols: &[u16], endianness: Endianness) -> WrappedShard {
+	symbols.iter().map(|symbol| endianness.symbol_to_bytes(*symbol)).collect()
+}
+
+// `dest ^= src * factor`
+fn mul_add(tables: &Tables, dest: &mut [u16], src: &[u16], factor: u16) {
+	if factor != 0 {
+		tables.multiplier(tables.log(factor)).mul_add_slice(dest, src);
+	}
+}
+
+/// `sum coefficients[i] * shards[i]`, the shards must be of the same length.
+pub fn combine(shards: &[WrappedShard], coefficients: &[u16], endianness: Endianness) -> Result<WrappedShard> {
+	combine_refs(shards.iter(), coefficients, endianness)
+}
+
+// `combine` of borrowed shards, e.g. those of coded shards
+fn combine_refs<'a>(
+	shards: impl ExactSizeIterator<Item = &'a WrappedShard>,
+	coefficients: &[u16],
+	endianness: Endianness,
+) -> Result<WrappedShard> {
+	if shards.len() != coefficients.len() {
+		return Err(Error::WrongShardCount { expected: coefficients.len(), got: shards.len() });
+	}
+	let tables = Tables::get();
+	let mut combined = None;
+	for (shard, coefficient) in shards.zip(coefficients) {
+		let symbols = to_symbols(shard, endianness)?;
+		let combined = combined.get_or_insert_with(|| vec![0; symbols.len()]);
+		if symbols.len() != combined.len() {
+			return Err(Error::InconsistentShardLengths { first: combined.len() * 2, other: symbols.len() * 2 });
+		}
+		mul_add(&tables, combined, &symbols, *coefficient);
+	}
+	Ok(to_shard(&combined.unwrap_or_default(), endianness))
+}
+
+/// A random combination of `coded`, as forwarded by a node, with the coefficients over
+/// the source shards.
+pub fn recombine(coded: &[CodedShard], endianness: Endianness, rng: &mut impl Rng) -> Result<CodedShard> {
+	let count = coded.first().map_or(0, |coded| coded.coefficients.len());
+	if let Some(other) = coded.iter().find(|coded| coded.coefficients.len() != count) {
+		return Err(Error::WrongShardCount { expected: count, got: other.coefficients.len() });
+	}
+	let weights = (0..coded.len()).map(|_| rng.gen()).collect::<Vec<u16>>();
+	let tables = Tables::get();
+	let mut coefficients = vec![0; count];
+	for (coded, weight) in coded.iter().zip(&weights) {
+		mul_add(&tables, &mut coefficients, &coded.coefficients, *weight);
+	}
+	let shard = combine_refs(coded.iter().map(|coded| &coded.shard), &weights, endianness)?;
+	Ok(CodedShard { coefficients, shard })
+}
+
+/// The source shards, solved for from `coded` by Gaussian elimination, failing with
+/// `TooFewShards` unless `coded` has full rank.
+pub fn decode(coded: &[CodedShard], endianness: Endianness) -> Result<Vec<WrappedShard>> {
+	let count = coded.first().map_or(0, |coded| coded.coefficients.len());
+	let tables = Tables::get();
+	// each row holds the coefficients followed by the symbols
+	let mut rows = Vec::with_capacity(coded.len());
+	for shard in coded {
+		if shard.coefficients.len() != count {
+			return Err(Error::WrongShardCount { expected: count, got: shard.coefficients.len() });
+		}
+		rows.push([&shard.coefficients[..], &to_symbols(&shard.shard, endianness)?].concat());
+	}
+	if let Some(other) = rows.iter().find(|row| row.len() != rows[0].len()) {
+		let (first, other) = ((rows[0].len() - count) * 2, (other.len() - count) * 2);
+		return Err(Error::InconsistentShardLengths { first, other });
+	}
+
+	for column in 0..count {
+		let pivot = (column..rows.len()).find(|row| rows[*row][column] != 0);
+		let pivot = pivot.ok_or(Error::TooFewShards { available: column, required: count })?;
+		rows.swap(column, pivot);
+		let inverse = (MODULO - tables.log(rows[column][column])) % MODULO;
+		tables.multiplier(inverse).mul_assign_slice(&mut rows[column]);
+		let pivot_row = rows[column].clone();
+		for (at, row) in rows.iter_mut().enumerate() {
+			if at != column {
+				let factor = row[column];
+				mul_add(&tables, row, &pivot_row, factor);
+			}
+		}
+	}
+	Ok(rows[..count].iter().map(|row| to_shard(&row[count..], endianness)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rand::{rngs::StdRng, SeedableRng};
+
+	#[test]
+	fn gossiped_combinations_decode() {
+		let mut shard = WrappedShard::new(vec![1, 2, 3, 4]);
+		shard.xor_assign(&WrappedShard::new(vec![1, 0, 3, 0])).unwrap();
+		assert_eq!(shard.into_inner(), vec![0, 2, 0, 4]);
+		let mismatch = Err(Error::InconsistentShardLengths { first: 2, other: 4 });
+		assert_eq!(WrappedShard::new(vec![1, 2]).xor_assign(&WrappedShard::new(vec![0; 4])), mismatch);
+
+		let params = CodeParams::new(16, 4).unwrap();
+		let payload = &random_payload(999, 0);
+		let sources = novel_poly_basis::encode_with_params(&params, payload).unwrap();
+		let sources = &sources[..4];
+		let endianness = params.endianness();
+		let mut rng = StdRng::seed_from_u64(0);
+
+		// two relays each hold two source shards and forward combinations of them
+		let held = sources.iter().enumerate().map(|(idx, shard)| CodedShard::source(idx, 4, shard.clone()));
+		let held = held.collect::<Vec<_>>();
+		let mut received = Vec::new();
+		for relay in held.chunks(2) {
+			for _ in 0..2 {
+				received.push(recombine(relay, endianness, &mut rng).unwrap());
+			}
+		}
+		let combined = combine(sources, &received[0].coefficients, endianness).unwrap();
+		assert_eq!(combined, received[0].shard);
+		assert_eq!(decode(&received, endianness).unwrap(), sources);
+
+		// combinations of the first relay alone only span two shards
+		let first = [received[0].clone(), received[1].clone(), received[0].clone()];
+		assert_eq!(decode(&first, endianness), Err(Error::TooFewShards { available: 2, required: 4 }));
+
+		// odd length shards have no 2 byte symbols
+		let odd = [WrappedShard::new_unpadded(vec![1, 2, 3])];
+		assert_eq!(combine(&odd, &[1], endianness), Err(Error::UnalignedShardLength(3)));
+		let odd = [CodedShard::source(0, 1, odd[0].clone())];
+		assert_eq!(recombine(&odd, endianness, &mut rng), Err(Error::UnalignedShardLength(3)));
+		assert_eq!(decode(&odd, endianness), Err(Error::UnalignedShardLength(3)));
+	}
+}
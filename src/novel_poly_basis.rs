@@ -6,50 +6,26 @@
 // (http://arxiv.org/abs/1404.3458)
 
 #![allow(dead_code)]
+#![allow(clippy::needless_range_loop)]
 
 use super::*;
 
-use std::slice::from_raw_parts;
+use crate::fault;
+use crate::profiling::{Phase, PhaseTimings, Recorder};
+use crate::stats::{record, Op};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
 
-type GFSymbol = u16;
+pub mod subfield;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+#[cfg(feature = "reference")]
+pub mod reference;
 
-const FIELD_BITS: usize = 16;
-
-const GENERATOR: GFSymbol = 0x2D; //x^16 + x^5 + x^3 + x^2 + 1
-
-// Cantor basis
-const BASE: [GFSymbol; FIELD_BITS] =
-	[1_u16, 44234, 15374, 5694, 50562, 60718, 37196, 16402, 27800, 4312, 27250, 47360, 64952, 64308, 65336, 39198];
-
-const FIELD_SIZE: usize = 1_usize << FIELD_BITS;
-
-const MODULO: GFSymbol = (FIELD_SIZE - 1) as GFSymbol;
-
-static mut LOG_TABLE: [GFSymbol; FIELD_SIZE] = [0_u16; FIELD_SIZE];
-static mut EXP_TABLE: [GFSymbol; FIELD_SIZE] = [0_u16; FIELD_SIZE];
-
-//-----Used in decoding procedure-------
-//twisted factors used in FFT
-static mut SKEW_FACTOR: [GFSymbol; MODULO as usize] = [0_u16; MODULO as usize];
-
-//factors used in formal derivative
-static mut B: [GFSymbol; FIELD_SIZE >> 1] = [0_u16; FIELD_SIZE >> 1];
-
-//factors used in the evaluation of the error locator polynomial
-static mut LOG_WALSH: [GFSymbol; FIELD_SIZE] = [0_u16; FIELD_SIZE];
-
-//return a*EXP_TABLE[b] over GF(2^r)
-fn mul_table(a: GFSymbol, b: GFSymbol) -> GFSymbol {
-	if a != 0_u16 {
-		unsafe {
-			let offset = (LOG_TABLE[a as usize] as u32 + b as u32 & MODULO as u32)
-				+ (LOG_TABLE[a as usize] as u32 + b as u32 >> FIELD_BITS);
-			EXP_TABLE[offset as usize]
-		}
-	} else {
-		0_u16
-	}
-}
+pub use crate::f2e16::{verify_tables, F2e16};
+use crate::f2e16::{self, mul_ct, mul_poly_basis, mul_table, mul_table_lookup, setup, skew_factor, walsh};
+use crate::f2e16::{GFSymbol, FIELD_BITS, FIELD_SIZE, MODULO};
 
 const fn log2(mut x: usize) -> usize {
 	let mut o: usize = 0;
@@ -61,218 +37,530 @@ const fn log2(mut x: usize) -> usize {
 }
 
 const fn is_power_of_2(x: usize) -> bool {
-	return x > 0_usize && x & (x - 1) == 0;
+	x > 0_usize && x & (x - 1) == 0
 }
 
-//fast Walsh–Hadamard transform over modulo mod
-fn walsh(data: &mut [GFSymbol], size: usize) {
-	let mut depart_no = 1_usize;
-	while depart_no < size {
-		let mut j = 0;
-		let depart_no_next = depart_no << 1;
-		while j < size {
-			for i in j..(depart_no + j) {
-				let tmp2: u32 = data[i] as u32 + MODULO as u32 - data[i + depart_no] as u32;
-				data[i] = ((data[i] as u32 + data[i + depart_no] as u32 & MODULO as u32)
-					+ (data[i] as u32 + data[i + depart_no] as u32 >> FIELD_BITS)) as GFSymbol;
-				data[i + depart_no] = ((tmp2 & MODULO as u32) + (tmp2 >> FIELD_BITS)) as GFSymbol;
-			}
-			j += depart_no_next;
+// formal derivative of `width` polynomials of `size` coefficients each, stored as rows,
+// all coefficients of the same degree are contiguous
+#[inline(always)]
+fn formal_derivative_rows(cos: &mut [GFSymbol], width: usize, size: usize) {
+	for i in 1..size {
+		let length = ((i ^ (i - 1)) + 1) >> 1;
+		let (lo, hi) = cos[(i - length) * width..(i + length) * width].split_at_mut(length * width);
+		for (l, h) in lo.iter_mut().zip(hi.iter()) {
+			*l ^= *h;
 		}
-		depart_no = depart_no_next;
+		record(Op::Xor, length * width);
 	}
 }
 
 //formal derivative of polynomial in the new basis
 fn formal_derivative(cos: &mut [GFSymbol], size: usize) {
 	for i in 1..size {
-		let length = ((i ^ i - 1) + 1) >> 1;
+		let length = ((i ^ (i - 1)) + 1) >> 1;
 		for j in (i - length)..i {
 			cos[j] ^= cos.get(j + length).copied().unwrap_or_default();
 		}
+		record(Op::Xor, length);
 	}
 	let mut i = size;
 	while i < FIELD_SIZE && i < cos.len() {
 		for j in 0..size {
 			cos[j] ^= cos.get(j + i).copied().unwrap_or_default();
 		}
+		record(Op::Xor, size);
 		i <<= 1;
 	}
 }
 
-//IFFT in the proposed basis
-fn inverse_fft_in_novel_poly_basis(data: &mut [GFSymbol], size: usize, index: usize) {
-	let mut depart_no = 1_usize;
-	while depart_no < size {
-		let mut j = depart_no;
-		while j < size {
-			for i in (j - depart_no)..j {
-				data[i + depart_no] ^= data[i];
+// Slices at least this long are multiplied via `RegionMultiplier`, which amortizes
+// building its tables. Shorter ones use the log and exp tables directly.
+const REGION_MIN_LEN: usize = 256;
+
+// Region multipliers of the skew factors by their log, built on first use while enabled,
+// see `low_level::set_skew_multipliers`. Each takes 1 KiB, 64 MiB if all factors are in use.
+type SkewMultipliers = Box<[OnceLock<Box<low_level::RegionMultiplier>>]>;
+static SKEW_MULTIPLIERS: OnceLock<SkewMultipliers> = OnceLock::new();
+static SKEW_MULTIPLIERS_ENABLED: AtomicBool = AtomicBool::new(false);
+
+// the precomputed multiplier by `EXP_TABLE[skew]`, if enabled, `setup` must have run
+#[inline(always)]
+fn skew_multiplier(skew: GFSymbol) -> Option<&'static low_level::RegionMultiplier> {
+	if !SKEW_MULTIPLIERS_ENABLED.load(Ordering::Relaxed) {
+		return None;
+	}
+	let multipliers = SKEW_MULTIPLIERS.get_or_init(|| (0..MODULO).map(|_| OnceLock::new()).collect());
+	Some(multipliers[skew as usize].get_or_init(|| Box::new(low_level::RegionMultiplier::new(skew))))
+}
+
+// `lo[i] ^= hi[i] * EXP_TABLE[skew]`, the multiplying half of a butterfly
+#[inline(always)]
+pub(crate) fn butterfly_mul_add(lo: &mut [GFSymbol], hi: &[GFSymbol], skew: GFSymbol) {
+	// the region tables are indexed by the data, unsuitable for constant time
+	let region = !cfg!(feature = "constant-time");
+	match skew_multiplier(skew).filter(|_| region) {
+		Some(multiplier) => multiplier.mul_add_slice(lo, hi),
+		None if region && lo.len() >= REGION_MIN_LEN => low_level::RegionMultiplier::new(skew).mul_add_slice(lo, hi),
+		None => {
+			for (l, h) in lo.iter_mut().zip(hi.iter()) {
+				*l ^= mul_table(*h, skew);
 			}
+		}
+	}
+	record(Op::Xor, lo.len());
+}
+
+// `hi[i] ^= lo[i]`, the adding half of a butterfly
+#[inline(always)]
+pub(crate) fn butterfly_add(lo: &[GFSymbol], hi: &mut [GFSymbol]) {
+	for (l, h) in lo.iter().zip(hi.iter_mut()) {
+		*h ^= *l;
+	}
+	record(Op::Xor, lo.len());
+}
+
+// IFFT of `width` independent codewords stored as rows, via the generic transforms
+#[inline(always)]
+fn inverse_fft_rows(data: &mut [GFSymbol], width: usize, size: usize, index: usize) {
+	afft::inverse_afft_rows::<F2e16>(data, width, size, index)
+}
+
+// FFT of `width` independent codewords stored as rows
+#[inline(always)]
+fn fft_rows(data: &mut [GFSymbol], width: usize, size: usize, index: usize) {
+	afft::afft_rows::<F2e16>(data, width, size, index)
+}
+
+// Transforms of small codes spend much of their time in the control flow of the loops above,
+// so sizes 16, 32 and 64 of single codewords and of full batches have kernels specialized at
+// compile time, see `low_level::set_unrolled_transforms`.
+static UNROLLED_TRANSFORMS_ENABLED: AtomicBool = AtomicBool::new(true);
+
+// `butterfly_mul_add` in the unrolled kernels, where the lengths are constant, butterflies too
+// short for region tables multiply via the log tables without looking for skew multipliers,
+// which keeps the unrolled code small
+#[inline(always)]
+fn butterfly_mul_add_unrolled(lo: &mut [GFSymbol], hi: &[GFSymbol], skew: GFSymbol) {
+	if lo.len() >= REGION_MIN_LEN {
+		return butterfly_mul_add(lo, hi, skew);
+	}
+	for (l, h) in lo.iter_mut().zip(hi.iter()) {
+		*l ^= mul_table(*h, skew);
+	}
+	record(Op::Xor, lo.len());
+}
 
-			let skew = unsafe { SKEW_FACTOR[j + index - 1] };
+// `inverse_fft_rows` of the constant `SIZE` and `WIDTH`, so all loops have constant bounds
+// and the butterflies constant lengths
+#[inline(always)]
+fn inverse_fft_rows_unrolled<const SIZE: usize, const WIDTH: usize>(data: &mut [GFSymbol], index: usize) {
+	let data = &mut data[..SIZE * WIDTH];
+	for level in 0..SIZE.trailing_zeros() {
+		let depart_no = 1 << level;
+		for block in 0..SIZE >> (level + 1) {
+			let j = depart_no + (block << (level + 1));
+			let (lo, hi) = data[(j - depart_no) * WIDTH..(j + depart_no) * WIDTH].split_at_mut(depart_no * WIDTH);
+			butterfly_add(lo, hi);
+			let skew = skew_factor(j + index - 1);
 			if skew != MODULO {
-				for i in (j - depart_no)..j {
-					data[i] ^= mul_table(data[i + depart_no], skew);
-				}
+				butterfly_mul_add_unrolled(lo, hi, skew);
 			}
-
-			j += depart_no << 1;
 		}
-		depart_no <<= 1;
 	}
 }
 
-//FFT in the proposed basis
-fn fft_in_novel_poly_basis(data: &mut [GFSymbol], size: usize, index: usize) {
-	let mut depart_no = size >> 1_usize;
-	while depart_no > 0 {
-		let mut j = depart_no;
-		while j < size {
-			let skew = unsafe { SKEW_FACTOR[j + index - 1] };
+// `fft_rows` of the constant `SIZE` and `WIDTH`, see `inverse_fft_rows_unrolled`
+#[inline(always)]
+fn fft_rows_unrolled<const SIZE: usize, const WIDTH: usize>(data: &mut [GFSymbol], index: usize) {
+	let data = &mut data[..SIZE * WIDTH];
+	for level in (0..SIZE.trailing_zeros()).rev() {
+		let depart_no = 1 << level;
+		for block in 0..SIZE >> (level + 1) {
+			let j = depart_no + (block << (level + 1));
+			let (lo, hi) = data[(j - depart_no) * WIDTH..(j + depart_no) * WIDTH].split_at_mut(depart_no * WIDTH);
+			let skew = skew_factor(j + index - 1);
 			if skew != MODULO {
-				for i in (j - depart_no)..j {
-					data[i] ^= mul_table(data[i + depart_no], skew);
-				}
-			}
-			for i in (j - depart_no)..j {
-				data[i + depart_no] ^= data[i];
+				butterfly_mul_add_unrolled(lo, hi, skew);
 			}
-			j += depart_no << 1;
+			butterfly_add(lo, hi);
 		}
-		depart_no >>= 1;
 	}
 }
 
-//initialize LOG_TABLE[], EXP_TABLE[]
-unsafe fn init() {
-	let mas: GFSymbol = (1 << FIELD_BITS - 1) - 1;
-	let mut state: usize = 1;
-	for i in 0_usize..(MODULO as usize) {
-		EXP_TABLE[state] = i as GFSymbol;
-		if (state >> FIELD_BITS - 1) != 0 {
-			state &= mas as usize;
-			state = state << 1_usize ^ GENERATOR as usize;
-		} else {
-			state <<= 1;
-		}
+// the unrolled kernel of `WIDTH` for `size`, false if there is none
+#[inline(always)]
+fn transform_rows_unrolled<const WIDTH: usize>(
+	data: &mut [GFSymbol],
+	size: usize,
+	index: usize,
+	inverse: bool,
+) -> bool {
+	match (size, inverse) {
+		(16, true) => inverse_fft_rows_unrolled::<16, WIDTH>(data, index),
+		(32, true) => inverse_fft_rows_unrolled::<32, WIDTH>(data, index),
+		(64, true) => inverse_fft_rows_unrolled::<64, WIDTH>(data, index),
+		(16, false) => fft_rows_unrolled::<16, WIDTH>(data, index),
+		(32, false) => fft_rows_unrolled::<32, WIDTH>(data, index),
+		(64, false) => fft_rows_unrolled::<64, WIDTH>(data, index),
+		_ => return false,
 	}
-	EXP_TABLE[0] = MODULO;
+	true
+}
 
-	LOG_TABLE[0] = 0;
-	for i in 0..FIELD_BITS {
-		for j in 0..(1 << i) {
-			LOG_TABLE[j + (1 << i)] = LOG_TABLE[j] ^ BASE[i];
+// via the unrolled kernels if enabled and there is one for `width` and `size`
+#[inline(always)]
+fn transform_rows_sized(data: &mut [GFSymbol], width: usize, size: usize, index: usize, inverse: bool) -> bool {
+	UNROLLED_TRANSFORMS_ENABLED.load(Ordering::Relaxed)
+		&& match width {
+			1 => transform_rows_unrolled::<1>(data, size, index, inverse),
+			SOA_WIDTH => transform_rows_unrolled::<SOA_WIDTH>(data, size, index, inverse),
+			_ => false,
 		}
+}
+
+// `inverse_fft_rows`, via the unrolled kernels where available
+#[inline(always)]
+fn inverse_fft_rows_sized(data: &mut [GFSymbol], width: usize, size: usize, index: usize) {
+	if !transform_rows_sized(data, width, size, index, true) {
+		inverse_fft_rows(data, width, size, index)
 	}
-	for i in 0..FIELD_SIZE {
-		LOG_TABLE[i] = EXP_TABLE[LOG_TABLE[i] as usize];
-	}
+}
 
-	for i in 0..FIELD_SIZE {
-		EXP_TABLE[LOG_TABLE[i] as usize] = i as GFSymbol;
+// `fft_rows`, via the unrolled kernels where available
+#[inline(always)]
+fn fft_rows_sized(data: &mut [GFSymbol], width: usize, size: usize, index: usize) {
+	if !transform_rows_sized(data, width, size, index, false) {
+		fft_rows(data, width, size, index)
 	}
-	EXP_TABLE[MODULO as usize] = EXP_TABLE[0];
 }
 
-//initialize SKEW_FACTOR[], B[], LOG_WALSH[]
-unsafe fn init_dec() {
-	let mut base: [GFSymbol; FIELD_BITS - 1] = Default::default();
+/// Instruction set the transforms were compiled for, selected once at runtime.
+///
+/// The transforms are compiled once per kernel into the same binary, so building with
+/// `target-cpu=native` is not needed to use the wider vectors of the running machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimdKernel {
+	/// The baseline of the compilation target, i.e. SSE2 on x86_64 and NEON on aarch64.
+	Generic,
+	/// x86_64 with AVX2.
+	Avx2,
+	/// x86_64 with AVX-512 F and BW.
+	Avx512,
+}
+
+/// The kernel used by encode and reconstruct on this machine.
+pub fn codec_features() -> SimdKernel {
+	static KERNEL: std::sync::OnceLock<SimdKernel> = std::sync::OnceLock::new();
+	*KERNEL.get_or_init(|| {
+		#[cfg(target_arch = "x86_64")]
+		{
+			if is_x86_feature_detected!("avx512f") && is_x86_feature_detected!("avx512bw") {
+				return SimdKernel::Avx512;
+			}
+			if is_x86_feature_detected!("avx2") {
+				return SimdKernel::Avx2;
+			}
+		}
+		SimdKernel::Generic
+	})
+}
+
+/// Capabilities of this backend as built and running on this machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodecInfo {
+	/// Bits per field element and symbol.
+	pub field_bits: usize,
+	/// Largest supported number of shards.
+	pub max_n: usize,
+	/// Whether codes with `k > n / 2` are encoded natively, otherwise `k` is
+	/// rounded down to a power of two and the code carries more parity than needed.
+	pub supports_high_rate: bool,
+	/// Kernel selected for the transforms.
+	pub simd_kernel: SimdKernel,
+	/// Whether multiplications avoid data dependent table lookups.
+	pub constant_time: bool,
+	/// Whether multiplications go through the polynomial basis instead of the log table.
+	pub compact_tables: bool,
+}
 
-	for i in 1..FIELD_BITS {
-		base[i - 1] = 1 << i;
+/// Query the capabilities, e.g. to log the configuration on startup.
+pub fn codec_info() -> CodecInfo {
+	CodecInfo {
+		field_bits: FIELD_BITS,
+		max_n: FIELD_SIZE,
+		supports_high_rate: false,
+		simd_kernel: codec_features(),
+		constant_time: cfg!(feature = "constant-time"),
+		compact_tables: cfg!(feature = "compact-tables"),
 	}
+}
 
-	for m in 0..(FIELD_BITS - 1) {
-		let step = 1 << (m + 1);
-		SKEW_FACTOR[(1 << m) - 1] = 0;
-		for i in m..(FIELD_BITS - 1) {
-			let s = 1 << (i + 1);
+// The transforms, instantiated once per kernel. The loops and the butterflies are
+// inlined, so they are compiled with the features of the kernel.
+macro_rules! kernel_transforms {
+	($name:ident $(, #[$feature:meta])?) => {
+		mod $name {
+			use super::*;
+
+			//IFFT in the proposed basis
+			$(#[$feature])?
+			pub(super) unsafe fn inverse_fft(data: &mut [GFSymbol], width: usize, size: usize, index: usize) {
+				inverse_fft_rows_sized(data, width, size, index)
+			}
+
+			//FFT in the proposed basis
+			$(#[$feature])?
+			pub(super) unsafe fn fft(data: &mut [GFSymbol], width: usize, size: usize, index: usize) {
+				fft_rows_sized(data, width, size, index)
+			}
 
-			let mut j = (1 << m) - 1;
-			while j < s {
-				SKEW_FACTOR[j + s] = SKEW_FACTOR[j] ^ base[i];
-				j += step;
+			// the butterflies of a plan, in order
+			$(#[$feature])?
+			pub(super) unsafe fn run_plan(
+				data: &mut [GFSymbol],
+				width: usize,
+				butterflies: &[Butterfly],
+				inverse: bool,
+			) {
+				for butterfly in butterflies {
+					let (at, half) = (butterfly.at as usize, butterfly.half as usize);
+					let (lo, hi) = data[at * width..(at + 2 * half) * width].split_at_mut(half * width);
+					if inverse {
+						butterfly_add(lo, hi);
+					}
+					if butterfly.skew != MODULO {
+						butterfly_mul_add(lo, hi, butterfly.skew);
+					}
+					if !inverse {
+						butterfly_add(lo, hi);
+					}
+				}
 			}
 		}
+	};
+}
 
-		let idx = mul_table(base[m], LOG_TABLE[(base[m] ^ 1_u16) as usize]);
-		base[m] = MODULO - LOG_TABLE[idx as usize];
+kernel_transforms!(generic);
+#[cfg(target_arch = "x86_64")]
+kernel_transforms!(avx2, #[target_feature(enable = "avx2")]);
+#[cfg(target_arch = "x86_64")]
+kernel_transforms!(avx512, #[target_feature(enable = "avx512f,avx512bw")]);
 
-		for i in (m + 1)..(FIELD_BITS - 1) {
-			let b = LOG_TABLE[(base[i] as u16 ^ 1_u16) as usize] as u32 + base[m] as u32;
-			let b = b % MODULO as u32;
-			base[i] = mul_table(base[i], b as u16);
-		}
+//IFFT in the proposed basis
+fn inverse_fft_in_novel_poly_basis(data: &mut [GFSymbol], size: usize, index: usize) {
+	inverse_fft_rows_selected(data, 1, size, index)
+}
+
+//FFT in the proposed basis
+fn fft_in_novel_poly_basis(data: &mut [GFSymbol], size: usize, index: usize) {
+	fft_rows_selected(data, 1, size, index)
+}
+
+// `inverse_fft_rows` offloaded to the GPU if worthwhile, otherwise via the kernel selected by `codec_features`
+fn inverse_fft_rows_selected(data: &mut [GFSymbol], width: usize, size: usize, index: usize) {
+	#[cfg(feature = "gpu")]
+	if gpu::try_offload(data, width, size, index, true) {
+		return;
+	}
+	match codec_features() {
+		// only detected if the CPU supports the features
+		#[cfg(target_arch = "x86_64")]
+		SimdKernel::Avx512 => unsafe { avx512::inverse_fft(data, width, size, index) },
+		#[cfg(target_arch = "x86_64")]
+		SimdKernel::Avx2 => unsafe { avx2::inverse_fft(data, width, size, index) },
+		_ => unsafe { generic::inverse_fft(data, width, size, index) },
 	}
-	for i in 0..(MODULO as usize) {
-		SKEW_FACTOR[i] = LOG_TABLE[SKEW_FACTOR[i] as usize];
+}
+
+// `fft_rows`, see `inverse_fft_rows_selected`
+fn fft_rows_selected(data: &mut [GFSymbol], width: usize, size: usize, index: usize) {
+	#[cfg(feature = "gpu")]
+	if gpu::try_offload(data, width, size, index, false) {
+		return;
 	}
+	match codec_features() {
+		// only detected if the CPU supports the features
+		#[cfg(target_arch = "x86_64")]
+		SimdKernel::Avx512 => unsafe { avx512::fft(data, width, size, index) },
+		#[cfg(target_arch = "x86_64")]
+		SimdKernel::Avx2 => unsafe { avx2::fft(data, width, size, index) },
+		_ => unsafe { generic::fft(data, width, size, index) },
+	}
+}
+
+// A butterfly between the rows `at..at + half` and `at + half..at + 2 * half`, with its skew
+// factor, `MODULO` if it only adds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Butterfly {
+	at: u32,
+	half: u32,
+	skew: GFSymbol,
+}
+
+// A transform of `size` symbols at the points offset by `index`, with its butterflies and
+// skew factors derived once, to be run on every batch of a payload, like the plans of FFTW.
+// The butterflies follow the transforms level by level and are valid for any width.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FftPlan {
+	size: usize,
+	index: usize,
+	inverse: bool,
+	butterflies: Vec<Butterfly>,
+}
 
-	base[0] = MODULO - base[0];
-	for i in 1..(FIELD_BITS - 1) {
-		base[i] = ((MODULO as u32 - base[i] as u32 + base[i - 1] as u32) % MODULO as u32) as GFSymbol;
+impl FftPlan {
+	fn new(size: usize, index: usize, inverse: bool) -> Self {
+		setup();
+		let butterfly = |j: usize, depart_no: usize| Butterfly {
+			at: (j - depart_no) as u32,
+			half: depart_no as u32,
+			skew: skew_factor(j + index - 1),
+		};
+		let levels = (0..size.trailing_zeros()).map(|level| 1_usize << level);
+		let levels = if inverse { levels.collect::<Vec<_>>() } else { levels.rev().collect() };
+		let butterflies = levels
+			.into_iter()
+			.flat_map(|depart_no| (depart_no..size).step_by(depart_no << 1).map(move |j| butterfly(j, depart_no)))
+			.collect();
+		FftPlan { size, index, inverse, butterflies }
 	}
 
-	B[0] = 0;
-	for i in 0..(FIELD_BITS - 1) {
-		let depart = 1 << i;
-		for j in 0..depart {
-			B[j + depart] = ((B[j] as u32 + base[i] as u32) % MODULO as u32) as GFSymbol;
+	fn run(&self, data: &mut [GFSymbol], width: usize) {
+		#[cfg(feature = "gpu")]
+		if gpu::try_offload(data, width, self.size, self.index, self.inverse) {
+			return;
+		}
+		if transform_rows_sized(data, width, self.size, self.index, self.inverse) {
+			return;
 		}
+		let butterflies = &self.butterflies[..];
+		match codec_features() {
+			// only detected if the CPU supports the features
+			#[cfg(target_arch = "x86_64")]
+			SimdKernel::Avx512 => unsafe { avx512::run_plan(data, width, butterflies, self.inverse) },
+			#[cfg(target_arch = "x86_64")]
+			SimdKernel::Avx2 => unsafe { avx2::run_plan(data, width, butterflies, self.inverse) },
+			_ => unsafe { generic::run_plan(data, width, butterflies, self.inverse) },
+		}
+	}
+}
+
+// The transforms of `encode_low_rows_in_place` for a `k` and `n`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct EncodePlan {
+	k: usize,
+	n: usize,
+	ifft: FftPlan,
+	// at the shifts `k, 2 * k, ..`
+	ffts: Vec<FftPlan>,
+}
+
+impl EncodePlan {
+	fn new(k: usize, n: usize) -> Self {
+		let ffts = (k..n).step_by(k).map(|shift| FftPlan::new(k, shift, false)).collect();
+		EncodePlan { k, n, ifft: FftPlan::new(k, 0, true), ffts }
 	}
+}
+
+// The transforms and formal derivative factors of `decode_main_rows` for an `n`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DecodePlan {
+	n: usize,
+	ifft: FftPlan,
+	fft: FftPlan,
+	// `B[i >> 1]` for row `i`, the rows are scaled by its inverse before the formal derivative
+	// and by itself after
+	derivative: Vec<GFSymbol>,
+}
 
-	mem_cpy(&mut LOG_WALSH[..], &LOG_TABLE[..]);
-	LOG_WALSH[0] = 0;
-	walsh(&mut LOG_WALSH[..], FIELD_SIZE);
+impl DecodePlan {
+	fn new(n: usize) -> Self {
+		let (ifft, fft) = (FftPlan::new(n, 0, true), FftPlan::new(n, 0, false));
+		DecodePlan { n, ifft, fft, derivative: (0..n).map(|i| f2e16::b_factors()[i >> 1]).collect() }
+	}
 }
 
 // Encoding alg for k/n < 0.5: message is a power of two
-fn encode_low(data: &[GFSymbol], k: usize, codeword: &mut [GFSymbol], n: usize) {
-	assert!(k + k <= n);
-	assert_eq!(codeword.len(), n);
-	assert_eq!(data.len(), n);
+fn encode_low(data: &[GFSymbol], k: usize, codeword: &mut [GFSymbol], n: usize, rec: &mut impl Recorder) {
+	encode_low_rows(data, 1, k, codeword, n, rec)
+}
 
-	assert!(is_power_of_2(n));
-	assert!(is_power_of_2(k));
+// `encode_low` of `width` codewords stored as rows
+fn encode_low_rows(
+	data: &[GFSymbol],
+	width: usize,
+	k: usize,
+	codeword: &mut [GFSymbol],
+	n: usize,
+	rec: &mut impl Recorder,
+) {
+	debug_assert_eq!(data.len(), n * width);
+	debug_assert_eq!(codeword.len(), n * width);
+
+	// only the message is needed, the remaining rows are overwritten
+	mem_cpy(&mut codeword[..k * width], &data[..k * width]);
+	encode_low_rows_in_place(codeword, width, k, n, rec)
+}
 
-	// k | n is guaranteed
-	assert_eq!((n / k) * k, n);
+// `encode_low_rows` on a single buffer holding the message in its first `k` rows,
+// the parity is written to the rows after, leaving the message untouched
+fn encode_low_rows_in_place(codeword: &mut [GFSymbol], width: usize, k: usize, n: usize, rec: &mut impl Recorder) {
+	encode_low_rows_planned(codeword, width, &EncodePlan::new(k, n), rec)
+}
 
-	// move the data to the codeword
-	mem_cpy(&mut codeword[0..], &data[0..]);
+// `encode_low_rows_in_place` with the transforms of a `plan`, e.g. reused for every batch
+fn encode_low_rows_planned(codeword: &mut [GFSymbol], width: usize, plan: &EncodePlan, rec: &mut impl Recorder) {
+	let (k, n) = (plan.k, plan.n);
+	debug_assert!(k + k <= n);
+	debug_assert_eq!(codeword.len(), n * width);
 
-	// split after the first k
-	let (codeword_first_k, codeword_skip_first_k) = codeword.split_at_mut(k);
+	debug_assert!(is_power_of_2(n));
+	debug_assert!(is_power_of_2(k));
 
-	inverse_fft_in_novel_poly_basis(codeword_first_k, k, 0);
+	// k | n is guaranteed
+	debug_assert_eq!((n / k) * k, n);
 
-	// the first codeword is now the basis for the remaining transforms
-	// denoted `M_topdash`
+	// `M_topdash` is derived in the last block, which is transformed last,
+	// so the message stays in place and needs not be restored
+	let (message, parity) = codeword.split_at_mut(k * width);
+	let (parity, last) = parity.split_at_mut((n - 2 * k) * width);
+	mem_cpy(last, message);
+	rec.time(Phase::Ifft, || plan.ifft.run(last, width));
 
-	for shift in (k..n).into_iter().step_by(k) {
-		let codeword_at_shift = &mut codeword_skip_first_k[(shift - k)..shift];
+	for (shift, fft) in (k..n - k).step_by(k).zip(&plan.ffts) {
+		let codeword_at_shift = &mut parity[(shift - k) * width..shift * width];
 		// copy `M_topdash` to the position we are currently at, the n transform
-		mem_cpy(codeword_at_shift, codeword_first_k);
-		fft_in_novel_poly_basis(codeword_at_shift, k, shift);
+		mem_cpy(codeword_at_shift, last);
+		rec.time(Phase::FftAtShift(shift / k), || fft.run(codeword_at_shift, width));
 	}
+	let fft = plan.ffts.last().expect("n exceeds k. qed");
+	rec.time(Phase::FftAtShift(n / k - 1), || fft.run(last, width));
+}
 
-	// restore `M` from the derived ones
-	mem_cpy(&mut codeword[0..k], &data[0..k]);
+// the contract of the transforms of `data[..size]` at the points offset by `index`,
+// beyond the field the skew factors run out
+fn check_transform(len: usize, size: usize, index: usize) -> Result<()> {
+	if !is_power_of_2(size) {
+		return Err(Error::NotPowerOfTwo(size));
+	}
+	if size > len {
+		return Err(Error::BufferTooShort { len, required: size });
+	}
+	if index + size > FIELD_SIZE {
+		return Err(Error::ShardCountTooHigh(index + size));
+	}
+	Ok(())
 }
 
 fn mem_zero(zerome: &mut [GFSymbol]) {
-	for i in 0..zerome.len() {
-		zerome[i] = 0_u16;
+	for sym in zerome.iter_mut() {
+		*sym = 0_u16;
 	}
 }
 
 fn mem_cpy(dest: &mut [GFSymbol], src: &[GFSymbol]) {
-	let sl = src.len();
-	debug_assert_eq!(dest.len(), sl);
-	for i in 0..sl {
-		dest[i] = src[i];
-	}
+	debug_assert_eq!(dest.len(), src.len());
+	dest.copy_from_slice(src);
 }
 
 //data: message array. parity: parity array. mem: buffer(size>= n-k)
@@ -298,310 +586,1887 @@ fn encode_high(data: &[GFSymbol], k: usize, parity: &mut [GFSymbol], mem: &mut [
 // Compute the evaluations of the error locator polynomial
 // `fn decode_init`
 // since this has only to be called once per reconstruction
-fn eval_error_polynomial(erasure: &[bool], log_walsh2: &mut [GFSymbol], n: usize) {
-	let z = std::cmp::min(n,erasure.len());
-	for i in 0..z {
-		log_walsh2[i] = erasure[i] as GFSymbol;
-	}
-	for i in z..N {
-		log_walsh2[i] = 0 as GFSymbol;
+//
+// Only the first `n` evaluations are computed, `n` being a power of two no smaller than
+// the number of erasures. The evaluations are the XOR convolution of the erasures with the log
+// table, which only involves its first `n` entries, so the transforms shrink to `n` symbols.
+//
+// The erasures are walked twice rather than collected, positions past their end are available.
+fn eval_error_polynomial(erasure: impl Iterator<Item = bool> + Clone, log_walsh2: &mut [GFSymbol], n: usize) {
+	debug_assert!(is_power_of_2(n) && n <= FIELD_SIZE && erasure.clone().count() <= n);
+	log_walsh2[..n].fill(0);
+	for (sym, erased) in log_walsh2[..n].iter_mut().zip(erasure.clone()) {
+		*sym = erased as GFSymbol;
 	}
-	walsh(log_walsh2, FIELD_SIZE);
+	let truncated;
+	let log_walsh = if n == FIELD_SIZE {
+		&f2e16::log_walsh()[..]
+	} else {
+		truncated = truncated_log_walsh(n);
+		&truncated[..]
+	};
+	walsh(log_walsh2, n);
 	for i in 0..n {
-		let tmp = log_walsh2[i] as u32 * unsafe { LOG_WALSH[i] } as u32;
+		let tmp = log_walsh2[i] as u32 * log_walsh[i] as u32;
 		log_walsh2[i] = (tmp % MODULO as u32) as GFSymbol;
 	}
-	walsh(log_walsh2, FIELD_SIZE);
-	for i in 0..z {
-		if erasure[i] {
-			log_walsh2[i] = MODULO - log_walsh2[i];
+	walsh(log_walsh2, n);
+	for (sym, erased) in log_walsh2[..n].iter_mut().zip(erasure) {
+		if erased {
+			*sym = MODULO - *sym;
 		}
 	}
 }
 
-fn decode_main(codeword: &mut [GFSymbol], k: usize, erasure: &[bool], log_walsh2: &[GFSymbol], n: usize) {
-	assert!(codeword.len() >= k);
-	assert_eq!(codeword.len(), n);
-	assert!(erasure.len() >= k);
-	assert_eq!(erasure.len(), n);
+// `LOG_WALSH` of the first `size` entries of the log table. The two transforms of
+// `size` symbols scale by `size`, which is cancelled by `FIELD_SIZE / size`, since
+// `FIELD_SIZE` is one modulo `MODULO`.
+fn truncated_log_walsh(size: usize) -> Vec<GFSymbol> {
+	let mut log_walsh = f2e16::log_table()[..size].to_vec();
+	log_walsh[0] = 0;
+	walsh(&mut log_walsh, size);
+	let scale = (FIELD_SIZE / size) as u32;
+	for x in log_walsh.iter_mut() {
+		*x = ((*x as u32 * scale) % MODULO as u32) as GFSymbol;
+	}
+	log_walsh
+}
+
+fn decode_main(
+	codeword: &mut [GFSymbol],
+	k: usize,
+	erasure: impl Iterator<Item = bool> + Clone,
+	log_walsh2: &[GFSymbol],
+	plan: &DecodePlan,
+	rec: &mut impl Recorder,
+) {
+	decode_main_rows(codeword, 1, k, erasure, log_walsh2, plan, rec)
+}
+
+// `decode_main` of `width` codewords stored as rows, sharing the erasures
+fn decode_main_rows(
+	codeword: &mut [GFSymbol],
+	width: usize,
+	k: usize,
+	erasure: impl Iterator<Item = bool> + Clone,
+	log_walsh2: &[GFSymbol],
+	plan: &DecodePlan,
+	rec: &mut impl Recorder,
+) {
+	let n = plan.n;
+	debug_assert!(n >= k);
+	debug_assert_eq!(codeword.len(), n * width);
+	debug_assert_eq!(erasure.clone().count(), n);
 
 	// technically we only need to recover
 	// the first `k` instead of all `n` which
 	// would include parity chunks.
 	let recover_up_to = n;
 
-	for i in 0..n {
-		codeword[i] = if erasure[i] { 0_u16 } else { mul_table(codeword[i], log_walsh2[i]) };
-	}
-	inverse_fft_in_novel_poly_basis(codeword, n, 0);
+	rec.time(Phase::DecodeIfft, || {
+		for ((row, erased), log) in codeword.chunks_exact_mut(width).zip(erasure.clone()).zip(log_walsh2) {
+			for sym in row {
+				*sym = if erased { 0_u16 } else { mul_table(*sym, *log) };
+			}
+		}
+		plan.ifft.run(codeword, width);
+		fault::after_ifft(codeword);
+	});
 
 	//formal derivative
-	for i in (0..n).into_iter().step_by(2) {
-		let b = MODULO - unsafe { B[i >> 1] };
-		codeword[i] = mul_table(codeword[i], b);
-		codeword[i + 1] = mul_table(codeword[i + 1], b);
-	}
+	rec.time(Phase::FormalDerivative, || {
+		let scramble = fault::scramble_skew(n);
+		for (i, (row, b)) in codeword.chunks_exact_mut(width).zip(&plan.derivative).enumerate() {
+			let mut b = MODULO - b;
+			if let Some((_, offset)) = scramble.filter(|(row, _)| *row == i) {
+				b = ((u32::from(b) + u32::from(offset)) % u32::from(MODULO)) as GFSymbol;
+			}
+			for sym in row {
+				*sym = mul_table(*sym, b);
+			}
+		}
 
-	formal_derivative(codeword, n);
+		formal_derivative_rows(codeword, width, n);
 
-	for i in (0..n).into_iter().step_by(2) {
-		let b = unsafe { B[i >> 1] };
-		codeword[i] = mul_table(codeword[i], b);
-		codeword[i + 1] = mul_table(codeword[i + 1], b);
-	}
+		for (row, b) in codeword.chunks_exact_mut(width).zip(&plan.derivative) {
+			for sym in row {
+				*sym = mul_table(*sym, *b);
+			}
+		}
+	});
 
-	fft_in_novel_poly_basis(codeword, n, 0);
+	rec.time(Phase::DecodeFft, || {
+		plan.fft.run(codeword, width);
 
-	for i in 0..recover_up_to {
-		codeword[i] = if erasure[i] { mul_table(codeword[i], log_walsh2[i]) } else { 0_u16 };
-	}
+		let rows = codeword.chunks_exact_mut(width).zip(erasure).zip(log_walsh2).take(recover_up_to);
+		for ((row, erased), log) in rows {
+			for sym in row {
+				*sym = if erased { mul_table(*sym, *log) } else { 0_u16 };
+			}
+		}
+	});
 }
 
 const N: usize = 32;
 const K: usize = 4;
 
-use itertools::Itertools;
-
-pub fn encode(data: &[u8]) -> Vec<WrappedShard> {
-	unsafe { init() };
+// the transforms address at most one position per field element
+const _: () = assert!(MAX_SHARDS == FIELD_SIZE);
+
+/// The power of two parameters `(n, k)` used internally for `params`.
+///
+/// `n` is rounded up, the shards beyond `params.n()` are never produced
+/// and treated as erased, `k` is rounded down, so any `params.k()` shards
+/// still suffice to reconstruct.
+pub fn internal_params(params: &CodeParams) -> Result<(usize, usize)> {
+	let n = params.n().next_power_of_two();
+	if n > FIELD_SIZE {
+		return Err(Error::ShardCountTooHigh(params.n()));
+	}
+	let k = if is_power_of_2(params.k()) { params.k() } else { params.k().next_power_of_two() >> 1 };
+	debug_assert!(k + k <= n);
+	Ok((n, k))
+}
 
-	// must be power of 2
-	let l = log2(data.len());
-	let l = 1 << l;
-	let l = if l >= data.len() { l } else { l << 1 };
-	assert!(l >= data.len());
-	assert!(is_power_of_2(l));
-	assert!(is_power_of_2(N), "Algorithm only works for 2^m sizes for N");
-	assert!(is_power_of_2(K), "Algorithm only works for 2^m sizes for K");
+// encode a single codeword from up to `2 * k` bytes of payload
+fn encode_sub(bytes: &[u8], n: usize, k: usize, endianness: Endianness, rec: &mut impl Recorder) -> Vec<GFSymbol> {
+	debug_assert!(is_power_of_2(n), "Algorithm only works for 2^m sizes for N");
+	debug_assert!(is_power_of_2(k), "Algorithm only works for 2^m sizes for K");
+	debug_assert!(bytes.len() <= k << 1);
+	debug_assert!(k <= n / 2);
 
 	// pad the incoming data with trailing 0s
-	let zero_bytes_to_add = dbg!(l) - dbg!(data.len());
-	let data: Vec<GFSymbol> = data
-		.into_iter()
-		.copied()
-		.chain(std::iter::repeat(0u8).take(zero_bytes_to_add))
-		.tuple_windows()
-		.step_by(2)
-		.map(|(a, b)| (b as u16) << 8 | a as u16)
-		.collect::<Vec<GFSymbol>>();
-
-	// assert_eq!(K, data.len());
-	assert_eq!(data.len() * 2, l + zero_bytes_to_add);
-
-	// two bytes make one `l / 2`
-	let l = l / 2;
-	assert_eq!(l, N, "For now we only want to test of variants that don't have to be 0 padded");
+	let data: Vec<GFSymbol> = rec.time(Phase::SymbolConversion, || {
+		let mut data = Vec::with_capacity(n);
+		data.extend(bytes_to_symbols(bytes, endianness));
+		data.resize(n, 0_u16);
+		data
+	});
+
+	// two bytes make one symbol
+	debug_assert_eq!(data.len(), n);
+
 	let mut codeword = data.clone();
-	assert_eq!(codeword.len(), N);
 
-	assert!(K <= N / 2);
-	// if K + K > N {
-	// 	let (data_till_t, data_skip_t) = data.split_at_mut(N - K);
-	// 	encode_high(data_skip_t, K, data_till_t, &mut codeword[..], N);
+	// if k + k > n {
+	// 	let (data_till_t, data_skip_t) = data.split_at_mut(n - k);
+	// 	encode_high(data_skip_t, k, data_till_t, &mut codeword[..], n);
 	// } else {
-	encode_low(&data[..], K, &mut codeword[..], N);
+	encode_low(&data[..], k, &mut codeword[..], n, rec);
 	// }
 
-	println!("Codeword:");
-	for i in 0..N {
-		print!("{:04x} ", codeword[i]);
-	}
-	println!("");
+	codeword
+}
 
-	// XXX currently this is only done for one codeword!
+/// `encode_with_params` for `N` shards of which `K` are data shards.
+pub fn encode(data: &[u8]) -> Result<Vec<WrappedShard>> {
+	encode_with_params(&CodeParams::new(N, K).expect("N and K are valid. qed"), data)
+}
 
-	let shards = (0..N)
-		.into_iter()
-		.map(|i| {
-			WrappedShard::new({
-				let arr = codeword[i].to_le_bytes();
-				arr.to_vec()
-			})
-		})
-		.collect::<Vec<WrappedShard>>();
+/// Encode `data` into `params.n()` shards, any `params.k()` of which suffice to reconstruct.
+pub fn encode_with_params(params: &CodeParams, data: &[u8]) -> Result<Vec<WrappedShard>> {
+	encode_recorded(params, data, &mut ())
+}
 
-	shards
+/// Same as `encode_with_params`, additionally reporting the time spent per phase.
+pub fn encode_with_timings(params: &CodeParams, data: &[u8]) -> Result<(Vec<WrappedShard>, PhaseTimings)> {
+	let mut timings = PhaseTimings::default();
+	let shards = encode_recorded(params, data, &mut timings)?;
+	Ok((shards, timings))
 }
 
-pub fn reconstruct(received_shards: Vec<Option<WrappedShard>>) -> Option<Vec<u8>> {
-	unsafe { init_dec() };
+// number of codewords, i.e. symbols per shard, required for `payload_len` bytes,
+// a multiple of `group`
+fn codewords(k: usize, group: usize, payload_len: usize) -> usize {
+	std::cmp::max(payload_len.div_ceil(k * group * 2), 1) * group
+}
 
-	// collect all `None` values
-	let mut erased_count = 0;
-	let erasures = received_shards
-		.iter()
-		.map(|x| x.is_none())
-		.inspect(|v| {
-			if *v {
-				erased_count += 1;
-			}
-		})
-		.collect::<Vec<bool>>();
+// Payload symbol carried by symbol `c` of data shard `idx` in the interleaved layout,
+// which assigns runs of `group` symbols round-robin to the data shards
+#[inline(always)]
+fn interleaved_symbol(k: usize, group: usize, idx: usize, c: usize) -> usize {
+	((c / group) * k + idx) * group + c % group
+}
 
-	// The recovered _data_ chunks AND parity chunks
-	let mut recovered: Vec<GFSymbol> = std::iter::repeat(0u16).take(N).collect();
+/// Length in bytes of each shard encoding a payload of `payload_len` bytes.
+pub fn required_shard_len(params: &CodeParams, payload_len: usize) -> Result<usize> {
+	let (_n, k) = internal_params(params)?;
+	params.check_payload_len(payload_len)?;
+	Ok(codewords(k, params.symbol_group(), payload_len) * 2)
+}
 
-	// get rid of all `None`s
-	let mut codeword = received_shards
-		.into_iter()
-		.enumerate()
-		.map(|(idx, wrapped)| {
-			// fill the gaps with `0_u16` codewords
-			if let Some(wrapped) = wrapped {
-				let v: &[[u8; 2]] = wrapped.as_ref();
-				(idx, u16::from_le_bytes(v[0]))
-			} else {
-				(idx, 0_u16)
+/// Length of the reconstructed payload including padding, for a payload of `payload_len` bytes.
+pub fn padded_payload_len(params: &CodeParams, payload_len: usize) -> Result<usize> {
+	let (_n, k) = internal_params(params)?;
+	params.check_payload_len(payload_len)?;
+	Ok(padded_len(k, codewords(k, params.symbol_group(), payload_len) * 2))
+}
+
+fn encode_recorded(params: &CodeParams, data: &[u8], rec: &mut impl Recorder) -> Result<Vec<WrappedShard>> {
+	let (n, k) = internal_params(params)?;
+	encode_recorded_planned(params, &EncodePlan::new(k, n), data, rec)
+}
+
+fn encode_recorded_planned(
+	params: &CodeParams,
+	plan: &EncodePlan,
+	data: &[u8],
+	rec: &mut impl Recorder,
+) -> Result<Vec<WrappedShard>> {
+	let shard_len = required_shard_len(params, data.len())?;
+
+	let mut shards = (0..params.n()).map(|_| Vec::<u8>::with_capacity(shard_len)).collect::<Vec<_>>();
+	encode_codewords_planned(params, plan, data, 0..shard_len / 2, rec, |_chunk_idx, width, rows| {
+		for (shard, row) in shards.iter_mut().zip(rows.chunks_exact(width)) {
+			for sym in row {
+				shard.extend_from_slice(&params.endianness().symbol_to_bytes(*sym));
 			}
-		})
-		.map(|(idx, codeword)| {
-			// copy the good messages (here it's just one codeword/u16 right now)
-			if idx < N {
-				recovered[idx] = codeword;
+		}
+	})?;
+
+	Ok(rec.time(Phase::ShardWrapping, || shards.into_iter().map(WrappedShard::new).collect::<Vec<WrappedShard>>()))
+}
+
+/// Encode into `arena`, replacing its content, the allocation is reused if large enough.
+pub fn encode_into_arena(params: &CodeParams, data: &[u8], arena: &mut ShardArena) -> Result<()> {
+	let shard_len = required_shard_len(params, data.len())?;
+
+	arena.reset(params.n(), shard_len);
+	encode_codewords(params, data, 0..shard_len / 2, &mut (), |chunk_idx, width, rows| {
+		for (idx, row) in rows.chunks_exact(width).take(params.n()).enumerate() {
+			let shard = arena.shard_mut(idx);
+			for (c, sym) in row.iter().enumerate() {
+				let at = (chunk_idx + c) * 2;
+				shard[at..at + 2].copy_from_slice(&params.endianness().symbol_to_bytes(*sym));
 			}
-			codeword
-		})
-		.collect::<Vec<u16>>();
+		}
+	})
+}
 
-	// filled up the remaining spots with 0s
-	assert_eq!(codeword.len(), N);
+/// Codewords encoded or decoded at once, their symbols are stored as structure of arrays,
+/// 32 symbols filling a cache line per position.
+const SOA_WIDTH: usize = 32;
+
+// Codewords per batch of `encode_codewords`, enough for the transforms of `k` symbols to be
+// offloaded if a GPU is available
+#[cfg_attr(not(feature = "gpu"), allow(unused_variables))]
+fn batch_width(k: usize, codewords: usize) -> usize {
+	#[cfg(feature = "gpu")]
+	if let Some(width) = gpu::batch_width(k, codewords) {
+		return width;
+	}
+	SOA_WIDTH
+}
 
-	let recover_up_to = N; // the first k would suffice for the original k message codewords
+// Encode the codewords in `range` of `data` in batches of up to `SOA_WIDTH`, stored as rows:
+// row `i` holds symbol `i` of every codeword of the batch, which is exactly the slice of
+// shard `i` covered by the batch. `emit` receives the index of the first codeword of the batch,
+// the number of codewords in it and the `n * width` symbols of the rows.
+fn encode_codewords(
+	params: &CodeParams,
+	data: &[u8],
+	range: std::ops::Range<usize>,
+	rec: &mut impl Recorder,
+	emit: impl FnMut(usize, usize, &[GFSymbol]),
+) -> Result<()> {
+	let (n, k) = internal_params(params)?;
+	encode_codewords_planned(params, &EncodePlan::new(k, n), data, range, rec, emit)
+}
 
-	//---------Erasure decoding----------------
-	let mut log_walsh2: [GFSymbol; FIELD_SIZE] = [0_u16; FIELD_SIZE];
+// `encode_codewords` with the transforms of a `plan` for the parameters
+fn encode_codewords_planned(
+	params: &CodeParams,
+	plan: &EncodePlan,
+	data: &[u8],
+	range: std::ops::Range<usize>,
+	rec: &mut impl Recorder,
+	mut emit: impl FnMut(usize, usize, &[GFSymbol]),
+) -> Result<()> {
+	setup();
+
+	let (n, k) = (plan.n, plan.k);
+	params.check_payload_len(data.len())?;
+
+	// every codeword carries `k` symbols or `2 * k` bytes of the payload,
+	// shard `i` receives symbol `i` of each codeword
+	// payloads shorter than `k` symbols, including the empty one, are zero padded
+	// to a single codeword
+	let shard_len = codewords(k, params.symbol_group(), data.len());
+	let batch_width = batch_width(k, range.len());
+	let max_width = std::cmp::min(batch_width, range.len());
+
+	let mut codeword = vec![0_u16; n * max_width];
+	for batch_start in range.clone().step_by(batch_width) {
+		let width = std::cmp::min(batch_width, range.end - batch_start);
+		let codeword = &mut codeword[..n * width];
+
+		// the message is zero padded beyond the end of the data
+		rec.time(Phase::SymbolConversion, || {
+			fill_message_rows(params, data, k, shard_len, batch_start, width, &mut codeword[..k * width]);
+		});
 
-	// Evaluate error locator polynomial
-	eval_error_polynomial(&erasures[..], &mut log_walsh2[..], FIELD_SIZE);
+		encode_low_rows_planned(codeword, width, plan, rec);
+		rec.time(Phase::ShardWrapping, || emit(batch_start, width, codeword));
+	}
 
-	//---------main processing----------
-	decode_main(&mut codeword[..], recover_up_to, &erasures[..], &log_walsh2[..], N);
+	Ok(())
+}
 
-	println!("Decoded result:");
-	for idx in 0..N {
-		if erasures[idx] {
-			print!("{:04x} ", codeword[idx]);
-			recovered[idx] = codeword[idx];
-		} else {
-			print!("XXXX ");
-		};
+// The `k` data symbols of the `width` codewords from `batch_start` on as rows
+fn fill_message_rows(
+	params: &CodeParams,
+	data: &[u8],
+	k: usize,
+	shard_len: usize,
+	batch_start: usize,
+	width: usize,
+	message: &mut [GFSymbol],
+) {
+	for (i, row) in message.chunks_exact_mut(width).take(k).enumerate() {
+		for (c, sym) in row.iter_mut().enumerate() {
+			*sym = message_symbol(params, data, k, shard_len, i, batch_start + c);
+		}
 	}
+}
 
-	let recovered = unsafe {
-		// TODO assure this does not leak memory
-		let x = from_raw_parts(recovered.as_ptr() as *const u8, recovered.len() * 2);
-		std::mem::forget(recovered);
-		x
+// Symbol `i` of codeword `chunk_idx`, beyond the end of `data` padded with 0s
+#[inline(always)]
+fn message_symbol(
+	params: &CodeParams,
+	data: &[u8],
+	k: usize,
+	shard_len: usize,
+	i: usize,
+	chunk_idx: usize,
+) -> GFSymbol {
+	let at = match params.layout() {
+		ShardLayout::Interleaved => interleaved_symbol(k, params.symbol_group(), i, chunk_idx) * 2,
+		// data shard `i` covers the payload symbols `i * shard_len..(i + 1) * shard_len`
+		ShardLayout::Sequential => (i * shard_len + chunk_idx) * 2,
 	};
-	Some(recovered.to_vec())
+	let bytes = [data.get(at).copied().unwrap_or_default(), data.get(at + 1).copied().unwrap_or_default()];
+	params.endianness().symbol_from_bytes(bytes)
 }
 
-#[cfg(test)]
-mod test {
-	use rand::seq::index::IndexVec;
+/// Lazily encode `data` into the shards of `encode_with_params`, yielded in order.
+///
+/// The data shards are copied out of `data` without any field arithmetic, so they can be
+/// sent before the parity is computed. The parity shards follow in blocks of `k`, one
+/// transform at a time, buffering a block and the message in the novel basis.
+pub fn encode_iter<'a>(params: &CodeParams, data: &'a [u8]) -> Result<EncodeIter<'a>> {
+	let (_n, k) = internal_params(params)?;
+	params.check_payload_len(data.len())?;
+	setup();
+	Ok(EncodeIter {
+		params: *params,
+		data,
+		k,
+		shard_len: codewords(k, params.symbol_group(), data.len()),
+		next: 0,
+		coefficients: Vec::new(),
+		parity: Vec::new().into_iter(),
+	})
+}
 
-	use super::*;
+/// Iterator returned by `encode_iter`.
+#[derive(Debug)]
+pub struct EncodeIter<'a> {
+	params: CodeParams,
+	data: &'a [u8],
+	k: usize,
+	// in symbols
+	shard_len: usize,
+	next: usize,
+	// the message transformed to the novel basis, in batches of `SOA_WIDTH` codewords
+	// of `k` rows each, computed with the first parity shard
+	coefficients: Vec<GFSymbol>,
+	// the remaining shards of the current parity block
+	parity: std::vec::IntoIter<WrappedShard>,
+}
 
-	fn print_sha256(txt: &'static str, data: &[GFSymbol]) {
-		use sha2::Digest;
-		let data = unsafe { ::std::slice::from_raw_parts(data.as_ptr() as *const u8, data.len() * 2) };
+impl EncodeIter<'_> {
+	fn shard_bytes(&self, rows: impl Iterator<Item = GFSymbol>) -> WrappedShard {
+		WrappedShard::new(rows.flat_map(|sym| self.params.endianness().symbol_to_bytes(sym)).collect())
+	}
 
-		let mut digest = sha2::Sha256::new();
-		digest.update(data);
-		println!("sha256(rs|{}):", txt);
-		for byte in digest.finalize().into_iter() {
-			print!("{:02x}", byte);
+	fn data_shard(&self, idx: usize) -> WrappedShard {
+		self.shard_bytes(
+			(0..self.shard_len).map(|c| message_symbol(&self.params, self.data, self.k, self.shard_len, idx, c)),
+		)
+	}
+
+	// the shards `shift..shift + k`, truncated to `n`
+	fn parity_block(&mut self, shift: usize) -> Vec<WrappedShard> {
+		let (k, shard_len) = (self.k, self.shard_len);
+		if self.coefficients.is_empty() {
+			self.coefficients = vec![0; k * shard_len];
+			let ifft = FftPlan::new(k, 0, true);
+			for batch_start in (0..shard_len).step_by(SOA_WIDTH) {
+				let width = std::cmp::min(SOA_WIDTH, shard_len - batch_start);
+				let message = &mut self.coefficients[batch_start * k..(batch_start + width) * k];
+				fill_message_rows(&self.params, self.data, k, shard_len, batch_start, width, message);
+				ifft.run(message, width);
+			}
+		}
+
+		let count = std::cmp::min(k, self.params.n() - shift);
+		let mut block = vec![vec![0; shard_len]; count];
+		let mut codeword = vec![0; k * SOA_WIDTH];
+		let fft = FftPlan::new(k, shift, false);
+		for batch_start in (0..shard_len).step_by(SOA_WIDTH) {
+			let width = std::cmp::min(SOA_WIDTH, shard_len - batch_start);
+			let codeword = &mut codeword[..k * width];
+			codeword.copy_from_slice(&self.coefficients[batch_start * k..(batch_start + width) * k]);
+			fft.run(codeword, width);
+			for (shard, row) in block.iter_mut().zip(codeword.chunks_exact(width)) {
+				shard[batch_start..batch_start + width].copy_from_slice(row);
+			}
 		}
-		println!("")
+		block.into_iter().map(|shard| self.shard_bytes(shard.into_iter())).collect()
 	}
+}
 
-	/// Generate a random index
-	fn rand_gf_element() -> GFSymbol {
-		use rand::distributions::{Distribution, Uniform};
-		use rand::thread_rng;
+impl Iterator for EncodeIter<'_> {
+	type Item = WrappedShard;
 
-		let mut rng = thread_rng();
-		let uni = Uniform::<GFSymbol>::new_inclusive(0, MODULO);
-		uni.sample(&mut rng)
+	fn next(&mut self) -> Option<WrappedShard> {
+		let idx = self.next;
+		if idx >= self.params.n() {
+			return None;
+		}
+		self.next += 1;
+		if idx < self.k {
+			return Some(self.data_shard(idx));
+		}
+		if idx.is_multiple_of(self.k) {
+			self.parity = self.parity_block(idx).into_iter();
+		}
+		self.parity.next()
 	}
 
-	#[test]
-	fn flt_back_and_forth() {
-		const N: usize = 128;
-		const K: usize = 32;
-		let mut data = (0..N).into_iter().map(|_x| rand_gf_element()).collect::<Vec<GFSymbol>>();
-		let expected = data.clone();
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let remaining = self.params.n() - self.next;
+		(remaining, Some(remaining))
+	}
+}
 
-		fft_in_novel_poly_basis(&mut data, N, K);
+impl ExactSizeIterator for EncodeIter<'_> {}
 
-		// make sure something is done
-		assert!(data.iter().zip(expected.iter()).filter(|(a, b)| { a != b }).count() > 0);
+pub fn reconstruct(received_shards: Vec<Option<WrappedShard>>) -> Result<Vec<u8>> {
+	let received_shards =
+		received_shards.iter().map(|shard| shard.as_ref().map(AsRef::as_ref)).collect::<Vec<Option<&[u8]>>>();
+	reconstruct_ref(&received_shards[..])
+}
 
-		inverse_fft_in_novel_poly_basis(&mut data, N, K);
+/// Reconstruct from borrowed shards, without requiring ownership of the shard set.
+pub fn reconstruct_ref(received_shards: &[Option<&[u8]>]) -> Result<Vec<u8>> {
+	let params = CodeParams::new(N, K).expect("N and K are valid. qed");
+	reconstruct_ref_with_params(&params, &received_shards[..std::cmp::min(N, received_shards.len())])
+}
 
-		itertools::assert_equal(data, expected);
+/// Reconstruct the payload of a `params` code, the result might have trailing zeros.
+pub fn reconstruct_with_params(params: &CodeParams, received_shards: Vec<Option<WrappedShard>>) -> Result<Vec<u8>> {
+	let received_shards =
+		received_shards.iter().map(|shard| shard.as_ref().map(AsRef::as_ref)).collect::<Vec<Option<&[u8]>>>();
+	reconstruct_ref_with_params(params, &received_shards[..])
+}
+
+/// Reconstruct the payload of a `params` code from borrowed shards.
+pub fn reconstruct_ref_with_params(params: &CodeParams, received_shards: &[Option<&[u8]>]) -> Result<Vec<u8>> {
+	reconstruct_recorded(params, received_shards, &mut Scratch::new(DEFAULT_STRIPE_LEN), &mut ())
+}
+
+/// Same as `reconstruct_ref_with_params`, reusing `scratch` across reconstructions,
+/// e.g. one with an erasure pattern cache, see `Scratch::with_erasure_cache`.
+pub fn reconstruct_with_scratch(
+	params: &CodeParams,
+	received_shards: &[Option<&[u8]>],
+	scratch: &mut Scratch,
+) -> Result<Vec<u8>> {
+	reconstruct_recorded(params, received_shards, scratch, &mut ())
+}
+
+/// Same as `reconstruct_ref_with_params`, re-encoding the payload to check it against all
+/// received shards, so faults of the decoder or corrupted shards are not passed on silently.
+///
+/// Only detects, but does not locate corrupted shards, and the check costs a full encode.
+pub fn reconstruct_checked(params: &CodeParams, received_shards: &[Option<&[u8]>]) -> Result<Vec<u8>> {
+	let payload = reconstruct_ref_with_params(params, received_shards)?;
+	let shards = encode_with_params(params, &payload)?;
+	let inconsistent = received_shards
+		.iter()
+		.zip(shards.iter())
+		.position(|(received, shard)| received.is_some_and(|received| received != AsRef::<[u8]>::as_ref(shard)));
+	match inconsistent {
+		Some(idx) => Err(Error::InconsistentShard(idx)),
+		None => Ok(payload),
 	}
+}
 
-	#[test]
-	fn flt_rountrip_small() {
-		const N: usize = 16;
-		const EXPECTED: [GFSymbol; N] = [1, 2, 3, 5, 8, 13, 21, 44, 65, 0, 0xFFFF, 2, 3, 5, 7, 11];
+/// Same as `reconstruct_ref_with_params`, additionally reporting the time spent per phase.
+pub fn reconstruct_with_timings(
+	params: &CodeParams,
+	received_shards: &[Option<&[u8]>],
+) -> Result<(Vec<u8>, PhaseTimings)> {
+	let mut timings = PhaseTimings::default();
+	let payload = reconstruct_recorded(params, received_shards, &mut Scratch::new(DEFAULT_STRIPE_LEN), &mut timings)?;
+	Ok((payload, timings))
+}
 
-		let mut data = EXPECTED.clone();
+/// Outcome of `reconstruct_with_report`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReconstructionReport {
+	/// The shards that were not provided, all of which are regenerated by encoding the
+	/// payload again, i.e. to gossip them back to the network.
+	pub recovered_indices: Vec<ChunkIndex>,
+	/// The provided shards the payload was decoded from.
+	pub used_indices: Vec<ChunkIndex>,
+	/// The payload, which might have trailing zeros.
+	pub payload: Vec<u8>,
+}
 
-		fft_in_novel_poly_basis(&mut data, N, N / 4);
+/// Same as `reconstruct_ref_with_params`, additionally reporting which shards were used
+/// and which have to be recovered.
+///
+/// If all data shards were provided the payload is copied from them, so the parity shards
+/// go unused, otherwise the decoder reads every provided shard.
+pub fn reconstruct_with_report(params: &CodeParams, received_shards: &[Option<&[u8]>]) -> Result<ReconstructionReport> {
+	let payload = reconstruct_ref_with_params(params, received_shards)?;
+	let (_n, k) = internal_params(params)?;
+	let data_complete = received_shards[..k].iter().all(Option::is_some);
+	let indices = |provided: bool| {
+		let indices = received_shards.iter().enumerate().filter(move |(_, shard)| shard.is_some() == provided);
+		indices.map(|(idx, _)| ChunkIndex(idx as u32))
+	};
+	Ok(ReconstructionReport {
+		recovered_indices: indices(false).collect(),
+		used_indices: indices(true).filter(|index| !data_complete || index.position() < k).collect(),
+		payload,
+	})
+}
 
-		println!("novel basis(rust):");
-		data.iter().for_each(|sym| {
-			print!(" {:04X}", sym);
-		});
-		println!("");
+/// Outcome of `reconstruct_partial`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartialReconstruction {
+	/// The payload with the bytes not recovered zeroed, which might have trailing zeros.
+	pub payload: Vec<u8>,
+	/// The recovered byte ranges of `payload`, ascending and disjoint.
+	pub recovered: Vec<std::ops::Range<usize>>,
+}
 
-		inverse_fft_in_novel_poly_basis(&mut data, N, N / 4);
-		itertools::assert_equal(data.iter(), EXPECTED.iter());
+impl PartialReconstruction {
+	/// Whether the whole payload was recovered.
+	pub fn is_complete(&self) -> bool {
+		self.recovered.iter().map(|range| range.len()).sum::<usize>() == self.payload.len()
 	}
+}
 
-	#[test]
-	fn ported_c_test() {
-		unsafe {
-			init(); //fill log table and exp table
-			init_dec(); //compute factors used in erasure decoder
+/// Same as `reconstruct_ref_with_params`, but recovering what it can if too few shards were
+/// received, i.e. for forensics on partially available data.
+///
+/// Shards might be truncated, so the codewords beyond the shortest shards are available
+/// from fewer shards than the first ones. Those are decoded wherever at least `k` shards
+/// cover them, and elsewhere only the bytes of the received data shards are recovered.
+pub fn reconstruct_partial(params: &CodeParams, received_shards: &[Option<&[u8]>]) -> Result<PartialReconstruction> {
+	let (_n, k) = internal_params(params)?;
+	if received_shards.len() != params.n() {
+		return Err(Error::WrongShardCount { expected: params.n(), got: received_shards.len() });
+	}
+	let shard_len = received_shards.iter().flatten().map(|shard| shard.len()).max().unwrap_or_default();
+
+	// the data shards as far as known, received as is or decoded per stripe
+	let mut data = vec![vec![0_u8; shard_len]; k];
+	let mut known = vec![vec![false; shard_len]; k];
+	for (idx, shard) in received_shards[..k].iter().enumerate() {
+		if let Some(shard) = shard {
+			data[idx][..shard.len()].copy_from_slice(shard);
+			known[idx][..shard.len()].iter_mut().for_each(|known| *known = true);
 		}
+	}
 
-		//-----------Generating message----------
-		//message array
-		let mut data: [GFSymbol; N] = [0; N];
-
-		for i in 0..K {
-			//filled with random numbers
-			data[i] = (i * i % MODULO as usize) as u16;
-			// data[i] = rand_gf_element();
+	// the set of shards covering a stripe only changes at the end of a shard, in whole symbol groups
+	let group_len = params.symbol_group() * 2;
+	let aligned = |shard: &[u8]| shard.len() / group_len * group_len;
+	let mut ends = received_shards.iter().flatten().map(|shard| aligned(shard)).collect::<Vec<_>>();
+	ends.sort_unstable();
+	ends.dedup();
+	let mut start = 0;
+	for end in ends.into_iter().filter(|end| *end > 0) {
+		let stripe = received_shards
+			.iter()
+			.map(|shard| shard.filter(|shard| aligned(shard) >= end).map(|shard| &shard[start..end]))
+			.collect::<Vec<_>>();
+		if stripe.iter().flatten().count() >= k {
+			let decoded = reconstruct_ref_with_params(params, &stripe)?;
+			for (idx, (data, known)) in data.iter_mut().zip(known.iter_mut()).enumerate() {
+				for offset in 0..end - start {
+					data[start + offset] = decoded[payload_byte(params, k, end - start, idx, offset)];
+					known[start + offset] = true;
+				}
+			}
 		}
+		start = end;
+	}
 
-		assert_eq!(data.len(), N);
-
-		println!("Message(Last n-k are zeros): ");
-		for i in 0..K {
-			print!("{:04x} ", data[i]);
+	let mut payload = vec![0_u8; padded_len(k, shard_len)];
+	let mut recovered_bytes = vec![false; payload.len()];
+	for (idx, (data, known)) in data.iter().zip(known.iter()).enumerate() {
+		for (offset, _) in known.iter().enumerate().filter(|(_, known)| **known) {
+			let at = payload_byte(params, k, shard_len, idx, offset);
+			payload[at] = data[offset];
+			recovered_bytes[at] = true;
+		}
+	}
+	let mut recovered = Vec::<std::ops::Range<usize>>::new();
+	for (at, _) in recovered_bytes.iter().enumerate().filter(|(_, recovered)| **recovered) {
+		match recovered.last_mut() {
+			Some(range) if range.end == at => range.end += 1,
+			_ => recovered.push(at..at + 1),
+		}
+	}
+	Ok(PartialReconstruction { payload, recovered })
+}
+
+// The byte of the padded payload carried by byte `offset` of data shard `idx`,
+// given `k` data shards of `shard_len` bytes.
+fn payload_byte(params: &CodeParams, k: usize, shard_len: usize, idx: usize, offset: usize) -> usize {
+	match params.layout() {
+		ShardLayout::Interleaved => interleaved_symbol(k, params.symbol_group(), idx, offset / 2) * 2 + offset % 2,
+		ShardLayout::Sequential => idx * shard_len.div_ceil(2) * 2 + offset,
+	}
+}
+
+fn reconstruct_recorded(
+	params: &CodeParams,
+	received_shards: &[Option<&[u8]>],
+	scratch: &mut Scratch,
+	rec: &mut impl Recorder,
+) -> Result<Vec<u8>> {
+	if let Some(equalized) = equalize_shard_lengths(params, received_shards) {
+		let equalized = equalized.iter().map(Option::as_deref).collect::<Vec<_>>();
+		return reconstruct_recorded(params, &equalized, scratch, rec);
+	}
+	let (_n, k, shard_len) = check_received(params, received_shards)?;
+
+	let mut recovered = vec![0_u8; padded_len(k, shard_len)];
+	reconstruct_stripes(params, received_shards, scratch, rec, |offset, bytes| {
+		recovered[offset..offset + bytes.len()].copy_from_slice(bytes)
+	})?;
+	Ok(recovered)
+}
+
+// The code is systematic, so if all data shards arrived the payload, or a stripe of it,
+// is assembled from them as is, in the order of the layout.
+fn copy_data_shards(params: &CodeParams, data_shards: &[&[u8]], recovered: &mut [u8]) {
+	let k = data_shards.len();
+	let stride = recovered.len() / k;
+	match params.layout() {
+		ShardLayout::Interleaved => {
+			for (idx, shard) in data_shards.iter().enumerate() {
+				for (c, sym) in shard.chunks(2).enumerate() {
+					let at = interleaved_symbol(k, params.symbol_group(), idx, c) * 2;
+					recovered[at..at + sym.len()].copy_from_slice(sym);
+				}
+			}
+		}
+		ShardLayout::Sequential => {
+			for (idx, shard) in data_shards.iter().enumerate() {
+				recovered[idx * stride..idx * stride + shard.len()].copy_from_slice(shard);
+			}
+		}
+	}
+}
+
+/// Reconstruct the payload stripe by stripe, reusing the fixed size `scratch`.
+///
+/// The peak memory does not grow with the payload, besides the received shards,
+/// see `bounded_scratch_size`. Each recovered part is passed to `sink` together
+/// with its offset within the payload, offsets are increasing for the interleaved
+/// layout but jump between data shards for the sequential one.
+pub fn reconstruct_bounded(
+	params: &CodeParams,
+	received_shards: &[Option<&[u8]>],
+	scratch: &mut Scratch,
+	sink: impl FnMut(usize, &[u8]),
+) -> Result<()> {
+	if let Some(equalized) = equalize_shard_lengths(params, received_shards) {
+		let equalized = equalized.iter().map(Option::as_deref).collect::<Vec<_>>();
+		return reconstruct_stripes(params, &equalized, scratch, &mut (), sink);
+	}
+	reconstruct_stripes(params, received_shards, scratch, &mut (), sink)
+}
+
+// validate the received shards, returns the internal `(n, k)` and the shard length
+fn check_received(params: &CodeParams, received_shards: &[Option<&[u8]>]) -> Result<(usize, usize, usize)> {
+	let (n, k) = internal_params(params)?;
+	if received_shards.len() != params.n() {
+		return Err(Error::WrongShardCount { expected: params.n(), got: received_shards.len() });
+	}
+
+	let available = received_shards.iter().flatten().count();
+	if available < k {
+		return Err(Error::TooFewShards { available, required: k });
+	}
+
+	// all shards must have the same length
+	let mut present = received_shards.iter().flatten();
+	let shard_len = present.next().map(|shard| shard.len()).unwrap_or_default();
+	if let Some(other) = present.map(|shard| shard.len()).find(|len| *len != shard_len) {
+		return Err(Error::InconsistentShardLengths { first: shard_len, other });
+	}
+	let group = params.symbol_group();
+	if group > 1 && !shard_len.is_multiple_of(group * 2) {
+		return Err(Error::UngroupedShardLength { len: shard_len, group });
+	}
+	Ok((n, k, shard_len))
+}
+
+// length of the reconstructed payload including padding, from the internal `k`
+pub(crate) fn padded_len(k: usize, shard_len: usize) -> usize {
+	shard_len.div_ceil(2) * 2 * k
+}
+
+fn reconstruct_stripes(
+	params: &CodeParams,
+	received_shards: &[Option<&[u8]>],
+	scratch: &mut Scratch,
+	rec: &mut impl Recorder,
+	mut sink: impl FnMut(usize, &[u8]),
+) -> Result<()> {
+	let shape = prepare_stripes(params, received_shards, scratch, rec)?;
+	for stripe_start in shape.stripes() {
+		decode_stripe(params, received_shards, scratch, &shape, stripe_start, rec, &mut sink);
+	}
+	Ok(())
+}
+
+// dimensions of a reconstruction in stripes
+struct StripeShape {
+	n: usize,
+	k: usize,
+	shard_len: usize,
+	stripe_len: usize,
+	// all data shards were received, so the message is copied without decoding
+	data_complete: bool,
+}
+
+impl StripeShape {
+	// the offsets within the shards at which stripes start
+	fn stripes(&self) -> impl Iterator<Item = usize> {
+		(0..self.shard_len).step_by(self.stripe_len)
+	}
+}
+
+// stripes cover whole groups of symbols, so they are contiguous in the interleaved payload
+fn grouped_stripe_len(params: &CodeParams, stripe_len: usize) -> usize {
+	let group_len = params.symbol_group() * 2;
+	std::cmp::max(stripe_len / group_len, 1) * group_len
+}
+
+// bytes of each shard decoded per stripe, at most the whole shards
+fn stripe_len(params: &CodeParams, scratch_stripe_len: usize, shard_len: usize) -> usize {
+	std::cmp::max(std::cmp::min(grouped_stripe_len(params, scratch_stripe_len), shard_len.div_ceil(2) * 2), 2)
+}
+
+// validate the received shards and evaluate the error locator polynomial into `scratch`
+fn prepare_stripes(
+	params: &CodeParams,
+	received_shards: &[Option<&[u8]>],
+	scratch: &mut Scratch,
+	rec: &mut impl Recorder,
+) -> Result<StripeShape> {
+	setup();
+
+	let (n, k, shard_len) = check_received(params, received_shards)?;
+	let stripe_len = stripe_len(params, scratch.stripe_len, shard_len);
+	scratch.prepare(n, k, stripe_len);
+
+	// collect all `None` values, shards beyond `params.n()` are never transmitted
+	for idx in 0..n {
+		scratch.erasures.set(idx, received_shards.get(idx).copied().flatten().is_none());
+	}
+
+	//---------Erasure decoding----------------
+
+	// Evaluate error locator polynomial, once for all codewords
+	// since the erasures are identical
+	let Scratch { log_walsh2, erasures, cache, .. } = scratch;
+	let data_complete = (0..k).all(|idx| !erasures[idx]);
+	if !data_complete {
+		match cache.as_mut().and_then(|cache| cache.get(erasures)) {
+			Some(cached) => log_walsh2[..n].copy_from_slice(cached),
+			None => {
+				rec.time(Phase::ErrorLocator, || eval_error_polynomial(erasures.iter(), &mut log_walsh2[..], n));
+				if let Some(cache) = cache {
+					cache.insert(erasures.clone(), log_walsh2[..n].to_vec());
+				}
+			}
+		}
+	}
+
+	Ok(StripeShape { n, k, shard_len, stripe_len, data_complete })
+}
+
+// decode the stripe starting at `stripe_start` and pass it to `sink`
+fn decode_stripe(
+	params: &CodeParams,
+	received_shards: &[Option<&[u8]>],
+	scratch: &mut Scratch,
+	shape: &StripeShape,
+	stripe_start: usize,
+	rec: &mut impl Recorder,
+	sink: &mut impl FnMut(usize, &[u8]),
+) {
+	let StripeShape { n, k, shard_len, stripe_len, data_complete } = *shape;
+	let Scratch { log_walsh2, erasures, codeword, received, stripe, decode_plan, .. } = scratch;
+	let plan = decode_plan.as_deref().expect("prepared with the stripes. qed");
+
+	let stride = shard_len.div_ceil(2) * 2;
+	let stripe_end = std::cmp::min(stripe_start + stripe_len, stride);
+	let len = stripe_end - stripe_start;
+	if data_complete {
+		// the code is systematic, so the message is copied without decoding
+		rec.time(Phase::SymbolConversion, || {
+			let data_shards = received_shards[..k].iter().map(|shard| {
+				let shard = shard.expect("all data shards were received. qed");
+				&shard[stripe_start..std::cmp::min(stripe_end, shard.len())]
+			});
+			let stripe = match params.layout() {
+				ShardLayout::Interleaved => &mut stripe[..len * k],
+				ShardLayout::Sequential => &mut stripe[..stripe_len * k],
+			};
+			// the padding byte of odd length shards is not copied
+			stripe.fill(0);
+			copy_data_shards(params, &data_shards.collect::<Vec<_>>(), stripe);
+		});
+	} else {
+		for batch_start in (stripe_start..stripe_end).step_by(SOA_WIDTH * 2) {
+			// the codewords of the batch are stored as rows, row `idx` being a slice of shard `idx`
+			let width = (std::cmp::min(batch_start + SOA_WIDTH * 2, stripe_end) - batch_start) / 2;
+			let (codeword, received) = (&mut codeword[..n * width], &mut received[..n * width]);
+
+			// fill the gaps with `0_u16` codewords
+			rec.time(Phase::SymbolConversion, || {
+				for (idx, row) in codeword.chunks_exact_mut(width).enumerate() {
+					match received_shards.get(idx).copied().flatten() {
+						Some(shard) => {
+							for (c, sym) in row.iter_mut().enumerate() {
+								let offset = batch_start + c * 2;
+								let bytes = [shard[offset], shard.get(offset + 1).copied().unwrap_or_default()];
+								*sym = params.endianness().symbol_from_bytes(bytes);
+							}
+						}
+						None => row.fill(0_u16),
+					}
+				}
+				received.copy_from_slice(&codeword[..]);
+			});
+
+			//---------main processing----------
+			decode_main_rows(&mut codeword[..], width, k, erasures.iter(), &log_walsh2[..], plan, rec);
+
+			// the first `k` symbols are the message
+			rec.time(Phase::SymbolConversion, || {
+				for idx in 0..k {
+					let row = if erasures[idx] { &codeword[idx * width..] } else { &received[idx * width..] };
+					for (c, sym) in row[..width].iter().enumerate() {
+						let bytes = params.endianness().symbol_to_bytes(*sym);
+						let at = batch_start + c * 2 - stripe_start;
+						let at = match params.layout() {
+							ShardLayout::Interleaved => interleaved_symbol(k, params.symbol_group(), idx, at / 2) * 2,
+							ShardLayout::Sequential => idx * stripe_len + at,
+						};
+						stripe[at..at + 2].copy_from_slice(&bytes);
+					}
+				}
+			});
+		}
+	}
+
+	rec.time(Phase::SymbolConversion, || match params.layout() {
+		ShardLayout::Interleaved => sink(stripe_start * k, &stripe[..len * k]),
+		ShardLayout::Sequential => {
+			for idx in 0..k {
+				sink(idx * stride + stripe_start, &stripe[idx * stripe_len..idx * stripe_len + len]);
+			}
+		}
+	});
+}
+
+/// A reconstruction performed in steps, so a caller with a latency budget, i.e. on a single
+/// threaded WASM or embedded target, can interleave other work between them.
+///
+/// The first step evaluates the error locator polynomial, every further one decodes a
+/// stripe of `scratch.stripe_len()` bytes per shard, so the length of the steps is bounded
+/// by the choice of the stripe length, see `reconstruct_bounded`.
+pub struct ReconstructionJob<'a> {
+	params: CodeParams,
+	received_shards: &'a [Option<&'a [u8]>],
+	scratch: &'a mut Scratch,
+	// `None` until the first step prepared the stripes
+	shape: Option<StripeShape>,
+	stripes: usize,
+	next_stripe: usize,
+	recovered: Vec<u8>,
+}
+
+impl<'a> ReconstructionJob<'a> {
+	/// Validate the received shards, without decoding anything yet.
+	pub fn new(params: &CodeParams, received_shards: &'a [Option<&'a [u8]>], scratch: &'a mut Scratch) -> Result<Self> {
+		let (_n, k, shard_len) = check_received(params, received_shards)?;
+		let stripes = shard_len.div_ceil(stripe_len(params, scratch.stripe_len, shard_len));
+		Ok(ReconstructionJob {
+			params: *params,
+			received_shards,
+			scratch,
+			shape: None,
+			stripes,
+			next_stripe: 0,
+			recovered: vec![0_u8; padded_len(k, shard_len)],
+		})
+	}
+
+	/// Perform the next step, returns whether the reconstruction is complete.
+	pub fn step(&mut self) -> Result<bool> {
+		let shape = match &self.shape {
+			Some(shape) => shape,
+			None => {
+				self.shape = Some(prepare_stripes(&self.params, self.received_shards, self.scratch, &mut ())?);
+				return Ok(self.is_complete());
+			}
+		};
+		if self.next_stripe < self.stripes {
+			let recovered = &mut self.recovered;
+			decode_stripe(
+				&self.params,
+				self.received_shards,
+				self.scratch,
+				shape,
+				self.next_stripe * shape.stripe_len,
+				&mut (),
+				&mut |offset, bytes| recovered[offset..offset + bytes.len()].copy_from_slice(bytes),
+			);
+			self.next_stripe += 1;
+		}
+		Ok(self.is_complete())
+	}
+
+	/// Whether all steps were performed, so `finish` returns without decoding.
+	pub fn is_complete(&self) -> bool {
+		self.remaining_steps() == 0
+	}
+
+	/// Number of steps left, including the preparation if not performed yet.
+	pub fn remaining_steps(&self) -> usize {
+		usize::from(self.shape.is_none()) + self.stripes - self.next_stripe
+	}
+
+	/// Perform the remaining steps and return the payload, which might have trailing zeros.
+	pub fn finish(mut self) -> Result<Vec<u8>> {
+		while !self.step()? {}
+		Ok(self.recovered)
+	}
+}
+
+// resolves after returning `Pending` once, to let the executor run other tasks
+#[cfg(feature = "async")]
+fn yield_now() -> impl std::future::Future<Output = ()> {
+	let mut yielded = false;
+	std::future::poll_fn(move |cx| {
+		if yielded {
+			std::task::Poll::Ready(())
+		} else {
+			yielded = true;
+			cx.waker().wake_by_ref();
+			std::task::Poll::Pending
+		}
+	})
+}
+
+/// Codewords encoded between two yields of `encode_async`.
+#[cfg(feature = "async")]
+const ASYNC_STRIPE_CODEWORDS: usize = DEFAULT_STRIPE_LEN / 2;
+
+/// Same as `encode_with_params`, yielding to the executor between stripes, so large
+/// payloads do not stall other tasks of the reactor thread.
+#[cfg(feature = "async")]
+pub async fn encode_async(params: &CodeParams, data: &[u8]) -> Result<Vec<WrappedShard>> {
+	let (_n, k) = internal_params(params)?;
+	params.check_payload_len(data.len())?;
+	let codewords = codewords(k, params.symbol_group(), data.len());
+
+	let mut shards = (0..params.n()).map(|_| Vec::<u8>::with_capacity(codewords * 2)).collect::<Vec<_>>();
+	for start in (0..codewords).step_by(ASYNC_STRIPE_CODEWORDS) {
+		let end = std::cmp::min(start + ASYNC_STRIPE_CODEWORDS, codewords);
+		encode_codewords(params, data, start..end, &mut (), |_chunk_idx, width, rows| {
+			for (shard, row) in shards.iter_mut().zip(rows.chunks_exact(width)) {
+				for sym in row {
+					shard.extend_from_slice(&params.endianness().symbol_to_bytes(*sym));
+				}
+			}
+		})?;
+		yield_now().await;
+	}
+
+	Ok(shards.into_iter().map(WrappedShard::new).collect())
+}
+
+/// Same as `reconstruct_ref_with_params`, yielding to the executor between stripes.
+#[cfg(feature = "async")]
+pub async fn reconstruct_async(params: &CodeParams, received_shards: &[Option<&[u8]>]) -> Result<Vec<u8>> {
+	let mut scratch = Scratch::new(DEFAULT_STRIPE_LEN);
+	let shape = prepare_stripes(params, received_shards, &mut scratch, &mut ())?;
+	yield_now().await;
+
+	let mut recovered = vec![0_u8; padded_len(shape.k, shape.shard_len)];
+	for stripe_start in shape.stripes() {
+		decode_stripe(params, received_shards, &mut scratch, &shape, stripe_start, &mut (), &mut |offset, bytes| {
+			recovered[offset..offset + bytes.len()].copy_from_slice(bytes)
+		});
+		yield_now().await;
+	}
+	Ok(recovered)
+}
+
+/// Compute the shards `params.n()..new_n` of the code extended to `new_n` shards,
+/// i.e. for a grown validator set, from the received shards of the `params` code.
+///
+/// The symbol at a position of a codeword does not depend on `n`, so the existing
+/// shards remain valid and only the missing data symbols are decoded, from which
+/// solely the transforms covering the new positions are evaluated. Nothing is returned
+/// if `new_n` does not exceed `params.n()`.
+pub fn extend_code(params: &CodeParams, received_shards: &[Option<&[u8]>], new_n: usize) -> Result<Vec<WrappedShard>> {
+	check_received(params, received_shards)?;
+	if new_n <= params.n() {
+		return Ok(Vec::new());
+	}
+	let extended = CodeParams::new(new_n, params.k())?;
+	internal_params(&extended)?;
+	encode_extra_parity(params, ParitySource::Shards(received_shards), params.n()..new_n)
+}
+
+/// Where `encode_extra_parity` takes the message from.
+#[derive(Debug, Clone, Copy)]
+pub enum ParitySource<'a> {
+	/// The payload, as passed to `encode_with_params`.
+	Data(&'a [u8]),
+	/// Received shards of the `params` code, missing data shards are decoded first.
+	Shards(&'a [Option<&'a [u8]>]),
+}
+
+/// Compute the shards at the positions `range` of the code extended beyond `params.n()`,
+/// without encoding the shards before `range.start`.
+///
+/// Meant for rateless distribution, where the sender keeps emitting shards of new positions
+/// until enough recipients confirm. Any `params.k()` distinct shards of the extended code,
+/// with positions up to the field size, suffice to reconstruct.
+pub fn encode_extra_parity(
+	params: &CodeParams,
+	source: ParitySource<'_>,
+	range: std::ops::Range<usize>,
+) -> Result<Vec<WrappedShard>> {
+	if range.end > FIELD_SIZE {
+		return Err(Error::ShardCountTooHigh(range.end));
+	}
+	let (n, k, shard_len) = match source {
+		ParitySource::Data(data) => {
+			let (n, k) = internal_params(params)?;
+			params.check_payload_len(data.len())?;
+			(n, k, codewords(k, params.symbol_group(), data.len()) * 2)
+		}
+		ParitySource::Shards(received_shards) => check_received(params, received_shards)?,
+	};
+	if range.is_empty() {
+		return Ok(Vec::new());
+	}
+
+	setup();
+
+	let erasures = match source {
+		ParitySource::Data(_) => ErasureMap::new(n),
+		ParitySource::Shards(received_shards) => {
+			(0..n).map(|idx| received_shards.get(idx).copied().flatten().is_none()).collect::<ErasureMap>()
+		}
+	};
+	let data_complete = (0..k).all(|idx| !erasures[idx]);
+	let mut log_walsh2 = vec![0_u16; n];
+	if !data_complete {
+		eval_error_polynomial(erasures.iter(), &mut log_walsh2[..], n);
+	}
+	let decode_plan = DecodePlan::new(n);
+
+	// the transforms at shifts of `k` covering the requested positions
+	let first_shift = range.start / k * k;
+	let mut shards = vec![Vec::<u8>::with_capacity(shard_len); range.len()];
+	let mut codeword = vec![0_u16; n];
+	let mut message = vec![0_u16; k];
+	let mut at_shift = vec![0_u16; k];
+	for offset in (0..shard_len).step_by(2) {
+		match source {
+			ParitySource::Data(data) => {
+				for (i, sym) in message.iter_mut().enumerate() {
+					*sym = message_symbol(params, data, k, shard_len / 2, i, offset / 2);
+				}
+			}
+			ParitySource::Shards(received_shards) => {
+				for (idx, sym) in codeword.iter_mut().enumerate() {
+					*sym = match received_shards.get(idx).copied().flatten() {
+						Some(shard) => {
+							let bytes = [shard[offset], shard.get(offset + 1).copied().unwrap_or_default()];
+							params.endianness().symbol_from_bytes(bytes)
+						}
+						None => 0_u16,
+					};
+				}
+				message.copy_from_slice(&codeword[..k]);
+				if !data_complete {
+					decode_main(&mut codeword[..], k, erasures.iter(), &log_walsh2[..], &decode_plan, &mut ());
+					for idx in 0..k {
+						if erasures[idx] {
+							message[idx] = codeword[idx];
+						}
+					}
+				}
+			}
+		}
+
+		// `M_topdash` as in `encode_low`
+		inverse_fft_in_novel_poly_basis(&mut message[..], k, 0);
+		for shift in (first_shift..range.end).step_by(k) {
+			at_shift.copy_from_slice(&message[..]);
+			fft_in_novel_poly_basis(&mut at_shift[..], k, shift);
+			for (pos, sym) in (shift..shift + k).zip(at_shift.iter()) {
+				if range.contains(&pos) {
+					shards[pos - range.start].extend_from_slice(&params.endianness().symbol_to_bytes(*sym));
+				}
+			}
+		}
+	}
+
+	Ok(shards.into_iter().map(WrappedShard::new).collect())
+}
+
+/// A few symbols summarizing the payload, sent alongside the shards, so a receiver
+/// verifies its reconstruction with `verify_checks` instead of hashing the payload.
+///
+/// Check `j` is the sum over all codewords `c` of their symbol at position
+/// `FIELD_SIZE - 1 - j`, multiplied by `exp(c)`, i.e. a virtual shard at a position
+/// reserved for checks, compressed to one symbol. By linearity it is computed from the
+/// weighted sum of the messages with a single transform of `k` symbols. This catches
+/// corruption, not a sender crafting a colliding payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckSymbols(pub Vec<GFSymbol>);
+
+/// Same as `encode_with_params`, additionally computing `count` check symbols.
+pub fn encode_with_checks(
+	params: &CodeParams,
+	data: &[u8],
+	count: usize,
+) -> Result<(Vec<WrappedShard>, CheckSymbols)> {
+	let checks = check_symbols(params, data, count)?;
+	Ok((encode_with_params(params, data)?, checks))
+}
+
+/// Compute `count` check symbols of `payload`, with or without the padding of the reconstruction.
+pub fn check_symbols(params: &CodeParams, payload: &[u8], count: usize) -> Result<CheckSymbols> {
+	let (_n, k) = internal_params(params)?;
+	params.check_payload_len(payload.len())?;
+	if count > FIELD_SIZE {
+		return Err(Error::ShardCountTooHigh(count));
+	}
+	setup();
+
+	// the message of the weighted sum of all codewords
+	let shard_len = codewords(k, params.symbol_group(), payload.len());
+	let mut message = vec![0_u16; k];
+	for c in 0..shard_len {
+		let weight = (c % MODULO as usize) as GFSymbol;
+		for (i, sym) in message.iter_mut().enumerate() {
+			*sym ^= mul_table(message_symbol(params, payload, k, shard_len, i, c), weight);
+		}
+	}
+
+	// evaluated as in `encode_low`, at the blocks of `k` positions holding the checks
+	inverse_fft_in_novel_poly_basis(&mut message[..], k, 0);
+	let mut checks = Vec::with_capacity(count);
+	let mut at_shift = vec![0_u16; k];
+	let mut shift = None;
+	for pos in (FIELD_SIZE - count..FIELD_SIZE).rev() {
+		let block = pos / k * k;
+		if shift != Some(block) {
+			at_shift.copy_from_slice(&message[..]);
+			fft_in_novel_poly_basis(&mut at_shift[..], k, block);
+			shift = Some(block);
+		}
+		checks.push(at_shift[pos - block]);
+	}
+	Ok(CheckSymbols(checks))
+}
+
+/// Verify a reconstructed `payload` against the check symbols computed by the encoder,
+/// reporting the first check that does not match.
+pub fn verify_checks(params: &CodeParams, payload: &[u8], checks: &CheckSymbols) -> Result<()> {
+	let expected = check_symbols(params, payload, checks.0.len())?;
+	match expected.0.iter().zip(checks.0.iter()).position(|(expected, check)| expected != check) {
+		Some(j) => Err(Error::CheckMismatch(j)),
+		None => Ok(()),
+	}
+}
+
+/// Bytes per shard decoded at once by the regular reconstruction.
+const DEFAULT_STRIPE_LEN: usize = 1 << 12;
+
+/// Everything derived from the code parameters alone, for a code used with many payloads.
+///
+/// `new` plans the transforms of encode and decode, with their skew factors, and the factors
+/// of the formal derivative, which the free functions derive again on every call.
+#[derive(Debug, Clone)]
+pub struct CodePlan {
+	params: CodeParams,
+	encode: EncodePlan,
+	decode: Arc<DecodePlan>,
+}
+
+impl CodePlan {
+	pub fn new(params: &CodeParams) -> Result<Self> {
+		let (n, k) = internal_params(params)?;
+		Ok(CodePlan {
+			params: *params,
+			encode: EncodePlan::new(k, n),
+			decode: Arc::new(DecodePlan::new(n)),
+		})
+	}
+
+	pub fn params(&self) -> &CodeParams {
+		&self.params
+	}
+
+	/// Same as `encode_with_params`.
+	pub fn encode(&self, data: &[u8]) -> Result<Vec<WrappedShard>> {
+		encode_recorded_planned(&self.params, &self.encode, data, &mut ())
+	}
+
+	/// Same as `reconstruct_ref_with_params`.
+	pub fn reconstruct(&self, received_shards: &[Option<&[u8]>]) -> Result<Vec<u8>> {
+		self.reconstruct_with_scratch(received_shards, &mut Scratch::new(DEFAULT_STRIPE_LEN))
+	}
+
+	/// Same as `reconstruct_with_scratch`, the scratch shares the decode plan afterwards.
+	pub fn reconstruct_with_scratch(
+		&self,
+		received_shards: &[Option<&[u8]>],
+		scratch: &mut Scratch,
+	) -> Result<Vec<u8>> {
+		scratch.decode_plan = Some(Arc::clone(&self.decode));
+		reconstruct_recorded(&self.params, received_shards, scratch, &mut ())
+	}
+}
+
+/// Reusable scratch buffers of `reconstruct_bounded`, their size only depends
+/// on the code parameters and the stripe length, not on the payload.
+#[derive(Debug, Clone, Default)]
+pub struct Scratch {
+	stripe_len: usize,
+	log_walsh2: Vec<GFSymbol>,
+	erasures: ErasureMap,
+	codeword: Vec<GFSymbol>,
+	received: Vec<GFSymbol>,
+	stripe: Vec<u8>,
+	cache: Option<ErasureCache>,
+	// derived from `n` alone, kept across reconstructions or shared with a `CodePlan`
+	decode_plan: Option<Arc<DecodePlan>>,
+}
+
+impl Scratch {
+	/// Scratch decoding `stripe_len` bytes of every shard at once, rounded up to whole symbols.
+	pub fn new(stripe_len: usize) -> Self {
+		Scratch { stripe_len: std::cmp::max(stripe_len.div_ceil(2) * 2, 2), ..Default::default() }
+	}
+
+	/// Keep the error locator evaluations of the last `capacity` erasure patterns, so
+	/// reconstructions with a repeated pattern skip its two full field Walsh transforms.
+	pub fn with_erasure_cache(mut self, capacity: usize) -> Self {
+		self.cache = Some(ErasureCache::new(capacity));
+		self
+	}
+
+	/// The erasure pattern cache, if enabled by `with_erasure_cache`.
+	pub fn erasure_cache(&self) -> Option<&ErasureCache> {
+		self.cache.as_ref()
+	}
+
+	/// Bytes per shard decoded at once.
+	pub fn stripe_len(&self) -> usize {
+		self.stripe_len
+	}
+
+	/// Bytes currently held by the buffers, including the erasure pattern cache.
+	pub fn size_in_bytes(&self) -> usize {
+		let symbol = std::mem::size_of::<GFSymbol>();
+		(self.log_walsh2.len() + self.codeword.len() + self.received.len()) * symbol
+			+ self.erasures.len().div_ceil(8)
+			+ self.stripe.len()
+			+ self.cache.as_ref().map_or(0, ErasureCache::size_in_bytes)
+	}
+
+	fn prepare(&mut self, n: usize, k: usize, stripe_len: usize) {
+		self.log_walsh2.resize(n, 0);
+		self.erasures.resize(n, true);
+		self.codeword.resize(n * SOA_WIDTH, 0);
+		self.received.resize(n * SOA_WIDTH, 0);
+		self.stripe.resize(stripe_len * k, 0);
+		if self.decode_plan.as_ref().is_none_or(|plan| plan.n != n) {
+			self.decode_plan = Some(Arc::new(DecodePlan::new(n)));
+		}
+	}
+}
+
+/// Least recently used error locator evaluations, keyed by the erasure pattern.
+///
+/// Availability systems tend to see the same validators offline for many
+/// reconstructions in a row, which all share the error locator polynomial.
+#[derive(Debug, Clone, Default)]
+pub struct ErasureCache {
+	capacity: usize,
+	// the evaluations at the internal `n` positions, with the tick of their last use
+	entries: HashMap<ErasureMap, (u64, Vec<GFSymbol>)>,
+	tick: u64,
+	hits: u64,
+	misses: u64,
+}
+
+impl ErasureCache {
+	/// Cache holding at most `capacity` erasure patterns.
+	pub fn new(capacity: usize) -> Self {
+		ErasureCache { capacity, ..Default::default() }
+	}
+
+	/// Number of cached erasure patterns.
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	/// Lookups that found their pattern.
+	pub fn hits(&self) -> u64 {
+		self.hits
+	}
+
+	/// Lookups that had to evaluate the error locator polynomial.
+	pub fn misses(&self) -> u64 {
+		self.misses
+	}
+
+	/// Bytes held by the cached evaluations and their keys.
+	pub fn size_in_bytes(&self) -> usize {
+		let symbol = std::mem::size_of::<GFSymbol>();
+		self.entries
+			.iter()
+			.map(|(erasures, (_, log_walsh2))| erasures.len().div_ceil(8) + log_walsh2.len() * symbol)
+			.sum()
+	}
+
+	fn get(&mut self, erasures: &ErasureMap) -> Option<&[GFSymbol]> {
+		self.tick += 1;
+		match self.entries.get_mut(erasures) {
+			Some((last_use, log_walsh2)) => {
+				self.hits += 1;
+				*last_use = self.tick;
+				Some(log_walsh2)
+			}
+			None => {
+				self.misses += 1;
+				None
+			}
+		}
+	}
+
+	fn insert(&mut self, erasures: ErasureMap, log_walsh2: Vec<GFSymbol>) {
+		if self.capacity == 0 {
+			return;
+		}
+		if self.entries.len() >= self.capacity {
+			let lru = self.entries.iter().min_by_key(|(_, (last_use, _))| *last_use).map(|(key, _)| key.clone());
+			if let Some(lru) = lru {
+				self.entries.remove(&lru);
+			}
+		}
+		self.entries.insert(erasures, (self.tick, log_walsh2));
+	}
+}
+
+// bytes held by a `Scratch` for the internal `n` and `stripe_bytes` of output per stripe
+fn scratch_size(n: usize, stripe_bytes: usize) -> usize {
+	let symbol = std::mem::size_of::<GFSymbol>();
+	n * symbol + n.div_ceil(8) + 2 * n * SOA_WIDTH * symbol + stripe_bytes
+}
+
+/// Memory in bytes held by encode and reconstruct for a given payload length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryUsage {
+	/// The encoded shards, all of them.
+	pub shards: usize,
+	/// Peak scratch memory while encoding, besides payload and shards.
+	pub encode_scratch: usize,
+	/// Peak scratch memory while reconstructing, besides shards and payload.
+	pub reconstruct_scratch: usize,
+	/// The reconstructed payload including padding.
+	pub payload: usize,
+}
+
+/// Report the memory required to encode and reconstruct a payload of `payload_len` bytes.
+pub fn memory_usage(params: &CodeParams, payload_len: usize) -> Result<MemoryUsage> {
+	let (n, k) = internal_params(params)?;
+	params.check_payload_len(payload_len)?;
+	let shard_len = codewords(k, params.symbol_group(), payload_len) * 2;
+	let symbol = std::mem::size_of::<GFSymbol>();
+	Ok(MemoryUsage {
+		shards: params.n() * shard_len,
+		// the codewords of a batch, encoded in place
+		encode_scratch: n * std::cmp::min(SOA_WIDTH, shard_len / 2) * symbol,
+		reconstruct_scratch: scratch_size(n, std::cmp::min(DEFAULT_STRIPE_LEN, shard_len) * k),
+		payload: padded_len(k, shard_len),
+	})
+}
+
+/// Peak scratch memory of `reconstruct_bounded` with a `Scratch::new(stripe_len)`,
+/// independent of the payload length.
+pub fn bounded_scratch_size(params: &CodeParams, stripe_len: usize) -> Result<usize> {
+	let (n, k) = internal_params(params)?;
+	Ok(scratch_size(n, grouped_stripe_len(params, Scratch::new(stripe_len).stripe_len()) * k))
+}
+
+/// Building blocks of the decoder, for experimenting with alternative decode strategies.
+///
+/// All functions operate on symbols in the novel polynomial basis and go through
+/// a `Tables` handle, which guarantees the field tables are initialized.
+pub mod low_level {
+	use super::*;
+
+	/// Handle to the initialized, from then on immutable, field tables.
+	#[derive(Debug, Clone, Copy)]
+	pub struct Tables {
+		_private: (),
+	}
+
+	impl Tables {
+		/// Initialize the tables if necessary and obtain a handle.
+		pub fn get() -> Self {
+			setup();
+			Tables { _private: () }
+		}
+
+		/// Size of the field and of the buffer used by `eval_error_polynomial`.
+		pub const FIELD_SIZE: usize = FIELD_SIZE;
+
+		/// `a * exp(log_b)` in the field, with `log_b` given in the log domain.
+		pub fn mul(&self, a: GFSymbol, log_b: GFSymbol) -> GFSymbol {
+			mul_table(a, log_b)
+		}
+
+		/// Same as `mul`, but in time independent of `a`, which is used by all field
+		/// operations with the `constant-time` feature, at a substantial slowdown.
+		pub fn mul_ct(&self, a: GFSymbol, log_b: GFSymbol) -> GFSymbol {
+			mul_ct(a, log_b)
+		}
+
+		/// Same as `mul`, via the polynomial basis and a carry-less multiplication instead of
+		/// the log table, which is used by all field operations with the `compact-tables` feature
+		/// to keep the tables out of the cache.
+		pub fn mul_poly_basis(&self, a: GFSymbol, log_b: GFSymbol) -> GFSymbol {
+			mul_poly_basis(a, log_b)
+		}
+
+		/// Discrete logarithm of `a`.
+		pub fn log(&self, a: GFSymbol) -> GFSymbol {
+			f2e16::log(a)
+		}
+
+		/// Inverse of `log`.
+		pub fn exp(&self, log_a: GFSymbol) -> GFSymbol {
+			f2e16::exp(log_a)
+		}
+
+		/// Log domain factors `B[i]` used to scale around the formal derivative.
+		pub fn b_factors(&self) -> &'static [GFSymbol] {
+			f2e16::b_factors()
+		}
+
+		/// Log domain skew factors of the transforms.
+		pub fn skew_factors(&self) -> &'static [GFSymbol] {
+			f2e16::skew_factors()
+		}
+
+		/// Forward transform of `data[..size]`, evaluating at the points offset by `index`.
+		///
+		/// `size` must be a power of two and the points must lie within the field.
+		pub fn fft(&self, data: &mut [GFSymbol], size: usize, index: usize) -> Result<()> {
+			check_transform(data.len(), size, index)?;
+			fft_in_novel_poly_basis(data, size, index);
+			Ok(())
+		}
+
+		/// Inverse of `fft`, with the same requirements.
+		pub fn inverse_fft(&self, data: &mut [GFSymbol], size: usize, index: usize) -> Result<()> {
+			check_transform(data.len(), size, index)?;
+			inverse_fft_in_novel_poly_basis(data, size, index);
+			Ok(())
+		}
+
+		/// Formal derivative of the polynomial `cos[..size]` in the novel basis.
+		pub fn formal_derivative(&self, cos: &mut [GFSymbol], size: usize) -> Result<()> {
+			if size > cos.len() {
+				return Err(Error::BufferTooShort { len: cos.len(), required: size });
+			}
+			formal_derivative(cos, size);
+			Ok(())
+		}
+
+		/// Multiply `codeword[i]` by `B[i / 2]`, or by its inverse.
+		///
+		/// The codeword must consist of pairs and fit into the field.
+		pub fn scale_by_b_factors(&self, codeword: &mut [GFSymbol], inverse: bool) -> Result<()> {
+			if codeword.len() & 0x01 != 0 {
+				return Err(Error::OddBufferLength(codeword.len()));
+			}
+			if codeword.len() > FIELD_SIZE {
+				return Err(Error::ShardCountTooHigh(codeword.len()));
+			}
+			for (i, pair) in codeword.chunks_exact_mut(2).enumerate() {
+				let b = f2e16::b_factors()[i];
+				let b = if inverse { MODULO - b } else { b };
+				pair[0] = mul_table(pair[0], b);
+				pair[1] = mul_table(pair[1], b);
+			}
+			Ok(())
+		}
+
+		/// Multiplier for the element `b`, given in the log domain.
+		pub fn multiplier(&self, log_b: GFSymbol) -> Multiplier {
+			Multiplier(log_b)
+		}
+
+		/// Region multiplier for the element `b`, given in the log domain.
+		pub fn region_multiplier(&self, log_b: GFSymbol) -> RegionMultiplier {
+			RegionMultiplier::new(log_b)
+		}
+
+		/// Precompute the region multipliers of the skew factors used by codes of up to `n`
+		/// shards and enable them, see `set_skew_multipliers`.
+		pub fn precompute_skew_multipliers(&self, n: usize) -> Result<()> {
+			if n > FIELD_SIZE {
+				return Err(Error::ShardCountTooHigh(n));
+			}
+			set_skew_multipliers(true);
+			// the transforms of codes of `n` shards evaluate at most the first `n` points
+			for &skew in self.skew_factors()[..n.min(MODULO as usize)].iter().filter(|skew| **skew != MODULO) {
+				skew_multiplier(skew);
+			}
+			Ok(())
+		}
+
+		/// Evaluate the error locator polynomial for `erasure` in the log domain
+		/// into `log_walsh2`, which must hold `FIELD_SIZE` symbols.
+		pub fn eval_error_polynomial(&self, erasure: &ErasureMap, log_walsh2: &mut [GFSymbol]) -> Result<()> {
+			if log_walsh2.len() != FIELD_SIZE {
+				return Err(Error::WrongBufferLength { len: log_walsh2.len(), expected: FIELD_SIZE });
+			}
+			if erasure.len() > FIELD_SIZE {
+				return Err(Error::ShardCountTooHigh(erasure.len()));
+			}
+			eval_error_polynomial(erasure.iter(), log_walsh2, FIELD_SIZE);
+			Ok(())
+		}
+
+		/// `eval_error_polynomial` for erasures given as an iterator, e.g. over a bitmap the caller
+		/// already holds, so no `ErasureMap` needs to be built. It is walked twice.
+		pub fn eval_error_polynomial_iter<I>(&self, erasures: I, log_walsh2: &mut [GFSymbol]) -> Result<()>
+		where
+			I: IntoIterator<Item = bool>,
+			I::IntoIter: Clone,
+		{
+			if log_walsh2.len() != FIELD_SIZE {
+				return Err(Error::WrongBufferLength { len: log_walsh2.len(), expected: FIELD_SIZE });
+			}
+			let erasures = erasures.into_iter();
+			let n = erasures.clone().take(FIELD_SIZE + 1).count();
+			if n > FIELD_SIZE {
+				return Err(Error::ShardCountTooHigh(erasures.count()));
+			}
+			eval_error_polynomial(erasures, log_walsh2, FIELD_SIZE);
+			Ok(())
+		}
+
+		/// Encode the message in `codeword[..k]` in place, writing the parity to the
+		/// remaining symbols, so `codeword.len()` is the number of shards.
+		///
+		/// Both `k` and `codeword.len()` must be powers of two, the latter within the field.
+		pub fn encode(&self, codeword: &mut [GFSymbol], k: usize) -> Result<()> {
+			self.encode_rows(codeword, 1, k)
+		}
+
+		/// `encode` of `width` codewords at once, stored as rows: symbol `i` of every codeword
+		/// is in row `codeword[i * width..(i + 1) * width]`, the message in the first `k` rows.
+		pub fn encode_rows(&self, codeword: &mut [GFSymbol], width: usize, k: usize) -> Result<()> {
+			let n = codeword.len() / width.max(1);
+			if n * width != codeword.len() {
+				return Err(Error::WrongBufferLength { len: codeword.len(), expected: n * width });
+			}
+			if k < 1 {
+				return Err(Error::DataShardCountTooLow(k));
+			}
+			if n <= k {
+				return Err(Error::ShardCountTooLow { n, k });
+			}
+			if n > FIELD_SIZE {
+				return Err(Error::ShardCountTooHigh(n));
+			}
+			if let Some(&size) = [n, k].iter().find(|size| !is_power_of_2(**size)) {
+				return Err(Error::NotPowerOfTwo(size));
+			}
+			encode_low_rows_in_place(codeword, width, k, n, &mut ());
+			Ok(())
+		}
+	}
+
+	/// Multiply by the skew factors of the transforms via region tables kept per factor,
+	/// instead of via the log and exp tables, or region tables built per butterfly.
+	/// This trades 1 KiB per factor in use for one table hop per multiplication, and
+	/// applies to the whole process. Disabled by default, ignored with `constant-time`.
+	pub fn set_skew_multipliers(enabled: bool) {
+		SKEW_MULTIPLIERS_ENABLED.store(enabled, Ordering::Relaxed)
+	}
+
+	/// Whether the skew multipliers are enabled.
+	pub fn skew_multipliers_enabled() -> bool {
+		SKEW_MULTIPLIERS_ENABLED.load(Ordering::Relaxed)
+	}
+
+	/// Run transforms of 16, 32 and 64 symbols per codeword, as used by small codes, via
+	/// kernels unrolled for their size instead of the generic loops. Applies to the whole
+	/// process, enabled by default.
+	pub fn set_unrolled_transforms(enabled: bool) {
+		UNROLLED_TRANSFORMS_ENABLED.store(enabled, Ordering::Relaxed)
+	}
+
+	/// Whether the unrolled transforms are enabled.
+	pub fn unrolled_transforms_enabled() -> bool {
+		UNROLLED_TRANSFORMS_ENABLED.load(Ordering::Relaxed)
+	}
+
+	/// Multiplication by a fixed field element, held in the log domain,
+	/// only obtainable via a `Tables` handle.
+	#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+	pub struct Multiplier(GFSymbol);
+
+	impl Multiplier {
+		/// The log domain value.
+		pub fn log(self) -> GFSymbol {
+			self.0
+		}
+
+		/// Multiply a single symbol.
+		#[inline(always)]
+		pub fn apply(self, a: GFSymbol) -> GFSymbol {
+			mul_table(a, self.0)
+		}
+
+		/// Multiply all symbols of `data` in place.
+		pub fn mul_assign_slice(self, data: &mut [GFSymbol]) {
+			for a in data.iter_mut() {
+				*a = mul_table(*a, self.0);
+			}
+		}
+
+		/// `dest[i] ^= src[i] * b`, the butterfly pattern of the transforms.
+		pub fn mul_add_slice(self, dest: &mut [GFSymbol], src: &[GFSymbol]) {
+			assert_eq!(dest.len(), src.len());
+			for (d, s) in dest.iter_mut().zip(src.iter()) {
+				*d ^= mul_table(*s, self.0);
+			}
+		}
+	}
+
+	/// Multiplication by a fixed field element via the products with all low and
+	/// all high bytes of a symbol, as in classic multiply region tables.
+	///
+	/// Multiplication is linear over GF(2) on symbols in the Cantor basis, so
+	/// `a * b = lo[a & 0xFF] ^ hi[a >> 8]`. The 1 KiB of tables stay in the L1 cache,
+	/// unlike the 256 KiB log and exp tables, which pays off for long slices.
+	/// The lookups depend on the data, so this is not constant time.
+	#[derive(Clone)]
+	pub struct RegionMultiplier {
+		log_b: GFSymbol,
+		lo: [GFSymbol; 256],
+		hi: [GFSymbol; 256],
+	}
+
+	impl RegionMultiplier {
+		// `setup` must have run
+		#[inline(always)]
+		pub(super) fn new(log_b: GFSymbol) -> Self {
+			let mut region = RegionMultiplier { log_b, lo: [0; 256], hi: [0; 256] };
+			for bit in 0..8 {
+				region.lo[1 << bit] = mul_table_lookup(1 << bit, log_b);
+				region.hi[1 << bit] = mul_table_lookup(1 << (bit + 8), log_b);
+			}
+			// every byte is the sum of its lowest set bit and the smaller remainder
+			for x in 3..256_usize {
+				let low = x & x.wrapping_neg();
+				region.lo[x] = region.lo[low] ^ region.lo[x ^ low];
+				region.hi[x] = region.hi[low] ^ region.hi[x ^ low];
+			}
+			record(Op::Lookup, 32);
+			region
+		}
+
+		/// The log domain value.
+		pub fn log(&self) -> GFSymbol {
+			self.log_b
+		}
+
+		/// Multiply a single symbol.
+		#[inline(always)]
+		pub fn apply(&self, a: GFSymbol) -> GFSymbol {
+			self.lo[(a & 0xFF) as usize] ^ self.hi[(a >> 8) as usize]
+		}
+
+		/// Multiply all symbols of `data` in place.
+		pub fn mul_assign_slice(&self, data: &mut [GFSymbol]) {
+			for a in data.iter_mut() {
+				*a = self.apply(*a);
+			}
+			record(Op::Mul, data.len());
+			record(Op::Lookup, 2 * data.len());
+		}
+
+		/// `dest[i] ^= src[i] * b`, the butterfly pattern of the transforms.
+		#[inline(always)]
+		pub fn mul_add_slice(&self, dest: &mut [GFSymbol], src: &[GFSymbol]) {
+			assert_eq!(dest.len(), src.len());
+			for (d, s) in dest.iter_mut().zip(src.iter()) {
+				*d ^= self.apply(*s);
+			}
+			record(Op::Mul, dest.len());
+			record(Op::Lookup, 2 * dest.len());
+		}
+	}
+
+	impl std::fmt::Debug for RegionMultiplier {
+		fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+			f.debug_struct("RegionMultiplier").field("log_b", &self.log_b).finish()
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use rand::seq::index::IndexVec;
+
+	use super::*;
+	use std::time::Duration;
+
+	fn print_sha256(txt: &'static str, data: &[GFSymbol]) {
+		use sha2::Digest;
+		let mut digest = sha2::Sha256::new();
+		for sym in data {
+			digest.update(sym.to_le_bytes());
+		}
+		println!("sha256(rs|{}):", txt);
+		for byte in digest.finalize() {
+			print!("{:02x}", byte);
+		}
+		println!()
+	}
+
+	/// Generate a random index
+	fn rand_gf_element() -> GFSymbol {
+		use rand::distributions::{Distribution, Uniform};
+		use rand::thread_rng;
+
+		let mut rng = thread_rng();
+		let uni = Uniform::<GFSymbol>::new_inclusive(0, MODULO);
+		uni.sample(&mut rng)
+	}
+
+	#[test]
+	fn flt_back_and_forth() {
+		const N: usize = 128;
+		const K: usize = 32;
+		let mut data = (0..N).map(|_x| rand_gf_element()).collect::<Vec<GFSymbol>>();
+		let expected = data.clone();
+
+		fft_in_novel_poly_basis(&mut data, N, K);
+
+		// make sure something is done
+		assert!(data.iter().zip(expected.iter()).filter(|(a, b)| { a != b }).count() > 0);
+
+		inverse_fft_in_novel_poly_basis(&mut data, N, K);
+
+		itertools::assert_equal(data, expected);
+	}
+
+	#[test]
+	fn flt_rountrip_small() {
+		const N: usize = 16;
+		const EXPECTED: [GFSymbol; N] = [1, 2, 3, 5, 8, 13, 21, 44, 65, 0, 0xFFFF, 2, 3, 5, 7, 11];
+
+		let mut data = EXPECTED;
+
+		fft_in_novel_poly_basis(&mut data, N, N / 4);
+
+		println!("novel basis(rust):");
+		data.iter().for_each(|sym| {
+			print!(" {:04X}", sym);
+		});
+		println!();
+
+		inverse_fft_in_novel_poly_basis(&mut data, N, N / 4);
+		itertools::assert_equal(data.iter(), EXPECTED.iter());
+	}
+
+	#[test]
+	fn ported_c_test() {
+		//fill log table and exp table, compute factors used in erasure decoder
+		setup();
+
+		//-----------Generating message----------
+		//message array
+		let mut data: [GFSymbol; N] = [0; N];
+
+		for i in 0..K {
+			//filled with random numbers
+			data[i] = (i * i % MODULO as usize) as u16;
+			// data[i] = rand_gf_element();
 		}
-		println!("");
-		print_sha256("data", &data[..]);
+
+		assert_eq!(data.len(), N);
+
+		println!("Message(Last n-k are zeros): ");
+		for i in 0..K {
+			print!("{:04x} ", data[i]);
+		}
+		println!();
+		print_sha256("data", &data[..]);
 
 		//---------encoding----------
 		let mut codeword = [0_u16; N];
 
-		if K + K > N && false {
-			let (data_till_t, data_skip_t) = data.split_at_mut(N - K);
-			encode_high(data_skip_t, K, data_till_t, &mut codeword[..], N);
-		} else {
-			encode_low(&data[..], K, &mut codeword[..], N);
-		}
+		// if K + K > N {
+		// 	let (data_till_t, data_skip_t) = data.split_at_mut(N - K);
+		// 	encode_high(data_skip_t, K, data_till_t, &mut codeword[..], N);
+		// } else {
+		encode_low(&data[..], K, &mut codeword[..], N, &mut ());
+		// }
 
 		// println!("Codeword:");
 		// for i in K..(K+100) {
 		// print!("{:04x} ", codeword[i]);
 		// }
-		// println!("");
+		// println!();
 
 		print_sha256("encoded", &codeword);
 
@@ -617,7 +2482,7 @@ mod test {
 
 			erasures_iv
 		} else {
-			IndexVec::from((0..(N - K)).into_iter().collect::<Vec<usize>>())
+			IndexVec::from((0..(N - K)).collect::<Vec<usize>>())
 		};
 		assert_eq!(erasures_iv.len(), N - K);
 
@@ -632,11 +2497,12 @@ mod test {
 		//---------Erasure decoding----------------
 		let mut log_walsh2: [GFSymbol; FIELD_SIZE] = [0_u16; FIELD_SIZE];
 
-		eval_error_polynomial(&erasure[..], &mut log_walsh2[..], FIELD_SIZE);
+		let erasure = ErasureMap::from(&erasure[..]);
+		eval_error_polynomial(erasure.iter(), &mut log_walsh2[..], FIELD_SIZE);
 
 		print_sha256("log_walsh2", &log_walsh2);
 
-		decode_main(&mut codeword[..], K, &erasure[..], &log_walsh2[..], N);
+		decode_main(&mut codeword[..], K, erasure.iter(), &log_walsh2[..], &DecodePlan::new(N), &mut ());
 
 		print_sha256("decoded", &codeword[0..K]);
 
@@ -645,7 +2511,7 @@ mod test {
 			// the data word plus a few more
 			print!("{:04x} ", codeword[i]);
 		}
-		println!("");
+		println!();
 
 		for i in 0..K {
 			//Check the correctness of the result
@@ -654,9 +2520,855 @@ mod test {
 				panic!("Decoding ERROR! value at [{}] should={:04x} vs is={:04x}", i, data[i], codeword[i]);
 			}
 		}
-		println!(r#">>>>>>>>> 🎉🎉🎉🎉
+		println!(
+			r#">>>>>>>>> 🎉🎉🎉🎉
 >>>>>>>>> > Decoding is **SUCCESS** ful! 🎈
->>>>>>>>>"#);
+>>>>>>>>>"#
+		);
+	}
+
+	#[test]
+	fn phase_timings_cover_pipeline() {
+		let params = CodeParams::new(32, 4).unwrap();
+		let payload = &random_payload(256, 0);
+		let (shards, timings) = encode_with_timings(&params, payload).unwrap();
+		assert!(timings.get(Phase::Ifft) > Duration::ZERO);
+		// one forward transform per shift of `k` beyond the message
+		assert_eq!(timings.iter().filter(|(phase, _)| matches!(phase, Phase::FftAtShift(_))).count(), 32 / 4 - 1);
+
+		let mut received = shards.iter().map(|shard| Some(shard.as_ref())).collect::<Vec<Option<&[u8]>>>();
+		received.iter_mut().take(8).for_each(|shard| *shard = None);
+		let (result, timings) = reconstruct_with_timings(&params, &received).unwrap();
+		assert_eq!(&result[..payload.len()], payload);
+		for phase in [Phase::ErrorLocator, Phase::DecodeIfft, Phase::FormalDerivative, Phase::DecodeFft] {
+			assert!(timings.get(phase) > Duration::ZERO, "{:?} was not recorded", phase);
+		}
+	}
+
+	#[test]
+	fn unrolled_transforms_match_loops() {
+		setup();
+		for (size, width) in [(16, 1), (32, 1), (64, 1), (16, SOA_WIDTH), (64, SOA_WIDTH)] {
+			let data = (0..size * width).map(|_| rand_gf_element()).collect::<Vec<GFSymbol>>();
+			for index in [0, size, 5 * size] {
+				let mut looped = data.clone();
+				let mut unrolled = data.clone();
+				fft_rows(&mut looped, width, size, index);
+				assert!(transform_rows_sized(&mut unrolled, width, size, index, false));
+				assert_eq!(looped, unrolled);
+
+				inverse_fft_rows(&mut looped, width, size, index);
+				assert!(transform_rows_sized(&mut unrolled, width, size, index, true));
+				assert_eq!(looped, unrolled);
+				assert_eq!(unrolled, data);
+			}
+		}
+		assert!(!transform_rows_sized(&mut [0; 24], 3, 8, 0, false));
+	}
+
+	#[test]
+	fn plans_match_transforms() {
+		setup();
+		// planned once, run on full batches and on the last one of 5 codewords as well
+		for (size, index) in [(8, 8), (128, 0), (256, 512)] {
+			for inverse in [false, true] {
+				let plan = FftPlan::new(size, index, inverse);
+				assert_eq!(plan.butterflies.len(), size - 1);
+				for width in [SOA_WIDTH, 5] {
+					let data = (0..size * width).map(|_| rand_gf_element()).collect::<Vec<GFSymbol>>();
+					let mut planned = data.clone();
+					let mut looped = data;
+					plan.run(&mut planned, width);
+					if inverse {
+						inverse_fft_rows_selected(&mut looped, width, size, index);
+					} else {
+						fft_rows_selected(&mut looped, width, size, index);
+					}
+					assert_eq!(planned, looped);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn code_plans_match_free_functions() {
+		let params = CodeParams::new(64, 16).unwrap().with_symbol_group(std::num::NonZeroUsize::new(3).unwrap());
+		let plan = CodePlan::new(&params).unwrap();
+		let payload = &random_payload(5000, 0);
+		let shards = plan.encode(payload).unwrap();
+		assert_eq!(shards, encode_with_params(&params, payload).unwrap());
+
+		let mut received = shards.iter().map(|shard| Some(AsRef::<[u8]>::as_ref(shard))).collect::<Vec<_>>();
+		received.iter_mut().step_by(2).take(40).for_each(|shard| *shard = None);
+		let expected = reconstruct_ref_with_params(&params, &received).unwrap();
+		assert_eq!(&expected[..payload.len()], payload);
+		assert_eq!(plan.reconstruct(&received).unwrap(), expected);
+
+		// a scratch keeps the plan of its last code, and replaces it for another
+		let mut scratch = Scratch::new(64);
+		assert_eq!(plan.reconstruct_with_scratch(&received, &mut scratch).unwrap(), expected);
+		assert!(Arc::ptr_eq(scratch.decode_plan.as_ref().unwrap(), &plan.decode));
+		let other = CodeParams::new(8, 2).unwrap();
+		let shards = encode_with_params(&other, payload).unwrap();
+		let received = shards.iter().skip(6).map(|shard| Some(AsRef::<[u8]>::as_ref(shard))).collect::<Vec<_>>();
+		let received = [vec![None; 6], received].concat();
+		let decoded = reconstruct_with_scratch(&other, &received, &mut scratch).unwrap();
+		assert_eq!(&decoded[..payload.len()], payload);
+		assert_eq!(scratch.decode_plan.as_ref().unwrap().n, 8);
+	}
+
+	#[test]
+	fn rows_match_single_codewords() {
+		setup();
+		const N: usize = 256;
+		const K: usize = 64;
+		const WIDTH: usize = 5;
+		let columns = (0..WIDTH).map(|_| (0..N).map(|_| rand_gf_element()).collect::<Vec<_>>()).collect::<Vec<_>>();
+		let to_rows =
+			|columns: &[Vec<GFSymbol>]| (0..N * WIDTH).map(|at| columns[at % WIDTH][at / WIDTH]).collect::<Vec<_>>();
+
+		let mut rows = to_rows(&columns);
+		let mut expected = columns.clone();
+		fft_rows_selected(&mut rows, WIDTH, N, K);
+		expected.iter_mut().for_each(|column| fft_in_novel_poly_basis(column, N, K));
+		assert_eq!(rows, to_rows(&expected));
+		inverse_fft_rows_selected(&mut rows, WIDTH, N, K);
+		assert_eq!(rows, to_rows(&columns));
+
+		let mut erasure = ErasureMap::new(N);
+		for idx in (0..N).step_by(3) {
+			erasure.set(idx, true);
+		}
+		let mut log_walsh2 = vec![0_u16; FIELD_SIZE];
+		eval_error_polynomial(erasure.iter(), &mut log_walsh2[..], FIELD_SIZE);
+		let mut rows = to_rows(&columns);
+		let mut expected = columns.clone();
+		let plan = DecodePlan::new(N);
+		decode_main_rows(&mut rows, WIDTH, K, erasure.iter(), &log_walsh2, &plan, &mut ());
+		expected.iter_mut().for_each(|column| decode_main(column, K, erasure.iter(), &log_walsh2, &plan, &mut ()));
+		assert_eq!(rows, to_rows(&expected));
+
+		// more codewords than a batch holds, the last batch being partial
+		let params = CodeParams::new(N, K).unwrap();
+		let payload = &random_payload(K * 2 * (SOA_WIDTH + 3) - 1, 0);
+		let shards = encode_with_params(&params, payload).unwrap();
+		for (chunk_idx, chunk) in payload.chunks(K * 2).enumerate() {
+			let codeword = encode_sub(chunk, N, K, Endianness::default(), &mut ());
+			for (shard, sym) in shards.iter().zip(codeword) {
+				let bytes = &AsRef::<[u8]>::as_ref(shard)[chunk_idx * 2..chunk_idx * 2 + 2];
+				assert_eq!(bytes, &Endianness::default().symbol_to_bytes(sym));
+			}
+		}
+	}
+
+	#[test]
+	fn codec_info_matches_limits() {
+		let info = codec_info();
+		assert_eq!(info.field_bits, 16);
+		assert_eq!(info.max_n, MAX_SHARDS);
+		assert!(internal_params(&CodeParams::new(info.max_n, 2).unwrap()).is_ok());
+		assert_eq!(info.constant_time, cfg!(feature = "constant-time"));
+		assert_eq!(info.compact_tables, cfg!(feature = "compact-tables"));
+	}
+
+	#[test]
+	fn kernels_match_generic() {
+		setup();
+		const N: usize = 1 << 12;
+		let data = (0..N).map(|_x| rand_gf_element()).collect::<Vec<GFSymbol>>();
+		let mut generic = data.clone();
+		let mut selected = data.clone();
+		unsafe { generic::fft(&mut generic, 1, N, N) };
+		fft_rows_selected(&mut selected, 1, N, N);
+		assert_eq!(generic, selected);
+
+		unsafe { generic::inverse_fft(&mut generic, 1, N, N) };
+		inverse_fft_rows_selected(&mut selected, 1, N, N);
+		assert_eq!(generic, data);
+		assert_eq!(selected, data);
+		assert_eq!(codec_info().simd_kernel, codec_features());
+	}
+
+	#[test]
+	fn endianness_roundtrips() {
+		let payload = &random_payload(100, 0);
+		let le = CodeParams::new(16, 4).unwrap();
+		let be = le.with_endianness(Endianness::Big);
+
+		let le_shards = encode_with_params(&le, payload).unwrap();
+		let be_shards = encode_with_params(&be, payload).unwrap();
+
+		// the code is systematic, so data shards carry the payload bytes in order
+		// independent of the symbol byte order, parity shards differ
+		assert_eq!(le_shards[..4], be_shards[..4]);
+		assert_ne!(le_shards[4..], be_shards[4..]);
+
+		for (params, shards) in [(le, le_shards), (be, be_shards)] {
+			let mut received = shards.into_iter().map(Some).collect::<Vec<_>>();
+			received.iter_mut().take(12).for_each(|shard| *shard = None);
+			let result = reconstruct_with_params(&params, received).unwrap();
+			assert_eq!(&result[..payload.len()], payload);
+		}
+	}
+
+	#[test]
+	fn shard_layouts_roundtrip() {
+		let payload = &random_payload(101, 0);
+		let interleaved = CodeParams::new(16, 4).unwrap();
+		let sequential = interleaved.with_layout(ShardLayout::Sequential);
+
+		// 101 bytes in 4 data shards of 13 symbols each
+		let shards = encode_with_params(&sequential, payload).unwrap();
+		assert_eq!(AsRef::<[u8]>::as_ref(&shards[0]), &payload[..26]);
+		assert_eq!(AsRef::<[u8]>::as_ref(&shards[1]), &payload[26..52]);
+
+		let shards = encode_with_params(&interleaved, payload).unwrap();
+		assert_eq!(AsRef::<[u8]>::as_ref(&shards[0])[..4], [payload[0], payload[1], payload[8], payload[9]]);
+
+		for params in [interleaved, sequential] {
+			let mut received = encode_with_params(&params, payload).unwrap().into_iter().map(Some).collect::<Vec<_>>();
+			received.iter_mut().skip(1).take(12).for_each(|shard| *shard = None);
+			let result = reconstruct_with_params(&params, received).unwrap();
+			assert_eq!(&result[..payload.len()], payload);
+		}
+	}
+
+	#[test]
+	fn symbol_groups_roundtrip() {
+		use std::num::NonZeroUsize;
+
+		let payload = &random_payload(1001, 0);
+		for layout in [ShardLayout::Interleaved, ShardLayout::Sequential] {
+			for group in [1, 3, 8] {
+				let params = CodeParams::new(16, 4)
+					.unwrap()
+					.with_layout(layout)
+					.with_symbol_group(NonZeroUsize::new(group).unwrap());
+				let shards = encode_with_params(&params, payload).unwrap();
+				let shard_len = required_shard_len(&params, payload.len()).unwrap();
+				assert_eq!(shard_len % (group * 2), 0);
+				assert!(shards.iter().all(|shard| AsRef::<[u8]>::as_ref(shard).len() == shard_len));
+				assert!(encode_iter(&params, payload).unwrap().eq(shards.iter().cloned()));
+				if layout == ShardLayout::Interleaved {
+					// runs of `group` symbols go round-robin to the data shards
+					let element = group * 2;
+					assert_eq!(&AsRef::<[u8]>::as_ref(&shards[1])[..element], &payload[element..2 * element]);
+				}
+
+				let mut received = shards.iter().map(|shard| Some(shard.as_ref())).collect::<Vec<Option<&[u8]>>>();
+				assert_eq!(&reconstruct_ref_with_params(&params, &received).unwrap()[..payload.len()], payload);
+				received.iter_mut().skip(1).take(12).for_each(|shard| *shard = None);
+				assert_eq!(&reconstruct_ref_with_params(&params, &received).unwrap()[..payload.len()], payload);
+
+				// stripes shorter than a group
+				let mut recovered = vec![0; padded_payload_len(&params, payload.len()).unwrap()];
+				reconstruct_bounded(&params, &received, &mut Scratch::new(2), |offset, bytes| {
+					recovered[offset..offset + bytes.len()].copy_from_slice(bytes)
+				})
+				.unwrap();
+				assert_eq!(&recovered[..payload.len()], payload, "{:?} group {}", layout, group);
+
+				if group > 1 {
+					let truncated =
+						shards.iter().map(|shard| Some(&AsRef::<[u8]>::as_ref(shard)[2..])).collect::<Vec<_>>();
+					assert_eq!(
+						reconstruct_ref_with_params(&params, &truncated),
+						Err(Error::UngroupedShardLength { len: shard_len - 2, group })
+					);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn low_level_decode_matches_decode_main() {
+		use low_level::Tables;
+
+		let tables = Tables::get();
+		let mut codeword = encode_sub(&random_payload(8, 0), N, K, Endianness::Little, &mut ());
+		let expected = codeword.clone();
+
+		let mut erasure = [false; N];
+		for i in 0..(N - K) {
+			erasure[i] = true;
+			codeword[i] = 0;
+		}
+
+		let mut log_walsh2 = vec![0xFFFF_u16; Tables::FIELD_SIZE];
+		let erasure = ErasureMap::from(&erasure[..]);
+		tables.eval_error_polynomial(&erasure, &mut log_walsh2).unwrap();
+		let mut from_iter = vec![0xFFFF_u16; Tables::FIELD_SIZE];
+		tables.eval_error_polynomial_iter(erasure.iter(), &mut from_iter).unwrap();
+		assert_eq!(from_iter, log_walsh2);
+
+		for i in 0..N {
+			codeword[i] = if erasure[i] { 0 } else { tables.mul(codeword[i], log_walsh2[i]) };
+		}
+		tables.inverse_fft(&mut codeword, N, 0).unwrap();
+		tables.scale_by_b_factors(&mut codeword, true).unwrap();
+		tables.formal_derivative(&mut codeword, N).unwrap();
+		tables.scale_by_b_factors(&mut codeword, false).unwrap();
+		tables.fft(&mut codeword, N, 0).unwrap();
+
+		for i in 0..(N - K) {
+			assert_eq!(tables.mul(codeword[i], log_walsh2[i]), expected[i]);
+		}
+		assert_eq!(tables.exp(tables.log(0x1234)), 0x1234);
+	}
+
+	#[test]
+	fn low_level_encode_matches_encode_sub() {
+		let tables = low_level::Tables::get();
+		let expected = encode_sub(&random_payload(2 * K, 0), N, K, Endianness::Little, &mut ());
+		let mut codeword = vec![0_u16; N];
+		codeword[..K].copy_from_slice(&expected[..K]);
+		tables.encode(&mut codeword, K).unwrap();
+		assert_eq!(codeword, expected);
+
+		// two codewords as rows, the second all zero
+		let mut rows = codeword.iter().flat_map(|sym| [*sym, 0]).collect::<Vec<_>>();
+		rows[K * 2..].iter_mut().for_each(|sym| *sym = 0xFFFF);
+		tables.encode_rows(&mut rows, 2, K).unwrap();
+		assert_eq!(rows, codeword.iter().flat_map(|sym| [*sym, 0]).collect::<Vec<_>>());
+		let misaligned = Err(Error::WrongBufferLength { len: 63, expected: 62 });
+		assert_eq!(tables.encode_rows(&mut rows[..N * 2 - 1], 2, K), misaligned);
+	}
+
+	#[test]
+	fn low_level_rejects_invalid_input() {
+		let tables = low_level::Tables::get();
+		let mut data = vec![0_u16; 64];
+		for (size, index, err) in [
+			(48, 0, Error::NotPowerOfTwo(48)),
+			(0, 0, Error::NotPowerOfTwo(0)),
+			(128, 0, Error::BufferTooShort { len: 64, required: 128 }),
+			(64, FIELD_SIZE - 32, Error::ShardCountTooHigh(FIELD_SIZE + 32)),
+		] {
+			assert_eq!(tables.fft(&mut data, size, index), Err(err.clone()));
+			assert_eq!(tables.inverse_fft(&mut data, size, index), Err(err));
+		}
+		assert_eq!(tables.formal_derivative(&mut data, 65), Err(Error::BufferTooShort { len: 64, required: 65 }));
+		assert_eq!(tables.scale_by_b_factors(&mut data[..63], true), Err(Error::OddBufferLength(63)));
+		let mut beyond = vec![0_u16; FIELD_SIZE + 2];
+		assert_eq!(tables.scale_by_b_factors(&mut beyond, false), Err(Error::ShardCountTooHigh(FIELD_SIZE + 2)));
+
+		let erasure = ErasureMap::from(&[true; 8][..]);
+		assert_eq!(
+			tables.eval_error_polynomial(&erasure, &mut data),
+			Err(Error::WrongBufferLength { len: 64, expected: FIELD_SIZE })
+		);
+		let erasure = ErasureMap::from(&vec![false; FIELD_SIZE + 1][..]);
+		beyond.truncate(FIELD_SIZE);
+		assert_eq!(tables.eval_error_polynomial(&erasure, &mut beyond), Err(Error::ShardCountTooHigh(FIELD_SIZE + 1)));
+		assert_eq!(
+			tables.eval_error_polynomial_iter(vec![false; FIELD_SIZE + 1], &mut beyond),
+			Err(Error::ShardCountTooHigh(FIELD_SIZE + 1))
+		);
+
+		assert_eq!(tables.encode(&mut data, 0), Err(Error::DataShardCountTooLow(0)));
+		assert_eq!(tables.encode(&mut data, 64), Err(Error::ShardCountTooLow { n: 64, k: 64 }));
+		assert_eq!(tables.encode(&mut data[..48], 16), Err(Error::NotPowerOfTwo(48)));
+		assert_eq!(tables.encode(&mut data, 12), Err(Error::NotPowerOfTwo(12)));
+		let mut beyond = vec![0_u16; FIELD_SIZE * 2];
+		assert_eq!(tables.encode(&mut beyond, 16), Err(Error::ShardCountTooHigh(FIELD_SIZE * 2)));
+		assert!(data.iter().all(|sym| *sym == 0), "rejected input was modified");
+	}
+
+	#[test]
+	fn multiplier_matches_mul_table() {
+		let tables = low_level::Tables::get();
+		let data = (0..256).map(|_x| rand_gf_element()).collect::<Vec<GFSymbol>>();
+		let log_b = rand_gf_element() % MODULO;
+		let multiplier = tables.multiplier(log_b);
+
+		let mut batched = data.clone();
+		multiplier.mul_assign_slice(&mut batched);
+		let mut accumulated = vec![0_u16; data.len()];
+		multiplier.mul_add_slice(&mut accumulated, &data);
+
+		for (i, a) in data.iter().enumerate() {
+			assert_eq!(batched[i], mul_table(*a, log_b));
+			assert_eq!(accumulated[i], batched[i]);
+		}
+	}
+
+	#[test]
+	fn complete_data_shards_skip_decoding() {
+		let payload = &random_payload(1001, 0);
+		for &layout in &[ShardLayout::Interleaved, ShardLayout::Sequential] {
+			for &endianness in &[Endianness::Little, Endianness::Big] {
+				let params = CodeParams::new(20, 6).unwrap().with_layout(layout).with_endianness(endianness);
+				let shards = encode_with_params(&params, payload).unwrap();
+				let all = shards.iter().map(|shard| Some(shard.as_ref())).collect::<Vec<Option<&[u8]>>>();
+				let mut decoded = all.clone();
+				decoded[0] = None;
+
+				let copied = reconstruct_ref_with_params(&params, &all).unwrap();
+				assert_eq!(copied, reconstruct_ref_with_params(&params, &decoded).unwrap());
+				assert_eq!(&copied[..payload.len()], payload);
+
+				let mut bounded = vec![0_u8; copied.len()];
+				reconstruct_bounded(&params, &all, &mut Scratch::new(6), |offset, bytes| {
+					bounded[offset..offset + bytes.len()].copy_from_slice(bytes)
+				})
+				.unwrap();
+				assert_eq!(copied, bounded);
+			}
+		}
+	}
+
+	#[test]
+	fn encode_iter_matches_encode() {
+		for &(n, k, len) in &[(4, 2, 0), (20, 6, 1001), (64, 16, 5000), (100, 30, 77)] {
+			for &layout in &[ShardLayout::Interleaved, ShardLayout::Sequential] {
+				let params = CodeParams::new(n, k).unwrap().with_layout(layout).with_endianness(Endianness::Big);
+				let payload = &random_payload(len, 0);
+				let shards = encode_iter(&params, payload).unwrap();
+				assert_eq!(shards.len(), n);
+				assert_eq!(shards.collect::<Vec<_>>(), encode_with_params(&params, payload).unwrap());
+			}
+		}
+	}
+
+	#[test]
+	fn poly_basis_mul_matches_tables() {
+		let tables = low_level::Tables::get();
+		// `mul` itself goes through the polynomial basis with the `compact-tables` feature
+		let mul_log_exp = |a: GFSymbol, log_b: GFSymbol| {
+			if a == 0 {
+				return 0;
+			}
+			let sum = u32::from(tables.log(a)) + u32::from(log_b);
+			tables.exp(((sum & u32::from(MODULO)) + (sum >> FIELD_BITS)) as GFSymbol)
+		};
+		for &log_b in &[0, 1, 0x1234, MODULO - 1, rand_gf_element() % MODULO] {
+			for a in (0..=MODULO).step_by(7).chain([MODULO]) {
+				assert_eq!(tables.mul_poly_basis(a, log_b), mul_log_exp(a, log_b), "{} * exp({})", a, log_b);
+			}
+		}
+	}
+
+	#[test]
+	fn region_multiplier_matches_mul_table() {
+		let tables = low_level::Tables::get();
+		let data = (0..=MODULO).collect::<Vec<GFSymbol>>();
+		for &log_b in &[0, 1, 0x1234, MODULO - 1, rand_gf_element() % MODULO] {
+			let region = tables.region_multiplier(log_b);
+			let mut accumulated = vec![0_u16; data.len()];
+			region.mul_add_slice(&mut accumulated, &data);
+			for (a, product) in data.iter().zip(accumulated.iter()) {
+				assert_eq!(*product, mul_table(*a, log_b), "{} * exp({})", a, log_b);
+			}
+		}
+	}
+
+	#[test]
+	fn skew_multipliers_match_log_tables() {
+		let tables = low_level::Tables::get();
+		let params = CodeParams::new(64, 20).unwrap();
+		let payload = &random_payload(20_000, 0);
+		let data = (0..4096).map(|_| rand_gf_element()).collect::<Vec<_>>();
+		let transformed = |data: &[GFSymbol]| {
+			let mut data = data.to_vec();
+			tables.inverse_fft(&mut data, 4096, 0).unwrap();
+			tables.fft(&mut data, 4096, 4096).unwrap();
+			data
+		};
+		let (expected, shards) = (transformed(&data), encode_with_params(&params, payload).unwrap());
+
+		assert_eq!(tables.precompute_skew_multipliers(FIELD_SIZE + 1), Err(Error::ShardCountTooHigh(FIELD_SIZE + 1)));
+		tables.precompute_skew_multipliers(64).unwrap();
+		assert!(low_level::skew_multipliers_enabled());
+		assert_eq!(transformed(&data), expected);
+		assert_eq!(encode_with_params(&params, payload).unwrap(), shards);
+		let mut received = shards.into_iter().map(Some).collect::<Vec<_>>();
+		received.iter_mut().step_by(2).for_each(|shard| *shard = None);
+		assert_eq!(&reconstruct_with_params(&params, received).unwrap()[..payload.len()], payload);
+		low_level::set_skew_multipliers(false);
+	}
+
+	#[test]
+	fn length_policies_equalize_shards() {
+		let params = CodeParams::new(16, 4).unwrap();
+		let payload = &random_payload(1000, 0);
+		let shards = encode_with_params(&params, payload).unwrap();
+		let shard_len = AsRef::<[u8]>::as_ref(&shards[0]).len();
+		let mut received = shards.iter().map(|shard| Some(AsRef::<[u8]>::as_ref(shard).to_vec())).collect::<Vec<_>>();
+		received[0] = None;
+		received[5].as_mut().unwrap().extend_from_slice(&[1, 2]);
+		received[7].as_mut().unwrap().truncate(shard_len - 2);
+		let received = received.iter().map(Option::as_deref).collect::<Vec<_>>();
+
+		let inconsistent = Error::InconsistentShardLengths { first: shard_len, other: shard_len + 2 };
+		assert_eq!(reconstruct_ref_with_params(&params, &received), Err(inconsistent));
+		// a codeword per symbol of the shards, so the payload is recovered up to the shortest shard
+		let with_policy = |policy| reconstruct_ref_with_params(&params.with_length_policy(policy), &received).unwrap();
+		assert_eq!(with_policy(ShardLengthPolicy::TruncateToMin), &payload[..(shard_len - 2) * 4]);
+		let padded = with_policy(ShardLengthPolicy::PadToMax);
+		assert_eq!(padded.len(), (shard_len + 2) * 4);
+		assert_eq!(&padded[..(shard_len - 2) * 4], &payload[..(shard_len - 2) * 4]);
+	}
+
+	#[test]
+	fn checked_reconstruct_flags_inconsistent_shards() {
+		let params = CodeParams::new(20, 5).unwrap();
+		let payload = &random_payload(1000, 0);
+		let mut shards = encode_with_params(&params, payload).unwrap();
+		let received = shards.iter().map(|shard| Some(shard.as_ref())).skip(4).collect::<Vec<Option<&[u8]>>>();
+		let received = [vec![None; 4], received].concat();
+		let recovered = reconstruct_checked(&params, &received).unwrap();
+		assert_eq!(&recovered[..payload.len()], payload);
+
+		AsMut::<[u8]>::as_mut(&mut shards[17])[3] ^= 1;
+		let mut received = shards.iter().map(|shard| Some(shard.as_ref())).collect::<Vec<Option<&[u8]>>>();
+		received[0] = None;
+		assert!(matches!(reconstruct_checked(&params, &received), Err(Error::InconsistentShard(_))));
+	}
+
+	#[test]
+	fn truncated_error_polynomial_matches_full() {
+		setup();
+		let mut rng = rand::thread_rng();
+		for n in [2, 4, 32, 256, 4096] {
+			for erased in [1, n / 2, n - 1] {
+				let mut erasure = ErasureMap::new(n);
+				for idx in rand::seq::index::sample(&mut rng, n, erased) {
+					erasure.set(idx, true);
+				}
+				let mut full = vec![0_u16; FIELD_SIZE];
+				eval_error_polynomial(erasure.iter(), &mut full, FIELD_SIZE);
+				let mut truncated = vec![0_u16; n];
+				eval_error_polynomial(erasure.iter(), &mut truncated, n);
+				// `0` and `MODULO` both are the logarithm of one
+				let normalize = |log: &u16| log % MODULO;
+				assert_eq!(
+					truncated.iter().map(normalize).collect::<Vec<_>>(),
+					full[..n].iter().map(normalize).collect::<Vec<_>>(),
+					"n = {}, {} erased",
+					n,
+					erased
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn erasure_cache_reuses_patterns() {
+		let params = CodeParams::new(20, 5).unwrap();
+		let payload = &random_payload(1000, 0);
+		let shards = encode_with_params(&params, payload).unwrap();
+		let received = |erased: &[usize]| {
+			let mut received = shards.iter().map(|shard| Some(shard.as_ref())).collect::<Vec<Option<&[u8]>>>();
+			erased.iter().for_each(|&idx| received[idx] = None);
+			received
+		};
+		let (a, b) = (received(&[0, 2, 7]), received(&[1, 3, 4, 19]));
+
+		let mut scratch = Scratch::new(64).with_erasure_cache(1);
+		for (received, hits, misses) in [(&a, 0, 1), (&a, 1, 1), (&b, 1, 2), (&a, 1, 3)] {
+			let recovered = reconstruct_with_scratch(&params, received, &mut scratch).unwrap();
+			assert_eq!(recovered, reconstruct_ref_with_params(&params, received).unwrap());
+			let cache = scratch.erasure_cache().unwrap();
+			assert_eq!((cache.hits(), cache.misses(), cache.len()), (hits, misses, 1));
+		}
+		assert!(scratch.size_in_bytes() > bounded_scratch_size(&params, 64).unwrap());
+	}
+
+	#[test]
+	fn bounded_reconstruct_matches_reconstruct() {
+		let payload = &random_payload(10_000, 0);
+		for layout in [ShardLayout::Interleaved, ShardLayout::Sequential] {
+			let params = CodeParams::new(20, 5).unwrap().with_layout(layout);
+			let shards = encode_with_params(&params, payload).unwrap();
+			let mut received = shards.iter().map(|shard| Some(shard.as_ref())).collect::<Vec<Option<&[u8]>>>();
+			for idx in [0, 2, 3, 7, 11, 12, 13, 19] {
+				received[idx] = None;
+			}
+			let expected = reconstruct_ref_with_params(&params, &received).unwrap();
+
+			let stripe_len = 66;
+			let mut scratch = Scratch::new(stripe_len);
+			let mut recovered = vec![0_u8; expected.len()];
+			reconstruct_bounded(&params, &received, &mut scratch, |offset, bytes| {
+				assert!(bytes.len() <= stripe_len * 4);
+				recovered[offset..offset + bytes.len()].copy_from_slice(bytes);
+			})
+			.unwrap();
+			assert_eq!(recovered, expected);
+			assert_eq!(&recovered[..payload.len()], payload);
+			assert_eq!(scratch.size_in_bytes(), bounded_scratch_size(&params, stripe_len).unwrap());
+		}
+	}
+
+	#[test]
+	fn reconstruction_job_resumes_per_stripe() {
+		let payload = &random_payload(10_000, 0);
+		for layout in [ShardLayout::Interleaved, ShardLayout::Sequential] {
+			let params = CodeParams::new(20, 5).unwrap().with_layout(layout);
+			let shards = encode_with_params(&params, payload).unwrap();
+			for erased in [&[][..], &[0, 2, 3, 7, 11, 12, 13, 19]] {
+				let mut received = shards.iter().map(|shard| Some(shard.as_ref())).collect::<Vec<Option<&[u8]>>>();
+				erased.iter().for_each(|&idx| received[idx] = None);
+				let expected = reconstruct_ref_with_params(&params, &received).unwrap();
+
+				let mut scratch = Scratch::new(66);
+				let mut job = ReconstructionJob::new(&params, &received, &mut scratch).unwrap();
+				let steps = job.remaining_steps();
+				assert_eq!(steps, 1 + AsRef::<[u8]>::as_ref(&shards[0]).len().div_ceil(66));
+				for step in 1..steps {
+					assert!(!job.step().unwrap());
+					assert_eq!(job.remaining_steps(), steps - step);
+				}
+				assert!(job.step().unwrap());
+				assert_eq!(job.finish().unwrap(), expected);
+
+				let mut scratch = Scratch::new(66);
+				let job = ReconstructionJob::new(&params, &received, &mut scratch).unwrap();
+				assert_eq!(job.finish().unwrap(), expected);
+			}
+		}
+	}
+
+	#[test]
+	fn report_tells_used_and_recovered_shards() {
+		let params = CodeParams::new(10, 4).unwrap();
+		let payload = &random_payload(1000, 0);
+		let shards = encode_with_params(&params, payload).unwrap();
+		let indices = |idx: &[u32]| idx.iter().map(|idx| ChunkIndex(*idx)).collect::<Vec<_>>();
+		for (erased, recovered, used) in [
+			(&[][..], &[][..], &[0, 1, 2, 3][..]),
+			(&[5, 9], &[5, 9], &[0, 1, 2, 3]),
+			(&[1, 2, 6, 7], &[1, 2, 6, 7], &[0, 3, 4, 5, 8, 9]),
+		] {
+			let mut received = shards.iter().map(|shard| Some(shard.as_ref())).collect::<Vec<Option<&[u8]>>>();
+			erased.iter().for_each(|&idx| received[idx] = None);
+			let report = reconstruct_with_report(&params, &received).unwrap();
+			assert_eq!(&report.payload[..payload.len()], payload);
+			assert_eq!(report.recovered_indices, indices(recovered));
+			assert_eq!(report.used_indices, indices(used));
+		}
+	}
+
+	#[test]
+	fn partial_reconstruction_recovers_covered_stripes() {
+		let payload = &random_payload(1000, 0);
+		let group = std::num::NonZeroUsize::new(3).unwrap();
+		for params in [
+			CodeParams::new(10, 4).unwrap(),
+			CodeParams::new(10, 4).unwrap().with_layout(ShardLayout::Sequential),
+			CodeParams::new(10, 4).unwrap().with_symbol_group(group),
+		] {
+			let shards = encode_with_params(&params, payload).unwrap();
+			let shard_len = AsRef::<[u8]>::as_ref(&shards[0]).len();
+			let mut received = shards.iter().map(|shard| Some(shard.as_ref())).collect::<Vec<Option<&[u8]>>>();
+			let complete = reconstruct_partial(&params, &received).unwrap();
+			assert!(complete.is_complete());
+			assert_eq!(&complete.payload[..payload.len()], payload);
+
+			// 3 full shards and 2 covering the first 60 bytes, so 1 data shard and the first stripe
+			for idx in [0, 2, 3, 5, 9] {
+				received[idx] = None;
+			}
+			received[4] = received[4].map(|shard| &shard[..60]);
+			received[6] = received[6].map(|shard| &shard[..61]);
+			let partial = reconstruct_partial(&params, &received).unwrap();
+			assert!(!partial.is_complete());
+			for (idx, offset) in (0..4).flat_map(|idx| (0..shard_len).map(move |offset| (idx, offset))) {
+				let at = payload_byte(&params, 4, shard_len, idx, offset);
+				let recovered = partial.recovered.iter().any(|range| range.contains(&at));
+				assert_eq!(recovered, idx == 1 || offset < 60, "{:?} shard {} byte {}", params, idx, offset);
+				if recovered && at < payload.len() {
+					assert_eq!(partial.payload[at], payload[at]);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn memory_usage_accounts_shards_and_payload() {
+		let params = CodeParams::new(20, 5).unwrap();
+		let usage = memory_usage(&params, 10_000).unwrap();
+		let shards = encode_with_params(&params, &random_payload(10_000, 0)).unwrap();
+		assert_eq!(usage.shards, shards.iter().map(|shard| AsRef::<[u8]>::as_ref(shard).len()).sum::<usize>());
+		assert_eq!(
+			usage.payload,
+			reconstruct_with_params(&params, shards.into_iter().map(Some).collect()).unwrap().len()
+		);
+		// the error locator is only evaluated at the internal `n` points
+		assert!(usage.reconstruct_scratch >= 32 * 2 && usage.reconstruct_scratch < FIELD_SIZE * 2);
+	}
+
+	#[test]
+	fn arena_matches_shards() {
+		let params = CodeParams::new(20, 5).unwrap().with_layout(ShardLayout::Sequential);
+		let payload = &random_payload(1000, 0);
+		let shards = encode_with_params(&params, payload).unwrap();
+
+		let mut arena = ShardArena::default();
+		encode_into_arena(&params, payload, &mut arena).unwrap();
+		assert_eq!(arena.n(), 20);
+		for (idx, shard) in shards.iter().enumerate() {
+			assert_eq!(arena.shard(idx), AsRef::<[u8]>::as_ref(shard));
+		}
+		assert_eq!(arena.to_shards(), shards);
+
+		// a smaller payload reuses the allocation
+		let ptr = arena.as_bytes().as_ptr();
+		encode_into_arena(&params, &payload[..100], &mut arena).unwrap();
+		assert_eq!(arena.as_bytes().as_ptr(), ptr);
+		let received = arena.iter().map(Some).collect::<Vec<_>>();
+		assert_eq!(&reconstruct_ref_with_params(&params, &received).unwrap()[..100], &payload[..100]);
+	}
+
+	#[test]
+	fn extend_code_matches_encode() {
+		let payload = &random_payload(3000, 0);
+		let params = CodeParams::new(20, 6).unwrap();
+		let shards = encode_with_params(&params, payload).unwrap();
+		let grown = encode_with_params(&CodeParams::new(100, 6).unwrap(), payload).unwrap();
+
+		let mut received = shards.iter().map(|shard| Some(shard.as_ref())).collect::<Vec<Option<&[u8]>>>();
+		assert_eq!(extend_code(&params, &received, 100).unwrap(), &grown[20..]);
+
+		// missing data shards are recovered first
+		for idx in [0, 1, 3, 5, 8, 9, 10, 11, 12, 13, 14, 15] {
+			received[idx] = None;
+		}
+		assert_eq!(extend_code(&params, &received, 100).unwrap(), &grown[20..]);
+		assert_eq!(extend_code(&params, &received, 30).unwrap(), &grown[20..30]);
+		assert!(extend_code(&params, &received, 20).unwrap().is_empty());
+	}
 
+	#[test]
+	fn check_symbols_verify_reconstruction() {
+		let payload = &random_payload(3000, 0);
+		for layout in [ShardLayout::Interleaved, ShardLayout::Sequential] {
+			let params = CodeParams::new(20, 6).unwrap().with_layout(layout);
+			let (shards, checks) = encode_with_checks(&params, payload, 3).unwrap();
+			assert_eq!(shards, encode_with_params(&params, payload).unwrap());
+			assert_eq!(checks.0.len(), 3);
+
+			// the weighted sum of the virtual shard at the reserved position
+			for (j, check) in checks.0.iter().enumerate() {
+				let pos = FIELD_SIZE - 1 - j;
+				let virtual_shard = encode_extra_parity(&params, ParitySource::Data(payload), pos..pos + 1).unwrap();
+				let sum = virtual_shard[0]
+					.symbols::<2>()
+					.unwrap()
+					.iter()
+					.enumerate()
+					.fold(0, |sum, (c, bytes)| sum ^ mul_table(u16::from_le_bytes(*bytes), c as GFSymbol));
+				assert_eq!(sum, *check, "{:?} check {}", layout, j);
+			}
+
+			let mut received = shards.iter().map(|shard| Some(shard.as_ref())).collect::<Vec<Option<&[u8]>>>();
+			for idx in [0, 2, 3, 7, 11, 12, 13, 19] {
+				received[idx] = None;
+			}
+			let mut recovered = reconstruct_ref_with_params(&params, &received).unwrap();
+			assert_eq!(verify_checks(&params, &recovered, &checks), Ok(()));
+			assert_eq!(verify_checks(&params, &recovered[..payload.len()], &checks), Ok(()));
+
+			recovered[1234] ^= 0x10;
+			assert!(matches!(verify_checks(&params, &recovered, &checks), Err(Error::CheckMismatch(_))));
+		}
+	}
+
+	#[test]
+	fn extra_parity_matches_encode() {
+		let payload = &random_payload(3000, 0);
+		for layout in [ShardLayout::Interleaved, ShardLayout::Sequential] {
+			let params = CodeParams::new(20, 6).unwrap().with_layout(layout);
+			let grown = encode_with_params(&CodeParams::new(200, 6).unwrap().with_layout(layout), payload).unwrap();
+
+			// emitted in rounds, as a rateless sender would
+			let mut extra = Vec::new();
+			for round in (20..200).step_by(7) {
+				let range = round..std::cmp::min(round + 7, 200);
+				extra.extend(encode_extra_parity(&params, ParitySource::Data(payload), range).unwrap());
+			}
+			assert_eq!(extra, &grown[20..]);
+
+			let mut received = grown[..20].iter().map(|shard| Some(shard.as_ref())).collect::<Vec<Option<&[u8]>>>();
+			for idx in [0, 2, 3, 7, 11, 12, 13, 19] {
+				received[idx] = None;
+			}
+			let source = ParitySource::Shards(&received);
+			assert_eq!(encode_extra_parity(&params, source, 150..163).unwrap(), &grown[150..163]);
+			assert_eq!(encode_extra_parity(&params, source, 0..20).unwrap(), &grown[..20]);
+		}
+
+		let params = CodeParams::new(20, 6).unwrap();
+		let range = FIELD_SIZE - 1..FIELD_SIZE + 1;
+		assert_eq!(
+			encode_extra_parity(&params, ParitySource::Data(payload), range),
+			Err(Error::ShardCountTooHigh(FIELD_SIZE + 1))
+		);
+	}
+
+	#[test]
+	fn generic_afft_matches_reference() {
+		use crate::afft::{afft, inverse_afft, FieldAdd};
+
+		// the field with the scalar butterflies of `FieldAdd` instead of the vectorized ones
+		struct Scalar;
+		impl FieldAdd for Scalar {
+			type Elt = GFSymbol;
+			const FIELD_BITS: usize = FIELD_BITS;
+			const MODULO: GFSymbol = MODULO;
+			fn setup() {
+				setup()
+			}
+			fn mul(a: GFSymbol, log_b: GFSymbol) -> GFSymbol {
+				mul_table(a, log_b)
+			}
+			fn skew(idx: usize) -> GFSymbol {
+				skew_factor(idx)
+			}
+		}
+
+		setup();
+		let data = (0..256).map(|_x| rand_gf_element()).collect::<Vec<GFSymbol>>();
+		for &index in &[0, 256, 1024] {
+			let mut reference = data.clone();
+			let mut generic = data.clone();
+			inverse_afft::<Scalar>(&mut reference, 256, index);
+			inverse_afft::<F2e16>(&mut generic, 256, index);
+			assert_eq!(generic, reference);
+
+			afft::<Scalar>(&mut reference, 256, index);
+			afft::<F2e16>(&mut generic, 256, index);
+			assert_eq!(generic, reference);
+			assert_eq!(generic, data);
+		}
+	}
+
+	#[test]
+	fn constant_time_mul_matches_lookup() {
+		setup();
+		for _ in 0..10_000 {
+			let (a, b) = (rand_gf_element(), rand_gf_element());
+			assert_eq!(mul_ct(a, b), mul_table_lookup(a, b));
+		}
+		assert_eq!(mul_ct(0, 1234), 0);
+		assert_eq!(mul_ct(4321, MODULO), mul_table_lookup(4321, MODULO));
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn async_matches_sync() {
+		let payload = &random_payload(100_000, 0);
+		for layout in [ShardLayout::Interleaved, ShardLayout::Sequential] {
+			let params = CodeParams::new(10, 3).unwrap().with_layout(layout);
+			let shards = futures::executor::block_on(encode_async(&params, payload)).unwrap();
+			assert_eq!(shards, encode_with_params(&params, payload).unwrap());
+
+			let mut received = shards.iter().map(|shard| Some(shard.as_ref())).collect::<Vec<Option<&[u8]>>>();
+			for shard in received.iter_mut().take(7) {
+				*shard = None;
+			}
+			let recovered = futures::executor::block_on(reconstruct_async(&params, &received)).unwrap();
+			assert_eq!(recovered, reconstruct_ref_with_params(&params, &received).unwrap());
+		}
 	}
 }
@@ -0,0 +1,70 @@
+// One layer of butterflies of the transforms in the novel polynomial basis, see `gpu.rs`.
+//
+// The codewords are stored as rows, two symbols packed per word, so each invocation
+// updates one word of the low and one of the high half of a butterfly.
+
+struct Layer {
+	// words per row, i.e. half the number of codewords
+	row_words: u32,
+	depart_no: u32,
+	index: u32,
+	inverse: u32,
+	// butterflies in words, across all rows
+	words: u32,
+	// invocations per row of workgroups
+	stride: u32,
+	pad0: u32,
+	pad1: u32,
+}
+
+@group(0) @binding(0) var<storage, read_write> data: array<u32>;
+@group(0) @binding(1) var<storage, read> log_table: array<u32>;
+@group(0) @binding(2) var<storage, read> exp_table: array<u32>;
+@group(0) @binding(3) var<storage, read> skew_factors: array<u32>;
+@group(0) @binding(4) var<uniform> layer: Layer;
+
+const MODULO: u32 = 65535u;
+
+fn mul(a: u32, log_b: u32) -> u32 {
+	if (a == 0u) {
+		return 0u;
+	}
+	let sum = log_table[a] + log_b;
+	return exp_table[(sum & MODULO) + (sum >> 16u)];
+}
+
+fn mul_packed(word: u32, log_b: u32) -> u32 {
+	return mul(word & 0xFFFFu, log_b) | (mul(word >> 16u, log_b) << 16u);
+}
+
+@compute @workgroup_size(256)
+fn butterflies(@builtin(global_invocation_id) id: vec3<u32>) {
+	let t = id.x + id.y * layer.stride;
+	if (t >= layer.words) {
+		return;
+	}
+	let d = layer.depart_no;
+	let pair = t / layer.row_words;
+	let offset = pair % d;
+	// the first row of the upper half of this butterfly group
+	let j = (pair - offset) * 2u + d;
+	let lo = (j - d + offset) * layer.row_words + t % layer.row_words;
+	let hi = lo + d * layer.row_words;
+	let skew = skew_factors[j + layer.index - 1u];
+
+	var l = data[lo];
+	var h = data[hi];
+	if (layer.inverse != 0u) {
+		h ^= l;
+		if (skew != MODULO) {
+			l ^= mul_packed(h, skew);
+		}
+	} else {
+		if (skew != MODULO) {
+			l ^= mul_packed(h, skew);
+		}
+		h ^= l;
+	}
+	data[lo] = l;
+	data[hi] = h;
+}
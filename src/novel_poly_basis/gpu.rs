@@ -0,0 +1,330 @@
+//! Prototype offloading the transforms of large payloads to the GPU via wgpu.
+//!
+//! Each layer of butterflies is one compute dispatch, with one invocation per pair of
+//! codewords, i.e. the column-parallel butterflies of the CPU transforms. Only transforms
+//! of at least `MIN_OFFLOAD_SYMBOLS` symbols over an even number of codewords are offloaded,
+//! anything else, or any machine without a usable adapter, stays on the CPU. Offloaded
+//! transforms are not counted by `stats`, and never used with the `constant-time` feature,
+//! since the shader looks up the tables by the data.
+//!
+//! Encoding then transforms as many codewords at once as fit into a GPU buffer, instead of
+//! cache sized batches. Decoding is not batched that way yet, so it rarely qualifies.
+
+use super::low_level::Tables;
+use super::{check_transform, GFSymbol, FIELD_SIZE};
+use crate::{Error, Result};
+
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use wgpu::util::DeviceExt;
+
+/// Transforms of fewer symbols, across all codewords, are not worth the transfers.
+pub const MIN_OFFLOAD_SYMBOLS: usize = 1 << 22;
+
+const WORKGROUP_SIZE: u32 = 256;
+
+static OFFLOAD: AtomicBool = AtomicBool::new(true);
+
+/// The device running the butterflies, with the field tables uploaded.
+pub struct Gpu {
+	info: wgpu::AdapterInfo,
+	device: wgpu::Device,
+	queue: wgpu::Queue,
+	pipeline: wgpu::ComputePipeline,
+	log_table: wgpu::Buffer,
+	exp_table: wgpu::Buffer,
+	skew_factors: wgpu::Buffer,
+}
+
+/// The GPU, if this machine has a usable adapter, initialized on first use.
+pub fn gpu() -> Option<&'static Gpu> {
+	static GPU: OnceLock<Option<Gpu>> = OnceLock::new();
+	GPU.get_or_init(|| pollster::block_on(Gpu::new())).as_ref()
+}
+
+/// Whether transforms are offloaded, if a GPU is available, which is the default.
+pub fn offload_enabled() -> bool {
+	OFFLOAD.load(Ordering::Relaxed)
+}
+
+/// Enable or disable offloading for the whole process, e.g. to compare against the CPU.
+pub fn set_offload(enabled: bool) {
+	OFFLOAD.store(enabled, Ordering::Relaxed)
+}
+
+// run the transform of the rows on the GPU if it qualifies, `false` leaves it to the caller
+pub(super) fn try_offload(data: &mut [GFSymbol], width: usize, size: usize, index: usize, inverse: bool) -> bool {
+	if cfg!(feature = "constant-time") || !offload_enabled() || size * width < MIN_OFFLOAD_SYMBOLS {
+		return false;
+	}
+	match gpu() {
+		Some(gpu) => gpu.transform_rows(&mut data[..size * width], width, size, index, inverse).is_ok(),
+		None => false,
+	}
+}
+
+// Codewords per batch for encoding `codewords` codewords of `k` symbols on the GPU, as many
+// as fit into a buffer, if enough to be offloaded at all
+pub(super) fn batch_width(k: usize, codewords: usize) -> Option<usize> {
+	if cfg!(feature = "constant-time") || !offload_enabled() || k * codewords < MIN_OFFLOAD_SYMBOLS {
+		return None;
+	}
+	let width = std::cmp::min(codewords, gpu()?.max_symbols() / k) & !1;
+	Some(width).filter(|width| k * width >= MIN_OFFLOAD_SYMBOLS)
+}
+
+fn table_buffer(device: &wgpu::Device, label: &str, table: impl Iterator<Item = GFSymbol>) -> wgpu::Buffer {
+	let contents = table.flat_map(|sym| u32::from(sym).to_le_bytes()).collect::<Vec<u8>>();
+	device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+		label: Some(label),
+		contents: &contents,
+		usage: wgpu::BufferUsages::STORAGE,
+	})
+}
+
+impl Gpu {
+	async fn new() -> Option<Gpu> {
+		let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+		let adapter = instance
+			.request_adapter(&wgpu::RequestAdapterOptions {
+				power_preference: wgpu::PowerPreference::HighPerformance,
+				..Default::default()
+			})
+			.await?;
+		let descriptor = wgpu::DeviceDescriptor {
+			label: Some("rs-ec-perf"),
+			required_features: wgpu::Features::empty(),
+			required_limits: adapter.limits(),
+			memory_hints: wgpu::MemoryHints::Performance,
+		};
+		let (device, queue) = adapter.request_device(&descriptor, None).await.ok()?;
+
+		let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+			label: Some("butterflies"),
+			source: wgpu::ShaderSource::Wgsl(include_str!("butterflies.wgsl").into()),
+		});
+		let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+			label: Some("butterflies"),
+			layout: None,
+			module: &module,
+			entry_point: Some("butterflies"),
+			compilation_options: Default::default(),
+			cache: None,
+		});
+
+		let tables = Tables::get();
+		let all = || (0..FIELD_SIZE).map(|sym| sym as GFSymbol);
+		Some(Gpu {
+			info: adapter.get_info(),
+			log_table: table_buffer(&device, "log", all().map(|a| tables.log(a))),
+			exp_table: table_buffer(&device, "exp", all().map(|log_a| tables.exp(log_a))),
+			skew_factors: table_buffer(&device, "skew", tables.skew_factors().iter().copied()),
+			device,
+			queue,
+			pipeline,
+		})
+	}
+
+	/// Name and backend of the adapter, for reports.
+	pub fn adapter_info(&self) -> &wgpu::AdapterInfo {
+		&self.info
+	}
+
+	/// Most symbols, across all codewords, transformed at once.
+	pub fn max_symbols(&self) -> usize {
+		let limits = self.device.limits();
+		let bytes = std::cmp::min(limits.max_buffer_size, u64::from(limits.max_storage_buffer_binding_size));
+		usize::try_from(bytes / 2).unwrap_or(usize::MAX)
+	}
+
+	/// Same as `Tables::fft`, for `width` codewords stored as rows as in the CPU transforms,
+	/// so `data` holds `size * width` symbols. `width` must be even.
+	pub fn fft_rows(&self, data: &mut [GFSymbol], width: usize, size: usize, index: usize) -> Result<()> {
+		self.transform_rows(data, width, size, index, false)
+	}
+
+	/// Inverse of `fft_rows`, with the same requirements.
+	pub fn inverse_fft_rows(&self, data: &mut [GFSymbol], width: usize, size: usize, index: usize) -> Result<()> {
+		self.transform_rows(data, width, size, index, true)
+	}
+
+	fn transform_rows(
+		&self,
+		data: &mut [GFSymbol],
+		width: usize,
+		size: usize,
+		index: usize,
+		inverse: bool,
+	) -> Result<()> {
+		check_transform(data.len() / width.max(1), size, index)?;
+		if width % 2 == 1 {
+			return Err(Error::GpuOffload("odd number of codewords"));
+		}
+		let data = &mut data[..size * width];
+		if data.len() > self.max_symbols() {
+			return Err(Error::GpuOffload("rows exceed the buffer size limit"));
+		}
+		let len = (data.len() * 2) as u64;
+		let limits = self.device.limits();
+		if size < 2 || width == 0 {
+			return Ok(());
+		}
+
+		let contents = data.iter().flat_map(|sym| sym.to_le_bytes()).collect::<Vec<u8>>();
+		let buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("rows"),
+			contents: &contents,
+			usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+		});
+		let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("staging"),
+			size: len,
+			usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		});
+
+		let row_words = (width / 2) as u32;
+		let words = (size / 2) as u32 * row_words;
+		let groups = words.div_ceil(WORKGROUP_SIZE);
+		let x = groups.min(limits.max_compute_workgroups_per_dimension);
+		let y = groups.div_ceil(x);
+
+		let mut depart_nos = (0..size.trailing_zeros()).map(|i| 1_u32 << i).collect::<Vec<_>>();
+		if !inverse {
+			depart_nos.reverse();
+		}
+		let bind_groups = depart_nos
+			.iter()
+			.map(|depart_no| {
+				let layer = [row_words, *depart_no, index as u32, u32::from(inverse), words, x * WORKGROUP_SIZE, 0, 0];
+				let uniform = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+					label: Some("layer"),
+					contents: &layer.iter().flat_map(|word| word.to_le_bytes()).collect::<Vec<u8>>(),
+					usage: wgpu::BufferUsages::UNIFORM,
+				});
+				let entries = [&buffer, &self.log_table, &self.exp_table, &self.skew_factors, &uniform]
+					.iter()
+					.enumerate()
+					.map(|(binding, buffer)| wgpu::BindGroupEntry {
+						binding: binding as u32,
+						resource: buffer.as_entire_binding(),
+					})
+					.collect::<Vec<_>>();
+				self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+					label: Some("layer"),
+					layout: &self.pipeline.get_bind_group_layout(0),
+					entries: &entries,
+				})
+			})
+			.collect::<Vec<_>>();
+
+		let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+		{
+			let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+			pass.set_pipeline(&self.pipeline);
+			for bind_group in &bind_groups {
+				pass.set_bind_group(0, bind_group, &[]);
+				pass.dispatch_workgroups(x, y, 1);
+			}
+		}
+		encoder.copy_buffer_to_buffer(&buffer, 0, &staging, 0, len);
+		self.queue.submit(Some(encoder.finish()));
+
+		let slice = staging.slice(..);
+		let (tx, rx) = std::sync::mpsc::channel();
+		slice.map_async(wgpu::MapMode::Read, move |mapped| {
+			let _ = tx.send(mapped);
+		});
+		self.device.poll(wgpu::Maintain::Wait);
+		rx.recv()
+			.ok()
+			.and_then(|mapped| mapped.ok())
+			.ok_or(Error::GpuOffload("mapping the result failed"))?;
+		for (sym, bytes) in data.iter_mut().zip(slice.get_mapped_range().chunks_exact(2)) {
+			*sym = GFSymbol::from_le_bytes([bytes[0], bytes[1]]);
+		}
+		staging.unmap();
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::{Mutex, MutexGuard, PoisonError};
+
+	// serializes the tests toggling `OFFLOAD`, the guard restores it when dropped, even on failure
+	static OFFLOAD_LOCK: Mutex<()> = Mutex::new(());
+
+	struct OffloadGuard {
+		previous: bool,
+		_lock: MutexGuard<'static, ()>,
+	}
+
+	impl OffloadGuard {
+		fn lock() -> Self {
+			let _lock = OFFLOAD_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+			OffloadGuard { previous: offload_enabled(), _lock }
+		}
+	}
+
+	impl Drop for OffloadGuard {
+		fn drop(&mut self) {
+			set_offload(self.previous);
+		}
+	}
+
+	#[test]
+	fn gpu_transforms_match_cpu() {
+		let gpu = match gpu() {
+			Some(gpu) => gpu,
+			// nothing to compare against, offloading falls back to the CPU
+			None => {
+				eprintln!("skipping gpu_transforms_match_cpu: no GPU adapter");
+				return;
+			}
+		};
+		let tables = Tables::get();
+		let (width, size, index) = (6, 64, 128);
+		let rows = crate::workload::random_payload(width * size * 2, 0)
+			.chunks_exact(2)
+			.map(|pair| GFSymbol::from_le_bytes([pair[0], pair[1]]))
+			.collect::<Vec<_>>();
+
+		let mut on_gpu = rows.clone();
+		gpu.fft_rows(&mut on_gpu, width, size, index).unwrap();
+		let mut column = vec![0; size];
+		for c in 0..width {
+			column.iter_mut().enumerate().for_each(|(i, sym)| *sym = rows[i * width + c]);
+			tables.fft(&mut column, size, index).unwrap();
+			assert!(column.iter().enumerate().all(|(i, sym)| on_gpu[i * width + c] == *sym), "codeword {}", c);
+		}
+
+		gpu.inverse_fft_rows(&mut on_gpu, width, size, index).unwrap();
+		assert_eq!(on_gpu, rows);
+		assert_eq!(gpu.fft_rows(&mut on_gpu, 3, size, index), Err(Error::GpuOffload("odd number of codewords")));
+	}
+
+	#[test]
+	fn offloaded_encoding_matches_cpu() {
+		// never offloaded in constant time
+		if cfg!(feature = "constant-time") {
+			eprintln!("skipping offloaded_encoding_matches_cpu: never offloaded with constant-time");
+			return;
+		}
+		if gpu().is_none() {
+			eprintln!("skipping offloaded_encoding_matches_cpu: no GPU adapter");
+			return;
+		}
+		let params = crate::CodeParams::new(8, 4).unwrap();
+		let payload = &crate::workload::random_payload(MIN_OFFLOAD_SYMBOLS * 2, 0);
+		let _offload = OffloadGuard::lock();
+		set_offload(true);
+		assert!(batch_width(4, MIN_OFFLOAD_SYMBOLS / 2).is_some());
+		let offloaded = crate::novel_poly_basis::encode_with_params(&params, payload).unwrap();
+		set_offload(false);
+		let on_cpu = crate::novel_poly_basis::encode_with_params(&params, payload).unwrap();
+		assert_eq!(offloaded, on_cpu);
+	}
+}
@@ -0,0 +1,232 @@
+//! The plain scalar multiplication and transforms, one symbol at a time, without
+//! blocking, region tables, bit-slicing or SIMD kernels.
+//!
+//! Only compiled with the `reference` feature, which also enables the differential
+//! tests of every optimized path against these, over random inputs.
+
+use super::check_transform;
+use super::low_level::Tables;
+use crate::f2e16::{GFSymbol, BASE, FIELD_BITS, GENERATOR, MODULO};
+use crate::Result;
+
+/// `a * exp(log_b)` via one log and one exp table lookup.
+pub fn mul(tables: &Tables, a: GFSymbol, log_b: GFSymbol) -> GFSymbol {
+	if a == 0 {
+		return 0;
+	}
+	let sum = u32::from(tables.log(a)) + u32::from(log_b);
+	tables.exp(((sum & u32::from(MODULO)) + (sum >> FIELD_BITS)) as GFSymbol)
+}
+
+// the element of Cantor basis coordinates `a` in the polynomial basis
+fn to_poly_basis(a: GFSymbol) -> GFSymbol {
+	(0..FIELD_BITS).filter(|i| a >> i & 1 == 1).fold(0, |acc, i| acc ^ BASE[i])
+}
+
+// inverse of `to_poly_basis`, by elimination over GF(2) on the basis elements
+fn from_poly_basis(mut x: GFSymbol) -> GFSymbol {
+	let mut rows = BASE.iter().enumerate().map(|(i, base)| (*base, 1 << i)).collect::<Vec<(GFSymbol, GFSymbol)>>();
+	let mut a = 0;
+	for bit in (0..FIELD_BITS).rev() {
+		let pivot = match rows.iter().position(|(row, _)| row >> bit & 1 == 1) {
+			Some(at) => rows.swap_remove(at),
+			None => continue,
+		};
+		for row in rows.iter_mut().filter(|(row, _)| row >> bit & 1 == 1) {
+			*row = (row.0 ^ pivot.0, row.1 ^ pivot.1);
+		}
+		if x >> bit & 1 == 1 {
+			x ^= pivot.0;
+			a ^= pivot.1;
+		}
+	}
+	debug_assert_eq!(x, 0, "the Cantor basis spans the field");
+	a
+}
+
+/// `a * b` by schoolbook multiplication modulo the field polynomial, after mapping both
+/// to the polynomial basis. Uses no table, only the definitions of the field and of the
+/// Cantor basis, to catch bugs of the table generation, e.g. by `fuzz/field_arithmetic`.
+pub fn mul_bitwise(a: GFSymbol, b: GFSymbol) -> GFSymbol {
+	let (mut a, mut b) = (to_poly_basis(a), to_poly_basis(b));
+	let mut product = 0;
+	while b != 0 {
+		if b & 1 == 1 {
+			product ^= a;
+		}
+		b >>= 1;
+		a = (a << 1) ^ if a >> (FIELD_BITS - 1) == 1 { GENERATOR } else { 0 };
+	}
+	from_poly_basis(product)
+}
+
+/// Same as `Tables::fft`.
+pub fn fft(tables: &Tables, data: &mut [GFSymbol], size: usize, index: usize) -> Result<()> {
+	check_transform(data.len(), size, index)?;
+	let skews = tables.skew_factors();
+	let mut depart_no = size >> 1;
+	while depart_no > 0 {
+		for j in (depart_no..size).step_by(depart_no << 1) {
+			let skew = skews[j + index - 1];
+			for i in (j - depart_no)..j {
+				if skew != MODULO {
+					data[i] ^= mul(tables, data[i + depart_no], skew);
+				}
+				data[i + depart_no] ^= data[i];
+			}
+		}
+		depart_no >>= 1;
+	}
+	Ok(())
+}
+
+/// Same as `Tables::inverse_fft`.
+pub fn inverse_fft(tables: &Tables, data: &mut [GFSymbol], size: usize, index: usize) -> Result<()> {
+	check_transform(data.len(), size, index)?;
+	let skews = tables.skew_factors();
+	let mut depart_no = 1;
+	while depart_no < size {
+		for j in (depart_no..size).step_by(depart_no << 1) {
+			let skew = skews[j + index - 1];
+			for i in (j - depart_no)..j {
+				data[i + depart_no] ^= data[i];
+				if skew != MODULO {
+					data[i] ^= mul(tables, data[i + depart_no], skew);
+				}
+			}
+		}
+		depart_no <<= 1;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::*;
+	use super::*;
+	use crate::f2e16::mul_log_exp;
+
+	use rand::{rngs::StdRng, Rng, SeedableRng};
+
+	#[test]
+	fn multiplications_match_reference() {
+		let tables = Tables::get();
+		let mut rng = StdRng::seed_from_u64(0x5eed);
+		for _ in 0..256 {
+			let log_b = rng.gen_range(0..MODULO);
+			let src = (0..200).map(|i| if i == 0 { 0 } else { rng.gen::<GFSymbol>() }).collect::<Vec<_>>();
+			let expected = src.iter().map(|a| mul(&tables, *a, log_b)).collect::<Vec<_>>();
+
+			for (name, product) in [
+				("mul_table", src.iter().map(|a| mul_table(*a, log_b)).collect::<Vec<_>>()),
+				("mul_log_exp", src.iter().map(|a| mul_log_exp(*a, log_b)).collect()),
+				("mul_ct", src.iter().map(|a| mul_ct(*a, log_b)).collect()),
+				("mul_poly_basis", src.iter().map(|a| mul_poly_basis(*a, log_b)).collect()),
+				("Multiplier", src.iter().map(|a| tables.multiplier(log_b).apply(*a)).collect()),
+				("RegionMultiplier", src.iter().map(|a| tables.region_multiplier(log_b).apply(*a)).collect()),
+			] {
+				assert_eq!(product, expected, "{} by exp({})", name, log_b);
+			}
+
+			let mut region = vec![0; src.len()];
+			tables.region_multiplier(log_b).mul_add_slice(&mut region, &src);
+			assert_eq!(region, expected, "RegionMultiplier::mul_add_slice by exp({})", log_b);
+		}
+	}
+
+	#[test]
+	fn tables_match_bitwise_multiplication() {
+		let tables = Tables::get();
+		let mut rng = StdRng::seed_from_u64(0xb175);
+		assert_eq!(mul_bitwise(1, 0x1234), 0x1234);
+		for _ in 0..4096 {
+			let (a, b) = (rng.gen::<GFSymbol>(), rng.gen_range(1..=MODULO));
+			assert_eq!(from_poly_basis(to_poly_basis(a)), a);
+			assert_eq!(mul(&tables, a, tables.log(b)), mul_bitwise(a, b), "{} * {}", a, b);
+			assert_eq!(mul_bitwise(a, b), mul_bitwise(b, a));
+		}
+		assert_eq!(mul_bitwise(0x1234, 0), 0);
+	}
+
+	// a transform of `width` codewords stored as rows, see `fft_rows`
+	type RowsTransform = fn(&mut [GFSymbol], usize, usize, usize);
+
+	// every implementation of the forward or inverse transform of rows
+	fn transforms(inverse: bool) -> Vec<(&'static str, RowsTransform)> {
+		let mut transforms: Vec<(&'static str, RowsTransform)> = if inverse {
+			vec![
+				("flat", inverse_fft_rows),
+				("selected", inverse_fft_rows_selected),
+				("generic", |data, width, size, index| unsafe { generic::inverse_fft(data, width, size, index) }),
+			]
+		} else {
+			vec![
+				("flat", fft_rows),
+				("selected", fft_rows_selected),
+				("generic", |data, width, size, index| unsafe { generic::fft(data, width, size, index) }),
+			]
+		};
+		#[cfg(target_arch = "x86_64")]
+		{
+			if is_x86_feature_detected!("avx2") {
+				transforms.push(if inverse {
+					("avx2", |data, width, size, index| unsafe { avx2::inverse_fft(data, width, size, index) })
+				} else {
+					("avx2", |data, width, size, index| unsafe { avx2::fft(data, width, size, index) })
+				});
+			}
+			if is_x86_feature_detected!("avx512f") && is_x86_feature_detected!("avx512bw") {
+				transforms.push(if inverse {
+					("avx512", |data, width, size, index| unsafe { avx512::inverse_fft(data, width, size, index) })
+				} else {
+					("avx512", |data, width, size, index| unsafe { avx512::fft(data, width, size, index) })
+				});
+			}
+		}
+		transforms
+	}
+
+	#[test]
+	fn transforms_match_reference() {
+		let tables = Tables::get();
+		let mut rng = StdRng::seed_from_u64(0xfff7);
+		for _ in 0..64 {
+			let size = 1 << rng.gen_range(0..=13);
+			let index = rng.gen_range(0..FIELD_SIZE / size) * size;
+			let width = rng.gen_range(1..=5);
+			let columns = (0..width)
+				.map(|_| (0..size).map(|_| rng.gen::<GFSymbol>()).collect::<Vec<_>>())
+				.collect::<Vec<_>>();
+
+			for inverse in [false, true] {
+				let reference = if inverse { inverse_fft } else { fft };
+				let expected = columns
+					.iter()
+					.map(|column| {
+						let mut column = column.clone();
+						reference(&tables, &mut column, size, index).unwrap();
+						column
+					})
+					.collect::<Vec<_>>();
+				let expected_rows = (0..size * width).map(|at| expected[at % width][at / width]).collect::<Vec<_>>();
+				let context = format!("inverse {} size {} index {} width {}", inverse, size, index, width);
+
+				for (name, transform) in transforms(inverse) {
+					let mut rows = (0..size * width).map(|at| columns[at % width][at / width]).collect::<Vec<_>>();
+					transform(&mut rows, width, size, index);
+					assert_eq!(rows, expected_rows, "{} {}", name, context);
+				}
+
+				for (column, expected) in columns.iter().zip(expected.iter()) {
+					let mut dispatched = column.clone();
+					if inverse {
+						tables.inverse_fft(&mut dispatched, size, index).unwrap();
+					} else {
+						tables.fft(&mut dispatched, size, index).unwrap();
+					}
+					assert_eq!(&dispatched, expected, "Tables {}", context);
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,374 @@
+//! GF(2^8) as the subfield of GF(2^16) spanned by the first 8 elements of the Cantor basis.
+//!
+//! Symbols are stored in Cantor basis coordinates, so a byte is a subfield element as is and
+//! `embed` and `extract` merely widen and narrow it. The transforms of codes of at most
+//! `SUBFIELD_SIZE` shards evaluate at subfield points only, with skew factors in the subfield,
+//! hence byte data encodes to shards of byte symbols, any symbol error affects a single byte.
+//!
+//! `encode` and `reconstruct` run the transforms on bytes, multiplying via the 256 entry log
+//! and exp tables of the subfield, so a codeword takes half the memory of one of the full
+//! field. Only the error locator is evaluated in the full field, once per reconstruction.
+//! The byte tables are used with the `constant-time` feature as well.
+//! The shards equal those of the full field codec on the payload widened to one byte per
+//! symbol, narrowed again.
+
+use super::low_level::Tables;
+use crate::{equalize_shard_lengths, CodeParams, Error, Result, ShardLayout, WrappedShard};
+use std::convert::TryFrom;
+use std::sync::OnceLock;
+
+/// Number of elements of the subfield, also the largest number of shards of a subfield code.
+pub const SUBFIELD_SIZE: usize = 256;
+
+/// Log of the subfield, its multiplicative group has order `MODULO`, `log(0)` is `MODULO`.
+pub const MODULO: u8 = u8::MAX;
+
+// the subfield is the multiplicative subgroup generated by the `257`th power of the generator
+const LOG_STEP: u16 = 257;
+
+struct SubfieldTables {
+	log: [u8; SUBFIELD_SIZE],
+	exp: [u8; SUBFIELD_SIZE],
+	// the skew factors and `B` factors of the full field for subfield points, in the log domain
+	skew: [u8; SUBFIELD_SIZE - 1],
+	b: [u8; SUBFIELD_SIZE >> 1],
+}
+
+impl SubfieldTables {
+	#[inline(always)]
+	fn mul(&self, a: u8, log_b: u8) -> u8 {
+		if a == 0 {
+			return 0;
+		}
+		let sum = u16::from(self.log[a as usize]) + u16::from(log_b);
+		self.exp[((sum & u16::from(MODULO)) + (sum >> 8)) as usize]
+	}
+}
+
+// the log of a subfield element in the full field as log in the subfield,
+// the full field marks zero skew factors by its `MODULO`
+fn narrow_log(log: u16) -> u8 {
+	if log == u16::MAX {
+		return MODULO;
+	}
+	debug_assert_eq!(log % LOG_STEP, 0, "{} is no log of a subfield element", log);
+	(log / LOG_STEP) as u8
+}
+
+fn tables() -> &'static SubfieldTables {
+	static TABLES: OnceLock<SubfieldTables> = OnceLock::new();
+	TABLES.get_or_init(|| {
+		let tables = Tables::get();
+		let mut subfield = SubfieldTables {
+			log: [MODULO; SUBFIELD_SIZE],
+			exp: [0; SUBFIELD_SIZE],
+			skew: [MODULO; SUBFIELD_SIZE - 1],
+			b: [0; SUBFIELD_SIZE >> 1],
+		};
+		for log in 0..MODULO {
+			let element = tables.exp(u16::from(log) * LOG_STEP);
+			let element = extract(element).expect("the subgroup lies in the subfield. qed");
+			subfield.log[element as usize] = log;
+			subfield.exp[log as usize] = element;
+		}
+		subfield.exp[MODULO as usize] = subfield.exp[0];
+		for (skew, full) in subfield.skew.iter_mut().zip(tables.skew_factors()) {
+			*skew = narrow_log(*full);
+		}
+		for (b, full) in subfield.b.iter_mut().zip(tables.b_factors()) {
+			*b = narrow_log(*full);
+		}
+		subfield
+	})
+}
+
+/// The subfield element `byte` as a symbol of the full field.
+#[inline(always)]
+pub fn embed(byte: u8) -> u16 {
+	u16::from(byte)
+}
+
+/// The subfield element `symbol` as a byte, `None` if it lies outside the subfield.
+#[inline(always)]
+pub fn extract(symbol: u16) -> Option<u8> {
+	u8::try_from(symbol).ok()
+}
+
+/// Discrete logarithm of `a` in the subfield.
+pub fn log(a: u8) -> u8 {
+	tables().log[a as usize]
+}
+
+/// Inverse of `log`.
+pub fn exp(log_a: u8) -> u8 {
+	tables().exp[log_a as usize]
+}
+
+/// `a * exp(log_b)` in the subfield, with `log_b` given in the log domain.
+pub fn mul(a: u8, log_b: u8) -> u8 {
+	tables().mul(a, log_b)
+}
+
+// `fft_in_novel_poly_basis` of `data[..size]` on bytes
+fn fft(tables: &SubfieldTables, data: &mut [u8], size: usize, index: usize) {
+	let mut depart_no = size >> 1;
+	while depart_no > 0 {
+		for j in (depart_no..size).step_by(depart_no << 1) {
+			let skew = tables.skew[j + index - 1];
+			let (lo, hi) = data[j - depart_no..j + depart_no].split_at_mut(depart_no);
+			if skew != MODULO {
+				for (l, h) in lo.iter_mut().zip(hi.iter()) {
+					*l ^= tables.mul(*h, skew);
+				}
+			}
+			for (l, h) in lo.iter().zip(hi.iter_mut()) {
+				*h ^= *l;
+			}
+		}
+		depart_no >>= 1;
+	}
+}
+
+// `inverse_fft_in_novel_poly_basis` of `data[..size]` on bytes
+fn inverse_fft(tables: &SubfieldTables, data: &mut [u8], size: usize, index: usize) {
+	let mut depart_no = 1;
+	while depart_no < size {
+		for j in (depart_no..size).step_by(depart_no << 1) {
+			let skew = tables.skew[j + index - 1];
+			let (lo, hi) = data[j - depart_no..j + depart_no].split_at_mut(depart_no);
+			for (l, h) in lo.iter().zip(hi.iter_mut()) {
+				*h ^= *l;
+			}
+			if skew != MODULO {
+				for (l, h) in lo.iter_mut().zip(hi.iter()) {
+					*l ^= tables.mul(*h, skew);
+				}
+			}
+		}
+		depart_no <<= 1;
+	}
+}
+
+// formal derivative of a polynomial of `size` coefficients in the novel basis
+fn formal_derivative(cos: &mut [u8], size: usize) {
+	for i in 1..size {
+		let length = ((i ^ (i - 1)) + 1) >> 1;
+		let (lo, hi) = cos[i - length..i + length].split_at_mut(length);
+		for (l, h) in lo.iter_mut().zip(hi.iter()) {
+			*l ^= *h;
+		}
+	}
+}
+
+// `encode_low` of a codeword holding the message in its first `k` symbols
+fn encode_codeword(tables: &SubfieldTables, codeword: &mut [u8], k: usize) {
+	let n = codeword.len();
+	let (message, parity) = codeword.split_at_mut(k);
+	let (parity, last) = parity.split_at_mut(n - 2 * k);
+	last.copy_from_slice(message);
+	inverse_fft(tables, last, k, 0);
+	for (shift, block) in (k..).step_by(k).zip(parity.chunks_exact_mut(k)) {
+		block.copy_from_slice(last);
+		fft(tables, block, k, shift);
+	}
+	fft(tables, last, k, n - k);
+}
+
+// `decode_main` of a codeword with the erased symbols zeroed
+fn decode_codeword(tables: &SubfieldTables, codeword: &mut [u8], erasures: &[bool], log_walsh2: &[u8]) {
+	let n = codeword.len();
+	for ((sym, erased), log) in codeword.iter_mut().zip(erasures).zip(log_walsh2) {
+		*sym = if *erased { 0 } else { tables.mul(*sym, *log) };
+	}
+	inverse_fft(tables, codeword, n, 0);
+
+	for (i, sym) in codeword.iter_mut().enumerate() {
+		*sym = tables.mul(*sym, MODULO - tables.b[i >> 1]);
+	}
+	formal_derivative(codeword, n);
+	for (i, sym) in codeword.iter_mut().enumerate() {
+		*sym = tables.mul(*sym, tables.b[i >> 1]);
+	}
+
+	fft(tables, codeword, n, 0);
+	for ((sym, erased), log) in codeword.iter_mut().zip(erasures).zip(log_walsh2) {
+		*sym = if *erased { tables.mul(*sym, *log) } else { 0 };
+	}
+}
+
+// the internal `(n, k)` of a subfield code
+fn subfield_params(params: &CodeParams) -> Result<(usize, usize)> {
+	if params.n() > SUBFIELD_SIZE {
+		return Err(Error::ShardCountTooHigh(params.n()));
+	}
+	super::internal_params(params)
+}
+
+// The payload byte carried by symbol `c` of data shard `idx`, like `message_symbol`
+// with one byte per symbol
+fn payload_index(params: &CodeParams, k: usize, shard_len: usize, idx: usize, c: usize) -> usize {
+	match params.layout() {
+		ShardLayout::Interleaved => super::interleaved_symbol(k, params.symbol_group(), idx, c),
+		ShardLayout::Sequential => idx * shard_len + c,
+	}
+}
+
+/// Encode `data` with one byte per symbol into `params.n()` shards, of which there are at
+/// most `SUBFIELD_SIZE`.
+pub fn encode(params: &CodeParams, data: &[u8]) -> Result<Vec<WrappedShard>> {
+	let (n, k) = subfield_params(params)?;
+	params.check_payload_len(data.len())?;
+	let tables = tables();
+
+	// every codeword carries `k` bytes of the payload, shard `i` receives symbol `i` of each
+	let group = params.symbol_group();
+	let shard_len = std::cmp::max(data.len().div_ceil(k * group), 1) * group;
+	let mut shards = vec![Vec::with_capacity(shard_len); params.n()];
+	let mut codeword = vec![0_u8; n];
+	for c in 0..shard_len {
+		for (idx, sym) in codeword[..k].iter_mut().enumerate() {
+			*sym = data.get(payload_index(params, k, shard_len, idx, c)).copied().unwrap_or_default();
+		}
+		encode_codeword(tables, &mut codeword, k);
+		for (shard, sym) in shards.iter_mut().zip(&codeword) {
+			shard.push(*sym);
+		}
+	}
+	// shards of an odd number of symbols must not be padded
+	Ok(shards.into_iter().map(WrappedShard::new_unpadded).collect())
+}
+
+/// Reconstruct the padded payload from shards encoded by `encode`.
+pub fn reconstruct(params: &CodeParams, received_shards: &[Option<&[u8]>]) -> Result<Vec<u8>> {
+	if let Some(equalized) = equalize_shard_lengths(params, received_shards) {
+		let equalized = equalized.iter().map(Option::as_deref).collect::<Vec<_>>();
+		return reconstruct(params, &equalized);
+	}
+	let (n, k) = subfield_params(params)?;
+	if received_shards.len() != params.n() {
+		return Err(Error::WrongShardCount { expected: params.n(), got: received_shards.len() });
+	}
+	let available = received_shards.iter().flatten().count();
+	if available < k {
+		return Err(Error::TooFewShards { available, required: k });
+	}
+	let mut present = received_shards.iter().flatten();
+	let shard_len = present.next().map(|shard| shard.len()).unwrap_or_default();
+	if let Some(other) = present.map(|shard| shard.len()).find(|len| *len != shard_len) {
+		return Err(Error::InconsistentShardLengths { first: shard_len, other });
+	}
+	let group = params.symbol_group();
+	if !shard_len.is_multiple_of(group) {
+		return Err(Error::UngroupedShardLength { len: shard_len, group });
+	}
+
+	// shards beyond `params.n()` are never transmitted
+	let erasures = (0..n).map(|idx| received_shards.get(idx).copied().flatten().is_none()).collect::<Vec<_>>();
+	let data_complete = !erasures[..k].contains(&true);
+	let mut log_walsh2 = vec![0; n];
+	if !data_complete {
+		// the locator evaluates to subfield elements, found once in the log domain of the full field
+		let mut full = vec![0; n];
+		super::eval_error_polynomial(erasures.iter().copied(), &mut full, n);
+		for (log, full) in log_walsh2.iter_mut().zip(&full) {
+			*log = narrow_log(*full);
+		}
+	}
+
+	let tables = tables();
+	let mut payload = vec![0_u8; shard_len * k];
+	let (mut received, mut codeword) = (vec![0_u8; n], vec![0_u8; n]);
+	for c in 0..shard_len {
+		for (sym, shard) in received.iter_mut().zip(received_shards) {
+			*sym = shard.map_or(0, |shard| shard[c]);
+		}
+		if !data_complete {
+			codeword.copy_from_slice(&received);
+			decode_codeword(tables, &mut codeword, &erasures, &log_walsh2);
+		}
+		for idx in 0..k {
+			let sym = if erasures[idx] { codeword[idx] } else { received[idx] };
+			payload[payload_index(params, k, shard_len, idx, c)] = sym;
+		}
+	}
+	Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::workload::random_payload;
+
+	#[test]
+	fn bytes_encode_in_the_subfield() {
+		let tables = Tables::get();
+		for a in 0..=u8::MAX {
+			assert_eq!(extract(embed(a)), Some(a));
+			if a != 0 {
+				assert_eq!(tables.log(embed(a)) % LOG_STEP, 0, "{} is no subfield element", a);
+				assert_eq!(exp(log(a)), a);
+			}
+			for log_b in [0, 1, 77, 254] {
+				let full = tables.mul(embed(a), u16::from(log_b) * LOG_STEP);
+				assert_eq!(Some(mul(a, log_b)), extract(full));
+			}
+		}
+		assert_eq!(extract(256), None);
+
+		let payload = random_payload(1000, 0);
+		for (n, k) in [(20, 6), (256, 64)] {
+			let params = CodeParams::new(n, k).unwrap();
+			let shards = encode(&params, &payload).unwrap();
+			let shard_len = super::super::required_shard_len(&params, 2 * payload.len()).unwrap();
+			assert!(shards.iter().all(|shard| AsRef::<[u8]>::as_ref(shard).len() * 2 == shard_len));
+			let mut received = shards.iter().map(|shard| Some(AsRef::<[u8]>::as_ref(shard))).collect::<Vec<_>>();
+			received.iter_mut().skip(1).take(n - k).for_each(|shard| *shard = None);
+			assert_eq!(&reconstruct(&params, &received).unwrap()[..payload.len()], &payload[..]);
+		}
+		// 25 symbols per shard
+		let params = CodeParams::new(12, 4).unwrap();
+		let shards = encode(&params, &payload[..100]).unwrap();
+		assert!(shards.iter().all(|shard| AsRef::<[u8]>::as_ref(shard).len() == 25));
+		let mut received = shards.iter().map(|shard| Some(AsRef::<[u8]>::as_ref(shard))).collect::<Vec<_>>();
+		received.iter_mut().take(8).for_each(|shard| *shard = None);
+		assert_eq!(&reconstruct(&params, &received).unwrap()[..100], &payload[..100]);
+
+		let params = CodeParams::new(257, 64).unwrap();
+		assert_eq!(encode(&params, &payload), Err(Error::ShardCountTooHigh(257)));
+	}
+
+	#[test]
+	fn shards_match_the_full_field() {
+		use crate::Endianness;
+		use std::num::NonZeroUsize;
+
+		let payload = random_payload(999, 1);
+		let grouped = NonZeroUsize::new(3).unwrap();
+		for params in [
+			CodeParams::new(20, 6).unwrap(),
+			CodeParams::new(100, 33).unwrap().with_layout(ShardLayout::Sequential),
+			CodeParams::new(64, 16).unwrap().with_symbol_group(grouped),
+			CodeParams::new(256, 128).unwrap(),
+		] {
+			let shards = encode(&params, &payload).unwrap();
+
+			// the payload widened to one byte per symbol
+			let widened = payload.iter().flat_map(|&byte| embed(byte).to_le_bytes()).collect::<Vec<_>>();
+			let full = super::super::encode_with_params(&params.with_endianness(Endianness::Little), &widened);
+			for (shard, full) in shards.iter().zip(full.unwrap()) {
+				let full = AsRef::<[u8]>::as_ref(&full);
+				let narrowed = full.chunks_exact(2).map(|pair| extract(u16::from_le_bytes([pair[0], pair[1]])));
+				let narrowed = narrowed.collect::<Option<Vec<_>>>();
+				assert_eq!(narrowed.as_deref(), Some(AsRef::<[u8]>::as_ref(shard)), "{:?}", params);
+			}
+
+			let (_n, k) = super::super::internal_params(&params).unwrap();
+			for erased in [0, 1, params.n() - k] {
+				let mut received = shards.iter().map(|shard| Some(AsRef::<[u8]>::as_ref(shard))).collect::<Vec<_>>();
+				received.iter_mut().take(erased).for_each(|shard| *shard = None);
+				let reconstructed = reconstruct(&params, &received).unwrap();
+				assert_eq!(&reconstructed[..payload.len()], &payload[..], "{:?} erased {}", params, erased);
+			}
+		}
+	}
+}
@@ -0,0 +1,135 @@
+//! Order of the encoded shards, i.e. the assignment of shards to validators.
+//!
+//! The permutation is applied to the shard vector inside `encode_ordered`,
+//! moving shards without copying their content.
+
+use super::*;
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+/// Order of the shards returned by `encode_ordered`.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub enum ShardOrder {
+	/// The data shards followed by the parity shards.
+	#[default]
+	Systematic,
+	/// Position `i` holds the systematic shard `permutation[i]`.
+	Permuted(Vec<ChunkIndex>),
+}
+
+impl ShardOrder {
+	/// A pseudo random permutation of `n` shards, deterministic in `seed`,
+	/// e.g. derived from the relay parent.
+	pub fn shuffled(n: usize, seed: u64) -> Self {
+		let mut permutation = (0..n as u32).map(ChunkIndex).collect::<Vec<_>>();
+		permutation.shuffle(&mut StdRng::seed_from_u64(seed));
+		ShardOrder::Permuted(permutation)
+	}
+
+	/// Check that this order is a permutation of `n` shards.
+	pub fn validate(&self, n: usize) -> Result<()> {
+		if let ShardOrder::Permuted(permutation) = self {
+			if permutation.len() != n {
+				return Err(Error::WrongShardCount { expected: n, got: permutation.len() });
+			}
+			let mut seen = vec![false; n];
+			for index in permutation {
+				let idx = index.position();
+				if idx >= n {
+					return Err(Error::ChunkIndexOutOfRange { index: idx, n });
+				}
+				if std::mem::replace(&mut seen[idx], true) {
+					return Err(Error::DuplicateChunkIndex(idx));
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Reorder shards given in systematic order.
+	pub fn apply<T>(&self, shards: Vec<T>) -> Result<Vec<T>> {
+		self.validate(shards.len())?;
+		match self {
+			ShardOrder::Systematic => Ok(shards),
+			ShardOrder::Permuted(permutation) => {
+				let mut shards = shards.into_iter().map(Some).collect::<Vec<_>>();
+				Ok(permutation
+					.iter()
+					.map(|index| shards[index.position()].take().expect("permutation was validated. qed"))
+					.collect())
+			}
+		}
+	}
+
+	/// Inverse of `apply`, restoring the systematic order of received shards.
+	pub fn restore<T>(&self, received_shards: Vec<Option<T>>) -> Result<Vec<Option<T>>> {
+		self.validate(received_shards.len())?;
+		match self {
+			ShardOrder::Systematic => Ok(received_shards),
+			ShardOrder::Permuted(permutation) => {
+				let mut restored = (0..received_shards.len()).map(|_| None).collect::<Vec<Option<T>>>();
+				for (index, shard) in permutation.iter().zip(received_shards) {
+					restored[index.position()] = shard;
+				}
+				Ok(restored)
+			}
+		}
+	}
+}
+
+/// Encode with the backend picked by the default `Selector`, returning the shards in `order`.
+pub fn encode_ordered(params: &CodeParams, data: &[u8], order: &ShardOrder) -> Result<Vec<WrappedShard>> {
+	order.validate(params.n())?;
+	order.apply(auto::encode(params, data)?)
+}
+
+/// Reconstruct from shards received in `order`.
+pub fn reconstruct_ordered(
+	params: &CodeParams,
+	received_shards: Vec<Option<WrappedShard>>,
+	order: &ShardOrder,
+) -> Result<Vec<u8>> {
+	auto::reconstruct(params, order.restore(received_shards)?)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn permuted_roundtrip() {
+		let payload = &random_payload(1000, 0);
+		for &(n, k) in &[(16, 5), (200, 67)] {
+			let params = CodeParams::new(n, k).unwrap();
+			let systematic = auto::encode(&params, payload).unwrap();
+			let order = ShardOrder::shuffled(n, 42);
+			assert_eq!(order, ShardOrder::shuffled(n, 42));
+
+			let shards = encode_ordered(&params, payload, &order).unwrap();
+			let ShardOrder::Permuted(permutation) = &order else { unreachable!() };
+			for (shard, index) in shards.iter().zip(permutation) {
+				assert_eq!(shard, &systematic[index.position()]);
+			}
+
+			let received =
+				shards.into_iter().enumerate().map(|(i, s)| if i % 3 == 0 { Some(s) } else { None }).collect();
+			assert_eq!(&reconstruct_ordered(&params, received, &order).unwrap()[..payload.len()], payload);
+		}
+
+		let params = CodeParams::new(4, 2).unwrap();
+		assert_eq!(
+			encode_ordered(&params, payload, &ShardOrder::Systematic).unwrap(),
+			auto::encode(&params, payload).unwrap()
+		);
+		let bad = |idx: &[u32]| ShardOrder::Permuted(idx.iter().map(|idx| ChunkIndex(*idx)).collect());
+		assert_eq!(
+			encode_ordered(&params, payload, &bad(&[0, 1, 2])),
+			Err(Error::WrongShardCount { expected: 4, got: 3 })
+		);
+		assert_eq!(encode_ordered(&params, payload, &bad(&[0, 1, 2, 2])), Err(Error::DuplicateChunkIndex(2)));
+		assert_eq!(
+			encode_ordered(&params, payload, &bad(&[0, 1, 2, 4])),
+			Err(Error::ChunkIndexOutOfRange { index: 4, n: 4 })
+		);
+	}
+}
@@ -0,0 +1,257 @@
+//! Packing many small payloads into one set of shards.
+//!
+//! Erasure coding each tiny message, e.g. a statement, separately is dominated
+//! by the per message overhead of padding and transforms. Instead the payloads
+//! are concatenated behind an index of their lengths and encoded once with the
+//! novel poly basis. Since the code is systematic, a single payload is read
+//! straight from the data shards covering it, if they were received.
+//!
+//! The index is the number of payloads followed by the length of each, all as
+//! little endian `u32`.
+//!
+//! Conversely `split_encode` spreads one payload over several independent codes, e.g.
+//! to send them via different overlays, each part packed alone so its length is kept.
+
+use super::*;
+
+use std::convert::{TryFrom, TryInto};
+use std::num::NonZeroUsize;
+use std::ops::Range;
+
+const LEN_BYTES: usize = 4;
+
+/// Concatenate `payloads` behind their index, as encoded by `encode`.
+///
+/// Fails if a payload is too long for its `u32` length.
+pub fn pack(payloads: &[&[u8]]) -> Result<Vec<u8>> {
+	let total = payloads.iter().map(|payload| payload.len()).sum::<usize>();
+	let mut packed = Vec::with_capacity(LEN_BYTES * (payloads.len() + 1) + total);
+	packed.extend_from_slice(&write_len(payloads.len())?);
+	for payload in payloads {
+		packed.extend_from_slice(&write_len(payload.len())?);
+	}
+	for payload in payloads {
+		packed.extend_from_slice(payload);
+	}
+	Ok(packed)
+}
+
+/// Split a packed payload, possibly with trailing padding, into the original payloads.
+pub fn unpack(packed: &[u8]) -> Result<Vec<Vec<u8>>> {
+	let count = read_len(packed, 0)?;
+	(0..count).map(|index| payload_range(packed, count, index).map(|range| packed[range].to_vec())).collect()
+}
+
+/// Encode `payloads` into `params.n()` shards of a single code.
+pub fn encode(params: &CodeParams, payloads: &[&[u8]]) -> Result<Vec<WrappedShard>> {
+	novel_poly_basis::encode_with_params(params, &pack(payloads)?)
+}
+
+/// Reconstruct all payloads packed by `encode`.
+pub fn reconstruct_all(params: &CodeParams, received_shards: &[Option<&[u8]>]) -> Result<Vec<Vec<u8>>> {
+	unpack(&novel_poly_basis::reconstruct_ref_with_params(params, received_shards)?)
+}
+
+/// Reconstruct only the payload at `index` of those packed by `encode`.
+///
+/// If the data shards holding the index and the payload were received, the payload is
+/// copied out of them without decoding, otherwise the whole packed payload is decoded.
+pub fn reconstruct_one(params: &CodeParams, received_shards: &[Option<&[u8]>], index: usize) -> Result<Vec<u8>> {
+	if received_shards.len() != params.n() {
+		return Err(Error::WrongShardCount { expected: params.n(), got: received_shards.len() });
+	}
+	let data = DataShards::new(params, received_shards)?;
+	let direct = || -> Result<Option<Vec<u8>>> {
+		let count = match data.read(0..LEN_BYTES)? {
+			Some(bytes) => read_len(&bytes, 0)?,
+			None => return Ok(None),
+		};
+		if index >= count {
+			return Err(Error::PayloadIndexOutOfRange { index, count });
+		}
+		let lens = match data.read(LEN_BYTES..LEN_BYTES * (count + 1))? {
+			Some(lens) => lens,
+			None => return Ok(None),
+		};
+		let preceding = (0..index).map(|i| read_len(&lens, i * LEN_BYTES)).sum::<Result<usize>>()?;
+		let start = LEN_BYTES * (count + 1) + preceding;
+		data.read(start..start + read_len(&lens, index * LEN_BYTES)?)
+	};
+	if let Some(payload) = direct()? {
+		return Ok(payload);
+	}
+
+	let packed = novel_poly_basis::reconstruct_ref_with_params(params, received_shards)?;
+	let count = read_len(&packed, 0)?;
+	Ok(packed[payload_range(&packed, count, index)?].to_vec())
+}
+
+/// Split `data` into `parts` contiguous parts and encode each into its own `params.n()` shards.
+pub fn split_encode(params: &CodeParams, data: &[u8], parts: NonZeroUsize) -> Result<Vec<Vec<WrappedShard>>> {
+	let part_len = std::cmp::max(data.len().div_ceil(parts.get()), 1);
+	let mut chunks = data.chunks(part_len).collect::<Vec<_>>();
+	chunks.resize(parts.get(), &[]);
+	chunks.into_iter().map(|chunk| encode(params, &[chunk])).collect()
+}
+
+/// Reconstruct the payload encoded by `split_encode` from the received shards of each part,
+/// in order, every part decoded independently of the others.
+pub fn split_reconstruct(params: &CodeParams, received_parts: &[Vec<Option<&[u8]>>]) -> Result<Vec<u8>> {
+	let mut data = Vec::new();
+	for received_shards in received_parts {
+		match &reconstruct_all(params, received_shards)?[..] {
+			[part] => data.extend_from_slice(part),
+			_ => return Err(Error::BadPayload),
+		}
+	}
+	Ok(data)
+}
+
+// `len` as index entry
+fn write_len(len: usize) -> Result<[u8; LEN_BYTES]> {
+	let max = u32::MAX as usize;
+	u32::try_from(len).map(u32::to_le_bytes).map_err(|_| Error::PayloadTooLarge { len, max })
+}
+
+// the `u32` at byte `at` of `packed`
+fn read_len(packed: &[u8], at: usize) -> Result<usize> {
+	let bytes = packed.get(at..at + LEN_BYTES).ok_or(Error::BadPayload)?;
+	Ok(u32::from_le_bytes(bytes.try_into().expect("slice has 4 bytes. qed")) as usize)
+}
+
+// the bytes of payload `index` of the `count` packed ones
+fn payload_range(packed: &[u8], count: usize, index: usize) -> Result<Range<usize>> {
+	if index >= count {
+		return Err(Error::PayloadIndexOutOfRange { index, count });
+	}
+	let mut start = LEN_BYTES * (count + 1);
+	if start > packed.len() {
+		return Err(Error::BadPayload);
+	}
+	for i in 0..index {
+		start += read_len(packed, LEN_BYTES * (i + 1))?;
+	}
+	let end = start + read_len(packed, LEN_BYTES * (index + 1))?;
+	if end > packed.len() {
+		return Err(Error::BadPayload);
+	}
+	Ok(start..end)
+}
+
+// The received data shards of the novel poly basis, addressed by payload bytes.
+struct DataShards<'a> {
+	params: &'a CodeParams,
+	received_shards: &'a [Option<&'a [u8]>],
+	k: usize,
+	group: usize,
+	shard_len: usize,
+}
+
+impl<'a> DataShards<'a> {
+	fn new(params: &'a CodeParams, received_shards: &'a [Option<&'a [u8]>]) -> Result<Self> {
+		let (_n, k) = novel_poly_basis::internal_params(params)?;
+		let shard_len = received_shards.iter().flatten().map(|shard| shard.len()).next().unwrap_or_default();
+		Ok(DataShards { params, received_shards, k, group: params.symbol_group(), shard_len })
+	}
+
+	// the payload bytes `range`, `None` if a data shard holding them is missing or
+	// the range lies beyond the shards, left to the decoder to report
+	fn read(&self, range: Range<usize>) -> Result<Option<Vec<u8>>> {
+		if range.end > novel_poly_basis::padded_len(self.k, self.shard_len) {
+			return Ok(None);
+		}
+		let symbols = self.shard_len / 2;
+		Ok(range
+			.map(|at| {
+				let (sym, byte) = (at / 2, at % 2);
+				let (shard, offset) = match self.params.layout() {
+					ShardLayout::Interleaved => {
+						let element = sym / self.group;
+						(element % self.k, ((element / self.k) * self.group + sym % self.group) * 2 + byte)
+					}
+					ShardLayout::Sequential => (sym / symbols, (sym % symbols) * 2 + byte),
+				};
+				self.received_shards[shard].filter(|shard| shard.len() == self.shard_len).map(|shard| shard[offset])
+			})
+			.collect::<Option<Vec<u8>>>())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn single_payloads_with_and_without_data_shards() {
+		let bytes = random_payload(1000, 0);
+		let payloads = (0..50).map(|i| &bytes[i * 7..i * 7 + i % 13]).collect::<Vec<_>>();
+		let group = std::num::NonZeroUsize::new(5).unwrap();
+		for params in [
+			CodeParams::new(24, 8).unwrap(),
+			CodeParams::new(24, 8).unwrap().with_layout(ShardLayout::Sequential),
+			CodeParams::new(24, 8).unwrap().with_symbol_group(group),
+		] {
+			let shards = encode(&params, &payloads).unwrap();
+			let mut received = shards.iter().map(|shard| Some(shard.as_ref())).collect::<Vec<Option<&[u8]>>>();
+			assert_eq!(reconstruct_all(&params, &received).unwrap(), payloads);
+			for (index, payload) in payloads.iter().enumerate() {
+				assert_eq!(&reconstruct_one(&params, &received, index).unwrap(), payload);
+			}
+
+			// data shards lost, decoded instead
+			for idx in [1, 2, 5, 9, 10, 20] {
+				received[idx] = None;
+			}
+			assert_eq!(reconstruct_all(&params, &received).unwrap(), payloads);
+			for (index, payload) in payloads.iter().enumerate() {
+				assert_eq!(&reconstruct_one(&params, &received, index).unwrap(), payload);
+			}
+			assert_eq!(
+				reconstruct_one(&params, &received, 50),
+				Err(Error::PayloadIndexOutOfRange { index: 50, count: 50 })
+			);
+			assert_eq!(
+				reconstruct_one(&params, &[None; 24], 0),
+				Err(Error::TooFewShards { available: 0, required: 8 })
+			);
+		}
+	}
+
+	#[test]
+	fn split_codes_roundtrip() {
+		let params = CodeParams::new(12, 4).unwrap();
+		let parts = NonZeroUsize::new(3).unwrap();
+		for data in [&random_payload(1001, 0), &random_payload(2, 0), &[][..]] {
+			let shards = split_encode(&params, data, parts).unwrap();
+			assert_eq!(shards.len(), 3);
+			// a different subset of shards per part, as if received via different overlays
+			let received = shards
+				.iter()
+				.enumerate()
+				.map(|(part, shards)| {
+					let shards = shards.iter().enumerate();
+					shards.map(|(idx, shard)| Some(shard.as_ref()).filter(|_| idx % 3 == part)).collect::<Vec<_>>()
+				})
+				.collect::<Vec<_>>();
+			assert_eq!(split_reconstruct(&params, &received).unwrap(), data);
+		}
+
+		let one = split_encode(&params, &random_payload(100, 0), NonZeroUsize::MIN).unwrap();
+		assert_eq!(one, vec![encode(&params, &[&random_payload(100, 0)]).unwrap()]);
+		let packed = encode(&params, &[b"ab", b"c"]).unwrap();
+		let received = vec![packed.iter().map(|shard| Some(shard.as_ref())).collect::<Vec<_>>()];
+		assert_eq!(split_reconstruct(&params, &received), Err(Error::BadPayload));
+	}
+
+	#[test]
+	fn rejects_malformed_index() {
+		assert_eq!(
+			unpack(&pack(&[b"ab", b"", b"cde"]).unwrap()).unwrap(),
+			vec![b"ab".to_vec(), vec![], b"cde".to_vec()]
+		);
+		assert_eq!(unpack(&[1, 0]), Err(Error::BadPayload));
+		assert_eq!(unpack(&[2, 0, 0, 0, 1, 0, 0, 0]), Err(Error::BadPayload));
+		assert_eq!(unpack(&[1, 0, 0, 0, 9, 0, 0, 0, 1]), Err(Error::BadPayload));
+		assert_eq!(unpack(&[0; 16]).unwrap(), Vec::<Vec<u8>>::new());
+	}
+}
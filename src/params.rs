@@ -0,0 +1,405 @@
+use super::*;
+
+use std::borrow::Cow;
+use std::num::NonZeroUsize;
+use std::ops::Range;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Byte order of the 2 byte field symbols, both when packing the payload
+/// into symbols and when serializing symbols into shards.
+///
+/// Only the novel poly basis backend operates on `u16` symbols, the
+/// status quo backend treats shards as opaque pairs of bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Endianness {
+	#[default]
+	Little,
+	Big,
+}
+
+impl Endianness {
+	/// Combine two bytes into one symbol.
+	#[inline(always)]
+	pub fn symbol_from_bytes(self, bytes: [u8; 2]) -> u16 {
+		match self {
+			Endianness::Little => u16::from_le_bytes(bytes),
+			Endianness::Big => u16::from_be_bytes(bytes),
+		}
+	}
+
+	/// Split one symbol into two bytes.
+	#[inline(always)]
+	pub fn symbol_to_bytes(self, symbol: u16) -> [u8; 2] {
+		match self {
+			Endianness::Little => symbol.to_le_bytes(),
+			Endianness::Big => symbol.to_be_bytes(),
+		}
+	}
+}
+
+/// Assignment of payload symbols to data shards, once a payload spans multiple codewords.
+///
+/// Only applies to the novel poly basis backend, the status quo backend
+/// always uses the sequential layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ShardLayout {
+	/// Codeword `c` carries the payload symbols `c * k..(c + 1) * k`, so payload
+	/// symbols are spread round-robin over the data shards. A burst of lost
+	/// shards thus affects evenly spread symbols.
+	#[default]
+	Interleaved,
+	/// Each data shard is a contiguous slice of the payload, which allows
+	/// partial reads from data shards without decoding.
+	Sequential,
+}
+
+/// Treatment of received shards of different lengths on reconstruction, i.e. trailing bytes
+/// lost or appended by a faulty transport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ShardLengthPolicy {
+	/// Reject them with `Error::InconsistentShardLengths`.
+	#[default]
+	Strict,
+	/// Cut all shards to the shortest one, dropping the tail of the payload.
+	TruncateToMin,
+	/// Extend all shards with zeros to the longest one, which recovers the payload
+	/// if the missing bytes were zero padding.
+	PadToMax,
+}
+
+/// Largest number of shards, the size of the field GF(2^16) of both backends.
+pub const MAX_SHARDS: usize = 1 << 16;
+
+/// Largest shard in bytes, shards are SCALE encoded with a `u32` length.
+pub const MAX_SHARD_LEN: usize = u32::MAX as usize & !1;
+
+/// Number of shards required to recover the payload when less than
+/// one third of the `n` shard holders may be faulty.
+pub fn recovery_threshold(n: usize) -> Result<usize> {
+	if n > MAX_SHARDS {
+		return Err(Error::ShardCountTooHigh(n));
+	}
+	if n <= 1 {
+		return Err(Error::ShardCountTooLow { n, k: 1 });
+	}
+	Ok((n - 1) / 3 + 1)
+}
+
+/// Parameters of an erasure code, `n` shards in total of which
+/// any `k` suffice to reconstruct the payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(try_from = "RawCodeParams"))]
+pub struct CodeParams {
+	n: usize,
+	k: usize,
+	endianness: Endianness,
+	layout: ShardLayout,
+	group: NonZeroUsize,
+	local_group: Option<NonZeroUsize>,
+	length_policy: ShardLengthPolicy,
+}
+
+// the serialized form of `CodeParams`, deserialized parameters are validated by `CodeParams::new`
+// and missing fields take the defaults of the builder
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct RawCodeParams {
+	n: usize,
+	k: usize,
+	#[serde(default)]
+	endianness: Endianness,
+	#[serde(default)]
+	layout: ShardLayout,
+	#[serde(default = "ungrouped")]
+	group: NonZeroUsize,
+	#[serde(default)]
+	local_group: Option<NonZeroUsize>,
+	#[serde(default)]
+	length_policy: ShardLengthPolicy,
+}
+
+#[cfg(feature = "serde")]
+impl std::convert::TryFrom<RawCodeParams> for CodeParams {
+	type Error = Error;
+
+	fn try_from(raw: RawCodeParams) -> Result<Self> {
+		let params = CodeParams::new(raw.n, raw.k)?
+			.with_endianness(raw.endianness)
+			.with_layout(raw.layout)
+			.with_symbol_group(raw.group)
+			.with_length_policy(raw.length_policy);
+		Ok(CodeParams { local_group: raw.local_group, ..params })
+	}
+}
+
+// a single symbol per shard element
+const fn ungrouped() -> NonZeroUsize {
+	NonZeroUsize::MIN
+}
+
+impl CodeParams {
+	/// Create validated code parameters.
+	pub const fn new(n: usize, k: usize) -> Result<Self> {
+		if k < 1 {
+			return Err(Error::DataShardCountTooLow(k));
+		}
+		if n <= k {
+			return Err(Error::ShardCountTooLow { n, k });
+		}
+		if n > MAX_SHARDS {
+			return Err(Error::ShardCountTooHigh(n));
+		}
+		Ok(Self {
+			n,
+			k,
+			endianness: Endianness::Little,
+			layout: ShardLayout::Interleaved,
+			group: ungrouped(),
+			local_group: None,
+			length_policy: ShardLengthPolicy::Strict,
+		})
+	}
+
+	/// Use `endianness` for symbol serialization.
+	pub fn with_endianness(mut self, endianness: Endianness) -> Self {
+		self.endianness = endianness;
+		self
+	}
+
+	/// Use `layout` to assign payload symbols to shards.
+	pub fn with_layout(mut self, layout: ShardLayout) -> Self {
+		self.layout = layout;
+		self
+	}
+
+	/// Use `group` consecutive symbols per shard element, see `symbol_group`.
+	pub fn with_symbol_group(mut self, group: NonZeroUsize) -> Self {
+		self.group = group;
+		self
+	}
+
+	/// Split the shards into local groups of `size` consecutive shards, each with a local
+	/// parity shard, see `lrc`.
+	pub fn with_local_groups(mut self, size: NonZeroUsize) -> Self {
+		self.local_group = Some(size);
+		self
+	}
+
+	/// Use `policy` for received shards of different lengths.
+	pub fn with_length_policy(mut self, policy: ShardLengthPolicy) -> Self {
+		self.length_policy = policy;
+		self
+	}
+
+	/// Total number of shards.
+	pub fn n(&self) -> usize {
+		self.n
+	}
+
+	/// Number of data shards, required to reconstruct.
+	pub fn k(&self) -> usize {
+		self.k
+	}
+
+	/// Byte order of symbols.
+	pub fn endianness(&self) -> Endianness {
+		self.endianness
+	}
+
+	/// Assignment of payload symbols to shards.
+	pub fn layout(&self) -> ShardLayout {
+		self.layout
+	}
+
+	/// Consecutive symbols per shard element, `1` unless set.
+	///
+	/// Shards are a multiple of `group` symbols long and the interleaved layout
+	/// spreads the payload over the data shards in runs of `group` symbols, like
+	/// the shards of many symbols of reed-solomon-erasure. So the granularity of
+	/// shards is chosen independently of the field size.
+	///
+	/// Only applies to the novel poly basis backend.
+	pub fn symbol_group(&self) -> usize {
+		self.group.get()
+	}
+
+	/// Treatment of received shards of different lengths, `Strict` unless set.
+	pub fn length_policy(&self) -> ShardLengthPolicy {
+		self.length_policy
+	}
+
+	/// Layout of the local groups, if set.
+	///
+	/// Only applies to `lrc`, the backends encode and reconstruct the `n` shards
+	/// of the global code alone.
+	pub fn local_groups(&self) -> Option<LocalGroups> {
+		self.local_group.map(|size| LocalGroups { n: self.n, size: size.get().min(self.n) })
+	}
+
+	/// Largest payload in bytes, such that no shard exceeds `MAX_SHARD_LEN`.
+	///
+	/// The novel poly basis carries `k` rounded down to a power of two symbols
+	/// per codeword, in whole symbol groups, so the limit holds for both backends.
+	pub const fn max_payload_len(&self) -> usize {
+		let group_len = self.group.get() * 2;
+		(MAX_SHARD_LEN / group_len * group_len).saturating_mul(1 << self.k.ilog2())
+	}
+
+	// reject payloads beyond `max_payload_len`
+	pub(crate) fn check_payload_len(&self, len: usize) -> Result<()> {
+		let max = self.max_payload_len();
+		if len > max {
+			return Err(Error::PayloadTooLarge { len, max });
+		}
+		Ok(())
+	}
+}
+
+// The received shards brought to a common length by the length policy of `params`, `None` if
+// they already share one or the policy is strict, leaving the mismatch to the backend to report.
+pub(crate) fn equalize_shard_lengths<'a>(
+	params: &CodeParams,
+	received_shards: &[Option<&'a [u8]>],
+) -> Option<Vec<Option<Cow<'a, [u8]>>>> {
+	let lens = received_shards.iter().flatten().map(|shard| shard.len());
+	let (min, max) = (lens.clone().min()?, lens.max()?);
+	let len = match params.length_policy() {
+		_ if min == max => return None,
+		ShardLengthPolicy::Strict => return None,
+		ShardLengthPolicy::TruncateToMin => min,
+		ShardLengthPolicy::PadToMax => max,
+	};
+	let equalized = received_shards.iter().map(|shard| {
+		shard.map(|shard| match shard.get(..len) {
+			Some(truncated) => Cow::Borrowed(truncated),
+			None => {
+				let mut padded = shard.to_vec();
+				padded.resize(len, 0);
+				Cow::Owned(padded)
+			}
+		})
+	});
+	Some(equalized.collect())
+}
+
+/// The `n` shards of the global code in groups of `size` consecutive shards, the last
+/// one possibly smaller. The local parity shards follow the global ones, in group order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalGroups {
+	n: usize,
+	size: usize,
+}
+
+impl LocalGroups {
+	/// Number of global shards per group.
+	pub fn size(&self) -> usize {
+		self.size
+	}
+
+	/// Number of groups, which is also the number of local parity shards.
+	pub fn count(&self) -> usize {
+		self.n.div_ceil(self.size)
+	}
+
+	/// Number of global and local parity shards.
+	pub fn total_shards(&self) -> usize {
+		self.n + self.count()
+	}
+
+	/// Global shards of `group`.
+	pub fn members(&self, group: usize) -> Range<usize> {
+		(group * self.size).min(self.n)..((group + 1) * self.size).min(self.n)
+	}
+
+	/// Index of the local parity shard of `group`.
+	pub fn parity_index(&self, group: usize) -> usize {
+		self.n + group
+	}
+
+	/// Group of the global or local parity shard at `index`.
+	pub fn group_of(&self, index: usize) -> Option<usize> {
+		match index {
+			_ if index < self.n => Some(index / self.size),
+			_ if index < self.total_shards() => Some(index - self.n),
+			_ => None,
+		}
+	}
+
+	/// The shards that recover the shard at `index` locally, the rest of its group.
+	pub fn repair_set(&self, index: usize) -> Option<Vec<usize>> {
+		let group = self.group_of(index)?;
+		let group_shards = self.members(group).chain(std::iter::once(self.parity_index(group)));
+		Some(group_shards.filter(|other| *other != index).collect())
+	}
+}
+
+impl Default for CodeParams {
+	fn default() -> Self {
+		Self {
+			n: N_VALIDATORS,
+			k: DATA_SHARDS,
+			endianness: Endianness::default(),
+			layout: ShardLayout::default(),
+			group: ungrouped(),
+			local_group: None,
+			length_policy: ShardLengthPolicy::default(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn recovery_threshold_tolerates_a_third() {
+		assert_eq!(recovery_threshold(1), Err(Error::ShardCountTooLow { n: 1, k: 1 }));
+		assert_eq!(recovery_threshold(4), Ok(2));
+		assert_eq!(recovery_threshold(MAX_SHARDS), Ok(21846));
+		assert_eq!(recovery_threshold(MAX_SHARDS + 1), Err(Error::ShardCountTooHigh(MAX_SHARDS + 1)));
+	}
+
+	#[test]
+	fn limits_are_enforced() {
+		let params = CodeParams::new(MAX_SHARDS, 3).expect("within the field");
+		assert_eq!(CodeParams::new(MAX_SHARDS + 1, 3), Err(Error::ShardCountTooHigh(MAX_SHARDS + 1)));
+
+		// 2 data shards of the novel poly basis
+		let max = params.max_payload_len();
+		assert_eq!(max, MAX_SHARD_LEN.saturating_mul(2));
+		assert!(params.check_payload_len(max).is_ok());
+		assert_eq!(params.check_payload_len(max + 1), Err(Error::PayloadTooLarge { len: max + 1, max }));
+		let grouped = params.with_symbol_group(NonZeroUsize::new(3).unwrap());
+		assert_eq!(grouped.max_payload_len() % 6, 0);
+		assert!(grouped.max_payload_len() <= max);
+	}
+
+	#[test]
+	fn local_groups_partition_shards() {
+		let params = CodeParams::new(10, 4).unwrap();
+		assert_eq!(params.local_groups(), None);
+		let groups = params.with_local_groups(NonZeroUsize::new(4).unwrap()).local_groups().unwrap();
+		assert_eq!((groups.size(), groups.count(), groups.total_shards()), (4, 3, 13));
+		assert_eq!(groups.members(2), 8..10);
+		assert_eq!(groups.parity_index(2), 12);
+		let group_of = [0, 3, 4, 9, 10, 12, 13].map(|index| groups.group_of(index));
+		assert_eq!(group_of, [Some(0), Some(0), Some(1), Some(2), Some(0), Some(2), None]);
+		assert_eq!(groups.repair_set(9), Some(vec![8, 12]));
+		assert_eq!(groups.repair_set(11), Some(vec![4, 5, 6, 7]));
+		let single = params.with_local_groups(NonZeroUsize::new(16).unwrap()).local_groups().unwrap();
+		assert_eq!((single.size(), single.count()), (10, 1));
+	}
+
+	#[test]
+	fn endianness_is_explicit() {
+		assert_eq!(Endianness::Little.symbol_from_bytes([0x01, 0x02]), 0x0201);
+		assert_eq!(Endianness::Big.symbol_from_bytes([0x01, 0x02]), 0x0102);
+		assert_eq!(Endianness::Little.symbol_to_bytes(0x0201), [0x01, 0x02]);
+		assert_eq!(Endianness::Big.symbol_to_bytes(0x0102), [0x01, 0x02]);
+	}
+}
@@ -0,0 +1,67 @@
+//! Synthetic payloads resembling Polkadot proofs of validity.
+//!
+//! A PoV consists mostly of storage proof trie nodes, which are dominated by
+//! incompressible 32 byte hashes, and of the block with its SCALE encoded
+//! extrinsics, which repeat a few account ids and carry small, zero padded
+//! integers. The generated payloads mix both, so unlike the uniformly
+//! random `workload::random_payload` they are partially compressible.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Typical PoV sizes, from a moderately to a fully used block.
+pub const POV_SIZES: &[usize] = &[2 << 20, 5 << 20];
+
+// number of distinct accounts appearing in extrinsics
+const ACCOUNTS: usize = 64;
+
+/// Generate a PoV-like payload of exactly `len` bytes, deterministic in `seed`.
+pub fn pov_payload(len: usize, seed: u64) -> Vec<u8> {
+	let mut rng = StdRng::seed_from_u64(seed);
+	let accounts = (0..ACCOUNTS).map(|_| rng.gen::<[u8; 32]>()).collect::<Vec<_>>();
+
+	let mut payload = Vec::with_capacity(len + 64);
+	while payload.len() < len {
+		match rng.gen_range(0..10) {
+			// trie branch node: header, bitmap and a number of child hashes
+			0..=3 => {
+				payload.extend_from_slice(&[0x80, rng.gen(), rng.gen()]);
+				for _ in 0..rng.gen_range(1..=4) {
+					payload.extend_from_slice(&rng.gen::<[u8; 32]>());
+				}
+			}
+			// transfer-like extrinsic: signer, destination, balance and nonce
+			4..=7 => {
+				payload.extend_from_slice(&[0x84, 0x00]);
+				payload.extend_from_slice(&accounts[rng.gen_range(0..ACCOUNTS)]);
+				payload.extend_from_slice(&accounts[rng.gen_range(0..ACCOUNTS)]);
+				payload.extend_from_slice(&(rng.gen_range(0..1_000_000_u128) * 1_000_000_000).to_le_bytes());
+				payload.extend_from_slice(&rng.gen_range(0..1024_u32).to_le_bytes());
+			}
+			// trie leaf with a small, zero padded value
+			_ => {
+				payload.extend_from_slice(&[0x40, rng.gen()]);
+				payload.extend_from_slice(&rng.gen_range(0..u16::MAX).to_le_bytes());
+				payload.extend_from_slice(&[0_u8; 14]);
+			}
+		}
+	}
+	payload.truncate(len);
+	payload
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn deterministic_and_structured() {
+		let payload = pov_payload(100_000, 7);
+		assert_eq!(payload.len(), 100_000);
+		assert_eq!(payload, pov_payload(100_000, 7));
+		assert_ne!(payload, pov_payload(100_000, 8));
+
+		// far more zeros than in uniformly random data
+		let zeros = payload.iter().filter(|b| **b == 0).count();
+		assert!(zeros > payload.len() / 10);
+	}
+}
@@ -0,0 +1,85 @@
+//! Per phase timing breakdown of encode and reconstruct.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// A phase of the novel poly basis encode or reconstruct.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Phase {
+	/// Packing payload bytes into field symbols and back.
+	SymbolConversion,
+	/// The inverse transform of the message in `encode_low`.
+	Ifft,
+	/// The forward transform at the `n`-th shift of `k` in `encode_low`.
+	FftAtShift(usize),
+	/// Distributing the codeword symbols onto the shards.
+	ShardWrapping,
+	/// Evaluating the error locator polynomial.
+	ErrorLocator,
+	/// The inverse transform of the received codeword.
+	DecodeIfft,
+	/// The formal derivative including the `B` factor scaling.
+	FormalDerivative,
+	/// The forward transform yielding the recovered symbols.
+	DecodeFft,
+}
+
+/// Records the duration of phases, the no-op impl for `()` is used by the
+/// regular code paths and optimized away.
+pub(crate) trait Recorder {
+	fn time<R>(&mut self, phase: Phase, f: impl FnOnce() -> R) -> R;
+}
+
+impl Recorder for () {
+	#[inline(always)]
+	fn time<R>(&mut self, _phase: Phase, f: impl FnOnce() -> R) -> R {
+		f()
+	}
+}
+
+/// Accumulated time spent per phase, summed over all codewords.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PhaseTimings {
+	phases: BTreeMap<Phase, Duration>,
+}
+
+impl PhaseTimings {
+	/// Time spent in `phase`, zero if it never ran.
+	pub fn get(&self, phase: Phase) -> Duration {
+		self.phases.get(&phase).copied().unwrap_or_default()
+	}
+
+	/// Time spent in all forward transforms of the encoder, across all shifts.
+	pub fn fft_total(&self) -> Duration {
+		self.iter().filter(|(phase, _)| matches!(phase, Phase::FftAtShift(_))).map(|(_, d)| d).sum()
+	}
+
+	/// Sum of all phases.
+	pub fn total(&self) -> Duration {
+		self.phases.values().sum()
+	}
+
+	/// Iterate over all phases that ran, in pipeline order.
+	pub fn iter(&self) -> impl Iterator<Item = (Phase, Duration)> + '_ {
+		self.phases.iter().map(|(phase, d)| (*phase, *d))
+	}
+}
+
+impl Recorder for PhaseTimings {
+	fn time<R>(&mut self, phase: Phase, f: impl FnOnce() -> R) -> R {
+		let start = Instant::now();
+		let r = f();
+		*self.phases.entry(phase).or_default() += start.elapsed();
+		r
+	}
+}
+
+impl fmt::Display for PhaseTimings {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for (phase, d) in self.iter() {
+			writeln!(f, "{:>24}: {:?}", format!("{:?}", phase), d)?;
+		}
+		write!(f, "{:>24}: {:?}", "Total", self.total())
+	}
+}
@@ -0,0 +1,160 @@
+//! Erasure coders behind trait objects, selectable by name.
+//!
+//! Benchmark harnesses and the `rs-ec` tool look backends up by string, third
+//! parties can `register` their own implementations next to the built in ones.
+
+use super::*;
+use crate::auto::Backend;
+
+use std::collections::BTreeMap;
+
+/// An erasure code backend, object safe so backends can be chosen at run time.
+pub trait ErasureCoder: Send + Sync {
+	/// Name the coder is registered under.
+	fn name(&self) -> &str;
+
+	/// Encode `data` into `params.n()` shards, any `params.k()` of which suffice to reconstruct.
+	fn encode(&self, params: &CodeParams, data: &[u8]) -> Result<Vec<WrappedShard>>;
+
+	/// Reconstruct the payload, the result might have trailing zeros.
+	fn reconstruct(&self, params: &CodeParams, received_shards: Vec<Option<WrappedShard>>) -> Result<Vec<u8>>;
+
+	/// Length in bytes of each shard encoding a payload of `payload_len` bytes.
+	fn required_shard_len(&self, params: &CodeParams, payload_len: usize) -> Result<usize>;
+
+	/// Number of leading shards holding the payload as is, see `ShardIndex`.
+	fn data_shards(&self, params: &CodeParams) -> Result<usize> {
+		Ok(params.k())
+	}
+}
+
+impl ErasureCoder for Backend {
+	fn name(&self) -> &str {
+		match self {
+			Backend::StatusQuo => "status-quo",
+			Backend::NovelPolyBasis => "novel-f2e16",
+		}
+	}
+
+	fn encode(&self, params: &CodeParams, data: &[u8]) -> Result<Vec<WrappedShard>> {
+		match self {
+			Backend::StatusQuo => status_quo::encode_with_params(params, data),
+			Backend::NovelPolyBasis => novel_poly_basis::encode_with_params(params, data),
+		}
+	}
+
+	fn reconstruct(&self, params: &CodeParams, received_shards: Vec<Option<WrappedShard>>) -> Result<Vec<u8>> {
+		match self {
+			Backend::StatusQuo => status_quo::reconstruct_with_params(params, received_shards),
+			Backend::NovelPolyBasis => novel_poly_basis::reconstruct_with_params(params, received_shards),
+		}
+	}
+
+	fn required_shard_len(&self, params: &CodeParams, payload_len: usize) -> Result<usize> {
+		auto::required_shard_len(*self, params, payload_len)
+	}
+
+	fn data_shards(&self, params: &CodeParams) -> Result<usize> {
+		auto::data_shards(*self, params)
+	}
+}
+
+/// Coders by name, `default` holds the built in backends.
+///
+/// There is no GF(2^8) backend yet, `f2e8` only provides the field for the generic
+/// additive FFT, so `novel-f2e8-simd` is left for an external registration.
+pub struct Registry {
+	coders: BTreeMap<String, Box<dyn ErasureCoder>>,
+}
+
+impl Default for Registry {
+	fn default() -> Self {
+		let mut registry = Self::empty();
+		registry.register(Box::new(Backend::StatusQuo));
+		registry.register(Box::new(Backend::NovelPolyBasis));
+		registry.register(Box::new(fp31::Fp31));
+		registry
+	}
+}
+
+impl Registry {
+	/// A registry without any coders.
+	pub fn empty() -> Self {
+		Self { coders: BTreeMap::new() }
+	}
+
+	/// Add `coder` under its name, returning the coder it replaces.
+	pub fn register(&mut self, coder: Box<dyn ErasureCoder>) -> Option<Box<dyn ErasureCoder>> {
+		self.coders.insert(coder.name().to_owned(), coder)
+	}
+
+	/// Look up the coder registered as `name`.
+	pub fn get(&self, name: &str) -> Option<&dyn ErasureCoder> {
+		self.coders.get(name).map(AsRef::as_ref)
+	}
+
+	/// Names of all registered coders, in lexicographic order.
+	pub fn names(&self) -> impl Iterator<Item = &str> {
+		self.coders.keys().map(String::as_str)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// shards all zero, just to tell an external coder apart
+	struct Blank;
+
+	impl ErasureCoder for Blank {
+		fn name(&self) -> &str {
+			"blank"
+		}
+
+		fn encode(&self, params: &CodeParams, data: &[u8]) -> Result<Vec<WrappedShard>> {
+			Ok(vec![WrappedShard::new(vec![0; self.required_shard_len(params, data.len())?]); params.n()])
+		}
+
+		fn reconstruct(&self, params: &CodeParams, _received_shards: Vec<Option<WrappedShard>>) -> Result<Vec<u8>> {
+			Ok(vec![0; params.k()])
+		}
+
+		fn required_shard_len(&self, _params: &CodeParams, _payload_len: usize) -> Result<usize> {
+			Ok(2)
+		}
+	}
+
+	#[test]
+	fn builtin_coders_roundtrip() {
+		let registry = Registry::default();
+		assert_eq!(registry.names().collect::<Vec<_>>(), ["novel-f2e16", "ntt-fp31", "status-quo"]);
+
+		let params = CodeParams::new(16, 5).unwrap();
+		let payload = &random_payload(1000, 0);
+		for name in registry.names() {
+			let coder = registry.get(name).unwrap();
+			assert_eq!(coder.name(), name);
+			let shards = coder.encode(&params, payload).unwrap();
+			let shard_len = coder.required_shard_len(&params, payload.len()).unwrap();
+			assert!(shards.iter().all(|shard| AsRef::<[u8]>::as_ref(shard).len() == shard_len));
+
+			let mut received = shards.into_iter().map(Some).collect::<Vec<_>>();
+			received.iter_mut().skip(params.k()).for_each(|shard| *shard = None);
+			let recovered = coder.reconstruct(&params, received).unwrap();
+			assert_eq!(&recovered[..payload.len()], payload, "{}", name);
+		}
+	}
+
+	#[test]
+	fn registers_external_coders() {
+		let mut registry = Registry::default();
+		assert!(registry.get("blank").is_none());
+		assert!(registry.register(Box::new(Blank)).is_none());
+		assert!(registry.register(Box::new(Blank)).is_some());
+
+		let coder = registry.get("blank").unwrap();
+		let shards = coder.encode(&CodeParams::default(), &random_payload(10, 0)).unwrap();
+		assert!(shards.iter().all(|shard| AsRef::<[u8]>::as_ref(shard) == [0, 0]));
+		assert_eq!(registry.names().count(), 4);
+	}
+}
@@ -0,0 +1,159 @@
+//! Plans to regenerate lost shards, e.g. for storage nodes scheduling repairs.
+//!
+//! Groups missing a single shard are repaired locally first, see `lrc`, each by the XOR
+//! of the rest of its group. All global shards still missing are then regenerated by a
+//! single decode of the global code, which reuses the shards already at hand and prefers
+//! data shards, so systematic backends decode fewer erasures. Local parity shards of groups
+//! which lost more are recomputed from the group afterwards.
+
+use super::*;
+
+/// One step of a `RepairPlan`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepairStep {
+	/// Recover `shard` as the XOR of the shards `from`, the rest of its local `group`.
+	Local { group: usize, shard: usize, from: Vec<usize> },
+	/// Decode the payload from the `k` global shards `from` and encode the global `shards` again.
+	Global { shards: Vec<usize>, from: Vec<usize> },
+}
+
+/// Shards to fetch, in the order they are needed, and the steps regenerating the missing ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepairPlan {
+	pub fetch: Vec<usize>,
+	pub steps: Vec<RepairStep>,
+}
+
+impl RepairPlan {
+	/// Number of decodes of the global code, at most one.
+	pub fn decodes(&self) -> usize {
+		self.steps.iter().filter(|step| matches!(step, RepairStep::Global { .. })).count()
+	}
+}
+
+// fetch those of `indices` not yet at hand
+fn fetch(plan: &mut RepairPlan, at_hand: &mut [bool], indices: &[usize]) {
+	for &index in indices {
+		if !std::mem::replace(&mut at_hand[index], true) {
+			plan.fetch.push(index);
+		}
+	}
+}
+
+/// Plan the regeneration of the shards at `missing` from those at `available`, of the global
+/// code and, if `params` has local groups, of the local parity shards following it. Shards in
+/// neither are neither fetched nor regenerated.
+pub fn repair_plan(missing: &[usize], available: &[usize], params: &CodeParams) -> Result<RepairPlan> {
+	let (n, k) = (params.n(), params.k());
+	let groups = params.local_groups();
+	let total = groups.map_or(n, |groups| groups.total_shards());
+
+	let mut is_missing = vec![false; total];
+	let mut is_available = vec![false; total];
+	let listed = missing.iter().map(|&index| (index, true)).chain(available.iter().map(|&index| (index, false)));
+	for (index, lost) in listed {
+		if index >= total {
+			return Err(Error::ChunkIndexOutOfRange { index, n: total });
+		}
+		if is_missing[index] || is_available[index] {
+			return Err(Error::DuplicateChunkIndex(index));
+		}
+		if lost {
+			is_missing[index] = true;
+		} else {
+			is_available[index] = true;
+		}
+	}
+
+	// fetched or regenerated shards
+	let mut at_hand = vec![false; total];
+	let mut plan = RepairPlan::default();
+
+	if let Some(groups) = groups {
+		for group in 0..groups.count() {
+			let group_shards = groups.members(group).chain(std::iter::once(groups.parity_index(group)));
+			let lost = group_shards.filter(|index| is_missing[*index]).collect::<Vec<_>>();
+			let (shard, from) = match lost[..] {
+				[shard] => (shard, groups.repair_set(shard).expect("shards of a group have a repair set. qed")),
+				_ => continue,
+			};
+			if from.iter().all(|index| is_available[*index]) {
+				fetch(&mut plan, &mut at_hand, &from);
+				is_missing[shard] = false;
+				at_hand[shard] = true;
+				plan.steps.push(RepairStep::Local { group, shard, from });
+			}
+		}
+	}
+
+	let lost = (0..n).filter(|index| is_missing[*index]).collect::<Vec<_>>();
+	if !lost.is_empty() {
+		// the shards at hand first, then the data shards, each in index order
+		let mut from = (0..n).filter(|index| at_hand[*index] || is_available[*index]).collect::<Vec<_>>();
+		if from.len() < k {
+			return Err(Error::TooFewShards { available: from.len(), required: k });
+		}
+		from.sort_by_key(|index| (!at_hand[*index], *index >= k));
+		from.truncate(k);
+		fetch(&mut plan, &mut at_hand, &from);
+		for &index in &lost {
+			is_missing[index] = false;
+			at_hand[index] = true;
+		}
+		plan.steps.push(RepairStep::Global { shards: lost, from });
+	}
+
+	// local parity shards of groups which lost more than one shard
+	if let Some(groups) = groups {
+		for group in 0..groups.count() {
+			let shard = groups.parity_index(group);
+			if !is_missing[shard] {
+				continue;
+			}
+			let from = groups.members(group).collect::<Vec<_>>();
+			if let Some(&unknown) = from.iter().find(|index| !at_hand[**index] && !is_available[**index]) {
+				return Err(Error::MissingChunk(unknown));
+			}
+			fetch(&mut plan, &mut at_hand, &from);
+			plan.steps.push(RepairStep::Local { group, shard, from });
+		}
+	}
+	Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::num::NonZeroUsize;
+
+	#[test]
+	fn plans_local_repairs_before_one_decode() {
+		// groups of 5 shards, the last of 1, local parities at 16 to 19
+		let params = CodeParams::new(16, 4).unwrap().with_local_groups(NonZeroUsize::new(5).unwrap());
+		let missing = [2, 10, 11, 17, 18];
+		let available = (0..20).filter(|index| !missing.contains(index)).collect::<Vec<_>>();
+		let plan = repair_plan(&missing, &available, &params).unwrap();
+		assert_eq!(
+			plan.steps,
+			[
+				RepairStep::Local { group: 0, shard: 2, from: vec![0, 1, 3, 4, 16] },
+				RepairStep::Local { group: 1, shard: 17, from: vec![5, 6, 7, 8, 9] },
+				RepairStep::Global { shards: vec![10, 11], from: vec![0, 1, 2, 3] },
+				RepairStep::Local { group: 2, shard: 18, from: vec![10, 11, 12, 13, 14] },
+			]
+		);
+		assert_eq!(plan.fetch, [0, 1, 3, 4, 16, 5, 6, 7, 8, 9, 12, 13, 14]);
+		assert_eq!(plan.decodes(), 1);
+
+		// without groups, the data shards are preferred
+		let params = CodeParams::new(8, 3).unwrap();
+		let plan = repair_plan(&[1, 6], &[7, 5, 4, 3, 2, 0], &params).unwrap();
+		assert_eq!(plan.steps, [RepairStep::Global { shards: vec![1, 6], from: vec![0, 2, 3] }]);
+		assert_eq!(plan.fetch, [0, 2, 3]);
+		assert_eq!(repair_plan(&[], &[0], &params).unwrap(), RepairPlan::default());
+
+		assert_eq!(repair_plan(&[1], &[0, 2], &params), Err(Error::TooFewShards { available: 2, required: 3 }));
+		assert_eq!(repair_plan(&[1], &[1, 2], &params), Err(Error::DuplicateChunkIndex(1)));
+		assert_eq!(repair_plan(&[8], &[0], &params), Err(Error::ChunkIndexOutOfRange { index: 8, n: 8 }));
+	}
+}
@@ -0,0 +1,80 @@
+use super::*;
+
+/// All `n` shards of one encoding in a single contiguous allocation.
+///
+/// Shard `i` is stored at the stable offset `i * shard_len`, so the whole
+/// shard set can be handed over as one buffer. The arena itself is not
+/// `#[repr(C)]` and there is no C API, foreign code receives the pointer and
+/// length of `as_bytes` together with `n` and `shard_len`.
+/// Resetting the arena for the next encoding keeps the allocation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShardArena {
+	buffer: Vec<u8>,
+	n: usize,
+	shard_len: usize,
+}
+
+impl ShardArena {
+	/// An arena of `n` zeroed shards of `shard_len` bytes each.
+	pub fn new(n: usize, shard_len: usize) -> Self {
+		ShardArena { buffer: vec![0_u8; n * shard_len], n, shard_len }
+	}
+
+	/// Number of shards.
+	pub fn n(&self) -> usize {
+		self.n
+	}
+
+	/// Length in bytes of each shard.
+	pub fn shard_len(&self) -> usize {
+		self.shard_len
+	}
+
+	/// Offset of shard `idx` within `as_bytes`.
+	pub fn offset(&self, idx: usize) -> usize {
+		assert!(idx < self.n, "Shard index {} out of range for {} shards", idx, self.n);
+		idx * self.shard_len
+	}
+
+	/// Reshape to `n` zeroed shards of `shard_len` bytes, reusing the allocation if large enough.
+	pub fn reset(&mut self, n: usize, shard_len: usize) {
+		self.buffer.clear();
+		self.buffer.resize(n * shard_len, 0);
+		self.n = n;
+		self.shard_len = shard_len;
+	}
+
+	pub fn shard(&self, idx: usize) -> &[u8] {
+		let offset = self.offset(idx);
+		&self.buffer[offset..offset + self.shard_len]
+	}
+
+	pub fn shard_mut(&mut self, idx: usize) -> &mut [u8] {
+		let offset = self.offset(idx);
+		&mut self.buffer[offset..offset + self.shard_len]
+	}
+
+	/// All shards in order.
+	pub fn iter(&self) -> impl Iterator<Item = &[u8]> + '_ {
+		(0..self.n).map(move |idx| self.shard(idx))
+	}
+
+	/// The shards back to back.
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.buffer[..]
+	}
+
+	pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+		&mut self.buffer[..]
+	}
+
+	/// Yield the underlying buffer, shard `i` at `i * shard_len`.
+	pub fn into_inner(self) -> Vec<u8> {
+		self.buffer
+	}
+
+	/// Copy the shards out into individually allocated shards.
+	pub fn to_shards(&self) -> Vec<WrappedShard> {
+		self.iter().map(|shard| WrappedShard::new(shard.to_vec())).collect()
+	}
+}
@@ -0,0 +1,112 @@
+//! Pushing shards to their destination as soon as they are encoded.
+//!
+//! Encoding into a `ShardSink` decouples producing the shards from collecting
+//! them, a sink can pipe each shard straight into a network buffer and apply
+//! backpressure by blocking in, or awaiting, `push`.
+
+use super::*;
+
+/// Destination of encoded shards, each pushed once complete, in increasing index order.
+pub trait ShardSink {
+	/// Take the shard at `index`. Blocking applies backpressure, an error aborts the encoding.
+	fn push(&mut self, index: ChunkIndex, shard: WrappedShard) -> Result<()>;
+}
+
+impl<F: FnMut(ChunkIndex, WrappedShard) -> Result<()>> ShardSink for F {
+	fn push(&mut self, index: ChunkIndex, shard: WrappedShard) -> Result<()> {
+		self(index, shard)
+	}
+}
+
+/// Async counterpart of `ShardSink`, awaiting `push` applies backpressure.
+#[cfg(feature = "async")]
+pub trait AsyncShardSink {
+	fn push(&mut self, index: ChunkIndex, shard: WrappedShard) -> impl std::future::Future<Output = Result<()>>;
+}
+
+/// Collects all shards, as returned by `encode_with_params`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VecSink {
+	shards: Vec<WrappedShard>,
+}
+
+impl VecSink {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn shards(&self) -> &[WrappedShard] {
+		&self.shards
+	}
+
+	pub fn into_shards(self) -> Vec<WrappedShard> {
+		self.shards
+	}
+}
+
+impl ShardSink for VecSink {
+	fn push(&mut self, _index: ChunkIndex, shard: WrappedShard) -> Result<()> {
+		self.shards.push(shard);
+		Ok(())
+	}
+}
+
+#[cfg(feature = "async")]
+impl AsyncShardSink for VecSink {
+	async fn push(&mut self, index: ChunkIndex, shard: WrappedShard) -> Result<()> {
+		ShardSink::push(self, index, shard)
+	}
+}
+
+/// Encode `data` with the novel poly basis, pushing every shard into `sink` as it is
+/// produced, the data shards before any parity is computed, see `novel_poly_basis::encode_iter`.
+pub fn encode_into_sink(params: &CodeParams, data: &[u8], sink: &mut impl ShardSink) -> Result<()> {
+	for (idx, shard) in novel_poly_basis::encode_iter(params, data)?.enumerate() {
+		sink.push(ChunkIndex(idx as u32), shard)?;
+	}
+	Ok(())
+}
+
+/// Same as `encode_into_sink`, awaiting the sink after every shard.
+#[cfg(feature = "async")]
+pub async fn encode_into_sink_async(params: &CodeParams, data: &[u8], sink: &mut impl AsyncShardSink) -> Result<()> {
+	for (idx, shard) in novel_poly_basis::encode_iter(params, data)?.enumerate() {
+		sink.push(ChunkIndex(idx as u32), shard).await?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sinks_receive_all_shards_in_order() {
+		let params = CodeParams::new(20, 6).unwrap();
+		let payload = &random_payload(1001, 0);
+		let expected = novel_poly_basis::encode_with_params(&params, payload).unwrap();
+
+		let mut sink = VecSink::new();
+		encode_into_sink(&params, payload, &mut sink).unwrap();
+		assert_eq!(sink.into_shards(), expected);
+
+		// a full sink stops the encoding
+		let mut sent = Vec::new();
+		let mut bounded = |index: ChunkIndex, _shard| {
+			if sent.len() == 5 {
+				return Err(std::io::ErrorKind::WouldBlock.into());
+			}
+			sent.push(index);
+			Ok(())
+		};
+		assert_eq!(encode_into_sink(&params, payload, &mut bounded), Err(std::io::ErrorKind::WouldBlock.into()));
+		assert_eq!(sent, (0..5).map(ChunkIndex).collect::<Vec<_>>());
+
+		#[cfg(feature = "async")]
+		{
+			let mut sink = VecSink::new();
+			futures::executor::block_on(encode_into_sink_async(&params, payload, &mut sink)).unwrap();
+			assert_eq!(sink.into_shards(), expected);
+		}
+	}
+}
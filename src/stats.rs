@@ -0,0 +1,90 @@
+//! Counts of field operations, for comparing algorithms analytically
+//! alongside wall clock benchmarks.
+//!
+//! Counting is only performed with the `op-stats` feature, otherwise the
+//! counters compile to nothing and `count_ops` reports zeros.
+
+use std::fmt;
+
+/// Field operations performed by the novel poly basis.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpCounts {
+	/// Multiplications of two field elements.
+	pub muls: u64,
+	/// Additions of field elements, i.e. XORs of symbols.
+	pub xors: u64,
+	/// Lookups in the log, exp and skew factor tables.
+	pub lookups: u64,
+}
+
+impl fmt::Display for OpCounts {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "{:<8} {:>14}", "muls", self.muls)?;
+		writeln!(f, "{:<8} {:>14}", "xors", self.xors)?;
+		writeln!(f, "{:<8} {:>14}", "lookups", self.lookups)
+	}
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Op {
+	Mul,
+	Xor,
+	Lookup,
+}
+
+#[cfg(feature = "op-stats")]
+thread_local! {
+	static COUNTS: std::cell::Cell<OpCounts> = std::cell::Cell::new(OpCounts::default());
+}
+
+/// Record `count` operations of kind `op` on the current thread.
+#[inline(always)]
+pub(crate) fn record(op: Op, count: usize) {
+	#[cfg(feature = "op-stats")]
+	COUNTS.with(|counts| {
+		let mut c = counts.get();
+		match op {
+			Op::Mul => c.muls += count as u64,
+			Op::Xor => c.xors += count as u64,
+			Op::Lookup => c.lookups += count as u64,
+		}
+		counts.set(c);
+	});
+	#[cfg(not(feature = "op-stats"))]
+	let _ = (op, count);
+}
+
+/// Run `f` and count the field operations it performed on the current thread.
+pub fn count_ops<R>(f: impl FnOnce() -> R) -> (R, OpCounts) {
+	#[cfg(feature = "op-stats")]
+	{
+		let before = COUNTS.with(|counts| counts.replace(OpCounts::default()));
+		let r = f();
+		let counts = COUNTS.with(|counts| counts.replace(before));
+		(r, counts)
+	}
+	#[cfg(not(feature = "op-stats"))]
+	(f(), OpCounts::default())
+}
+
+#[cfg(all(test, feature = "op-stats"))]
+mod tests {
+	use super::*;
+	use crate::*;
+
+	#[test]
+	fn counts_scale_with_n() {
+		let payload = &random_payload(1 << 12, 0);
+		let count = |n: usize| {
+			let params = CodeParams::new(n, n / 4).unwrap();
+			count_ops(|| novel_poly_basis::encode_with_params(&params, payload).unwrap()).1
+		};
+		let (small, large) = (count(64), count(256));
+		assert!(small.muls > 0 && small.xors > 0 && small.lookups >= small.muls);
+		// `O(n log n)` per codeword, with 4 times fewer codewords for the larger `k`
+		assert!(large.muls > small.muls && large.muls < 2 * small.muls);
+
+		let (_, nothing) = count_ops(|| ());
+		assert_eq!(nothing, OpCounts::default());
+	}
+}
@@ -1,19 +1,38 @@
+//! The `reed-solomon-erasure` backend, the baseline of the benchmarks.
+//!
+//! Its multiplications are table lookups, the `constant-time` feature does not apply here.
+
 use super::*;
 
 use reed_solomon_erasure::galois_16::ReedSolomon;
 
 pub fn to_shards(payload: &[u8]) -> Vec<WrappedShard> {
-	let base_len = payload.len();
+	to_shards_with_params(&CodeParams::default(), payload)
+}
 
+/// Length in bytes of each shard encoding a payload of `payload_len` bytes.
+pub fn required_shard_len(params: &CodeParams, payload_len: usize) -> usize {
 	// how many bytes we actually need.
-	let needed_shard_len = (base_len + DATA_SHARDS - 1) / DATA_SHARDS;
+	let needed_shard_len = payload_len.div_ceil(params.k());
 
 	// round up, ing GF(2^16) there are only 2 byte values, so each shard must a multiple of 2
 	let needed_shard_len = needed_shard_len + (needed_shard_len & 0x01);
 
-	let shard_len = needed_shard_len;
+	// even an empty payload occupies one symbol per shard
+	std::cmp::max(needed_shard_len, 2)
+}
+
+/// Length of the reconstructed payload including padding, for a payload of `payload_len` bytes.
+pub fn padded_payload_len(params: &CodeParams, payload_len: usize) -> usize {
+	required_shard_len(params, payload_len) * params.k()
+}
+
+/// Split `payload` into `params.k()` zero padded data shards, followed by
+/// `params.n() - params.k()` blank parity shards.
+pub fn to_shards_with_params(params: &CodeParams, payload: &[u8]) -> Vec<WrappedShard> {
+	let shard_len = required_shard_len(params, payload.len());
 
-	let mut shards = vec![WrappedShard::new(vec![0u8; shard_len]); N_VALIDATORS];
+	let mut shards = vec![WrappedShard::new(vec![0u8; shard_len]); params.n()];
 	for (data_chunk, blank_shard) in payload.chunks(shard_len).zip(&mut shards) {
 		// fill the empty shards with the corresponding piece of the payload,
 		// zero-padded to fit in the shards.
@@ -25,22 +44,69 @@ pub fn to_shards(payload: &[u8]) -> Vec<WrappedShard> {
 	shards
 }
 
+// The codec of `reed_solomon_erasure` views shards as 2 byte symbols of GF(2^16), which panics
+// for odd length shards, e.g. from `WrappedShard::new_unpadded`.
+fn check_even<'a>(shards: impl IntoIterator<Item = &'a WrappedShard>) -> Result<()> {
+	shards.into_iter().try_for_each(|shard| shard.symbols::<2>().map(|_| ()))
+}
+
 pub fn rs() -> ReedSolomon {
-	ReedSolomon::new(DATA_SHARDS, PARITY_SHARDS).expect("this struct is not created with invalid shard number; qed")
+	rs_with_params(&CodeParams::default()).expect("this struct is not created with invalid shard number; qed")
 }
 
-pub fn encode(data: &[u8]) -> Vec<WrappedShard> {
-	let encoder = rs();
-	let mut shards = to_shards(data);
-	encoder.encode(&mut shards).unwrap();
-	shards
+pub fn rs_with_params(params: &CodeParams) -> Result<ReedSolomon> {
+	if params.n() > MAX_SHARDS {
+		return Err(Error::ShardCountTooHigh(params.n()));
+	}
+	Ok(ReedSolomon::new(params.k(), params.n() - params.k())?)
+}
+
+/// `encode_with_params` with the default parameters.
+pub fn encode(data: &[u8]) -> Result<Vec<WrappedShard>> {
+	encode_with_params(&CodeParams::default(), data)
+}
+
+/// Encode `data` into `params.n()` shards, any `params.k()` of which suffice to reconstruct.
+pub fn encode_with_params(params: &CodeParams, data: &[u8]) -> Result<Vec<WrappedShard>> {
+	let encoder = rs_with_params(params)?;
+	params.check_payload_len(data.len())?;
+	let mut shards = to_shards_with_params(params, data);
+	encoder.encode(&mut shards)?;
+	Ok(shards)
+}
+
+/// Reconstruct with the default parameters.
+pub fn reconstruct(received_shards: Vec<Option<WrappedShard>>) -> Result<Vec<u8>> {
+	reconstruct_with_params(&CodeParams::default(), received_shards)
 }
 
-pub fn reconstruct(mut received_shards: Vec<Option<WrappedShard>>) -> Option<Vec<u8>> {
-	let r = rs();
+/// Reconstruct the payload of a `params` code, the result might have trailing zeros.
+pub fn reconstruct_with_params(params: &CodeParams, mut received_shards: Vec<Option<WrappedShard>>) -> Result<Vec<u8>> {
+	let r = rs_with_params(params)?;
+
+	let borrowed = received_shards.iter().map(|shard| shard.as_ref().map(AsRef::as_ref)).collect::<Vec<_>>();
+	if let Some(equalized) = equalize_shard_lengths(params, &borrowed) {
+		let owned = equalized.into_iter().map(|shard| Some(WrappedShard::new_unpadded(shard?.into_owned())));
+		return reconstruct_with_params(params, owned.collect());
+	}
+
+	if received_shards.len() != params.n() {
+		return Err(Error::WrongShardCount { expected: params.n(), got: received_shards.len() });
+	}
+	let available = received_shards.iter().flatten().count();
+	if available < params.k() {
+		return Err(Error::TooFewShards { available, required: params.k() });
+	}
+	let mut present = received_shards.iter().flatten();
+	let shard_len = present.next().map(|shard| AsRef::<[u8]>::as_ref(shard).len()).unwrap_or_default();
+	if let Some(other) = present.map(|shard| AsRef::<[u8]>::as_ref(shard).len()).find(|len| *len != shard_len) {
+		return Err(Error::InconsistentShardLengths { first: shard_len, other });
+	}
+
+	check_even(received_shards.iter().flatten())?;
 
 	// Try to reconstruct missing shards
-	r.reconstruct_data(&mut received_shards).expect("Sufficient shards must be received. qed");
+	r.reconstruct_data(&mut received_shards)?;
 
 	// Convert back to normal shard arrangement
 	// let l = received_shards.len();
@@ -50,13 +116,40 @@ pub fn reconstruct(mut received_shards: Vec<Option<WrappedShard>>) -> Option<Vec
 	// 	.filter_map(|x| x)
 	// 	.collect::<Vec<WrappedShard>>();
 
-	let result = received_shards.into_iter().filter_map(|x| x).take(DATA_SHARDS).fold(
-		Vec::with_capacity(12 << 20),
+	let result = received_shards.into_iter().flatten().take(params.k()).fold(
+		Vec::with_capacity(shard_len * params.k()),
 		|mut acc, x| {
 			acc.extend_from_slice(x.into_inner().as_slice());
 			acc
 		},
 	);
 
-	Some(result)
+	Ok(result)
+}
+
+/// Reconstruct from borrowed shards.
+///
+/// If all data shards are present, the payload is assembled directly from them,
+/// otherwise the available shards have to be copied, since the matrix decoder
+/// reconstructs in place.
+pub fn reconstruct_ref(received_shards: &[Option<&[u8]>]) -> Result<Vec<u8>> {
+	reconstruct_ref_with_params(&CodeParams::default(), received_shards)
+}
+
+/// Reconstruct the payload of a `params` code from borrowed shards.
+pub fn reconstruct_ref_with_params(params: &CodeParams, received_shards: &[Option<&[u8]>]) -> Result<Vec<u8>> {
+	let k = params.k();
+	if received_shards.len() == params.n() && received_shards[..k].iter().all(Option::is_some) {
+		let result = received_shards.iter().flatten().take(k).fold(Vec::new(), |mut acc, x| {
+			acc.extend_from_slice(x);
+			acc
+		});
+		return Ok(result);
+	}
+
+	let received_shards = received_shards
+		.iter()
+		.map(|shard| shard.map(|shard| WrappedShard::new(shard.to_vec())))
+		.collect::<Vec<Option<WrappedShard>>>();
+	reconstruct_with_params(params, received_shards)
 }
@@ -0,0 +1,557 @@
+//! Shard sets as byte streams, to persist them to disk or pipe them between processes.
+//!
+//! A stream starts with a header of the magic `RSEC`, the format version, the code
+//! parameters and the number of shards which follow, each as its chunk index, its length
+//! and its bytes. All integers are little endian `u32`s, the enums single bytes. Missing
+//! shards are simply not written, so partial sets can be stored as well.
+//!
+//! Shards kept as separate files are instead reconstructed by `reconstruct_from_files`,
+//! which streams them stripe by stripe. Payloads of the sequential layout are only written
+//! once all their stripes are recovered. With the `spill` feature and a memory cap in
+//! `ReconstructOptions` those beyond the cap are spilled to temporary files meanwhile.
+
+use super::*;
+
+use sink::ShardSink;
+use std::convert::TryFrom;
+#[cfg(feature = "spill")]
+use std::fs::File;
+#[cfg(feature = "spill")]
+use std::io::{Seek, SeekFrom};
+use std::io::{Read, Write};
+use std::num::NonZeroUsize;
+#[cfg(feature = "spill")]
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 4] = b"RSEC";
+const FORMAT_VERSION: u8 = 1;
+
+// bytes of every shard read and decoded at once by `reconstruct_from_files`
+const STRIPE_LEN: usize = 1 << 16;
+
+fn write_u32(w: &mut impl Write, value: usize) -> Result<()> {
+	let value = u32::try_from(value).map_err(|_| Error::MalformedShardStream("value exceeds u32"))?;
+	w.write_all(&value.to_le_bytes())?;
+	Ok(())
+}
+
+fn read_u32(r: &mut impl Read) -> Result<usize> {
+	let mut bytes = [0_u8; 4];
+	r.read_exact(&mut bytes)?;
+	Ok(u32::from_le_bytes(bytes) as usize)
+}
+
+fn read_u8(r: &mut impl Read) -> Result<u8> {
+	let mut byte = [0_u8; 1];
+	r.read_exact(&mut byte)?;
+	Ok(byte[0])
+}
+
+/// Writes a header and then the shards, e.g. as a `ShardSink` while encoding.
+pub struct ShardWriter<W: Write> {
+	inner: W,
+	n: usize,
+	remaining: usize,
+}
+
+impl<W: Write> ShardWriter<W> {
+	/// Write the header announcing `count` shards of a `params` code.
+	pub fn new(mut inner: W, params: &CodeParams, count: usize) -> Result<Self> {
+		if count > params.n() {
+			return Err(Error::WrongShardCount { expected: params.n(), got: count });
+		}
+		inner.write_all(MAGIC)?;
+		inner.write_all(&[FORMAT_VERSION])?;
+		write_u32(&mut inner, params.n())?;
+		write_u32(&mut inner, params.k())?;
+		let endianness = match params.endianness() {
+			Endianness::Little => 0,
+			Endianness::Big => 1,
+		};
+		let layout = match params.layout() {
+			ShardLayout::Interleaved => 0,
+			ShardLayout::Sequential => 1,
+		};
+		inner.write_all(&[endianness, layout])?;
+		write_u32(&mut inner, params.symbol_group())?;
+		write_u32(&mut inner, count)?;
+		Ok(ShardWriter { inner, n: params.n(), remaining: count })
+	}
+
+	/// Write the shard at `index`, one of the announced ones.
+	pub fn write_shard(&mut self, index: ChunkIndex, shard: &[u8]) -> Result<()> {
+		if self.remaining == 0 {
+			return Err(Error::MalformedShardStream("more shards than announced"));
+		}
+		if index.position() >= self.n {
+			return Err(Error::ChunkIndexOutOfRange { index: index.position(), n: self.n });
+		}
+		write_u32(&mut self.inner, index.position())?;
+		write_u32(&mut self.inner, shard.len())?;
+		self.inner.write_all(shard)?;
+		self.remaining -= 1;
+		Ok(())
+	}
+
+	/// Flush and yield the writer, once all announced shards are written.
+	pub fn finish(mut self) -> Result<W> {
+		if self.remaining != 0 {
+			return Err(Error::MalformedShardStream("fewer shards than announced"));
+		}
+		self.inner.flush()?;
+		Ok(self.inner)
+	}
+}
+
+impl<W: Write> ShardSink for ShardWriter<W> {
+	fn push(&mut self, index: ChunkIndex, shard: WrappedShard) -> Result<()> {
+		self.write_shard(index, shard.as_ref())
+	}
+}
+
+/// Write the received shards of a `params` code to `inner`.
+pub fn write_shards(inner: impl Write, params: &CodeParams, received_shards: &[Option<WrappedShard>]) -> Result<()> {
+	if received_shards.len() != params.n() {
+		return Err(Error::WrongShardCount { expected: params.n(), got: received_shards.len() });
+	}
+	let mut writer = ShardWriter::new(inner, params, received_shards.iter().flatten().count())?;
+	for (idx, shard) in received_shards.iter().enumerate() {
+		if let Some(shard) = shard {
+			writer.write_shard(ChunkIndex(idx as u32), shard.as_ref())?;
+		}
+	}
+	writer.finish()?;
+	Ok(())
+}
+
+/// Parses the header and then yields the shards of a stream written by `ShardWriter`.
+pub struct ShardReader<R: Read> {
+	inner: R,
+	params: CodeParams,
+	remaining: usize,
+}
+
+impl<R: Read> ShardReader<R> {
+	/// Parse the header, the shards are read on demand.
+	pub fn new(mut inner: R) -> Result<Self> {
+		let mut magic = [0_u8; 4];
+		inner.read_exact(&mut magic)?;
+		if &magic != MAGIC {
+			return Err(Error::MalformedShardStream("not a shard stream"));
+		}
+		if read_u8(&mut inner)? != FORMAT_VERSION {
+			return Err(Error::MalformedShardStream("unknown format version"));
+		}
+		let (n, k) = (read_u32(&mut inner)?, read_u32(&mut inner)?);
+		let endianness = match read_u8(&mut inner)? {
+			0 => Endianness::Little,
+			1 => Endianness::Big,
+			_ => return Err(Error::MalformedShardStream("unknown endianness")),
+		};
+		let layout = match read_u8(&mut inner)? {
+			0 => ShardLayout::Interleaved,
+			1 => ShardLayout::Sequential,
+			_ => return Err(Error::MalformedShardStream("unknown shard layout")),
+		};
+		let group = NonZeroUsize::new(read_u32(&mut inner)?).ok_or(Error::MalformedShardStream("empty symbol group"))?;
+		let params = CodeParams::new(n, k)?.with_endianness(endianness).with_layout(layout).with_symbol_group(group);
+		let remaining = read_u32(&mut inner)?;
+		if remaining > n {
+			return Err(Error::WrongShardCount { expected: n, got: remaining });
+		}
+		Ok(ShardReader { inner, params, remaining })
+	}
+
+	/// Parameters of the code the shards belong to.
+	pub fn params(&self) -> &CodeParams {
+		&self.params
+	}
+
+	/// Number of shards not yet read.
+	pub fn remaining(&self) -> usize {
+		self.remaining
+	}
+
+	/// The next shard and its index, `None` once all announced shards are read.
+	pub fn read_shard(&mut self) -> Result<Option<(ChunkIndex, WrappedShard)>> {
+		if self.remaining == 0 {
+			return Ok(None);
+		}
+		let index = read_u32(&mut self.inner)?;
+		if index >= self.params.n() {
+			return Err(Error::ChunkIndexOutOfRange { index, n: self.params.n() });
+		}
+		let len = read_u32(&mut self.inner)?;
+		// the length is not trusted for allocating up front
+		let mut shard = Vec::new();
+		(&mut self.inner).take(len as u64).read_to_end(&mut shard)?;
+		if shard.len() != len {
+			return Err(std::io::ErrorKind::UnexpectedEof.into());
+		}
+		self.remaining -= 1;
+		Ok(Some((ChunkIndex(index as u32), WrappedShard::new_unpadded(shard))))
+	}
+
+	/// Read all remaining shards into the arrangement expected by `reconstruct`.
+	pub fn read_all(mut self) -> Result<Vec<Option<WrappedShard>>> {
+		let mut received_shards = vec![None; self.params.n()];
+		while let Some((index, shard)) = self.read_shard()? {
+			let slot = &mut received_shards[index.position()];
+			if slot.is_some() {
+				return Err(Error::DuplicateChunkIndex(index.position()));
+			}
+			*slot = Some(shard);
+		}
+		Ok(received_shards)
+	}
+}
+
+impl<R: Read> Iterator for ShardReader<R> {
+	type Item = Result<(ChunkIndex, WrappedShard)>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.read_shard().transpose()
+	}
+}
+
+// fill `buf` unless the reader ends first, returns the bytes read
+fn read_stripe(reader: &mut impl Read, buf: &mut [u8]) -> Result<usize> {
+	let mut filled = 0;
+	while filled < buf.len() {
+		match reader.read(&mut buf[filled..]) {
+			Ok(0) => break,
+			Ok(read) => filled += read,
+			Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+			Err(e) => return Err(e.into()),
+		}
+	}
+	Ok(filled)
+}
+
+/// Reconstruct the payload encoded with the novel poly basis from shards given as readers,
+/// e.g. files, together with their chunk indices. The result might have trailing zeros.
+///
+/// See `reconstruct_from_files_into`, which also writes the payload as it is recovered.
+pub fn reconstruct_from_files<R: Read>(
+	params: &CodeParams,
+	shards: impl IntoIterator<Item = (usize, R)>,
+) -> Result<Vec<u8>> {
+	let mut payload = Vec::new();
+	reconstruct_from_files_into(params, shards, &mut payload, None)?;
+	Ok(payload)
+}
+
+/// Options of `reconstruct_from_files_with_options`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReconstructOptions {
+	#[cfg(feature = "spill")]
+	memory_cap: Option<usize>,
+	#[cfg(feature = "spill")]
+	spill_dir: Option<PathBuf>,
+}
+
+#[cfg(feature = "spill")]
+impl ReconstructOptions {
+	/// Decode the sequential layout in stripes as well, holding at most `bytes` of the
+	/// recovered payload in memory until it can be written in order, and spilling the
+	/// rest to temporary files. Trades speed for a bounded resident set on huge payloads.
+	pub fn with_memory_cap(mut self, bytes: usize) -> Self {
+		self.memory_cap = Some(bytes);
+		self
+	}
+
+	/// Directory of the spilled files, by default that of `std::env::temp_dir`.
+	pub fn with_spill_dir(mut self, dir: impl AsRef<Path>) -> Self {
+		self.spill_dir = Some(dir.as_ref().to_owned());
+		self
+	}
+
+	pub fn memory_cap(&self) -> Option<usize> {
+		self.memory_cap
+	}
+}
+
+// The parts of the data shards after the first of a sequential payload, recovered stripe by
+// stripe but written in order at the end. Parts stay in memory up to `cap` bytes in total,
+// each part exceeding it moves to an anonymous file, removed once closed.
+#[cfg(feature = "spill")]
+struct SpilledParts {
+	cap: usize,
+	in_memory: usize,
+	dir: PathBuf,
+	parts: Vec<Part>,
+}
+
+#[cfg(feature = "spill")]
+enum Part {
+	Memory(Vec<u8>),
+	Spilled(File),
+}
+
+#[cfg(feature = "spill")]
+impl SpilledParts {
+	// append the next stripe of every part, given as consecutive runs of `part_len` bytes
+	fn append(&mut self, stripes: &[u8], part_len: usize) -> Result<()> {
+		let SpilledParts { cap, in_memory, dir, parts } = self;
+		for (part, stripe) in parts.iter_mut().zip(stripes.chunks(part_len)) {
+			match part {
+				Part::Memory(buf) if *in_memory + stripe.len() <= *cap => {
+					buf.extend_from_slice(stripe);
+					*in_memory += stripe.len();
+				}
+				Part::Memory(buf) => {
+					let mut file = tempfile::tempfile_in(&*dir)?;
+					file.write_all(buf)?;
+					file.write_all(stripe)?;
+					*in_memory -= buf.len();
+					*part = Part::Spilled(file);
+				}
+				Part::Spilled(file) => file.write_all(stripe)?,
+			}
+		}
+		Ok(())
+	}
+
+	// write the parts in order, at most `remaining` bytes, returns the bytes written
+	fn write_to(self, out: &mut impl Write, mut remaining: usize) -> Result<usize> {
+		let mut written = 0;
+		for part in self.parts {
+			let len = match part {
+				Part::Memory(buf) => {
+					let buf = &buf[..buf.len().min(remaining)];
+					out.write_all(buf)?;
+					buf.len()
+				}
+				Part::Spilled(mut file) => {
+					file.seek(SeekFrom::Start(0))?;
+					let limit = u64::try_from(remaining).unwrap_or(u64::MAX);
+					std::io::copy(&mut file.take(limit), out)? as usize
+				}
+			};
+			remaining -= len;
+			written += len;
+		}
+		Ok(written)
+	}
+}
+
+/// Reconstruct from shards given as readers into `out`, truncated to `payload_len` if given,
+/// returning the number of bytes written.
+///
+/// Only as many shards as needed are taken from `shards`, which may thus open files lazily.
+/// For the interleaved layout their readers are consumed in stripes, so neither the shards
+/// nor the payload are held in memory as a whole. The sequential layout can not be written
+/// in stripes, its shards are read completely first, see `reconstruct_from_files_with_options`.
+pub fn reconstruct_from_files_into<R: Read>(
+	params: &CodeParams,
+	shards: impl IntoIterator<Item = (usize, R)>,
+	out: impl Write,
+	payload_len: Option<usize>,
+) -> Result<usize> {
+	reconstruct_from_files_with_options(params, shards, out, payload_len, &ReconstructOptions::default())
+}
+
+/// Same as `reconstruct_from_files_into`, with a memory cap in `options` the sequential layout
+/// is decoded in stripes too, spilling recovered stripes beyond the cap to temporary files.
+/// Without the `spill` feature the options are empty.
+#[cfg_attr(not(feature = "spill"), allow(unused_variables))]
+pub fn reconstruct_from_files_with_options<R: Read>(
+	params: &CodeParams,
+	shards: impl IntoIterator<Item = (usize, R)>,
+	mut out: impl Write,
+	payload_len: Option<usize>,
+	options: &ReconstructOptions,
+) -> Result<usize> {
+	let (_n, k) = novel_poly_basis::internal_params(params)?;
+	let mut taken = vec![false; params.n()];
+	let mut readers = Vec::with_capacity(k);
+	for (idx, reader) in shards.into_iter() {
+		if idx >= params.n() {
+			return Err(Error::ChunkIndexOutOfRange { index: idx, n: params.n() });
+		}
+		if std::mem::replace(&mut taken[idx], true) {
+			return Err(Error::DuplicateChunkIndex(idx));
+		}
+		readers.push((idx, reader));
+		if readers.len() == k {
+			break;
+		}
+	}
+
+	#[cfg(feature = "spill")]
+	let mut spilled = match (params.layout(), options.memory_cap) {
+		(ShardLayout::Sequential, Some(cap)) => Some(SpilledParts {
+			cap,
+			in_memory: 0,
+			dir: options.spill_dir.clone().unwrap_or_else(std::env::temp_dir),
+			parts: (1..k).map(|_| Part::Memory(Vec::new())).collect(),
+		}),
+		_ => None,
+	};
+	#[cfg(feature = "spill")]
+	let striped = spilled.is_some();
+	#[cfg(not(feature = "spill"))]
+	let striped = false;
+	let group_len = params.symbol_group() * 2;
+	let stripe_len = match (params.layout(), striped) {
+		(ShardLayout::Sequential, false) => usize::MAX,
+		_ => std::cmp::max(STRIPE_LEN / group_len, 1) * group_len,
+	};
+	let mut stripes = vec![Vec::new(); readers.len()];
+	let mut scratch = novel_poly_basis::Scratch::new(STRIPE_LEN).with_erasure_cache(1);
+	let mut remaining = payload_len.unwrap_or(usize::MAX);
+	let mut written = 0;
+	loop {
+		let mut len = None;
+		for ((_, reader), stripe) in readers.iter_mut().zip(stripes.iter_mut()) {
+			let read = if stripe_len == usize::MAX {
+				stripe.clear();
+				reader.read_to_end(stripe)?
+			} else {
+				stripe.resize(stripe_len, 0);
+				read_stripe(reader, stripe)?
+			};
+			stripe.truncate(read);
+			match len {
+				Some(first) if first != read => return Err(Error::InconsistentShardLengths { first, other: read }),
+				_ => len = Some(read),
+			}
+		}
+		let len = len.unwrap_or_default();
+		// an empty first stripe still validates the shards
+		if len == 0 && written > 0 {
+			break;
+		}
+
+		let mut received_shards = vec![None; params.n()];
+		for ((idx, _), stripe) in readers.iter().zip(stripes.iter()) {
+			received_shards[*idx] = Some(&stripe[..]);
+		}
+		let recovered = novel_poly_basis::reconstruct_with_scratch(params, &received_shards, &mut scratch)?;
+		// the stripe of the first data shard is written right away, those of the others later
+		#[cfg(feature = "spill")]
+		let recovered = match &mut spilled {
+			Some(spilled) => {
+				let (first, others) = recovered.split_at(recovered.len() / k);
+				spilled.append(others, first.len())?;
+				first
+			}
+			None => &recovered[..],
+		};
+		let recovered = &recovered[..std::cmp::min(recovered.len(), remaining)];
+		out.write_all(recovered)?;
+		remaining -= recovered.len();
+		written += recovered.len();
+		if len < stripe_len || remaining == 0 || recovered.is_empty() {
+			break;
+		}
+	}
+	#[cfg(feature = "spill")]
+	if let Some(spilled) = spilled {
+		written += spilled.write_to(&mut out, remaining)?;
+	}
+	out.flush()?;
+	Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn shard_stream_roundtrip() {
+		let params =
+			CodeParams::new(20, 6).unwrap().with_endianness(Endianness::Big).with_layout(ShardLayout::Sequential);
+		let payload = &random_payload(1001, 0);
+		let shards = novel_poly_basis::encode_with_params(&params, payload).unwrap();
+		let mut received = shards.into_iter().map(Some).collect::<Vec<_>>();
+		received[3] = None;
+		received[17] = None;
+
+		let mut stream = Vec::new();
+		write_shards(&mut stream, &params, &received).unwrap();
+		let reader = ShardReader::new(&stream[..]).unwrap();
+		assert_eq!(reader.params(), &params);
+		assert_eq!(reader.remaining(), 18);
+		let read = reader.read_all().unwrap();
+		assert_eq!(read, received);
+		let recovered = novel_poly_basis::reconstruct_with_params(&params, read).unwrap();
+		assert_eq!(&recovered[..payload.len()], payload);
+
+		// encoding straight into a writer
+		let mut writer = ShardWriter::new(Vec::new(), &params, params.n()).unwrap();
+		sink::encode_into_sink(&params, payload, &mut writer).unwrap();
+		let shards = ShardReader::new(&writer.finish().unwrap()[..]).unwrap().collect::<Result<Vec<_>>>().unwrap();
+		assert_eq!(shards.len(), params.n());
+		assert!(shards.iter().enumerate().all(|(idx, (index, _))| index.position() == idx));
+
+		// truncated or corrupted streams are rejected
+		let truncated = ShardReader::new(&stream[..stream.len() - 1]).unwrap().read_all();
+		assert_eq!(truncated, Err(std::io::ErrorKind::UnexpectedEof.into()));
+		assert!(ShardReader::new(&stream[..10]).is_err());
+		let mut corrupted = stream.clone();
+		corrupted[0] = b'X';
+		assert_eq!(ShardReader::new(&corrupted[..]).err(), Some(Error::MalformedShardStream("not a shard stream")));
+		let writer = ShardWriter::new(Vec::new(), &params, 2).unwrap();
+		assert_eq!(writer.finish().err(), Some(Error::MalformedShardStream("fewer shards than announced")));
+	}
+
+	#[test]
+	fn reconstruct_streaming_shard_files() {
+		let dir = tempfile::tempdir().unwrap();
+		let payload = &random_payload(300_001, 0);
+		for layout in [ShardLayout::Interleaved, ShardLayout::Sequential] {
+			let params = CodeParams::new(16, 4).unwrap().with_layout(layout);
+			let shards = novel_poly_basis::encode_with_params(&params, payload).unwrap();
+			assert!(AsRef::<[u8]>::as_ref(&shards[0]).len() > STRIPE_LEN);
+			let path = |idx: usize| dir.path().join(format!("{}.shard", idx));
+			for (idx, shard) in shards.iter().enumerate() {
+				std::fs::write(path(idx), AsRef::<[u8]>::as_ref(shard)).unwrap();
+			}
+
+			// the data shards are lost, files are opened as needed
+			let files = (0..params.n())
+				.filter(|idx| *idx >= params.k())
+				.map(|idx| (idx, std::fs::File::open(path(idx)).unwrap()));
+			let recovered = reconstruct_from_files(&params, files).unwrap();
+			assert_eq!(&recovered[..payload.len()], payload, "{:?}", layout);
+
+			let mut out = Vec::new();
+			let files = (1..params.n()).map(|idx| (idx, std::fs::File::open(path(idx)).unwrap()));
+			assert_eq!(reconstruct_from_files_into(&params, files, &mut out, Some(payload.len())), Ok(payload.len()));
+			assert_eq!(&out[..], payload, "{:?}", layout);
+		}
+
+		let params = CodeParams::new(16, 4).unwrap();
+		let shards = [(0, &[0_u8; 4][..]), (1, &[0; 4]), (2, &[0; 2]), (3, &[0; 4])];
+		let inconsistent = Error::InconsistentShardLengths { first: 4, other: 2 };
+		assert_eq!(reconstruct_from_files(&params, shards), Err(inconsistent));
+		let shards = [(5, &[0_u8; 4][..]), (5, &[0; 4])];
+		assert_eq!(reconstruct_from_files(&params, shards), Err(Error::DuplicateChunkIndex(5)));
+		let shards = [(5, &[0_u8; 4][..])];
+		assert_eq!(reconstruct_from_files(&params, shards), Err(Error::TooFewShards { available: 1, required: 4 }));
+	}
+
+	#[cfg(feature = "spill")]
+	#[test]
+	fn sequential_payloads_spill_beyond_the_memory_cap() {
+		let dir = tempfile::tempdir().unwrap();
+		let payload = &random_payload(300_001, 0);
+		let params = CodeParams::new(16, 4).unwrap().with_layout(ShardLayout::Sequential);
+		let shards = novel_poly_basis::encode_with_params(&params, payload).unwrap();
+		// two stripes per shard, the second shorter
+		let shard_len = AsRef::<[u8]>::as_ref(&shards[0]).len();
+		assert!(shard_len > STRIPE_LEN && shard_len < 2 * STRIPE_LEN);
+
+		for cap in [0, 100_000, usize::MAX] {
+			let options = ReconstructOptions::default().with_memory_cap(cap).with_spill_dir(dir.path());
+			let files = shards.iter().enumerate().skip(3).map(|(idx, shard)| (idx, AsRef::<[u8]>::as_ref(shard)));
+			let mut out = Vec::new();
+			let written = reconstruct_from_files_with_options(&params, files, &mut out, Some(payload.len()), &options);
+			assert_eq!(written, Ok(payload.len()), "cap {}", cap);
+			assert_eq!(&out[..], payload, "cap {}", cap);
+		}
+		// spilled files are anonymous and gone once closed
+		assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+	}
+}
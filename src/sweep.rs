@@ -0,0 +1,121 @@
+//! Sweep encode and reconstruct over a grid of code parameters, emitting CSV
+//! for plotting the rate and size dependence of both backends.
+
+use super::*;
+use crate::auto::{Backend, Selector};
+
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Timings of one backend at one `(n, k)` point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepRow {
+	pub backend: Backend,
+	pub n: usize,
+	pub k: usize,
+	pub payload_len: usize,
+	pub shard_len: usize,
+	/// Best encode time over all iterations.
+	pub encode: Duration,
+	/// Best reconstruct time over all iterations, with `n - k` shards erased.
+	pub reconstruct: Duration,
+}
+
+impl SweepRow {
+	pub const CSV_HEADER: &'static str =
+		"backend,n,k,payload_len,shard_len,encode_ns,reconstruct_ns,encode_mib_s,reconstruct_mib_s";
+
+	/// One CSV line, without the trailing newline.
+	pub fn to_csv(&self) -> String {
+		let mib_s = |d: Duration| self.payload_len as f64 / (1 << 20) as f64 / d.as_secs_f64().max(f64::MIN_POSITIVE);
+		format!(
+			"{:?},{},{},{},{},{},{},{:.3},{:.3}",
+			self.backend,
+			self.n,
+			self.k,
+			self.payload_len,
+			self.shard_len,
+			self.encode.as_nanos(),
+			self.reconstruct.as_nanos(),
+			mib_s(self.encode),
+			mib_s(self.reconstruct),
+		)
+	}
+}
+
+/// The `(n, k)` pairs for every `n` in `ns` and each rate in `rates`, where
+/// `k = max(n * rate, 1)`, skipping invalid pairs.
+pub fn grid(ns: &[usize], rates: &[f64]) -> Vec<CodeParams> {
+	ns.iter()
+		.flat_map(|&n| rates.iter().map(move |&rate| (n, std::cmp::max((n as f64 * rate) as usize, 1))))
+		.filter_map(|(n, k)| CodeParams::new(n, k).ok())
+		.collect()
+}
+
+/// Time `backend` on `payload` for every point of `grid`, taking the best of
+/// `iterations` runs. The last `n - k` shards are erased for reconstruction.
+pub fn sweep(backend: Backend, payload: &[u8], grid: &[CodeParams], iterations: usize) -> Result<Vec<SweepRow>> {
+	let selector = Selector::fixed(backend);
+	grid.iter()
+		.map(|params| {
+			let mut row = SweepRow {
+				backend,
+				n: params.n(),
+				k: params.k(),
+				payload_len: payload.len(),
+				shard_len: 0,
+				encode: Duration::MAX,
+				reconstruct: Duration::MAX,
+			};
+			for _ in 0..std::cmp::max(iterations, 1) {
+				let start = Instant::now();
+				let shards = selector.encode(params, payload)?;
+				row.encode = row.encode.min(start.elapsed());
+				row.shard_len = AsRef::<[u8]>::as_ref(&shards[0]).len();
+
+				let received = shards
+					.into_iter()
+					.enumerate()
+					.map(|(idx, shard)| if idx < params.k() { Some(shard) } else { None })
+					.collect();
+				let start = Instant::now();
+				let recovered = selector.reconstruct(params, received)?;
+				row.reconstruct = row.reconstruct.min(start.elapsed());
+				assert_eq!(&recovered[..payload.len()], payload, "sweep reconstruction must match the payload");
+			}
+			Ok(row)
+		})
+		.collect()
+}
+
+/// Write `rows` as CSV including the header.
+pub fn write_csv(mut out: impl Write, rows: &[SweepRow]) -> io::Result<()> {
+	writeln!(out, "{}", SweepRow::CSV_HEADER)?;
+	for row in rows {
+		writeln!(out, "{}", row.to_csv())?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sweep_emits_csv() {
+		let grid = grid(&[16, 64], &[0.25, 0.5, 2.0]);
+		assert_eq!(grid.iter().map(|p| (p.n(), p.k())).collect::<Vec<_>>(), vec![(16, 4), (16, 8), (64, 16), (64, 32)]);
+
+		let mut rows = sweep(Backend::NovelPolyBasis, &random_payload(1000, 0), &grid, 1).unwrap();
+		rows.extend(sweep(Backend::StatusQuo, &random_payload(1000, 0), &grid, 1).unwrap());
+
+		let mut csv = Vec::new();
+		write_csv(&mut csv, &rows).unwrap();
+		let csv = String::from_utf8(csv).unwrap();
+		let lines = csv.lines().collect::<Vec<_>>();
+		assert_eq!(lines.len(), 9);
+		assert_eq!(lines[0], SweepRow::CSV_HEADER);
+		assert!(lines[1].starts_with("NovelPolyBasis,16,4,1000,250,"));
+		assert!(lines.iter().all(|line| line.split(',').count() == 9));
+	}
+}
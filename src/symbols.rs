@@ -0,0 +1,33 @@
+use super::*;
+
+/// Pack `bytes` into 2 byte symbols, an odd trailing byte is zero extended
+/// into a final symbol rather than dropped.
+pub fn bytes_to_symbols(bytes: &[u8], endianness: Endianness) -> impl Iterator<Item = u16> + '_ {
+	let pairs = bytes.chunks_exact(2);
+	let trailing = pairs.remainder().first().map(move |byte| endianness.symbol_from_bytes([*byte, 0]));
+	pairs.map(move |pair| endianness.symbol_from_bytes([pair[0], pair[1]])).chain(trailing)
+}
+
+/// Unpack `symbols` into `2 * symbols.len()` bytes.
+pub fn symbols_to_bytes(symbols: &[u16], endianness: Endianness) -> Vec<u8> {
+	symbols.iter().flat_map(|sym| endianness.symbol_to_bytes(*sym)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn odd_length_is_zero_extended() {
+		let symbols = bytes_to_symbols(&[1, 2, 3], Endianness::Little).collect::<Vec<_>>();
+		assert_eq!(symbols, vec![0x0201, 0x0003]);
+		assert_eq!(symbols_to_bytes(&symbols, Endianness::Little), vec![1, 2, 3, 0]);
+
+		let symbols = bytes_to_symbols(&[1, 2, 3], Endianness::Big).collect::<Vec<_>>();
+		assert_eq!(symbols, vec![0x0102, 0x0300]);
+		assert_eq!(symbols_to_bytes(&symbols, Endianness::Big), vec![1, 2, 3, 0]);
+
+		assert_eq!(bytes_to_symbols(&[], Endianness::Little).count(), 0);
+		assert_eq!(bytes_to_symbols(&[7], Endianness::Little).collect::<Vec<_>>(), vec![7]);
+	}
+}
@@ -0,0 +1,88 @@
+//! Reproducible erasure and corruption of shards for tests.
+//!
+//! All helpers are deterministic in their `seed`, so failures found in CI
+//! can be replayed exactly.
+
+use super::*;
+
+use rand::{rngs::StdRng, seq::index, Rng, SeedableRng};
+
+/// `count` distinct indices below `n` in increasing order, chosen uniformly,
+/// all of them if `count` exceeds `n`.
+pub fn erasure_indices(n: usize, count: usize, seed: u64) -> Vec<usize> {
+	let mut rng = StdRng::seed_from_u64(seed);
+	let mut indices = index::sample(&mut rng, n, count.min(n)).into_vec();
+	indices.sort_unstable();
+	indices
+}
+
+/// Erase `count` randomly chosen shards, all of them if `count` exceeds their number.
+pub fn erase_random<T>(shards: Vec<T>, count: usize, seed: u64) -> Vec<Option<T>> {
+	let mut received = shards.into_iter().map(Some).collect::<Vec<_>>();
+	for idx in erasure_indices(received.len(), count, seed) {
+		received[idx] = None;
+	}
+	received
+}
+
+/// Keep only `keep` randomly chosen shards, erasing all others, or none if `keep` exceeds
+/// their number.
+pub fn keep_random<T>(shards: Vec<T>, keep: usize, seed: u64) -> Vec<Option<T>> {
+	let count = shards.len().saturating_sub(keep);
+	erase_random(shards, count, seed)
+}
+
+/// Flip bits in one random byte of each of `count` randomly chosen shards,
+/// returning the indices of the corrupted shards in increasing order.
+///
+/// Empty shards among the chosen ones have no byte to flip, they are left as they are
+/// and not returned.
+pub fn corrupt_random(shards: &mut [WrappedShard], count: usize, seed: u64) -> Vec<usize> {
+	let mut corrupted = erasure_indices(shards.len(), count, seed);
+	corrupted.retain(|&idx| !AsRef::<[u8]>::as_ref(&shards[idx]).is_empty());
+	let mut rng = StdRng::seed_from_u64(seed.wrapping_add(1));
+	for &idx in &corrupted {
+		let shard: &mut [u8] = shards[idx].as_mut();
+		let pos = rng.gen_range(0..shard.len());
+		shard[pos] ^= rng.gen_range(1..=u8::MAX);
+	}
+	corrupted
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn seeded_erasures_are_reproducible() {
+		assert_eq!(erasure_indices(64, 40, 7), erasure_indices(64, 40, 7));
+		assert_ne!(erasure_indices(64, 40, 7), erasure_indices(64, 40, 8));
+		assert!(erasure_indices(64, 40, 7).windows(2).all(|w| w[0] < w[1]));
+
+		let params = CodeParams::new(64, 16).unwrap();
+		let shards = novel_poly_basis::encode_with_params(&params, &random_payload(1000, 0)).unwrap();
+		let received = keep_random(shards.clone(), params.k(), 3);
+		assert_eq!(received.iter().flatten().count(), params.k());
+		let recovered = novel_poly_basis::reconstruct_with_params(&params, received).unwrap();
+		assert_eq!(&recovered[..1000], &random_payload(1000, 0));
+
+		let mut corrupted = shards.clone();
+		let indices = corrupt_random(&mut corrupted, 5, 3);
+		assert_eq!(indices.len(), 5);
+		for (idx, (a, b)) in shards.iter().zip(corrupted.iter()).enumerate() {
+			assert_eq!(a == b, !indices.contains(&idx));
+		}
+	}
+
+	#[test]
+	fn out_of_range_counts_are_clamped() {
+		assert_eq!(erasure_indices(4, 10, 0), [0, 1, 2, 3]);
+		assert!(erase_random(vec![1, 2, 3], 5, 0).iter().all(Option::is_none));
+		assert_eq!(keep_random(vec![1, 2, 3], 5, 0), [Some(1), Some(2), Some(3)]);
+
+		let mut shards = vec![WrappedShard::new(Vec::new()), WrappedShard::new(vec![1, 2])];
+		assert_eq!(corrupt_random(&mut shards, 2, 0), [1]);
+		assert!(AsRef::<[u8]>::as_ref(&shards[0]).is_empty());
+		assert_ne!(shards[1], WrappedShard::new(vec![1, 2]));
+	}
+}
@@ -0,0 +1,146 @@
+//! Reversible payload transforms applied before sharding, such as compression.
+//!
+//! The transformed payload is prefixed with its length, so the padding added
+//! by the backends is stripped before the transform is reversed.
+
+use super::*;
+
+use std::convert::TryInto;
+
+/// A reversible transform of the payload.
+pub trait PayloadTransform {
+	/// Transform `payload` before it is encoded.
+	fn forward(&self, payload: &[u8]) -> Result<Vec<u8>>;
+
+	/// Reverse `forward` on the reconstructed payload.
+	fn inverse(&self, transformed: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// Leaves the payload as is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Identity;
+
+impl PayloadTransform for Identity {
+	fn forward(&self, payload: &[u8]) -> Result<Vec<u8>> {
+		Ok(payload.to_vec())
+	}
+
+	fn inverse(&self, transformed: &[u8]) -> Result<Vec<u8>> {
+		Ok(transformed.to_vec())
+	}
+}
+
+/// Zstandard compression at the given level.
+///
+/// Decompression stops at `max_len` bytes, the largest payload expected,
+/// so a malicious sender can not make the receiver allocate without bound.
+#[cfg(feature = "zstd")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Zstd {
+	pub level: i32,
+	pub max_len: usize,
+}
+
+#[cfg(feature = "zstd")]
+impl Zstd {
+	/// Bound the decompressed payload to `max_len` bytes.
+	pub fn with_max_len(mut self, max_len: usize) -> Self {
+		self.max_len = max_len;
+		self
+	}
+}
+
+#[cfg(feature = "zstd")]
+impl Default for Zstd {
+	fn default() -> Self {
+		// the largest PoV benchmarked
+		Zstd { level: zstd::DEFAULT_COMPRESSION_LEVEL, max_len: pov::POV_SIZES[pov::POV_SIZES.len() - 1] }
+	}
+}
+
+#[cfg(feature = "zstd")]
+impl PayloadTransform for Zstd {
+	fn forward(&self, payload: &[u8]) -> Result<Vec<u8>> {
+		Ok(zstd::bulk::compress(payload, self.level)?)
+	}
+
+	fn inverse(&self, transformed: &[u8]) -> Result<Vec<u8>> {
+		use std::io::Read;
+
+		// read one byte past the limit to tell a payload of exactly `max_len` from a larger one
+		let mut payload = Vec::new();
+		zstd::stream::read::Decoder::new(transformed)?.take(self.max_len as u64 + 1).read_to_end(&mut payload)?;
+		if payload.len() > self.max_len {
+			return Err(Error::PayloadTooLarge { len: payload.len(), max: self.max_len });
+		}
+		Ok(payload)
+	}
+}
+
+// bytes of the length prefix of the transformed payload
+const LEN_PREFIX: usize = 8;
+
+/// Transform `payload` and encode it with the backend picked by the default `Selector`.
+pub fn encode_with(
+	transform: &impl PayloadTransform,
+	params: &CodeParams,
+	payload: &[u8],
+) -> Result<Vec<WrappedShard>> {
+	let transformed = transform.forward(payload)?;
+	let mut prefixed = Vec::with_capacity(LEN_PREFIX + transformed.len());
+	prefixed.extend_from_slice(&(transformed.len() as u64).to_le_bytes());
+	prefixed.extend_from_slice(&transformed);
+	auto::encode(params, &prefixed)
+}
+
+/// Reconstruct the payload of `encode_with`, reversing the transform.
+pub fn reconstruct_with(
+	transform: &impl PayloadTransform,
+	params: &CodeParams,
+	received_shards: Vec<Option<WrappedShard>>,
+) -> Result<Vec<u8>> {
+	let prefixed = auto::reconstruct(params, received_shards)?;
+	let len = prefixed.get(..LEN_PREFIX).ok_or(Error::BadPayload)?;
+	let len = u64::from_le_bytes(len.try_into().expect("prefix has 8 bytes. qed")) as usize;
+	let transformed = prefixed[LEN_PREFIX..].get(..len).ok_or(Error::BadPayload)?;
+	transform.inverse(transformed)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn roundtrip(transform: &impl PayloadTransform, payload: &[u8]) -> usize {
+		let params = CodeParams::new(16, 4).unwrap();
+		let shards = encode_with(transform, &params, payload).unwrap();
+		let shard_len = AsRef::<[u8]>::as_ref(&shards[0]).len();
+		let received = shards.into_iter().enumerate().map(|(i, s)| if i % 4 == 0 { Some(s) } else { None }).collect();
+		assert_eq!(reconstruct_with(transform, &params, received).unwrap(), payload);
+		shard_len
+	}
+
+	#[test]
+	fn identity_roundtrip() {
+		roundtrip(&Identity, &random_payload(1000, 0));
+		roundtrip(&Identity, &[]);
+	}
+
+	#[cfg(feature = "zstd")]
+	#[test]
+	fn zstd_shrinks_pov_shards() {
+		let payload = pov::pov_payload(1 << 16, 1);
+		assert!(roundtrip(&Zstd::default(), &payload) < roundtrip(&Identity, &payload));
+		roundtrip(&Zstd::default(), &[]);
+
+		let params = CodeParams::new(16, 4).unwrap();
+		let shards = encode_with(&Identity, &params, &random_payload(100, 0)).unwrap();
+		let received = shards.into_iter().map(Some).collect();
+		assert!(matches!(reconstruct_with(&Zstd::default(), &params, received), Err(Error::Io(_))));
+
+		let zstd = Zstd::default().with_max_len(1000);
+		roundtrip(&zstd, &[7; 1000]);
+		let shards = encode_with(&Zstd::default(), &params, &[7; 1001]).unwrap();
+		let received = shards.into_iter().map(Some).collect();
+		assert_eq!(reconstruct_with(&zstd, &params, received), Err(Error::PayloadTooLarge { len: 1001, max: 1000 }));
+	}
+}
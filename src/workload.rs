@@ -0,0 +1,57 @@
+//! Sized payload generators for tests and benchmarks.
+//!
+//! Each benchmark draws its own payload of the size it measures, from a seed, instead of
+//! slicing a single random fixture shared by all of them. See `corpus` for named payloads
+//! of other kinds.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Generator of payloads of any length.
+pub trait Workload {
+	/// A payload of `len` bytes.
+	fn payload(&self, len: usize) -> Vec<u8>;
+}
+
+/// `len` uniformly random bytes, the same for the same `seed`.
+pub fn random_payload(len: usize, seed: u64) -> Vec<u8> {
+	let mut payload = vec![0_u8; len];
+	StdRng::seed_from_u64(seed).fill(&mut payload[..]);
+	payload
+}
+
+/// Uniformly random payloads from a seed, see `random_payload`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Random(pub u64);
+
+impl Workload for Random {
+	fn payload(&self, len: usize) -> Vec<u8> {
+		random_payload(len, self.0)
+	}
+}
+
+/// A given payload, repeated to the requested length, e.g. one generated outside the
+/// measured loop of a benchmark. An empty payload repeats as zeros.
+impl Workload for [u8] {
+	fn payload(&self, len: usize) -> Vec<u8> {
+		if self.is_empty() {
+			return vec![0; len];
+		}
+		self.iter().copied().cycle().take(len).collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn payloads_are_seeded() {
+		let payload = random_payload(1000, 1);
+		assert_eq!(payload, Random(1).payload(1000));
+		assert_eq!(&random_payload(10, 1)[..], &payload[..10]);
+		assert_ne!(random_payload(1000, 2), payload);
+		let repeated = payload[..3].payload(7);
+		assert_eq!(repeated, [&payload[..3], &payload[..3], &payload[..1]].concat());
+		assert_eq!(payload[..0].payload(4), [0; 4]);
+	}
+}
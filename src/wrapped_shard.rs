@@ -1,7 +1,64 @@
-// A shard with a even number of elements, which can sliced into 2 byte haps
-#[derive(Clone)]
+use super::*;
+
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "codec")]
+use codec::{Decode, Encode, Input, Output};
+
+// Owned or shared bytes, shared ones are copied on the first mutable access.
+#[derive(Clone, Debug)]
+enum Storage {
+	Owned(Vec<u8>),
+	Shared(Arc<[u8]>),
+	#[cfg(feature = "bytes")]
+	Bytes(bytes::Bytes),
+}
+
+impl Storage {
+	fn as_slice(&self) -> &[u8] {
+		match self {
+			Storage::Owned(data) => data,
+			Storage::Shared(data) => data,
+			#[cfg(feature = "bytes")]
+			Storage::Bytes(data) => data,
+		}
+	}
+
+	fn to_mut(&mut self) -> &mut Vec<u8> {
+		if !matches!(self, Storage::Owned(_)) {
+			*self = Storage::Owned(self.as_slice().to_vec());
+		}
+		match self {
+			Storage::Owned(data) => data,
+			_ => unreachable!("shared storage was just copied. qed"),
+		}
+	}
+
+	fn into_vec(self) -> Vec<u8> {
+		match self {
+			Storage::Owned(data) => data,
+			shared => shared.as_slice().to_vec(),
+		}
+	}
+}
+
+// A shard with a even number of elements, which can sliced into 2 byte haps,
+// unless created by `new_unpadded` for 1 byte symbols, so 2 byte symbols are best
+// read through the checked `symbols`.
+//
+// The bytes are either owned or shared with e.g. the network stack that received
+// them, in which case reconstruction reads them without copying.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(from = "Vec<u8>", into = "Vec<u8>"))]
 pub struct WrappedShard {
-	inner: Vec<u8>,
+	inner: Storage,
+	// metadata, not part of the shard's identity nor of its serializations
+	#[cfg(feature = "custody")]
+	custody: Option<custody::CustodyTag>,
 }
 
 impl WrappedShard {
@@ -11,46 +68,185 @@ impl WrappedShard {
 			data.push(0);
 		}
 
-		WrappedShard { inner: data }
+		WrappedShard::from_storage(Storage::Owned(data))
+	}
+
+	/// Wrap `data` of 1 byte symbols, e.g. of the GF(2^8) backend, which unlike `new`
+	/// keeps odd lengths, so only `symbols::<1>` is guaranteed to succeed.
+	pub fn new_unpadded(data: Vec<u8>) -> Self {
+		WrappedShard::from_storage(Storage::Owned(data))
 	}
 
-	/// Unwrap and yield inner data.
+	fn from_storage(inner: Storage) -> Self {
+		WrappedShard {
+			inner,
+			#[cfg(feature = "custody")]
+			custody: None,
+		}
+	}
+
+	/// Unwrap and yield inner data, shared bytes are copied.
 	pub fn into_inner(self) -> Vec<u8> {
-		self.inner
+		self.inner.into_vec()
+	}
+
+	/// View the shard as symbols of `N` bytes, i.e. `N = 2` for GF(2^16) and `N = 1` for GF(2^8),
+	/// failing if the length is not a multiple of `N`.
+	pub fn symbols<const N: usize>(&self) -> Result<&[[u8; N]]> {
+		let inner = self.inner.as_slice();
+		check_symbol_len::<N>(inner.len())?;
+		Ok(bytemuck::cast_slice(inner))
+	}
+
+	/// Mutable `symbols`, shared bytes are copied.
+	pub fn symbols_mut<const N: usize>(&mut self) -> Result<&mut [[u8; N]]> {
+		check_symbol_len::<N>(self.inner.as_slice().len())?;
+		Ok(bytemuck::cast_slice_mut(self.inner.to_mut()))
+	}
+
+	/// `self ^= other` bytewise, the sum of the shards in GF(2^16) or GF(2^8), e.g. for
+	/// the network coding experiments of `network_coding`. Shared bytes are copied.
+	pub fn xor_assign(&mut self, other: &WrappedShard) -> Result<()> {
+		let (first, other) = (self.inner.as_slice().len(), other.inner.as_slice());
+		if first != other.len() {
+			return Err(Error::InconsistentShardLengths { first, other: other.len() });
+		}
+		self.inner.to_mut().iter_mut().zip(other).for_each(|(byte, other)| *byte ^= other);
+		Ok(())
+	}
+
+	/// Whether the bytes are shared rather than owned, so mutating them copies.
+	pub fn is_shared(&self) -> bool {
+		!matches!(self.inner, Storage::Owned(_))
+	}
+
+	/// The proof of custody tag attached by `custody::tag_shards`, if any.
+	#[cfg(feature = "custody")]
+	pub fn custody_tag(&self) -> Option<&custody::CustodyTag> {
+		self.custody.as_ref()
+	}
+
+	/// Attach `tag`, replacing any previous one.
+	#[cfg(feature = "custody")]
+	pub fn set_custody_tag(&mut self, tag: custody::CustodyTag) {
+		self.custody = Some(tag);
+	}
+}
+
+fn check_symbol_len<const N: usize>(len: usize) -> Result<()> {
+	const { assert!(N > 0, "symbols have at least one byte") };
+	if !len.is_multiple_of(N) {
+		return Err(Error::UnalignedShardLength(len));
+	}
+	Ok(())
+}
+
+impl PartialEq for WrappedShard {
+	fn eq(&self, other: &Self) -> bool {
+		self.inner.as_slice() == other.inner.as_slice()
+	}
+}
+
+impl Eq for WrappedShard {}
+
+impl From<Vec<u8>> for WrappedShard {
+	/// Same as `WrappedShard::new`, pads odd length data with a trailing zero.
+	fn from(data: Vec<u8>) -> Self {
+		Self::new(data)
+	}
+}
+
+impl From<WrappedShard> for Vec<u8> {
+	fn from(shard: WrappedShard) -> Self {
+		shard.into_inner()
+	}
+}
+
+impl TryFrom<&[u8]> for WrappedShard {
+	type Error = Error;
+
+	/// Copy `data` into a shard, odd length data is rejected rather than padded.
+	fn try_from(data: &[u8]) -> Result<Self> {
+		if data.len() & 0x01 == 0x01 {
+			return Err(Error::UnalignedShardLength(data.len()));
+		}
+		Ok(WrappedShard::from_storage(Storage::Owned(data.to_vec())))
+	}
+}
+
+impl TryFrom<Arc<[u8]>> for WrappedShard {
+	type Error = Error;
+
+	/// Share `data` without copying, odd length data is rejected since it can not be padded.
+	fn try_from(data: Arc<[u8]>) -> Result<Self> {
+		if data.len() & 0x01 == 0x01 {
+			return Err(Error::UnalignedShardLength(data.len()));
+		}
+		Ok(WrappedShard::from_storage(Storage::Shared(data)))
+	}
+}
+
+#[cfg(feature = "bytes")]
+impl TryFrom<bytes::Bytes> for WrappedShard {
+	type Error = Error;
+
+	/// Share `data` without copying, odd length data is rejected since it can not be padded.
+	fn try_from(data: bytes::Bytes) -> Result<Self> {
+		if data.len() & 0x01 == 0x01 {
+			return Err(Error::UnalignedShardLength(data.len()));
+		}
+		Ok(WrappedShard::from_storage(Storage::Bytes(data)))
+	}
+}
+
+#[cfg(feature = "codec")]
+impl Encode for WrappedShard {
+	fn size_hint(&self) -> usize {
+		self.inner.as_slice().size_hint()
+	}
+
+	fn encode_to<O: Output + ?Sized>(&self, dest: &mut O) {
+		self.inner.as_slice().encode_to(dest)
+	}
+}
+
+#[cfg(feature = "codec")]
+impl Decode for WrappedShard {
+	fn decode<I: Input>(input: &mut I) -> std::result::Result<Self, codec::Error> {
+		let inner = Vec::<u8>::decode(input)?;
+		if inner.len() & 0x01 == 0x01 {
+			return Err("WrappedShard must have an even number of bytes".into());
+		}
+		Ok(WrappedShard::from_storage(Storage::Owned(inner)))
 	}
 }
 
 impl AsRef<[u8]> for WrappedShard {
 	fn as_ref(&self) -> &[u8] {
-		self.inner.as_ref()
+		self.inner.as_slice()
 	}
 }
 
 impl AsMut<[u8]> for WrappedShard {
 	fn as_mut(&mut self) -> &mut [u8] {
-		self.inner.as_mut()
+		self.inner.to_mut()
 	}
 }
 
+/// Deprecated, see the checked `symbols`: panics for shards created by `new_unpadded` of odd length.
+///
+/// Kept for the `reed_solomon_erasure` codec, which takes 2 byte symbol views, the compiler rejects
+/// `#[deprecated]` on trait impls.
 impl AsRef<[[u8; 2]]> for WrappedShard {
 	fn as_ref(&self) -> &[[u8; 2]] {
-		assert_eq!(self.inner.len() & 0x01, 0);
-		if self.inner.is_empty() {
-			return &[];
-		}
-		unsafe { ::std::slice::from_raw_parts(&self.inner[0] as *const _ as _, self.inner.len() / 2) }
+		self.symbols().expect("only unpadded shards may have an odd length. qed")
 	}
 }
 
+/// Deprecated, see the checked `symbols_mut`: panics for shards created by `new_unpadded` of odd length.
 impl AsMut<[[u8; 2]]> for WrappedShard {
 	fn as_mut(&mut self) -> &mut [[u8; 2]] {
-		let len = self.inner.len();
-		assert_eq!(len & 0x01, 0);
-
-		if self.inner.is_empty() {
-			return &mut [];
-		}
-		unsafe { ::std::slice::from_raw_parts_mut(&mut self.inner[0] as *mut _ as _, len / 2) }
+		self.symbols_mut().expect("only unpadded shards may have an odd length. qed")
 	}
 }
 
@@ -67,6 +263,170 @@ impl std::iter::FromIterator<[u8; 2]> for WrappedShard {
 		}
 
 		debug_assert_eq!(inner.len() & 0x01, 0);
-		WrappedShard { inner }
+		WrappedShard::from_storage(Storage::Owned(inner))
+	}
+}
+
+/// All shards of one encoded payload as transported over the wire,
+/// missing shards are `None`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "codec", derive(Encode, Decode))]
+pub struct ChunkSet {
+	pub shards: Vec<Option<WrappedShard>>,
+}
+
+impl ChunkSet {
+	/// Yield the shards in the form expected by `reconstruct`.
+	pub fn into_inner(self) -> Vec<Option<WrappedShard>> {
+		self.shards
+	}
+}
+
+impl From<Vec<WrappedShard>> for ChunkSet {
+	fn from(shards: Vec<WrappedShard>) -> Self {
+		ChunkSet { shards: shards.into_iter().map(Some).collect() }
+	}
+}
+
+impl From<Vec<Option<WrappedShard>>> for ChunkSet {
+	fn from(shards: Vec<Option<WrappedShard>>) -> Self {
+		ChunkSet { shards }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn conversions() {
+		let shard = WrappedShard::from(vec![1, 2, 3]);
+		assert_eq!(AsRef::<[u8]>::as_ref(&shard), &[1, 2, 3, 0]);
+		assert_eq!(Vec::<u8>::from(shard.clone()), vec![1, 2, 3, 0]);
+
+		assert_eq!(WrappedShard::try_from(&[1_u8, 2, 3, 0][..]), Ok(shard));
+		assert_eq!(WrappedShard::try_from(&[1_u8, 2, 3][..]), Err(Error::UnalignedShardLength(3)));
+	}
+
+	#[test]
+	fn symbol_views() {
+		let mut shard = WrappedShard::new(vec![1, 2, 3, 4]);
+		assert_eq!(shard.symbols::<1>().unwrap(), &[[1], [2], [3], [4]]);
+		assert_eq!(shard.symbols::<2>().unwrap(), &[[1, 2], [3, 4]]);
+		assert_eq!(shard.symbols::<3>(), Err(Error::UnalignedShardLength(4)));
+		shard.symbols_mut::<2>().unwrap()[1] = [5, 6];
+		assert_eq!(shard.into_inner(), vec![1, 2, 5, 6]);
+
+		let mut odd = WrappedShard::new_unpadded(vec![1, 2, 3]);
+		assert_eq!(odd.symbols::<1>().unwrap(), &[[1], [2], [3]]);
+		assert_eq!(odd.symbols_mut::<2>(), Err(Error::UnalignedShardLength(3)));
+		assert!(WrappedShard::new_unpadded(Vec::new()).symbols::<2>().unwrap().is_empty());
+
+		// the status quo backend reads 2 byte symbols, so odd shards are an error rather than a panic
+		let params = CodeParams::default();
+		let received = (0..params.n()).map(|_| Some(WrappedShard::new_unpadded(vec![1, 2, 3]))).collect();
+		assert_eq!(status_quo::reconstruct_with_params(&params, received), Err(Error::UnalignedShardLength(3)));
+	}
+
+	// small enough to run under `cargo +nightly miri test --lib wrapped_shard`
+	#[test]
+	fn symbol_views_of_shared_shards() {
+		let data = Arc::<[u8]>::from(&[1_u8, 2, 3, 4, 5, 6][..]);
+		let mut shard = WrappedShard::try_from(data.clone()).unwrap();
+		assert_eq!(shard.symbols::<2>().unwrap().as_ptr() as *const u8, data.as_ptr());
+		let symbols = shard.symbols_mut::<2>().unwrap();
+		symbols[2] = [7, 8];
+		assert_eq!(shard.symbols::<2>().unwrap(), &[[1, 2], [3, 4], [7, 8]]);
+		assert_eq!(shard.symbols::<6>().unwrap(), &[[1, 2, 3, 4, 7, 8]]);
+		assert_eq!(&data[..], &[1, 2, 3, 4, 5, 6]);
+	}
+
+	#[test]
+	fn shared_shards_copy_on_write() {
+		let data = Arc::<[u8]>::from(&[1_u8, 2, 3, 4][..]);
+		let mut shard = WrappedShard::try_from(data.clone()).unwrap();
+		assert!(shard.is_shared());
+		assert_eq!(shard, WrappedShard::new(vec![1, 2, 3, 4]));
+		assert_eq!(WrappedShard::try_from(Arc::<[u8]>::from(&[1_u8][..])), Err(Error::UnalignedShardLength(1)));
+
+		AsMut::<[u8]>::as_mut(&mut shard)[0] = 5;
+		assert!(!shard.is_shared());
+		assert_eq!(shard.into_inner(), vec![5, 2, 3, 4]);
+		assert_eq!(&data[..], &[1, 2, 3, 4]);
+	}
+
+	#[test]
+	fn reconstruct_from_shared_shards() {
+		let params = CodeParams::new(16, 4).unwrap();
+		let payload = &random_payload(1000, 0);
+		for backend in [auto::Backend::StatusQuo, auto::Backend::NovelPolyBasis] {
+			let selector = auto::Selector::fixed(backend);
+			let shared = selector
+				.encode(&params, payload)
+				.unwrap()
+				.into_iter()
+				.map(|shard| Arc::<[u8]>::from(shard.into_inner()))
+				.collect::<Vec<_>>();
+			let received = shared
+				.iter()
+				.enumerate()
+				.map(|(idx, shard)| Some(WrappedShard::try_from(shard.clone()).unwrap()).filter(|_| idx % 4 == 3))
+				.collect();
+			let recovered = selector.reconstruct(&params, received).unwrap();
+			assert_eq!(&recovered[..payload.len()], payload, "{:?}", backend);
+		}
+	}
+
+	#[cfg(feature = "bytes")]
+	#[test]
+	fn bytes_shards() {
+		let shard = WrappedShard::try_from(bytes::Bytes::from_static(&[1, 2, 3, 4])).unwrap();
+		assert!(shard.is_shared());
+		assert_eq!(shard.symbols::<2>().unwrap(), &[[1, 2], [3, 4]]);
+		assert!(WrappedShard::try_from(bytes::Bytes::from_static(&[1])).is_err());
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn serde_json_roundtrip() {
+		let shard = WrappedShard::new(vec![7, 8, 9, 10]);
+		let json = serde_json::to_string(&shard).unwrap();
+		assert_eq!(json, "[7,8,9,10]");
+		assert_eq!(serde_json::from_str::<WrappedShard>(&json).unwrap(), shard);
+
+		let params = CodeParams::new(32, 4).unwrap();
+		let json = serde_json::to_string(&params).unwrap();
+		assert_eq!(serde_json::from_str::<CodeParams>(&json).unwrap(), params);
+		// parameters serialized before symbol groups
+		let json = r#"{"n":32,"k":4,"endianness":"Little","layout":"Interleaved"}"#;
+		assert_eq!(serde_json::from_str::<CodeParams>(json).unwrap(), params);
+		assert_eq!(serde_json::from_str::<CodeParams>(r#"{"n":32,"k":4}"#).unwrap(), params);
+		// deserialization validates like `CodeParams::new`
+		for json in [r#"{"n":4,"k":4}"#, r#"{"n":4,"k":0}"#, r#"{"n":65537,"k":4}"#] {
+			assert!(serde_json::from_str::<CodeParams>(json).is_err(), "{} must be rejected", json);
+		}
+	}
+
+	#[cfg(feature = "codec")]
+	#[test]
+	fn scale_roundtrip() {
+		let shard = WrappedShard::new(vec![7, 8, 9, 10]);
+		let encoded = shard.encode();
+		assert_eq!(encoded, vec![4 << 2, 7, 8, 9, 10]);
+		assert_eq!(WrappedShard::decode(&mut &encoded[..]).unwrap(), shard);
+
+		// odd length shards are rejected
+		let odd = vec![7_u8, 8, 9].encode();
+		assert!(WrappedShard::decode(&mut &odd[..]).is_err());
+
+		let payload = &random_payload(64, 0);
+		let mut chunks = ChunkSet::from(novel_poly_basis::encode(payload).unwrap());
+		chunks.shards[1] = None;
+		let encoded = chunks.encode();
+		let decoded = ChunkSet::decode(&mut &encoded[..]).unwrap();
+		assert_eq!(decoded, chunks);
+
+		let reconstructed = novel_poly_basis::reconstruct(decoded.into_inner()).unwrap();
+		assert_eq!(&reconstructed[..payload.len()], payload);
 	}
 }